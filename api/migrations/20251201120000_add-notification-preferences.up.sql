@@ -0,0 +1,14 @@
+-- Add up migration script here
+CREATE TABLE notification_preference (
+    email VARCHAR(255) PRIMARY KEY,
+    invitations BOOLEAN NOT NULL DEFAULT TRUE,
+    reminders BOOLEAN NOT NULL DEFAULT TRUE,
+    event_updates BOOLEAN NOT NULL DEFAULT TRUE,
+    game_schedule_changes BOOLEAN NOT NULL DEFAULT TRUE,
+    digests BOOLEAN NOT NULL DEFAULT FALSE,
+    created_at TIMESTAMPTZ NOT NULL DEFAULT NOW(),
+    last_modified TIMESTAMPTZ NOT NULL DEFAULT NOW()
+);
+
+-- Emails are stored lowercased, but keep lookups consistent with the other tables
+CREATE UNIQUE INDEX notification_preference_lower_email ON notification_preference (LOWER(email));
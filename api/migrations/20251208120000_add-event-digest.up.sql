@@ -0,0 +1,11 @@
+-- Add up migration script here
+CREATE TABLE event_digest (
+   event_id INTEGER NOT NULL,
+   email VARCHAR(255) NOT NULL,
+   sent_at TIMESTAMPTZ NOT NULL DEFAULT NOW(),
+   PRIMARY KEY (event_id, email),
+   CONSTRAINT fk_event
+      FOREIGN KEY(event_id)
+	    REFERENCES event(id)
+        ON DELETE CASCADE
+);
@@ -0,0 +1,36 @@
+-- Add up migration script here
+CREATE TABLE webhook (
+   id serial PRIMARY KEY,
+   url TEXT NOT NULL,
+   secret TEXT NOT NULL,
+   event_types TEXT[] NOT NULL DEFAULT '{}',
+   is_active BOOLEAN NOT NULL DEFAULT true,
+   created_at TIMESTAMPTZ NOT NULL DEFAULT NOW(),
+   last_modified TIMESTAMPTZ NOT NULL DEFAULT NOW()
+);
+
+CREATE TYPE webhook_delivery_status AS ENUM ('pending', 'succeeded', 'failed');
+
+CREATE TABLE webhook_delivery (
+   id bigserial PRIMARY KEY,
+   webhook_id INTEGER NOT NULL,
+   event_type TEXT NOT NULL,
+   payload JSONB NOT NULL,
+   status webhook_delivery_status NOT NULL DEFAULT 'pending',
+   attempts INTEGER NOT NULL DEFAULT 0,
+   next_attempt_at TIMESTAMPTZ NOT NULL DEFAULT NOW(),
+   response_status INTEGER,
+   error TEXT,
+   created_at TIMESTAMPTZ NOT NULL DEFAULT NOW(),
+   last_modified TIMESTAMPTZ NOT NULL DEFAULT NOW(),
+   CONSTRAINT fk_webhook
+      FOREIGN KEY(webhook_id)
+	    REFERENCES webhook(id)
+        ON DELETE CASCADE
+);
+
+-- Index for the delivery log of a webhook
+CREATE INDEX idx_webhook_delivery_webhook_id ON webhook_delivery(webhook_id, created_at DESC);
+
+-- Index for finding deliveries that are due
+CREATE INDEX idx_webhook_delivery_pending ON webhook_delivery(next_attempt_at) WHERE status = 'pending';
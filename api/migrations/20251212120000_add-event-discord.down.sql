@@ -0,0 +1,3 @@
+-- Add down migration script here
+DROP TABLE event_discord_notice;
+DROP TABLE event_discord_config;
@@ -0,0 +1,29 @@
+-- Add up migration script here
+CREATE TABLE event_discord_config (
+   event_id INTEGER PRIMARY KEY,
+   webhook_url TEXT NOT NULL,
+   is_enabled BOOLEAN NOT NULL DEFAULT true,
+   notify_rsvps BOOLEAN NOT NULL DEFAULT true,
+   notify_top_game BOOLEAN NOT NULL DEFAULT true,
+   notify_schedule BOOLEAN NOT NULL DEFAULT true,
+   schedule_lead_minutes INTEGER NOT NULL DEFAULT 15,
+   top_game_id BIGINT,
+   created_at TIMESTAMPTZ NOT NULL DEFAULT NOW(),
+   last_modified TIMESTAMPTZ NOT NULL DEFAULT NOW(),
+   CONSTRAINT fk_event
+      FOREIGN KEY(event_id)
+	    REFERENCES event(id)
+        ON DELETE CASCADE
+);
+
+-- Messages already posted to Discord, so each announcement is only made once
+CREATE TABLE event_discord_notice (
+   event_id INTEGER NOT NULL,
+   notice_key TEXT NOT NULL,
+   sent_at TIMESTAMPTZ NOT NULL DEFAULT NOW(),
+   PRIMARY KEY (event_id, notice_key),
+   CONSTRAINT fk_event
+      FOREIGN KEY(event_id)
+	    REFERENCES event(id)
+        ON DELETE CASCADE
+);
@@ -0,0 +1,15 @@
+-- Add down migration script here
+DROP INDEX idx_steam_game_update_running;
+
+ALTER TABLE steam_game_update
+   DROP COLUMN status,
+   DROP COLUMN triggered_by,
+   DROP COLUMN pages_fetched,
+   DROP COLUMN apps_fetched,
+   DROP COLUMN rows_upserted,
+   DROP COLUMN error_count,
+   DROP COLUMN last_error,
+   DROP COLUMN finished_at,
+   DROP COLUMN last_modified;
+
+DROP TYPE steam_game_update_status;
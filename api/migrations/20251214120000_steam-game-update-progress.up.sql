@@ -0,0 +1,22 @@
+-- Add up migration script here
+CREATE TYPE steam_game_update_status AS ENUM ('running', 'succeeded', 'failed');
+
+ALTER TABLE steam_game_update
+   ADD COLUMN status steam_game_update_status NOT NULL DEFAULT 'succeeded',
+   ADD COLUMN triggered_by VARCHAR(255) NULL,
+   ADD COLUMN pages_fetched INT NOT NULL DEFAULT 0,
+   ADD COLUMN apps_fetched INT NOT NULL DEFAULT 0,
+   ADD COLUMN rows_upserted INT NOT NULL DEFAULT 0,
+   ADD COLUMN error_count INT NOT NULL DEFAULT 0,
+   ADD COLUMN last_error TEXT NULL,
+   ADD COLUMN finished_at TIMESTAMPTZ NULL,
+   ADD COLUMN last_modified TIMESTAMPTZ NOT NULL DEFAULT NOW();
+
+-- Updates from before progress was tracked ran to completion inside their request
+UPDATE steam_game_update
+SET finished_at = update_time, last_modified = update_time;
+
+ALTER TABLE steam_game_update ALTER COLUMN status SET DEFAULT 'running';
+
+-- Only one update can be running at a time
+CREATE UNIQUE INDEX idx_steam_game_update_running ON steam_game_update(status) WHERE status = 'running';
@@ -0,0 +1,9 @@
+-- Add down migration script here
+ALTER TABLE steam_game DROP COLUMN removed_at;
+
+ALTER TABLE steam_game_update
+   DROP COLUMN modified_since,
+   DROP COLUMN last_appid,
+   DROP COLUMN apps_added,
+   DROP COLUMN apps_renamed,
+   DROP COLUMN apps_removed;
@@ -0,0 +1,10 @@
+-- Add up migration script here
+ALTER TABLE steam_game_update
+   ADD COLUMN modified_since TIMESTAMPTZ NULL,
+   ADD COLUMN last_appid BIGINT NOT NULL DEFAULT 0,
+   ADD COLUMN apps_added INT NOT NULL DEFAULT 0,
+   ADD COLUMN apps_renamed INT NOT NULL DEFAULT 0,
+   ADD COLUMN apps_removed INT NOT NULL DEFAULT 0;
+
+-- Games that are no longer in the Steam app list are kept, as events may refer to them
+ALTER TABLE steam_game ADD COLUMN removed_at TIMESTAMPTZ NULL;
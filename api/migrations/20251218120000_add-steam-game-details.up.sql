@@ -0,0 +1,24 @@
+-- Add up migration script here
+CREATE TABLE steam_game_details (
+   appid BIGINT PRIMARY KEY,
+   is_available BOOLEAN NOT NULL DEFAULT true,
+   header_image TEXT NULL,
+   short_description TEXT NULL,
+   category_ids INT[] NOT NULL DEFAULT '{}',
+   categories TEXT[] NOT NULL DEFAULT '{}',
+   genres TEXT[] NOT NULL DEFAULT '{}',
+   is_windows BOOLEAN NOT NULL DEFAULT false,
+   is_mac BOOLEAN NOT NULL DEFAULT false,
+   is_linux BOOLEAN NOT NULL DEFAULT false,
+   is_free BOOLEAN NOT NULL DEFAULT false,
+   price_currency VARCHAR(3) NULL,
+   price_final INT NULL,
+   price_formatted TEXT NULL,
+   release_date TEXT NULL,
+   is_coming_soon BOOLEAN NOT NULL DEFAULT false,
+   fetched_at TIMESTAMPTZ NOT NULL DEFAULT NOW(),
+   CONSTRAINT fk_steam_game
+      FOREIGN KEY(appid)
+	    REFERENCES steam_game(appid)
+        ON DELETE CASCADE
+);
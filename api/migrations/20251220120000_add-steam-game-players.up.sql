@@ -0,0 +1,18 @@
+-- Add up migration script here
+CREATE TABLE steam_game_players (
+   appid BIGINT PRIMARY KEY,
+   min_players INT NULL,
+   max_players INT NULL,
+   has_lan BOOLEAN NOT NULL DEFAULT false,
+   has_split_screen BOOLEAN NOT NULL DEFAULT false,
+   -- Set once an admin has edited the counts, so they aren't replaced by ones guessed from Steam
+   is_manual BOOLEAN NOT NULL DEFAULT false,
+   modified_by VARCHAR(255) NULL,
+   last_modified TIMESTAMPTZ NOT NULL DEFAULT NOW(),
+   CONSTRAINT fk_steam_game
+      FOREIGN KEY(appid)
+	    REFERENCES steam_game(appid)
+        ON DELETE CASCADE,
+   CONSTRAINT min_players_positive CHECK (min_players >= 1),
+   CONSTRAINT max_players_at_least_min CHECK (max_players >= min_players)
+);
@@ -0,0 +1,15 @@
+-- Add down migration script here
+DELETE FROM steam_game WHERE source = 'custom';
+
+ALTER TABLE steam_game
+   DROP CONSTRAINT steam_game_source_update,
+   DROP CONSTRAINT steam_game_source_appid,
+   DROP COLUMN created_by,
+   DROP COLUMN image_url,
+   DROP COLUMN url,
+   DROP COLUMN source,
+   ALTER COLUMN update_id SET NOT NULL;
+
+DROP SEQUENCE custom_game_appid_seq;
+
+DROP TYPE game_source;
@@ -0,0 +1,15 @@
+-- Add up migration script here
+CREATE TYPE game_source AS ENUM ('steam', 'custom');
+
+-- Custom games live alongside Steam games, so they can be suggested, voted on and scheduled the same way.
+-- They count down from -1 so they never clash with Steam app IDs.
+CREATE SEQUENCE custom_game_appid_seq AS BIGINT INCREMENT BY -1 MINVALUE -9223372036854775808 MAXVALUE -1 START WITH -1;
+
+ALTER TABLE steam_game
+   ALTER COLUMN update_id DROP NOT NULL,
+   ADD COLUMN source game_source NOT NULL DEFAULT 'steam',
+   ADD COLUMN url TEXT NULL,
+   ADD COLUMN image_url TEXT NULL,
+   ADD COLUMN created_by VARCHAR(255) NULL,
+   ADD CONSTRAINT steam_game_source_appid CHECK ((source = 'steam') = (appid >= 0)),
+   ADD CONSTRAINT steam_game_source_update CHECK ((source = 'steam') = (update_id IS NOT NULL));
@@ -0,0 +1,4 @@
+-- Add down migration script here
+DROP TABLE event_game_ownership;
+
+DROP TYPE game_ownership;
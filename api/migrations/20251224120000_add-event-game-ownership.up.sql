@@ -0,0 +1,21 @@
+-- Add up migration script here
+CREATE TYPE game_ownership AS ENUM ('owned', 'notowned', 'installed');
+
+-- What attendees say about owning a game for an event, which takes priority over their Steam library
+CREATE TABLE event_game_ownership (
+   event_id INT NOT NULL,
+   game_id BIGINT NOT NULL,
+   email VARCHAR(255) NOT NULL,
+   ownership game_ownership NOT NULL,
+   last_modified TIMESTAMPTZ NOT NULL DEFAULT NOW(),
+   CONSTRAINT fk_event
+      FOREIGN KEY(event_id)
+	    REFERENCES event(id)
+        ON DELETE CASCADE,
+   CONSTRAINT fk_steam_game
+      FOREIGN KEY(game_id)
+	    REFERENCES steam_game(appid)
+        ON DELETE CASCADE
+);
+
+CREATE UNIQUE INDEX event_game_ownership_lower_email ON event_game_ownership (event_id, game_id, LOWER(email));
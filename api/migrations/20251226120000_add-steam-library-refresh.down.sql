@@ -0,0 +1,5 @@
+-- Add down migration script here
+ALTER TABLE profiles
+   DROP COLUMN refresh_failure_count,
+   DROP COLUMN refresh_failed_at,
+   DROP COLUMN refresh_error;
@@ -0,0 +1,6 @@
+-- Add up migration script here
+-- Libraries are refreshed in the background, so keep track of why the last attempt failed, such as a private profile
+ALTER TABLE profiles
+   ADD COLUMN refresh_error TEXT NULL,
+   ADD COLUMN refresh_failed_at TIMESTAMPTZ NULL,
+   ADD COLUMN refresh_failure_count INT NOT NULL DEFAULT 0;
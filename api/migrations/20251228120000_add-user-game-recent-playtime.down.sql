@@ -0,0 +1,3 @@
+-- Add down migration script here
+ALTER TABLE user_game
+   DROP COLUMN playtime_2weeks;
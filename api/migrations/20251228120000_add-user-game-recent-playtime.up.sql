@@ -0,0 +1,3 @@
+-- Add up migration script here
+ALTER TABLE user_game
+   ADD COLUMN playtime_2weeks INTEGER NOT NULL DEFAULT 0;
@@ -0,0 +1,8 @@
+-- Add down migration script here
+ALTER TABLE steam_game_details
+   DROP COLUMN app_type;
+
+DROP INDEX IF EXISTS idx_steam_game_name_simple_tsv;
+DROP INDEX IF EXISTS idx_steam_game_name_trgm;
+
+DROP EXTENSION IF EXISTS pg_trgm;
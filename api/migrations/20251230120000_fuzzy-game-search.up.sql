@@ -0,0 +1,12 @@
+-- Add up migration script here
+CREATE EXTENSION IF NOT EXISTS pg_trgm;
+
+-- Typo tolerant matching and prefix matching on game names
+CREATE INDEX IF NOT EXISTS idx_steam_game_name_trgm ON steam_game USING GIN (LOWER(name) gin_trgm_ops);
+
+-- Full text search without English stemming, so numerals and non-English titles match
+CREATE INDEX IF NOT EXISTS idx_steam_game_name_simple_tsv ON steam_game USING GIN (to_tsvector('simple', name));
+
+-- What kind of app the store says it is, such as "game", "dlc" or "music"
+ALTER TABLE steam_game_details
+   ADD COLUMN app_type TEXT NULL;
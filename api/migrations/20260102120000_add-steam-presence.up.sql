@@ -0,0 +1,13 @@
+-- Add up migration script here
+-- What attendees are playing on Steam, polled while their events are running
+CREATE TABLE steam_presence (
+   email VARCHAR(255) NOT NULL,
+   steam_id BIGINT NOT NULL,
+   -- Not a foreign key, as non-Steam games and games we don't know about have IDs too
+   game_id BIGINT NULL,
+   game_name TEXT NULL,
+   started_at TIMESTAMPTZ NULL,
+   last_seen TIMESTAMPTZ NOT NULL DEFAULT NOW()
+);
+
+CREATE UNIQUE INDEX steam_presence_lower_email ON steam_presence (LOWER(email));
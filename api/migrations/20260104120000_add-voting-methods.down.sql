@@ -0,0 +1,8 @@
+-- Add down migration script here
+ALTER TABLE event_game_vote
+   DROP COLUMN rank,
+   DROP COLUMN score;
+
+DROP TABLE IF EXISTS event_voting_config;
+
+DROP TYPE IF EXISTS voting_method;
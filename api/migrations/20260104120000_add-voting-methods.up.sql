@@ -0,0 +1,19 @@
+-- Add up migration script here
+CREATE TYPE voting_method AS ENUM ('approval', 'score', 'instantrunoff', 'borda');
+
+-- How votes on suggested games are counted for an event, approval voting if there's no config
+CREATE TABLE event_voting_config (
+   event_id INT NOT NULL PRIMARY KEY,
+   voting_method voting_method NOT NULL DEFAULT 'approval',
+   created_at TIMESTAMPTZ NOT NULL DEFAULT NOW(),
+   last_modified TIMESTAMPTZ NOT NULL DEFAULT NOW(),
+   CONSTRAINT fk_event
+      FOREIGN KEY(event_id)
+	    REFERENCES event(id)
+        ON DELETE CASCADE
+);
+
+-- Stars for score voting, and preferences for ranked choice voting (1 is the favourite)
+ALTER TABLE event_game_vote
+   ADD COLUMN score SMALLINT NULL CHECK (score BETWEEN 0 AND 5),
+   ADD COLUMN rank SMALLINT NULL CHECK (rank >= 1);
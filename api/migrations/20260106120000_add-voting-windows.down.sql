@@ -0,0 +1,10 @@
+-- Add down migration script here
+DROP TABLE IF EXISTS event_vote_result;
+
+ALTER TABLE event_voting_config
+   DROP CONSTRAINT voting_open_before_close,
+   DROP CONSTRAINT suggestions_open_before_close,
+   DROP COLUMN voting_close_at,
+   DROP COLUMN voting_open_at,
+   DROP COLUMN suggestions_close_at,
+   DROP COLUMN suggestions_open_at;
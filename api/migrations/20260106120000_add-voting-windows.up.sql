@@ -0,0 +1,30 @@
+-- Add up migration script here
+-- When games can be suggested and voted on. Missing times mean there's no limit.
+ALTER TABLE event_voting_config
+   ADD COLUMN suggestions_open_at TIMESTAMPTZ NULL,
+   ADD COLUMN suggestions_close_at TIMESTAMPTZ NULL,
+   ADD COLUMN voting_open_at TIMESTAMPTZ NULL,
+   ADD COLUMN voting_close_at TIMESTAMPTZ NULL,
+   ADD CONSTRAINT suggestions_open_before_close CHECK (suggestions_open_at < suggestions_close_at),
+   ADD CONSTRAINT voting_open_before_close CHECK (voting_open_at < voting_close_at);
+
+-- The final results, counted once voting closes so they don't change afterwards
+CREATE TABLE event_vote_result (
+   event_id INT NOT NULL,
+   game_id BIGINT NOT NULL,
+   position INT NOT NULL,
+   points BIGINT NOT NULL,
+   voter_count BIGINT NOT NULL,
+   eliminated_in_round INT NULL,
+   voting_method voting_method NOT NULL,
+   frozen_at TIMESTAMPTZ NOT NULL DEFAULT NOW(),
+   PRIMARY KEY (event_id, game_id),
+   CONSTRAINT fk_event
+      FOREIGN KEY(event_id)
+	    REFERENCES event(id)
+        ON DELETE CASCADE,
+   CONSTRAINT fk_steam_game
+      FOREIGN KEY(game_id)
+	    REFERENCES steam_game(appid)
+        ON DELETE CASCADE
+);
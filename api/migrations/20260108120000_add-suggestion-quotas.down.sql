@@ -0,0 +1,6 @@
+-- Add down migration script here
+ALTER TABLE steam_game_details
+   DROP COLUMN fullgame_appid;
+
+ALTER TABLE event_voting_config
+   DROP COLUMN max_suggestions_per_attendee;
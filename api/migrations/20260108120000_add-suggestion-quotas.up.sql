@@ -0,0 +1,8 @@
+-- Add up migration script here
+-- The most games each attendee can suggest for an event. Missing means there's no limit.
+ALTER TABLE event_voting_config
+   ADD COLUMN max_suggestions_per_attendee INT NULL CHECK (max_suggestions_per_attendee > 0);
+
+-- The game a DLC, demo or soundtrack belongs to, so suggesting both can be spotted
+ALTER TABLE steam_game_details
+   ADD COLUMN fullgame_appid BIGINT NULL;
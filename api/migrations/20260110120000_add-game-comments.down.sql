@@ -0,0 +1,3 @@
+-- Add down migration script here
+DROP TABLE IF EXISTS event_game_comment_reaction;
+DROP TABLE IF EXISTS event_game_comment;
@@ -0,0 +1,42 @@
+-- Add up migration script here
+-- Discussion threads on suggested games. Deleted comments are kept so replies to them still make sense.
+CREATE TABLE event_game_comment (
+   id SERIAL PRIMARY KEY,
+   event_id INT NOT NULL,
+   game_id BIGINT NOT NULL,
+   parent_id INT NULL,
+   email VARCHAR(255) NOT NULL,
+   body TEXT NOT NULL CHECK (char_length(body) BETWEEN 1 AND 2000),
+   created_at TIMESTAMPTZ NOT NULL DEFAULT NOW(),
+   edited_at TIMESTAMPTZ NULL,
+   deleted_at TIMESTAMPTZ NULL,
+   deleted_by VARCHAR(255) NULL,
+   CONSTRAINT fk_event
+      FOREIGN KEY(event_id)
+	    REFERENCES event(id)
+        ON DELETE CASCADE,
+   CONSTRAINT fk_steam_game
+      FOREIGN KEY(game_id)
+	    REFERENCES steam_game(appid)
+        ON DELETE CASCADE,
+   CONSTRAINT fk_parent
+      FOREIGN KEY(parent_id)
+	    REFERENCES event_game_comment(id)
+        ON DELETE CASCADE
+);
+
+CREATE INDEX idx_event_game_comment_thread ON event_game_comment (event_id, game_id, created_at);
+
+-- Emoji reactions to comments, one of each emoji per person
+CREATE TABLE event_game_comment_reaction (
+   comment_id INT NOT NULL,
+   email VARCHAR(255) NOT NULL,
+   emoji VARCHAR(32) NOT NULL,
+   created_at TIMESTAMPTZ NOT NULL DEFAULT NOW(),
+   CONSTRAINT fk_comment
+      FOREIGN KEY(comment_id)
+	    REFERENCES event_game_comment(id)
+        ON DELETE CASCADE
+);
+
+CREATE UNIQUE INDEX event_game_comment_reaction_lower_email ON event_game_comment_reaction (comment_id, LOWER(email), emoji);
@@ -0,0 +1,7 @@
+-- Add down migration script here
+DROP TABLE event_schedule_config;
+
+ALTER TABLE event_game
+   DROP COLUMN duration_minutes;
+
+DROP TABLE steam_game_duration;
@@ -0,0 +1,33 @@
+-- Add up migration script here
+-- How long a game usually takes, set by an admin
+CREATE TABLE steam_game_duration (
+   appid BIGINT PRIMARY KEY,
+   duration_minutes INT NOT NULL CHECK (duration_minutes > 0),
+   modified_by VARCHAR(255) NULL,
+   last_modified TIMESTAMPTZ NOT NULL DEFAULT NOW(),
+   CONSTRAINT fk_steam_game
+      FOREIGN KEY(appid)
+	    REFERENCES steam_game(appid)
+        ON DELETE CASCADE
+);
+
+-- How long a suggested game should be scheduled for at this event, if it differs from usual
+ALTER TABLE event_game
+   ADD COLUMN duration_minutes INT NULL CHECK (duration_minutes > 0);
+
+-- How games are scheduled for an event, the defaults below if there's no config
+CREATE TABLE event_schedule_config (
+   event_id INT NOT NULL PRIMARY KEY,
+   -- Used for games with no duration set, and no past schedules to go on
+   default_duration_minutes INT NOT NULL DEFAULT 120 CHECK (default_duration_minutes > 0),
+   -- Time left free between games to set up or download the next one
+   setup_buffer_minutes INT NOT NULL DEFAULT 0 CHECK (setup_buffer_minutes >= 0),
+   -- Whether to estimate durations from how long games were pinned for at other events
+   use_past_schedules BOOLEAN NOT NULL DEFAULT true,
+   created_at TIMESTAMPTZ NOT NULL DEFAULT NOW(),
+   last_modified TIMESTAMPTZ NOT NULL DEFAULT NOW(),
+   CONSTRAINT fk_event
+      FOREIGN KEY(event_id)
+	    REFERENCES event(id)
+        ON DELETE CASCADE
+);
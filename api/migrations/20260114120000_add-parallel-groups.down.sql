@@ -0,0 +1,3 @@
+-- Add down migration script here
+ALTER TABLE event_schedule_config
+   DROP COLUMN allow_parallel_groups;
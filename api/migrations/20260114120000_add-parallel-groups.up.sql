@@ -0,0 +1,4 @@
+-- Add up migration script here
+-- Whether a game more people want to play than it has room for can be scheduled for two groups at once
+ALTER TABLE event_schedule_config
+   ADD COLUMN allow_parallel_groups BOOLEAN NOT NULL DEFAULT false;
@@ -0,0 +1,9 @@
+-- Add down migration script here
+ALTER TABLE event_schedule_config
+   DROP COLUMN tracks_from_rooms,
+   DROP COLUMN track_count;
+
+ALTER TABLE event_game_schedule
+   DROP CONSTRAINT fk_room,
+   DROP COLUMN room_id,
+   DROP COLUMN track;
@@ -0,0 +1,14 @@
+-- Add up migration script here
+-- Which track a scheduled game is on when several games are played at once, and the room it's in
+ALTER TABLE event_game_schedule
+   ADD COLUMN track INT NOT NULL DEFAULT 1 CHECK (track >= 1),
+   ADD COLUMN room_id INT NULL,
+   ADD CONSTRAINT fk_room
+      FOREIGN KEY(room_id)
+	    REFERENCES room(id)
+        ON DELETE SET NULL;
+
+-- How many games can be played at once, or one per room if tracks come from the event's rooms
+ALTER TABLE event_schedule_config
+   ADD COLUMN track_count INT NOT NULL DEFAULT 1 CHECK (track_count >= 1),
+   ADD COLUMN tracks_from_rooms BOOLEAN NOT NULL DEFAULT false;
@@ -0,0 +1,4 @@
+-- Add up migration script here
+-- Whether to search for a schedule that suits more voters instead of placing games in vote order
+ALTER TABLE event_schedule_config
+   ADD COLUMN use_optimiser BOOLEAN NOT NULL DEFAULT false;
@@ -0,0 +1,6 @@
+-- Add down migration script here
+DROP TABLE event_blocked_period;
+
+ALTER TABLE event_schedule_config
+   DROP COLUMN quiet_hours_end,
+   DROP COLUMN quiet_hours_start;
@@ -0,0 +1,23 @@
+-- Add up migration script here
+-- Hours of each day (UTC) that games can't be played, none if they start and end at the same time
+ALTER TABLE event_schedule_config
+   ADD COLUMN quiet_hours_start TIME NOT NULL DEFAULT '01:00',
+   ADD COLUMN quiet_hours_end TIME NOT NULL DEFAULT '10:00';
+
+-- Other times no games can be played, such as dinner or the venue being closed
+CREATE TABLE event_blocked_period (
+   id SERIAL PRIMARY KEY,
+   event_id INT NOT NULL,
+   name VARCHAR(255) NOT NULL,
+   start_time TIMESTAMPTZ NOT NULL,
+   end_time TIMESTAMPTZ NOT NULL,
+   created_at TIMESTAMPTZ NOT NULL DEFAULT NOW(),
+   last_modified TIMESTAMPTZ NOT NULL DEFAULT NOW(),
+   CHECK (end_time > start_time),
+   CONSTRAINT fk_event
+      FOREIGN KEY(event_id)
+	    REFERENCES event(id)
+        ON DELETE CASCADE
+);
+
+CREATE INDEX idx_event_blocked_period_event_id ON event_blocked_period(event_id);
@@ -0,0 +1,2 @@
+-- Add down migration script here
+DROP INDEX idx_steam_game_custom_name;
@@ -0,0 +1,14 @@
+-- Add up migration script here
+-- Custom game names were only checked before inserting, so two could be added with the same name at once.
+-- Keep the first one added (custom app IDs count down) and tell later ones apart by their app ID.
+UPDATE steam_game
+SET name = name || ' (' || appid || ')', last_modified = NOW()
+WHERE source = 'custom'
+AND appid NOT IN (
+   SELECT MAX(appid)
+   FROM steam_game
+   WHERE source = 'custom'
+   GROUP BY LOWER(name)
+);
+
+CREATE UNIQUE INDEX idx_steam_game_custom_name ON steam_game (LOWER(name)) WHERE source = 'custom';
@@ -0,0 +1,3 @@
+-- Add down migration script here
+ALTER TABLE event_game_comment
+   DROP CONSTRAINT fk_event_game;
@@ -0,0 +1,15 @@
+-- Add up migration script here
+-- Remove the discussion about a game when its suggestion is removed, rather than leaving it behind
+DELETE FROM event_game_comment
+WHERE NOT EXISTS (
+   SELECT 1
+   FROM event_game
+   WHERE event_game.event_id = event_game_comment.event_id
+   AND event_game.game_id = event_game_comment.game_id
+);
+
+ALTER TABLE event_game_comment
+   ADD CONSTRAINT fk_event_game
+      FOREIGN KEY(event_id, game_id)
+	    REFERENCES event_game(event_id, game_id)
+        ON DELETE CASCADE;
@@ -6,7 +6,7 @@ use sqlx::PgPool;
 use std::collections::hash_map::DefaultHasher;
 use std::hash::{Hash, Hasher};
 
-use crate::repositories::audit_log;
+use crate::repositories::{audit_log, game_comment};
 
 #[derive(Debug, Clone)]
 pub struct ActivityTickerEvent {
@@ -34,7 +34,7 @@ impl std::fmt::Display for Error {
 }
 
 /// Get a consistent phrase from a list based on a hash of the event ID
-fn get_phrase<'a, T: Hash>(phrases: &'a [&str], seed: &T) -> &'a str {
+pub fn get_phrase<'a, T: Hash>(phrases: &'a [&str], seed: &T) -> &'a str {
     let mut hasher = DefaultHasher::new();
     seed.hash(&mut hasher);
     let hash = hasher.finish();
@@ -109,6 +109,8 @@ async fn get_events_for_period(
                 OR (al.action = 'game_suggestion.create' AND al.entity_type = 'game_suggestion' AND al.entity_id LIKE $2)
                 OR (al.action = 'game_vote.update' AND al.entity_type = 'game_vote' AND al.entity_id LIKE $2)
                 OR (al.action = 'seat_reservation.create' AND al.entity_type = 'seat_reservation' AND al.entity_id = $1::text)
+                OR (al.action IN ('presence.start', 'presence.group') AND al.entity_type = 'presence' AND al.entity_id LIKE $2)
+                OR (al.action = 'game_comment.create' AND al.entity_type = 'game_comment' AND al.entity_id LIKE $2)
             )
             ORDER BY al.timestamp DESC
             LIMIT $3
@@ -138,6 +140,8 @@ async fn get_events_for_period(
                 OR (al.action = 'game_suggestion.create' AND al.entity_type = 'game_suggestion' AND al.entity_id LIKE $2)
                 OR (al.action = 'game_vote.update' AND al.entity_type = 'game_vote' AND al.entity_id LIKE $2)
                 OR (al.action = 'seat_reservation.create' AND al.entity_type = 'seat_reservation' AND al.entity_id = $1::text)
+                OR (al.action IN ('presence.start', 'presence.group') AND al.entity_type = 'presence' AND al.entity_id LIKE $2)
+                OR (al.action = 'game_comment.create' AND al.entity_type = 'game_comment' AND al.entity_id LIKE $2)
             )
             AND al.timestamp >= $3
             ORDER BY al.timestamp DESC
@@ -174,7 +178,7 @@ async fn get_user_handle(pool: &PgPool, event_id: i32, user_id: &str) -> Option<
 }
 
 /// Format a raw audit log event into a user-friendly ticker event
-async fn format_ticker_event(
+pub async fn format_ticker_event(
     pool: &PgPool,
     event_id: i32,
     event: &audit_log::AuditLog,
@@ -185,6 +189,9 @@ async fn format_ticker_event(
         "game_suggestion.create" => format_game_suggestion_event(pool, event_id, event).await,
         "game_vote.update" => format_game_vote_event(pool, event_id, event).await,
         "seat_reservation.create" => format_seat_reservation_event(pool, event_id, event).await,
+        "presence.start" => format_presence_start_event(pool, event_id, event).await,
+        "presence.group" => format_presence_group_event(event),
+        "game_comment.create" => format_game_comment_event(pool, event_id, event).await,
         _ => None,
     }
 }
@@ -455,6 +462,84 @@ async fn format_game_vote_event(
     })
 }
 
+#[allow(
+    clippy::literal_string_with_formatting_args,
+    clippy::option_if_let_else
+)]
+async fn format_game_comment_event(
+    pool: &PgPool,
+    event_id: i32,
+    event: &audit_log::AuditLog,
+) -> Option<ActivityTickerEvent> {
+    let metadata = event.metadata.as_ref()?;
+    let game_name = metadata.get("game_name")?.as_str()?;
+    let body = metadata.get("body")?.as_str()?;
+    let is_reply = metadata
+        .get("parent_id")
+        .is_some_and(|parent_id| !parent_id.is_null());
+    let game_id = metadata.get("game_id").and_then(resend_rs::Value::as_i64);
+    let comment_id = i32::try_from(metadata.get("comment_id")?.as_i64()?).ok()?;
+
+    // Leave out comments that have since been deleted
+    match game_comment::get(pool, comment_id).await {
+        Ok(Some(comment)) if comment.deleted_at.is_none() => (),
+        _ => return None,
+    }
+
+    // Get user handle
+    let user_handle = if let Some(user_id) = &event.user_id {
+        get_user_handle(pool, event_id, user_id).await
+    } else {
+        None
+    };
+
+    // Generate avatar URL only if we have user_id (email)
+    let user_avatar_url = event.user_id.as_ref().map(|email| {
+        let digest = md5::compute(email.to_lowercase().as_bytes());
+        format!("https://www.gravatar.com/avatar/{digest:x}?d=robohash")
+    });
+
+    let display_name = user_handle.clone().unwrap_or_else(|| "Someone".to_string());
+
+    // UTF-8 safe truncation using character count instead of byte index
+    let truncated_body = if body.chars().count() > 50 {
+        format!("{}...", body.chars().take(50).collect::<String>())
+    } else {
+        body.to_string()
+    };
+
+    // Phrase variations for comments and replies
+    let phrases = if is_reply {
+        [
+            "{name} replied about '{game}': \"{comment}\"",
+            "{name} joined the '{game}' discussion: \"{comment}\"",
+            "{name} chimed in on '{game}': \"{comment}\"",
+        ]
+    } else {
+        [
+            "{name} commented on '{game}': \"{comment}\"",
+            "{name} has thoughts on '{game}': \"{comment}\"",
+            "{name} started talking about '{game}': \"{comment}\"",
+        ]
+    };
+    let template = get_phrase(&phrases, &event.id);
+    let message = template
+        .replace("{name}", &display_name)
+        .replace("{game}", game_name)
+        .replace("{comment}", &truncated_body);
+
+    Some(ActivityTickerEvent {
+        id: event.id,
+        timestamp: event.timestamp,
+        message,
+        icon: "💬".to_string(),
+        event_type: "game_comment".to_string(),
+        user_handle,
+        user_avatar_url,
+        game_id,
+    })
+}
+
 #[allow(
     clippy::literal_string_with_formatting_args,
     clippy::option_if_let_else
@@ -505,3 +590,85 @@ async fn format_seat_reservation_event(
         game_id: None,
     })
 }
+
+#[allow(
+    clippy::literal_string_with_formatting_args,
+    clippy::option_if_let_else
+)]
+async fn format_presence_start_event(
+    pool: &PgPool,
+    event_id: i32,
+    event: &audit_log::AuditLog,
+) -> Option<ActivityTickerEvent> {
+    let metadata = event.metadata.as_ref()?;
+    let game_name = metadata.get("game_name")?.as_str()?;
+    let game_id = metadata.get("game_id").and_then(resend_rs::Value::as_i64);
+
+    // Get user handle
+    let user_handle = if let Some(user_id) = &event.user_id {
+        get_user_handle(pool, event_id, user_id).await
+    } else {
+        None
+    };
+
+    // Generate avatar URL only if we have user_id (email)
+    let user_avatar_url = event.user_id.as_ref().map(|email| {
+        let digest = md5::compute(email.to_lowercase().as_bytes());
+        format!("https://www.gravatar.com/avatar/{digest:x}?d=robohash")
+    });
+
+    let display_name = user_handle.clone().unwrap_or_else(|| "Someone".to_string());
+
+    // Phrase variations for starting a game
+    let phrases = [
+        "{name} started playing '{game}' 🕹️",
+        "{name} jumped into '{game}'!",
+        "{name} is now playing '{game}'",
+        "{name} fired up '{game}'!",
+    ];
+    let template = get_phrase(&phrases, &event.id);
+    let message = template
+        .replace("{name}", &display_name)
+        .replace("{game}", game_name);
+
+    Some(ActivityTickerEvent {
+        id: event.id,
+        timestamp: event.timestamp,
+        message,
+        icon: "🕹️".to_string(),
+        event_type: "presence_start".to_string(),
+        user_handle,
+        user_avatar_url,
+        game_id,
+    })
+}
+
+#[allow(clippy::literal_string_with_formatting_args)]
+fn format_presence_group_event(event: &audit_log::AuditLog) -> Option<ActivityTickerEvent> {
+    let metadata = event.metadata.as_ref()?;
+    let game_name = metadata.get("game_name")?.as_str()?;
+    let player_count = metadata.get("player_count")?.as_i64()?;
+    let game_id = metadata.get("game_id").and_then(resend_rs::Value::as_i64);
+
+    // Phrase variations for a group playing together
+    let phrases = [
+        "{count} people are playing '{game}' together! 🔥",
+        "'{game}' is popular right now, with {count} playing!",
+        "{count} gamers have piled into '{game}'!",
+    ];
+    let template = get_phrase(&phrases, &event.id);
+    let message = template
+        .replace("{count}", &player_count.to_string())
+        .replace("{game}", game_name);
+
+    Some(ActivityTickerEvent {
+        id: event.id,
+        timestamp: event.timestamp,
+        message,
+        icon: "🔥".to_string(),
+        event_type: "presence_group".to_string(),
+        user_handle: None,
+        user_avatar_url: None,
+        game_id,
+    })
+}
@@ -0,0 +1,315 @@
+use rocket::serde::json::serde_json::json;
+use sqlx::PgPool;
+
+use crate::{
+    controllers::{game as game_controller, Error},
+    repositories::{game, steam_game_players},
+    routes::custom_games::{CustomGame, CustomGameSubmit},
+};
+
+const MAX_NAME_LENGTH: usize = 200;
+
+/// Check a custom game has a usable name and links, returning the trimmed name
+fn validate(submit: &CustomGameSubmit) -> Result<String, Error> {
+    let name = submit.name.trim();
+    if name.is_empty() {
+        return Err(Error::BadInput("Game name is required".to_string()));
+    }
+    if name.chars().count() > MAX_NAME_LENGTH {
+        return Err(Error::BadInput(format!(
+            "Game name must be at most {MAX_NAME_LENGTH} characters"
+        )));
+    }
+
+    for (field, url) in [("url", &submit.url), ("image URL", &submit.image_url)] {
+        if let Some(url) = url {
+            if !is_web_url(url) {
+                return Err(Error::BadInput(format!(
+                    "Game {field} must be an http or https link"
+                )));
+            }
+        }
+    }
+
+    game_controller::validate_players(submit.min_players, submit.max_players)?;
+
+    Ok(name.to_string())
+}
+
+fn is_web_url(url: &str) -> bool {
+    reqwest::Url::parse(url).is_ok_and(|url| matches!(url.scheme(), "http" | "https"))
+}
+
+fn name_taken(name: &str) -> Error {
+    Error::Conflict(format!("A custom game called {name} already exists"))
+}
+
+/// Make sure no other custom game already has this name, ignoring case.
+/// The database enforces this too, for games added with the same name at the same time.
+async fn check_name_available(pool: &PgPool, name: &str, appid: Option<i64>) -> Result<(), Error> {
+    match game::find_custom_by_name(pool, name).await {
+        Ok(Some(existing)) if Some(existing.appid) != appid => Err(name_taken(&existing.name)),
+        Ok(_) => Ok(()),
+        Err(e) => Err(Error::Controller(format!(
+            "Unable to check for existing custom games due to: {e}"
+        ))),
+    }
+}
+
+async fn save_players(
+    executor: impl sqlx::PgExecutor<'_>,
+    appid: i64,
+    submit: &CustomGameSubmit,
+    user_email: String,
+) -> Result<steam_game_players::SteamGamePlayers, Error> {
+    steam_game_players::upsert(
+        executor,
+        appid,
+        submit.min_players,
+        submit.max_players,
+        submit.has_lan,
+        submit.has_split_screen,
+        user_email,
+    )
+    .await
+    .map_err(|e| Error::Controller(format!("Unable to save custom game players due to: {e}")))
+}
+
+fn to_response(game: game::Game, players: steam_game_players::SteamGamePlayers) -> CustomGame {
+    CustomGame {
+        appid: game.appid,
+        name: game.name,
+        url: game.url,
+        image_url: game.image_url,
+        players: Some(players.into()),
+        last_modified: game.last_modified,
+    }
+}
+
+/// Add a game that isn't on Steam
+pub async fn create(
+    pool: &PgPool,
+    submit: CustomGameSubmit,
+    user_email: String,
+) -> Result<CustomGame, Error> {
+    let name = validate(&submit)?;
+    check_name_available(pool, &name, None).await?;
+
+    // Save the game and its players together, so a game is never left without them
+    let mut transaction = pool
+        .begin()
+        .await
+        .map_err(|e| Error::Controller(format!("Unable to create custom game due to: {e}")))?;
+
+    let game = match game::create_custom(
+        &mut *transaction,
+        name.clone(),
+        submit.url.clone(),
+        submit.image_url.clone(),
+        user_email.clone(),
+    )
+    .await
+    {
+        Ok(game) => game,
+        Err(sqlx::Error::Database(e)) if e.is_unique_violation() => return Err(name_taken(&name)),
+        Err(e) => {
+            return Err(Error::Controller(format!(
+                "Unable to create custom game due to: {e}"
+            )))
+        }
+    };
+    let players = save_players(&mut *transaction, game.appid, &submit, user_email.clone()).await?;
+
+    transaction
+        .commit()
+        .await
+        .map_err(|e| Error::Controller(format!("Unable to create custom game due to: {e}")))?;
+
+    // Log audit entry
+    let metadata = json!({
+        "appid": game.appid,
+        "name": game.name,
+        "url": game.url,
+        "min_players": players.min_players,
+        "max_players": players.max_players,
+    });
+    crate::util::log_audit(
+        pool,
+        Some(user_email),
+        "custom_game.create".to_string(),
+        "steam_game".to_string(),
+        Some(game.appid.to_string()),
+        Some(metadata),
+    )
+    .await;
+
+    Ok(to_response(game, players))
+}
+
+/// Update a custom game. Only the person who added it can, unless they're an admin.
+pub async fn update(
+    pool: &PgPool,
+    appid: i64,
+    submit: CustomGameSubmit,
+    user_email: String,
+    is_admin: bool,
+) -> Result<CustomGame, Error> {
+    let not_found = || Error::NotFound(format!("Custom game {appid} not found"));
+
+    if !game_controller::is_custom_appid(appid) {
+        return Err(not_found());
+    }
+
+    let existing = match game::get_many(pool, &[appid]).await {
+        Ok(games) => games.into_iter().next().ok_or_else(not_found)?,
+        Err(e) => {
+            return Err(Error::Controller(format!(
+                "Unable to get custom game due to: {e}"
+            )))
+        }
+    };
+
+    let is_creator = existing
+        .created_by
+        .as_ref()
+        .is_some_and(|created_by| created_by.eq_ignore_ascii_case(&user_email));
+    if !is_admin && !is_creator {
+        return Err(Error::NotPermitted(
+            "Only the person who added a custom game can update it".to_string(),
+        ));
+    }
+
+    let name = validate(&submit)?;
+    check_name_available(pool, &name, Some(appid)).await?;
+
+    let mut transaction = pool
+        .begin()
+        .await
+        .map_err(|e| Error::Controller(format!("Unable to update custom game due to: {e}")))?;
+
+    let game = match game::update_custom(
+        &mut *transaction,
+        appid,
+        name.clone(),
+        submit.url.clone(),
+        submit.image_url.clone(),
+    )
+    .await
+    {
+        Ok(Some(game)) => game,
+        Ok(None) => return Err(not_found()),
+        Err(sqlx::Error::Database(e)) if e.is_unique_violation() => return Err(name_taken(&name)),
+        Err(e) => {
+            return Err(Error::Controller(format!(
+                "Unable to update custom game due to: {e}"
+            )))
+        }
+    };
+    let players = save_players(&mut *transaction, appid, &submit, user_email.clone()).await?;
+
+    transaction
+        .commit()
+        .await
+        .map_err(|e| Error::Controller(format!("Unable to update custom game due to: {e}")))?;
+
+    // Log audit entry
+    let metadata = json!({
+        "appid": appid,
+        "old_name": existing.name,
+        "name": game.name,
+        "url": game.url,
+        "min_players": players.min_players,
+        "max_players": players.max_players,
+    });
+    crate::util::log_audit(
+        pool,
+        Some(user_email),
+        "custom_game.update".to_string(),
+        "steam_game".to_string(),
+        Some(appid.to_string()),
+        Some(metadata),
+    )
+    .await;
+
+    Ok(to_response(game, players))
+}
+
+/// Delete a custom game. Suggestions, votes and schedule entries for it are deleted with it.
+pub async fn delete(pool: &PgPool, appid: i64, user_email: String) -> Result<(), Error> {
+    match game::delete_custom(pool, appid).await {
+        Ok(true) => {}
+        Ok(false) => return Err(Error::NotFound(format!("Custom game {appid} not found"))),
+        Err(e) => {
+            return Err(Error::Controller(format!(
+                "Unable to delete custom game due to: {e}"
+            )))
+        }
+    }
+
+    // Log audit entry
+    crate::util::log_audit(
+        pool,
+        Some(user_email),
+        "custom_game.delete".to_string(),
+        "steam_game".to_string(),
+        Some(appid.to_string()),
+        None,
+    )
+    .await;
+
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn submit(name: &str) -> CustomGameSubmit {
+        CustomGameSubmit {
+            name: name.to_string(),
+            url: None,
+            image_url: None,
+            min_players: None,
+            max_players: None,
+            has_lan: false,
+            has_split_screen: false,
+        }
+    }
+
+    #[test]
+    fn test_validate_trims_name() {
+        assert_eq!(validate(&submit("  Catan  ")).unwrap(), "Catan");
+    }
+
+    #[test]
+    fn test_validate_rejects_blank_or_long_names() {
+        assert!(matches!(validate(&submit("   ")), Err(Error::BadInput(_))));
+        assert!(matches!(
+            validate(&submit(&"a".repeat(MAX_NAME_LENGTH + 1))),
+            Err(Error::BadInput(_))
+        ));
+    }
+
+    #[test]
+    fn test_validate_only_allows_web_links() {
+        let mut game = submit("Catan");
+        game.url = Some("https://www.catan.com/".to_string());
+        game.image_url = Some("http://example.com/catan.png".to_string());
+        assert!(validate(&game).is_ok());
+
+        game.url = Some("javascript:alert(1)".to_string());
+        assert!(matches!(validate(&game), Err(Error::BadInput(_))));
+
+        game.url = None;
+        game.image_url = Some("not a url".to_string());
+        assert!(matches!(validate(&game), Err(Error::BadInput(_))));
+    }
+
+    #[test]
+    fn test_validate_checks_players() {
+        let mut game = submit("Catan");
+        game.min_players = Some(3);
+        game.max_players = Some(2);
+        assert!(matches!(validate(&game), Err(Error::BadInput(_))));
+    }
+}
@@ -0,0 +1,514 @@
+use std::collections::HashMap;
+
+use chrono::{DateTime, Duration, Utc};
+use rocket::serde::Serialize;
+use sqlx::PgPool;
+
+use crate::{
+    controllers::{notification_preference, Error},
+    repositories::{
+        event, event_digest, game_schedule, game_suggestion, invitation,
+        notification_preference::NotificationCategory,
+    },
+};
+
+/// How often each recipient receives a digest for an event
+pub const DIGEST_INTERVAL_DAYS: i64 = 7;
+
+/// How many of the most voted games to include
+const VOTE_LEADER_COUNT: usize = 5;
+
+#[derive(Serialize, Clone)]
+#[serde(crate = "rocket::serde")]
+pub struct DigestRsvp {
+    pub handle: String,
+    pub response: String,
+}
+
+#[derive(Serialize, Clone)]
+#[serde(crate = "rocket::serde")]
+pub struct DigestSuggestedGame {
+    pub name: String,
+    pub suggested_by: String,
+    pub comment: Option<String>,
+}
+
+#[derive(Serialize, Clone)]
+#[serde(crate = "rocket::serde")]
+pub struct DigestVoteLeader {
+    pub name: String,
+    pub votes: i32,
+}
+
+#[derive(Serialize, Clone)]
+#[serde(crate = "rocket::serde")]
+pub struct DigestScheduledGame {
+    pub name: String,
+    pub start_time: String,
+    pub duration_minutes: i32,
+}
+
+/// Summary of what has happened at an event since a point in time
+#[derive(Serialize, Clone)]
+#[serde(crate = "rocket::serde")]
+pub struct Digest {
+    pub new_rsvps: Vec<DigestRsvp>,
+    pub new_games: Vec<DigestSuggestedGame>,
+    pub vote_leaders: Vec<DigestVoteLeader>,
+    pub schedule: Vec<DigestScheduledGame>,
+}
+
+impl Digest {
+    /// Vote leaders and the schedule are always present once an event gets going,
+    /// so only new RSVPs and suggestions count as something worth emailing about
+    pub const fn has_activity(&self) -> bool {
+        !self.new_rsvps.is_empty() || !self.new_games.is_empty()
+    }
+}
+
+fn handle_or_default(handle: Option<&String>) -> String {
+    handle.map_or_else(|| "Someone".to_string(), Clone::clone)
+}
+
+/// Invitees who have said they are or might be going since `since`, oldest first
+fn new_rsvps(invitations: &[invitation::Invitation], since: DateTime<Utc>) -> Vec<DigestRsvp> {
+    let mut new_rsvps: Vec<&invitation::Invitation> = invitations
+        .iter()
+        .filter(|i| {
+            i.responded_at
+                .is_some_and(|responded_at| responded_at >= since)
+                && matches!(
+                    i.response,
+                    Some(invitation::Response::Yes | invitation::Response::Maybe)
+                )
+        })
+        .collect();
+    new_rsvps.sort_by_key(|i| i.responded_at);
+
+    new_rsvps
+        .into_iter()
+        .map(|i| DigestRsvp {
+            handle: handle_or_default(i.handle.as_ref()),
+            response: match i.response {
+                Some(invitation::Response::Yes) => "going".to_string(),
+                _ => "might be going".to_string(),
+            },
+        })
+        .collect()
+}
+
+/// Games suggested since `since`, oldest first, credited by handle rather than email
+fn new_games(
+    mut suggestions: Vec<game_suggestion::GameSuggestion>,
+    handles: &HashMap<String, Option<String>>,
+    since: DateTime<Utc>,
+) -> Vec<DigestSuggestedGame> {
+    suggestions.retain(|s| s.requested_at >= since);
+    suggestions.sort_by_key(|s| s.requested_at);
+
+    suggestions
+        .into_iter()
+        .map(|s| DigestSuggestedGame {
+            name: s.game_name,
+            suggested_by: handle_or_default(
+                handles
+                    .get(&s.user_email.to_lowercase())
+                    .and_then(Option::as_ref),
+            ),
+            comment: s.comment,
+        })
+        .collect()
+}
+
+/// Build the digest for an event, covering activity since `since`
+pub async fn build(pool: &PgPool, event_id: i32, since: DateTime<Utc>) -> Result<Digest, Error> {
+    let invitations = match invitation::filter(
+        pool,
+        invitation::Filter {
+            event_id: Some(event_id),
+            email: None,
+        },
+    )
+    .await
+    {
+        Ok(invitations) => invitations,
+        Err(e) => {
+            return Err(Error::Controller(format!(
+                "Unable to get event invitations due to: {e}"
+            )))
+        }
+    };
+
+    // Never expose email addresses in the digest, only handles
+    let handles: HashMap<String, Option<String>> = invitations
+        .iter()
+        .map(|i| (i.email.to_lowercase(), i.handle.clone()))
+        .collect();
+
+    let new_rsvps = new_rsvps(&invitations, since);
+
+    let suggestions = match game_suggestion::filter(
+        pool,
+        game_suggestion::Filter {
+            event_id: Some(event_id),
+            game_id: None,
+        },
+        String::new(),
+    )
+    .await
+    {
+        Ok(suggestions) => suggestions,
+        Err(e) => {
+            return Err(Error::Controller(format!(
+                "Unable to get game suggestions due to: {e}"
+            )))
+        }
+    };
+    let new_games = new_games(suggestions, &handles, since);
+
+    let vote_leaders = match game_suggestion::get_games_with_votes(pool, event_id).await {
+        Ok(games) => games
+            .into_iter()
+            .take(VOTE_LEADER_COUNT)
+            .map(|g| DigestVoteLeader {
+                name: g.game_name,
+                votes: g.vote_count.unwrap_or_default(),
+            })
+            .collect(),
+        Err(e) => {
+            return Err(Error::Controller(format!(
+                "Unable to get game votes due to: {e}"
+            )))
+        }
+    };
+
+    let schedule = match game_schedule::filter(
+        pool,
+        game_schedule::Filter {
+            event_id: Some(event_id),
+            is_pinned: None,
+        },
+    )
+    .await
+    {
+        Ok(schedule) => schedule
+            .into_iter()
+            .map(|s| DigestScheduledGame {
+                name: s.game_name,
+                start_time: s.start_time.format("%a %e %b %Y %H:%M").to_string(),
+                duration_minutes: s.duration_minutes,
+            })
+            .collect(),
+        Err(e) => {
+            return Err(Error::Controller(format!(
+                "Unable to get game schedule due to: {e}"
+            )))
+        }
+    };
+
+    Ok(Digest {
+        new_rsvps,
+        new_games,
+        vote_leaders,
+        schedule,
+    })
+}
+
+/// Events that haven't finished yet, which are the only ones digests are sent for
+pub async fn upcoming_events(pool: &PgPool) -> Result<Vec<event::Event>, Error> {
+    match event::index(pool).await {
+        Ok(events) => Ok(events
+            .into_iter()
+            .filter(|e| e.time_end > Utc::now())
+            .collect()),
+        Err(e) => Err(Error::Controller(format!(
+            "Unable to get events due to: {e}"
+        ))),
+    }
+}
+
+/// Get the invitees of an event who have opted in to digests and haven't had one recently.
+/// Invitees who have declined are skipped.
+/// `tolerance` allows a digest to go out slightly early so it doesn't drift later each week.
+pub async fn due_recipients(
+    pool: &PgPool,
+    event_id: i32,
+    tolerance: Duration,
+) -> Result<Vec<String>, Error> {
+    let invitations = match invitation::filter(
+        pool,
+        invitation::Filter {
+            event_id: Some(event_id),
+            email: None,
+        },
+    )
+    .await
+    {
+        Ok(invitations) => invitations,
+        Err(e) => {
+            return Err(Error::Controller(format!(
+                "Unable to get event invitations due to: {e}"
+            )))
+        }
+    };
+
+    let emails = undeclined_emails(invitations);
+
+    let emails =
+        notification_preference::filter_subscribed(pool, emails, NotificationCategory::Digests)
+            .await?;
+
+    let sent = match event_digest::filter(pool, event_id).await {
+        Ok(sent) => sent,
+        Err(e) => {
+            return Err(Error::Controller(format!(
+                "Unable to get sent digests due to: {e}"
+            )))
+        }
+    };
+
+    Ok(due_emails(emails, &sent, due_before(Utc::now(), tolerance)))
+}
+
+/// Recipients last sent a digest at or before this are due another
+fn due_before(now: DateTime<Utc>, tolerance: Duration) -> DateTime<Utc> {
+    now - Duration::days(DIGEST_INTERVAL_DAYS) + tolerance
+}
+
+/// Emails of invitees who haven't declined
+fn undeclined_emails(invitations: Vec<invitation::Invitation>) -> Vec<String> {
+    invitations
+        .into_iter()
+        .filter(|i| i.response != Some(invitation::Response::No))
+        .map(|i| i.email)
+        .collect()
+}
+
+/// Emails that have never been sent a digest, or were last sent one at or before `due_before`
+fn due_emails(
+    emails: Vec<String>,
+    sent: &[event_digest::EventDigest],
+    due_before: DateTime<Utc>,
+) -> Vec<String> {
+    emails
+        .into_iter()
+        .filter(|email| {
+            sent.iter()
+                .find(|digest| digest.email.eq_ignore_ascii_case(email))
+                .is_none_or(|digest| digest.sent_at <= due_before)
+        })
+        .collect()
+}
+
+/// Claim the digest for a recipient before sending it, so that if several instances run the
+/// job only one of them sends it. Returns false if it isn't due or has already been claimed.
+pub async fn claim(
+    pool: &PgPool,
+    event_id: i32,
+    email: &str,
+    tolerance: Duration,
+) -> Result<bool, Error> {
+    match event_digest::claim(pool, event_id, email, due_before(Utc::now(), tolerance)).await {
+        Ok(claimed) => Ok(claimed.is_some()),
+        Err(e) => Err(Error::Controller(format!(
+            "Unable to claim digest due to: {e}"
+        ))),
+    }
+}
+
+/// Release a claimed digest that couldn't be sent, so it is retried on the next check
+pub async fn release(pool: &PgPool, event_id: i32, email: &str) -> Result<(), Error> {
+    match event_digest::delete(pool, event_id, email).await {
+        Ok(_) => Ok(()),
+        Err(e) => Err(Error::Controller(format!(
+            "Unable to release digest due to: {e}"
+        ))),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn invitation(
+        email: &str,
+        handle: Option<&str>,
+        response: Option<invitation::Response>,
+        responded_at: Option<DateTime<Utc>>,
+    ) -> invitation::Invitation {
+        let now = Utc::now();
+        invitation::Invitation {
+            event_id: 1,
+            email: email.to_string(),
+            avatar_url: None,
+            handle: handle.map(ToString::to_string),
+            invited_at: now - Duration::days(30),
+            responded_at,
+            response,
+            attendance: None,
+            last_modified: now,
+        }
+    }
+
+    fn suggestion(
+        game_name: &str,
+        user_email: &str,
+        requested_at: DateTime<Utc>,
+    ) -> game_suggestion::GameSuggestion {
+        game_suggestion::GameSuggestion {
+            event_id: 1,
+            game_id: 730,
+            game_name: game_name.to_string(),
+            user_email: user_email.to_string(),
+            comment: None,
+            self_vote: None,
+            self_score: None,
+            self_rank: None,
+            votes: None,
+            requested_at,
+            last_modified: requested_at,
+        }
+    }
+
+    fn sent(email: &str, sent_at: DateTime<Utc>) -> event_digest::EventDigest {
+        event_digest::EventDigest {
+            event_id: 1,
+            email: email.to_string(),
+            sent_at,
+        }
+    }
+
+    #[test]
+    fn test_only_rsvps_since_the_last_digest_are_included() {
+        let since = Utc::now() - Duration::days(DIGEST_INTERVAL_DAYS);
+        let hour = Duration::hours(1);
+
+        let rsvps = new_rsvps(
+            &[
+                invitation(
+                    "late@example.com",
+                    Some("late"),
+                    Some(invitation::Response::Maybe),
+                    Some(since + hour * 2),
+                ),
+                invitation(
+                    "old@example.com",
+                    Some("old"),
+                    Some(invitation::Response::Yes),
+                    Some(since - hour),
+                ),
+                invitation(
+                    "early@example.com",
+                    None,
+                    Some(invitation::Response::Yes),
+                    Some(since),
+                ),
+                invitation(
+                    "declined@example.com",
+                    Some("declined"),
+                    Some(invitation::Response::No),
+                    Some(since + hour),
+                ),
+                invitation("pending@example.com", Some("pending"), None, None),
+            ],
+            since,
+        );
+
+        assert_eq!(rsvps.len(), 2);
+        assert_eq!(rsvps[0].handle, "Someone");
+        assert_eq!(rsvps[0].response, "going");
+        assert_eq!(rsvps[1].handle, "late");
+        assert_eq!(rsvps[1].response, "might be going");
+    }
+
+    #[test]
+    fn test_only_games_suggested_since_the_last_digest_are_included() {
+        let since = Utc::now() - Duration::days(DIGEST_INTERVAL_DAYS);
+        let hour = Duration::hours(1);
+        let handles = HashMap::from([
+            ("a@example.com".to_string(), Some("alice".to_string())),
+            ("b@example.com".to_string(), None),
+        ]);
+
+        let games = new_games(
+            vec![
+                suggestion("Later", "B@example.com", since + hour * 2),
+                suggestion("Old", "a@example.com", since - hour),
+                suggestion("Sooner", "A@Example.com", since + hour),
+                suggestion("Stranger", "c@example.com", since),
+            ],
+            &handles,
+            since,
+        );
+
+        let names: Vec<&str> = games.iter().map(|g| g.name.as_str()).collect();
+        assert_eq!(names, vec!["Stranger", "Sooner", "Later"]);
+        assert_eq!(games[0].suggested_by, "Someone");
+        assert_eq!(games[1].suggested_by, "alice");
+        assert_eq!(games[2].suggested_by, "Someone");
+    }
+
+    #[test]
+    fn test_declined_invitees_are_not_sent_digests() {
+        let emails = undeclined_emails(vec![
+            invitation(
+                "yes@example.com",
+                None,
+                Some(invitation::Response::Yes),
+                None,
+            ),
+            invitation("no@example.com", None, Some(invitation::Response::No), None),
+            invitation(
+                "maybe@example.com",
+                None,
+                Some(invitation::Response::Maybe),
+                None,
+            ),
+            invitation("pending@example.com", None, None, None),
+        ]);
+
+        assert_eq!(
+            emails,
+            vec![
+                "yes@example.com",
+                "maybe@example.com",
+                "pending@example.com"
+            ]
+        );
+    }
+
+    #[test]
+    fn test_recipients_are_due_once_the_interval_has_passed() {
+        let now = Utc::now();
+        let tolerance = Duration::hours(1);
+        let due_before = due_before(now, tolerance);
+
+        let emails = due_emails(
+            vec![
+                "never@example.com".to_string(),
+                "recent@example.com".to_string(),
+                "early@example.com".to_string(),
+                "Overdue@example.com".to_string(),
+            ],
+            &[
+                sent("recent@example.com", now - Duration::days(1)),
+                // Within the tolerance of a full interval ago, so it doesn't drift later
+                sent(
+                    "early@example.com",
+                    now - Duration::days(DIGEST_INTERVAL_DAYS) + Duration::minutes(30),
+                ),
+                sent("overdue@example.com", now - Duration::days(8)),
+            ],
+            due_before,
+        );
+
+        assert_eq!(
+            emails,
+            vec![
+                "never@example.com",
+                "early@example.com",
+                "Overdue@example.com"
+            ]
+        );
+    }
+}
@@ -0,0 +1,607 @@
+use chrono::{DateTime, Utc};
+use rocket::serde::json::serde_json::json;
+use sqlx::PgPool;
+
+use crate::{
+    controllers::{
+        activity_ticker::{format_ticker_event, get_phrase},
+        Error,
+    },
+    repositories::{
+        audit_log::AuditLog,
+        discord_api::{self, Embed, EmbedAuthor, EmbedField, WebhookMessage},
+        event, event_discord_config, game_suggestion,
+    },
+    routes::event_discord::{EventDiscordConfig, EventDiscordConfigSubmit},
+};
+
+/// Embed colours, matching the icons used in the activity ticker
+const COLOR_ANNOUNCEMENT: u32 = 0x00_9C_DE;
+const COLOR_RSVP: u32 = 0x2E_CC_71;
+const COLOR_TOP_GAME: u32 = 0xF1_C4_0F;
+const COLOR_SCHEDULE: u32 = 0x9B_59_B6;
+
+/// Discord rejects embed descriptions longer than this
+const MAX_DESCRIPTION_LENGTH: usize = 4096;
+
+impl From<event_discord_config::EventDiscordConfig> for EventDiscordConfig {
+    fn from(config: event_discord_config::EventDiscordConfig) -> Self {
+        Self {
+            event_id: config.event_id,
+            webhook_url: config.webhook_url,
+            is_enabled: config.is_enabled,
+            notify_rsvps: config.notify_rsvps,
+            notify_top_game: config.notify_top_game,
+            notify_schedule: config.notify_schedule,
+            schedule_lead_minutes: config.schedule_lead_minutes,
+            created_at: config.created_at,
+            last_modified: config.last_modified,
+        }
+    }
+}
+
+/// Check a URL is a Discord webhook, so event details can't be posted anywhere else
+pub fn is_discord_webhook_url(url: &str) -> bool {
+    let Ok(url) = reqwest::Url::parse(url) else {
+        return false;
+    };
+
+    url.scheme() == "https"
+        && matches!(
+            url.host_str(),
+            Some("discord.com" | "ptb.discord.com" | "canary.discord.com" | "discordapp.com")
+        )
+        && url.path().starts_with("/api/webhooks/")
+}
+
+/// Format a time so Discord shows it in each reader's own timezone
+/// https://discord.com/developers/docs/reference#message-formatting-timestamp-styles
+pub fn discord_timestamp(time: DateTime<Utc>, style: char) -> String {
+    format!("<t:{}:{style}>", time.timestamp())
+}
+
+fn event_url(event_id: i32) -> String {
+    format!("https://calandar.org/events/{event_id}")
+}
+
+/// Get the event ID from an audit log entry about an event, RSVP, game suggestion or vote
+fn audit_event_id(audit_log: &AuditLog) -> Option<i32> {
+    let entity_id = audit_log.entity_id.as_deref()?;
+    match audit_log.entity_type.as_str() {
+        "event" | "rsvp" => entity_id.parse().ok(),
+        "game_suggestion" | "game_vote" => entity_id.split('-').next()?.parse().ok(),
+        _ => None,
+    }
+}
+
+async fn post(webhook_url: &str, embed: Embed) -> Result<(), Error> {
+    let message = WebhookMessage {
+        username: "caLANdar".to_string(),
+        embeds: vec![embed],
+    };
+
+    match discord_api::execute_webhook(webhook_url, &message).await {
+        Ok(()) => Ok(()),
+        Err(e) => Err(Error::Controller(format!(
+            "Unable to post to Discord due to: {e}"
+        ))),
+    }
+}
+
+async fn get_config(
+    pool: &PgPool,
+    event_id: i32,
+) -> Result<Option<event_discord_config::EventDiscordConfig>, Error> {
+    match event_discord_config::get(pool, event_id).await {
+        Ok(config) => Ok(config),
+        Err(e) => Err(Error::Controller(format!(
+            "Unable to get Discord config due to: {e}"
+        ))),
+    }
+}
+
+async fn claim_notice(pool: &PgPool, event_id: i32, notice_key: &str) -> Result<bool, Error> {
+    match event_discord_config::claim_notice(pool, event_id, notice_key).await {
+        Ok(claimed) => Ok(claimed),
+        Err(e) => Err(Error::Controller(format!(
+            "Unable to record Discord notice due to: {e}"
+        ))),
+    }
+}
+
+async fn release_notice(pool: &PgPool, event_id: i32, notice_key: &str) -> Result<(), Error> {
+    match event_discord_config::release_notice(pool, event_id, notice_key).await {
+        Ok(()) => Ok(()),
+        Err(e) => Err(Error::Controller(format!(
+            "Unable to release Discord notice due to: {e}"
+        ))),
+    }
+}
+
+/// Post a notice that has been claimed, releasing the claim if Discord doesn't accept it
+async fn post_claimed(
+    pool: &PgPool,
+    event_id: i32,
+    notice_key: &str,
+    webhook_url: &str,
+    embed: Embed,
+) -> Result<(), Error> {
+    let result = post(webhook_url, embed).await;
+    if result.is_err() {
+        release_notice(pool, event_id, notice_key).await?;
+    }
+    result
+}
+
+pub async fn get(pool: &PgPool, event_id: i32) -> Result<EventDiscordConfig, Error> {
+    get_config(pool, event_id).await?.map_or_else(
+        || {
+            Err(Error::NotFound(format!(
+                "Discord is not configured for event {event_id}"
+            )))
+        },
+        |config| Ok(config.into()),
+    )
+}
+
+pub async fn upsert(
+    pool: &PgPool,
+    event_id: i32,
+    config: EventDiscordConfigSubmit,
+    user_email: String,
+) -> Result<EventDiscordConfig, Error> {
+    if !is_discord_webhook_url(&config.webhook_url) {
+        return Err(Error::BadInput(
+            "Webhook URL must be a Discord webhook, starting https://discord.com/api/webhooks/"
+                .to_string(),
+        ));
+    }
+
+    if !(1..=24 * 60).contains(&config.schedule_lead_minutes) {
+        return Err(Error::BadInput(
+            "Schedule lead time must be between 1 minute and 24 hours".to_string(),
+        ));
+    }
+
+    let config_result = match event_discord_config::upsert(
+        pool,
+        event_id,
+        config.webhook_url,
+        config.is_enabled,
+        config.notify_rsvps,
+        config.notify_top_game,
+        config.notify_schedule,
+        config.schedule_lead_minutes,
+    )
+    .await
+    {
+        Ok(config_result) => config_result,
+        Err(e) => {
+            return Err(Error::Controller(format!(
+                "Unable to save Discord config due to: {e}"
+            )))
+        }
+    };
+
+    // Log audit entry, leaving out the webhook URL as it allows anyone to post
+    let metadata = json!({
+        "event_id": event_id,
+        "is_enabled": config_result.is_enabled,
+        "notify_rsvps": config_result.notify_rsvps,
+        "notify_top_game": config_result.notify_top_game,
+        "notify_schedule": config_result.notify_schedule,
+        "schedule_lead_minutes": config_result.schedule_lead_minutes,
+    });
+    crate::util::log_audit(
+        pool,
+        Some(user_email),
+        "event_discord_config.update".to_string(),
+        "event_discord_config".to_string(),
+        Some(event_id.to_string()),
+        Some(metadata),
+    )
+    .await;
+
+    // Announce the event the first time Discord is turned on for it
+    if config_result.is_enabled && claim_notice(pool, event_id, "announcement").await? {
+        if let Err(e) = announce(pool, event_id).await {
+            log::error!("Error announcing event {event_id} on Discord: {e}");
+            // Announce it next time the config is saved instead
+            release_notice(pool, event_id, "announcement").await?;
+        }
+    }
+
+    Ok(config_result.into())
+}
+
+pub async fn delete(pool: &PgPool, event_id: i32, user_email: String) -> Result<(), Error> {
+    match event_discord_config::delete(pool, event_id).await {
+        Ok(()) => {
+            // Log audit entry
+            let metadata = json!({
+                "event_id": event_id,
+            });
+            crate::util::log_audit(
+                pool,
+                Some(user_email),
+                "event_discord_config.delete".to_string(),
+                "event_discord_config".to_string(),
+                Some(event_id.to_string()),
+                Some(metadata),
+            )
+            .await;
+
+            Ok(())
+        }
+        Err(e) => Err(Error::Controller(format!(
+            "Unable to delete Discord config due to: {e}"
+        ))),
+    }
+}
+
+/// Post the event details to Discord
+pub async fn announce(pool: &PgPool, event_id: i32) -> Result<(), Error> {
+    let Some(config) = get_config(pool, event_id).await? else {
+        return Err(Error::NotFound(format!(
+            "Discord is not configured for event {event_id}"
+        )));
+    };
+
+    if !config.is_enabled {
+        return Err(Error::BadInput(
+            "Discord is turned off for this event".to_string(),
+        ));
+    }
+
+    let event = match event::filter(
+        pool,
+        event::Filter {
+            ids: Some(vec![event_id]),
+        },
+    )
+    .await
+    {
+        Ok(events) => match events.into_iter().next() {
+            Some(event) => event,
+            None => return Err(Error::NotFound(format!("Event {event_id} not found"))),
+        },
+        Err(e) => {
+            return Err(Error::Controller(format!(
+                "Unable to get event due to: {e}"
+            )))
+        }
+    };
+
+    let description = if event.description.chars().count() > MAX_DESCRIPTION_LENGTH {
+        format!(
+            "{}...",
+            event
+                .description
+                .chars()
+                .take(MAX_DESCRIPTION_LENGTH - 3)
+                .collect::<String>()
+        )
+    } else {
+        event.description.clone()
+    };
+
+    post(
+        &config.webhook_url,
+        Embed {
+            title: format!("🎊 {}", event.title),
+            description: Some(description),
+            url: Some(event_url(event_id)),
+            color: Some(COLOR_ANNOUNCEMENT),
+            timestamp: Some(event.last_modified),
+            fields: vec![
+                EmbedField {
+                    name: "Starts".to_string(),
+                    value: discord_timestamp(event.time_begin, 'F'),
+                    inline: true,
+                },
+                EmbedField {
+                    name: "Ends".to_string(),
+                    value: discord_timestamp(event.time_end, 'F'),
+                    inline: true,
+                },
+                EmbedField {
+                    name: "RSVP".to_string(),
+                    value: format!("[Let us know if you're coming]({})", event_url(event_id)),
+                    inline: false,
+                },
+            ],
+            ..Embed::default()
+        },
+    )
+    .await
+}
+
+/// Post to Discord about an audit log entry, if its event has Discord turned on.
+/// Called from `util::log_audit` in the background, so errors are only logged.
+pub async fn on_audit(pool: &PgPool, audit_log: &AuditLog) {
+    let Some(event_id) = audit_event_id(audit_log) else {
+        return;
+    };
+
+    let config = match get_config(pool, event_id).await {
+        Ok(Some(config)) if config.is_enabled => config,
+        Ok(_) => return,
+        Err(e) => {
+            log::error!("{e}");
+            return;
+        }
+    };
+
+    let result = match audit_log.action.as_str() {
+        "rsvp.update" if config.notify_rsvps => post_rsvp(pool, &config, audit_log).await,
+        "game_suggestion.create" | "game_vote.update" if config.notify_top_game => {
+            post_top_game(pool, &config).await
+        }
+        _ => Ok(()),
+    };
+
+    if let Err(e) = result {
+        log::error!(
+            "Error posting {} for event {event_id} to Discord: {e}",
+            audit_log.action
+        );
+    }
+}
+
+async fn post_rsvp(
+    pool: &PgPool,
+    config: &event_discord_config::EventDiscordConfig,
+    audit_log: &AuditLog,
+) -> Result<(), Error> {
+    let Some(metadata) = audit_log.metadata.as_ref() else {
+        return Ok(());
+    };
+
+    if metadata.get("response").and_then(|v| v.as_str()) != Some("Yes") {
+        return Ok(());
+    }
+
+    // Admins can RSVP on someone's behalf
+    let Some(email) = metadata
+        .get("target_email")
+        .and_then(|v| v.as_str())
+        .or(audit_log.user_id.as_deref())
+    else {
+        return Ok(());
+    };
+
+    let Some(ticker_event) = format_ticker_event(pool, config.event_id, audit_log).await else {
+        return Ok(());
+    };
+
+    // Only announce each person once, however often they change their RSVP
+    let notice_key = format!("rsvp:{}", email.to_lowercase());
+    if !claim_notice(pool, config.event_id, &notice_key).await? {
+        return Ok(());
+    }
+
+    post_claimed(
+        pool,
+        config.event_id,
+        &notice_key,
+        &config.webhook_url,
+        Embed {
+            title: format!("{} New RSVP", ticker_event.icon),
+            description: Some(ticker_event.message),
+            url: Some(event_url(config.event_id)),
+            color: Some(COLOR_RSVP),
+            timestamp: Some(ticker_event.timestamp),
+            author: ticker_event.user_handle.map(|handle| EmbedAuthor {
+                name: handle,
+                icon_url: ticker_event.user_avatar_url,
+            }),
+            ..Embed::default()
+        },
+    )
+    .await
+}
+
+#[allow(clippy::literal_string_with_formatting_args)]
+async fn post_top_game(
+    pool: &PgPool,
+    config: &event_discord_config::EventDiscordConfig,
+) -> Result<(), Error> {
+    let games = match game_suggestion::get_games_with_votes(pool, config.event_id).await {
+        Ok(games) => games,
+        Err(e) => {
+            return Err(Error::Controller(format!(
+                "Unable to get game votes due to: {e}"
+            )))
+        }
+    };
+
+    let Some(leader) = games.first() else {
+        return Ok(());
+    };
+
+    if config.top_game_id == Some(leader.game_id) {
+        return Ok(());
+    }
+
+    // A game has to overtake the previous leader, not just draw level with it
+    let previous_votes = config.top_game_id.and_then(|top_game_id| {
+        games
+            .iter()
+            .find(|game| game.game_id == top_game_id)
+            .map(|game| game.vote_count.unwrap_or_default())
+    });
+    let votes = leader.vote_count.unwrap_or_default();
+    if previous_votes.is_some_and(|previous_votes| previous_votes >= votes) {
+        return Ok(());
+    }
+
+    if let Err(e) =
+        event_discord_config::set_top_game(pool, config.event_id, Some(leader.game_id)).await
+    {
+        return Err(Error::Controller(format!(
+            "Unable to save top game due to: {e}"
+        )));
+    }
+
+    let phrases = [
+        "'{game}' is now the most wanted game with {votes} votes! 👑",
+        "'{game}' has taken the lead with {votes} votes!",
+        "Everyone wants to play '{game}', it's top of the votes with {votes}!",
+    ];
+    let message = get_phrase(&phrases, &(config.event_id, leader.game_id))
+        .replace("{game}", &leader.game_name)
+        .replace("{votes}", &votes.to_string());
+
+    let result = post(
+        &config.webhook_url,
+        Embed {
+            title: "👑 New top game".to_string(),
+            description: Some(message),
+            url: Some(event_url(config.event_id)),
+            color: Some(COLOR_TOP_GAME),
+            timestamp: Some(Utc::now()),
+            ..Embed::default()
+        },
+    )
+    .await;
+
+    // Put the previous leader back, so the new one is posted again on the next vote
+    if result.is_err() {
+        if let Err(e) =
+            event_discord_config::set_top_game(pool, config.event_id, config.top_game_id).await
+        {
+            return Err(Error::Controller(format!(
+                "Unable to restore top game due to: {e}"
+            )));
+        }
+    }
+
+    result
+}
+
+/// Post about scheduled games that are about to start, returning how many were posted
+#[allow(clippy::literal_string_with_formatting_args)]
+pub async fn post_starting_games(pool: &PgPool) -> Result<usize, Error> {
+    let games = match event_discord_config::starting_games(pool).await {
+        Ok(games) => games,
+        Err(e) => {
+            return Err(Error::Controller(format!(
+                "Unable to get starting games due to: {e}"
+            )))
+        }
+    };
+
+    let mut posted = 0;
+    for game in games {
+        // Include the start time, so a game that is moved gets announced again
+        let notice_key = format!(
+            "schedule:{}:{}",
+            game.schedule_id,
+            game.start_time.timestamp()
+        );
+        if !claim_notice(pool, game.event_id, &notice_key).await? {
+            continue;
+        }
+
+        let phrases = [
+            "'{game}' is starting {start}, get ready! 🕹️",
+            "Time to load up '{game}', it starts {start}!",
+            "'{game}' kicks off {start}. See you there!",
+        ];
+        let message = get_phrase(&phrases, &game.schedule_id)
+            .replace("{game}", &game.game_name)
+            .replace("{start}", &discord_timestamp(game.start_time, 'R'));
+
+        if let Err(e) = post_claimed(
+            pool,
+            game.event_id,
+            &notice_key,
+            &game.webhook_url,
+            Embed {
+                title: format!("🕹️ {}", game.game_name),
+                description: Some(message),
+                url: Some(event_url(game.event_id)),
+                color: Some(COLOR_SCHEDULE),
+                timestamp: Some(game.start_time),
+                fields: vec![
+                    EmbedField {
+                        name: "Starts".to_string(),
+                        value: discord_timestamp(game.start_time, 't'),
+                        inline: true,
+                    },
+                    EmbedField {
+                        name: "Duration".to_string(),
+                        value: format!("{} minutes", game.duration_minutes),
+                        inline: true,
+                    },
+                ],
+                ..Embed::default()
+            },
+        )
+        .await
+        {
+            log::error!(
+                "Error posting scheduled game {} to Discord: {e}",
+                game.schedule_id
+            );
+            continue;
+        }
+
+        posted += 1;
+    }
+
+    Ok(posted)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use chrono::TimeZone;
+
+    #[test]
+    fn test_is_discord_webhook_url() {
+        assert!(is_discord_webhook_url(
+            "https://discord.com/api/webhooks/123/abc"
+        ));
+        assert!(is_discord_webhook_url(
+            "https://discordapp.com/api/webhooks/123/abc"
+        ));
+        assert!(!is_discord_webhook_url(
+            "http://discord.com/api/webhooks/123/abc"
+        ));
+        assert!(!is_discord_webhook_url(
+            "https://discord.com.example.com/api/webhooks/123/abc"
+        ));
+        assert!(!is_discord_webhook_url("https://discord.com/channels/123"));
+        assert!(!is_discord_webhook_url("not a url"));
+    }
+
+    #[test]
+    fn test_discord_timestamp() {
+        let time = Utc.with_ymd_and_hms(2025, 2, 7, 18, 0, 0).unwrap();
+        assert_eq!(discord_timestamp(time, 'F'), "<t:1738951200:F>");
+    }
+
+    #[test]
+    fn test_audit_event_id() {
+        let mut audit_log = AuditLog {
+            id: 1,
+            timestamp: Utc::now(),
+            user_id: None,
+            action: "rsvp.update".to_string(),
+            entity_type: "rsvp".to_string(),
+            entity_id: Some("12".to_string()),
+            metadata: None,
+            ip_address: None,
+            user_agent: None,
+        };
+        assert_eq!(audit_event_id(&audit_log), Some(12));
+
+        audit_log.entity_type = "game_vote".to_string();
+        audit_log.entity_id = Some("12-730".to_string());
+        assert_eq!(audit_event_id(&audit_log), Some(12));
+
+        audit_log.entity_type = "room".to_string();
+        assert_eq!(audit_event_id(&audit_log), None);
+    }
+}
@@ -0,0 +1,719 @@
+use std::collections::{HashMap, HashSet};
+
+use chrono::{DateTime, Utc};
+use sqlx::PgPool;
+
+use crate::{
+    controllers::Error,
+    repositories::{event_vote_result, event_voting_config, game_suggestion},
+    routes::event_voting::{
+        EventGameRanking, EventVotingConfig, EventVotingConfigSubmit, VoteResults, VoteStanding,
+        VotingMethod,
+    },
+    util::{is_attending_event, is_event_active},
+    voting,
+};
+
+impl From<event_voting_config::VotingMethod> for VotingMethod {
+    fn from(method: event_voting_config::VotingMethod) -> Self {
+        match method {
+            event_voting_config::VotingMethod::Approval => Self::Approval,
+            event_voting_config::VotingMethod::Score => Self::Score,
+            event_voting_config::VotingMethod::InstantRunoff => Self::InstantRunoff,
+            event_voting_config::VotingMethod::Borda => Self::Borda,
+        }
+    }
+}
+
+impl From<VotingMethod> for event_voting_config::VotingMethod {
+    fn from(method: VotingMethod) -> Self {
+        match method {
+            VotingMethod::Approval => Self::Approval,
+            VotingMethod::Score => Self::Score,
+            VotingMethod::InstantRunoff => Self::InstantRunoff,
+            VotingMethod::Borda => Self::Borda,
+        }
+    }
+}
+
+impl From<VotingMethod> for voting::Method {
+    fn from(method: VotingMethod) -> Self {
+        match method {
+            VotingMethod::Approval => Self::Approval,
+            VotingMethod::Score => Self::Score,
+            VotingMethod::InstantRunoff => Self::InstantRunoff,
+            VotingMethod::Borda => Self::Borda,
+        }
+    }
+}
+
+impl From<event_voting_config::EventVotingConfig> for EventVotingConfig {
+    fn from(config: event_voting_config::EventVotingConfig) -> Self {
+        let now = Utc::now();
+        Self {
+            event_id: config.event_id,
+            voting_method: config.voting_method.into(),
+            suggestions_open_at: config.suggestions_open_at,
+            suggestions_close_at: config.suggestions_close_at,
+            voting_open_at: config.voting_open_at,
+            voting_close_at: config.voting_close_at,
+            max_suggestions_per_attendee: config.max_suggestions_per_attendee,
+            suggestions_open: window_state(
+                config.suggestions_open_at,
+                config.suggestions_close_at,
+                now,
+            ) == WindowState::Open,
+            voting_open: window_state(config.voting_open_at, config.voting_close_at, now)
+                == WindowState::Open,
+            created_at: config.created_at,
+            last_modified: config.last_modified,
+        }
+    }
+}
+
+impl From<game_suggestion::EventVote> for voting::Vote {
+    fn from(vote: game_suggestion::EventVote) -> Self {
+        Self {
+            voter: vote.email,
+            game_id: vote.game_id,
+            approved: matches!(vote.vote, game_suggestion::GameVote::Yes),
+            score: vote.score,
+            rank: vote.rank,
+        }
+    }
+}
+
+impl From<event_vote_result::EventVoteResult> for voting::Standing {
+    fn from(result: event_vote_result::EventVoteResult) -> Self {
+        Self {
+            game_id: result.game_id,
+            points: result.points,
+            voter_count: result.voter_count,
+            eliminated_in_round: result
+                .eliminated_in_round
+                .and_then(|round| usize::try_from(round).ok()),
+        }
+    }
+}
+
+impl From<&voting::Standing> for event_vote_result::ResultInsert {
+    fn from(standing: &voting::Standing) -> Self {
+        Self {
+            game_id: standing.game_id,
+            points: standing.points,
+            voter_count: standing.voter_count,
+            eliminated_in_round: standing
+                .eliminated_in_round
+                .and_then(|round| i32::try_from(round).ok()),
+        }
+    }
+}
+
+/// How a window of time for suggesting or voting stands
+#[derive(Debug, PartialEq, Eq)]
+enum WindowState {
+    NotYetOpen(DateTime<Utc>),
+    Open,
+    Closed(DateTime<Utc>),
+}
+
+/// Work out whether a window is open, where missing times mean there's no limit
+fn window_state(
+    open_at: Option<DateTime<Utc>>,
+    close_at: Option<DateTime<Utc>>,
+    now: DateTime<Utc>,
+) -> WindowState {
+    match (open_at, close_at) {
+        (Some(open_at), _) if now < open_at => WindowState::NotYetOpen(open_at),
+        (_, Some(close_at)) if now >= close_at => WindowState::Closed(close_at),
+        _ => WindowState::Open,
+    }
+}
+
+/// Check a window is open, explaining when it opens or closed if not
+fn ensure_window_open(
+    action: &str,
+    open_at: Option<DateTime<Utc>>,
+    close_at: Option<DateTime<Utc>>,
+    now: DateTime<Utc>,
+) -> Result<(), Error> {
+    match window_state(open_at, close_at, now) {
+        WindowState::Open => Ok(()),
+        WindowState::NotYetOpen(open_at) => Err(Error::NotPermitted(format!(
+            "{action} for this event opens at {}",
+            open_at.format("%Y-%m-%d %H:%M UTC")
+        ))),
+        WindowState::Closed(close_at) => Err(Error::NotPermitted(format!(
+            "{action} for this event closed at {}",
+            close_at.format("%Y-%m-%d %H:%M UTC")
+        ))),
+    }
+}
+
+/// Check games can be suggested for an event right now
+pub fn ensure_suggestions_open(config: &EventVotingConfig) -> Result<(), Error> {
+    ensure_window_open(
+        "Suggesting games",
+        config.suggestions_open_at,
+        config.suggestions_close_at,
+        Utc::now(),
+    )
+}
+
+/// Check votes can be changed for an event right now
+pub fn ensure_voting_open(config: &EventVotingConfig) -> Result<(), Error> {
+    ensure_window_open(
+        "Voting",
+        config.voting_open_at,
+        config.voting_close_at,
+        Utc::now(),
+    )
+}
+
+/// Check windows open before they close
+fn validate_windows(windows: &event_voting_config::Windows) -> Result<(), Error> {
+    if let (Some(open_at), Some(close_at)) =
+        (windows.suggestions_open_at, windows.suggestions_close_at)
+    {
+        if open_at >= close_at {
+            return Err(Error::BadInput(
+                "Suggestions must open before they close".to_string(),
+            ));
+        }
+    }
+    if let (Some(open_at), Some(close_at)) = (windows.voting_open_at, windows.voting_close_at) {
+        if open_at >= close_at {
+            return Err(Error::BadInput(
+                "Voting must open before it closes".to_string(),
+            ));
+        }
+    }
+    Ok(())
+}
+
+/// Whether a voting method has attendees rank games rather than vote on them one at a time
+pub const fn is_ranked(method: VotingMethod) -> bool {
+    matches!(method, VotingMethod::InstantRunoff | VotingMethod::Borda)
+}
+
+/// Get how votes are counted for an event, which is approval voting with no time limits
+/// unless set otherwise
+pub async fn get_config(pool: &PgPool, event_id: i32) -> Result<EventVotingConfig, Error> {
+    match event_voting_config::get(pool, event_id).await {
+        Ok(Some(config)) => Ok(config.into()),
+        Ok(None) => Ok(EventVotingConfig {
+            event_id,
+            voting_method: VotingMethod::Approval,
+            suggestions_open_at: None,
+            suggestions_close_at: None,
+            voting_open_at: None,
+            voting_close_at: None,
+            max_suggestions_per_attendee: None,
+            suggestions_open: true,
+            voting_open: true,
+            created_at: chrono::Utc::now(),
+            last_modified: chrono::Utc::now(),
+        }),
+        Err(e) => Err(Error::Controller(format!(
+            "Unable to get voting config due to: {e}"
+        ))),
+    }
+}
+
+pub async fn upsert_config(
+    pool: &PgPool,
+    event_id: i32,
+    config: EventVotingConfigSubmit,
+    user_email: String,
+) -> Result<EventVotingConfig, Error> {
+    let windows = event_voting_config::Windows {
+        suggestions_open_at: config.suggestions_open_at,
+        suggestions_close_at: config.suggestions_close_at,
+        voting_open_at: config.voting_open_at,
+        voting_close_at: config.voting_close_at,
+    };
+    validate_windows(&windows)?;
+    if config
+        .max_suggestions_per_attendee
+        .is_some_and(|max_suggestions| max_suggestions < 1)
+    {
+        return Err(Error::BadInput(
+            "Attendees must be able to suggest at least one game".to_string(),
+        ));
+    }
+
+    let previous_close_at = match event_voting_config::get(pool, event_id).await {
+        Ok(previous) => previous.and_then(|previous| previous.voting_close_at),
+        Err(e) => {
+            return Err(Error::Controller(format!(
+                "Unable to get voting config due to: {e}"
+            )))
+        }
+    };
+
+    match event_voting_config::upsert(
+        pool,
+        event_id,
+        config.voting_method.into(),
+        windows,
+        config.max_suggestions_per_attendee,
+    )
+    .await
+    {
+        Ok(config_result) => {
+            // Results frozen when voting closed no longer apply once the closing time moves,
+            // the same as reopening voting
+            if previous_close_at != config.voting_close_at {
+                if let Err(e) = event_vote_result::delete(pool, event_id).await {
+                    return Err(Error::Controller(format!(
+                        "Unable to clear frozen vote results due to: {e}"
+                    )));
+                }
+            }
+
+            // Log audit entry
+            let metadata = rocket::serde::json::serde_json::json!({
+                "event_id": event_id,
+                "voting_method": format!("{:?}", config.voting_method),
+                "suggestions_open_at": config.suggestions_open_at,
+                "suggestions_close_at": config.suggestions_close_at,
+                "voting_open_at": config.voting_open_at,
+                "voting_close_at": config.voting_close_at,
+                "max_suggestions_per_attendee": config.max_suggestions_per_attendee,
+            });
+            crate::util::log_audit(
+                pool,
+                Some(user_email),
+                "event_voting_config.update".to_string(),
+                "event_voting_config".to_string(),
+                Some(event_id.to_string()),
+                Some(metadata),
+            )
+            .await;
+
+            Ok(config_result.into())
+        }
+        Err(e) => Err(Error::Controller(format!(
+            "Unable to save voting config due to: {e}"
+        ))),
+    }
+}
+
+/// Reopen voting for an event, closing again at the given time if there is one.
+/// The frozen results are thrown away, so they're counted again when voting next closes.
+pub async fn reopen_voting(
+    pool: &PgPool,
+    event_id: i32,
+    voting_close_at: Option<DateTime<Utc>>,
+    user_email: String,
+) -> Result<EventVotingConfig, Error> {
+    if voting_close_at.is_some_and(|close_at| close_at <= Utc::now()) {
+        return Err(Error::BadInput(
+            "Voting can only be reopened until a time in the future".to_string(),
+        ));
+    }
+
+    let config = match event_voting_config::get(pool, event_id).await {
+        Ok(Some(config)) => config,
+        Ok(None) => {
+            return Err(Error::BadInput(
+                "Voting for this event has no closing time".to_string(),
+            ))
+        }
+        Err(e) => {
+            return Err(Error::Controller(format!(
+                "Unable to get voting config due to: {e}"
+            )))
+        }
+    };
+    validate_windows(&event_voting_config::Windows {
+        suggestions_open_at: config.suggestions_open_at,
+        suggestions_close_at: config.suggestions_close_at,
+        voting_open_at: config.voting_open_at,
+        voting_close_at,
+    })?;
+
+    let config =
+        match event_voting_config::update_voting_close(pool, event_id, voting_close_at).await {
+            Ok(Some(config)) => config,
+            Ok(None) => {
+                return Err(Error::BadInput(
+                    "Voting for this event has no closing time".to_string(),
+                ))
+            }
+            Err(e) => {
+                return Err(Error::Controller(format!(
+                    "Unable to reopen voting due to: {e}"
+                )))
+            }
+        };
+
+    if let Err(e) = event_vote_result::delete(pool, event_id).await {
+        return Err(Error::Controller(format!(
+            "Unable to clear frozen vote results due to: {e}"
+        )));
+    }
+
+    // Log audit entry
+    let metadata = rocket::serde::json::serde_json::json!({
+        "event_id": event_id,
+        "voting_close_at": voting_close_at,
+    });
+    crate::util::log_audit(
+        pool,
+        Some(user_email),
+        "event_voting_config.reopen".to_string(),
+        "event_voting_config".to_string(),
+        Some(event_id.to_string()),
+        Some(metadata),
+    )
+    .await;
+
+    Ok(config.into())
+}
+
+/// The votes counted for the games suggested for an event
+pub struct Tally {
+    pub voting_method: VotingMethod,
+
+    /// Every suggested game, best first
+    pub standings: Vec<voting::Standing>,
+
+    /// When the results were frozen, if voting has closed
+    pub frozen_at: Option<DateTime<Utc>>,
+}
+
+/// Count the votes for the games suggested for an event as they stand
+async fn count_votes(
+    pool: &PgPool,
+    event_id: i32,
+    method: VotingMethod,
+) -> Result<Vec<voting::Standing>, Error> {
+    let candidates = match game_suggestion::get_game_ids(pool, event_id).await {
+        Ok(game_ids) => game_ids,
+        Err(e) => {
+            return Err(Error::Controller(format!(
+                "Unable to get suggested games due to: {e}"
+            )))
+        }
+    };
+
+    let votes: Vec<voting::Vote> = match game_suggestion::get_votes(pool, event_id).await {
+        Ok(votes) => votes.into_iter().map(Into::into).collect(),
+        Err(e) => {
+            return Err(Error::Controller(format!(
+                "Unable to get votes due to: {e}"
+            )))
+        }
+    };
+
+    Ok(voting::tally(method.into(), &candidates, &votes))
+}
+
+/// Get the frozen results for an event, freezing them first if nobody has yet
+async fn frozen_results(
+    pool: &PgPool,
+    event_id: i32,
+    method: VotingMethod,
+) -> Result<Vec<event_vote_result::EventVoteResult>, Error> {
+    let results = match event_vote_result::filter(pool, event_id).await {
+        Ok(results) => results,
+        Err(e) => {
+            return Err(Error::Controller(format!(
+                "Unable to get frozen vote results due to: {e}"
+            )))
+        }
+    };
+    if !results.is_empty() {
+        return Ok(results);
+    }
+
+    let standings = count_votes(pool, event_id, method).await?;
+    let inserts: Vec<event_vote_result::ResultInsert> = standings.iter().map(Into::into).collect();
+    if let Err(e) = event_vote_result::create_many(pool, event_id, method.into(), &inserts).await {
+        return Err(Error::Controller(format!(
+            "Unable to freeze vote results due to: {e}"
+        )));
+    }
+
+    // Read them back, in case another request froze them first
+    event_vote_result::filter(pool, event_id)
+        .await
+        .map_err(|e| Error::Controller(format!("Unable to get frozen vote results due to: {e}")))
+}
+
+/// Count the votes for the games suggested for an event, best first.
+/// Once voting has closed the results are frozen, so they don't change afterwards.
+pub async fn tally(pool: &PgPool, event_id: i32) -> Result<Tally, Error> {
+    let config = get_config(pool, event_id).await?;
+
+    if !matches!(
+        window_state(config.voting_open_at, config.voting_close_at, Utc::now()),
+        WindowState::Closed(_)
+    ) {
+        return Ok(Tally {
+            voting_method: config.voting_method,
+            standings: count_votes(pool, event_id, config.voting_method).await?,
+            frozen_at: None,
+        });
+    }
+
+    let results = frozen_results(pool, event_id, config.voting_method).await?;
+    let voting_method = results
+        .first()
+        .map_or(config.voting_method, |result| result.voting_method.into());
+    let frozen_at = results
+        .first()
+        .map(|result| result.frozen_at)
+        .or(config.voting_close_at);
+
+    Ok(Tally {
+        voting_method,
+        standings: results.into_iter().map(Into::into).collect(),
+        frozen_at,
+    })
+}
+
+/// Get how the games suggested for an event are doing in the vote
+pub async fn get_results(
+    pool: &PgPool,
+    event_id: i32,
+    email: String,
+) -> Result<VoteResults, Error> {
+    let names: HashMap<i64, String> = match game_suggestion::filter(
+        pool,
+        game_suggestion::Filter {
+            event_id: Some(event_id),
+            game_id: None,
+        },
+        email,
+    )
+    .await
+    {
+        Ok(suggestions) => suggestions
+            .into_iter()
+            .map(|suggestion| (suggestion.game_id, suggestion.game_name))
+            .collect(),
+        Err(e) => {
+            return Err(Error::Controller(format!(
+                "Unable to get game suggestions due to: {e}"
+            )))
+        }
+    };
+    let tally = tally(pool, event_id).await?;
+
+    Ok(VoteResults {
+        voting_method: tally.voting_method,
+        frozen_at: tally.frozen_at,
+        standings: tally
+            .standings
+            .into_iter()
+            .enumerate()
+            .map(|(index, standing)| VoteStanding {
+                appid: standing.game_id,
+                name: names.get(&standing.game_id).cloned().unwrap_or_default(),
+                position: index + 1,
+                points: standing.points,
+                voter_count: standing.voter_count,
+                eliminated_in_round: standing.eliminated_in_round,
+            })
+            .collect(),
+    })
+}
+
+/// Check a ranking only has suggested games, each once
+fn validate_ranking(ranking: &[i64], suggested: &HashSet<i64>) -> Result<(), Error> {
+    let mut seen = HashSet::new();
+    for game_id in ranking {
+        if !suggested.contains(game_id) {
+            return Err(Error::BadInput(format!(
+                "Game {game_id} hasn't been suggested for this event"
+            )));
+        }
+        if !seen.insert(game_id) {
+            return Err(Error::BadInput(format!(
+                "Game {game_id} is ranked more than once"
+            )));
+        }
+    }
+    Ok(())
+}
+
+/// Replace someone's ranking of the games suggested for an event
+pub async fn set_ranking(
+    pool: &PgPool,
+    event_id: i32,
+    email: String,
+    ranking: Vec<i64>,
+) -> Result<EventGameRanking, Error> {
+    let config = get_config(pool, event_id).await?;
+    let method = config.voting_method;
+    if !is_ranked(method) {
+        return Err(Error::BadInput(
+            "This event doesn't use ranked choice voting".to_string(),
+        ));
+    }
+    ensure_voting_open(&config)?;
+
+    match is_event_active(pool, event_id).await {
+        Err(e) => {
+            return Err(Error::Controller(format!(
+                "Unable to check if event is active, due to: {e}"
+            )))
+        }
+        Ok((false, _)) => {
+            return Err(Error::NotPermitted(
+                "You can only vote on games for active events".to_string(),
+            ))
+        }
+        Ok((true, _event)) => (),
+    }
+
+    match is_attending_event(pool, event_id, email.clone()).await {
+        Err(e) => {
+            return Err(Error::Controller(format!(
+                "Unable to check if attending event, due to: {e}"
+            )))
+        }
+        Ok(false) => {
+            return Err(Error::NotPermitted(
+                "You can only vote on games for events you are attending".to_string(),
+            ))
+        }
+        Ok(true) => (),
+    }
+
+    let suggested: HashSet<i64> = match game_suggestion::filter(
+        pool,
+        game_suggestion::Filter {
+            event_id: Some(event_id),
+            game_id: None,
+        },
+        email.clone(),
+    )
+    .await
+    {
+        Ok(suggestions) => suggestions
+            .into_iter()
+            .map(|suggestion| suggestion.game_id)
+            .collect(),
+        Err(e) => {
+            return Err(Error::Controller(format!(
+                "Unable to get game suggestions due to: {e}"
+            )))
+        }
+    };
+    validate_ranking(&ranking, &suggested)?;
+
+    if let Err(e) = game_suggestion::set_ranking(pool, event_id, email.clone(), &ranking).await {
+        return Err(Error::Controller(format!(
+            "Unable to save ranking due to: {e}"
+        )));
+    }
+
+    // Log audit entry
+    let metadata = rocket::serde::json::serde_json::json!({
+        "event_id": event_id,
+        "voting_method": format!("{:?}", method),
+        "ranking": ranking,
+    });
+    crate::util::log_audit(
+        pool,
+        Some(email),
+        "game_ranking.update".to_string(),
+        "game_vote".to_string(),
+        Some(event_id.to_string()),
+        Some(metadata),
+    )
+    .await;
+
+    Ok(EventGameRanking { games: ranking })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_rankings_only_have_suggested_games_once() {
+        let suggested = HashSet::from([1, 2, 3]);
+        assert!(validate_ranking(&[3, 1], &suggested).is_ok());
+        assert!(validate_ranking(&[], &suggested).is_ok());
+        assert!(matches!(
+            validate_ranking(&[1, 4], &suggested),
+            Err(Error::BadInput(_))
+        ));
+        assert!(matches!(
+            validate_ranking(&[1, 2, 1], &suggested),
+            Err(Error::BadInput(_))
+        ));
+    }
+
+    #[test]
+    fn test_windows_without_times_are_always_open() {
+        assert_eq!(window_state(None, None, Utc::now()), WindowState::Open);
+    }
+
+    #[test]
+    fn test_windows_open_and_close_at_their_times() {
+        let open_at = Utc::now();
+        let close_at = open_at + chrono::Duration::days(1);
+        let hour = chrono::Duration::hours(1);
+
+        assert_eq!(
+            window_state(Some(open_at), Some(close_at), open_at - hour),
+            WindowState::NotYetOpen(open_at)
+        );
+        assert_eq!(
+            window_state(Some(open_at), Some(close_at), open_at),
+            WindowState::Open
+        );
+        assert_eq!(
+            window_state(Some(open_at), Some(close_at), close_at),
+            WindowState::Closed(close_at)
+        );
+        assert_eq!(
+            window_state(None, Some(close_at), open_at),
+            WindowState::Open
+        );
+        assert_eq!(
+            window_state(Some(open_at), None, close_at + hour),
+            WindowState::Open
+        );
+    }
+
+    #[test]
+    fn test_closed_windows_are_not_permitted() {
+        let close_at = Utc::now();
+        assert!(matches!(
+            ensure_window_open("Voting", None, Some(close_at), close_at),
+            Err(Error::NotPermitted(message)) if message.starts_with("Voting for this event closed at")
+        ));
+        assert!(ensure_window_open(
+            "Voting",
+            None,
+            Some(close_at),
+            close_at - chrono::Duration::seconds(1)
+        )
+        .is_ok());
+    }
+
+    #[test]
+    fn test_windows_must_open_before_they_close() {
+        let now = Utc::now();
+        let windows = event_voting_config::Windows {
+            suggestions_open_at: Some(now),
+            suggestions_close_at: Some(now + chrono::Duration::days(1)),
+            voting_open_at: None,
+            voting_close_at: Some(now),
+        };
+        assert!(validate_windows(&windows).is_ok());
+        assert!(matches!(
+            validate_windows(&event_voting_config::Windows {
+                voting_open_at: Some(now),
+                ..windows
+            }),
+            Err(Error::BadInput(_))
+        ));
+    }
+}
@@ -1,10 +1,19 @@
-use futures::future::join_all;
+use std::collections::HashMap;
+
+use chrono::{Duration, Utc};
+use rocket::serde::json::serde_json::json;
 use sqlx::PgPool;
 
 use crate::{
     controllers::Error,
-    repositories::{game, game_update, steam_api},
-    routes::games::SteamGameResponse,
+    repositories::{
+        game, game_update, steam_api, steam_game_details, steam_game_duration, steam_game_players,
+    },
+    routes::games::{
+        GamePlayers, GamePlayersSubmit, GameSource, SteamGameDetails, SteamGamePlatforms,
+        SteamGameResponse, SteamGameUpdateResponse, SteamGameUpdateStatus,
+    },
+    search,
 };
 
 // Implement From for SteamGameResponse from Game
@@ -12,72 +21,894 @@ impl From<crate::repositories::game::Game> for SteamGameResponse {
     fn from(game: crate::repositories::game::Game) -> Self {
         Self {
             appid: game.appid,
+            url: game_url(game.source, game.appid, game.url),
             name: game.name,
             last_modified: game.last_modified,
             rank: game.rank,
+            details: None,
+            source: game.source.into(),
+            image_url: game.image_url,
+        }
+    }
+}
+
+impl From<game::GameSource> for GameSource {
+    fn from(source: game::GameSource) -> Self {
+        match source {
+            game::GameSource::Steam => Self::Steam,
+            game::GameSource::Custom => Self::Custom,
+        }
+    }
+}
+
+/// Link Steam games to their store page, and custom games to wherever they were added with
+pub fn game_url(source: game::GameSource, appid: i64, url: Option<String>) -> Option<String> {
+    match source {
+        game::GameSource::Steam => Some(format!("https://store.steampowered.com/app/{appid}")),
+        game::GameSource::Custom => url,
+    }
+}
+
+/// Whether an app ID is for a custom game rather than one from Steam
+pub const fn is_custom_appid(appid: i64) -> bool {
+    appid < 0
+}
+
+/// Steam store category IDs for playing with other people
+const MULTIPLAYER_CATEGORY_IDS: [i32; 13] = [1, 9, 20, 24, 27, 36, 37, 38, 39, 44, 47, 48, 49];
+
+/// Steam store category IDs for LAN PvP and LAN Co-op
+const LAN_CATEGORY_IDS: [i32; 2] = [47, 48];
+
+/// Steam store category IDs for Shared/Split Screen, including its PvP and Co-op variants
+const SPLIT_SCREEN_CATEGORY_IDS: [i32; 3] = [24, 37, 39];
+
+/// Steam store category ID for Single-player
+const SINGLE_PLAYER_CATEGORY_ID: i32 = 2;
+
+/// How long details from the Steam store are cached before they are fetched again
+const DETAILS_MAX_AGE_DAYS: i64 = 7;
+
+impl From<steam_game_details::SteamGameDetails> for SteamGameDetails {
+    fn from(details: steam_game_details::SteamGameDetails) -> Self {
+        Self {
+            header_image: details.header_image,
+            short_description: details.short_description,
+            is_multiplayer: details
+                .category_ids
+                .iter()
+                .any(|id| MULTIPLAYER_CATEGORY_IDS.contains(id)),
+            has_lan: details
+                .category_ids
+                .iter()
+                .any(|id| LAN_CATEGORY_IDS.contains(id)),
+            categories: details.categories,
+            genres: details.genres,
+            platforms: SteamGamePlatforms {
+                windows: details.is_windows,
+                mac: details.is_mac,
+                linux: details.is_linux,
+            },
+            is_free: details.is_free,
+            price: details.price_formatted,
+            release_date: details.release_date,
+            coming_soon: details.is_coming_soon,
+            fetched_at: details.fetched_at,
+        }
+    }
+}
+
+impl From<steam_api::SteamAPIAppDetails> for steam_game_details::SteamGameDetails {
+    fn from(app: steam_api::SteamAPIAppDetails) -> Self {
+        let platforms = app.platforms.unwrap_or(steam_api::SteamAPIPlatforms {
+            windows: false,
+            mac: false,
+            linux: false,
+        });
+        let (price_currency, price_final, price_formatted) =
+            app.price_overview.map_or((None, None, None), |price| {
+                (
+                    Some(price.currency),
+                    Some(price.final_price),
+                    Some(price.final_formatted),
+                )
+            });
+
+        Self {
+            appid: app.steam_appid,
+            is_available: true,
+            header_image: app.header_image,
+            short_description: app.short_description,
+            category_ids: app.categories.iter().map(|category| category.id).collect(),
+            categories: app
+                .categories
+                .into_iter()
+                .map(|category| category.description)
+                .collect(),
+            genres: app
+                .genres
+                .into_iter()
+                .map(|genre| genre.description)
+                .collect(),
+            is_windows: platforms.windows,
+            is_mac: platforms.mac,
+            is_linux: platforms.linux,
+            is_free: app.is_free,
+            price_currency,
+            price_final,
+            price_formatted,
+            release_date: app
+                .release_date
+                .as_ref()
+                .map(|release_date| release_date.date.clone())
+                .filter(|date| !date.is_empty()),
+            is_coming_soon: app
+                .release_date
+                .is_some_and(|release_date| release_date.coming_soon),
+            fetched_at: Utc::now(),
+            app_type: app.app_type,
+            fullgame_appid: app
+                .fullgame
+                .and_then(|fullgame| fullgame.appid.parse().ok()),
         }
     }
 }
 
+/// Most games matched by the database to rank for a search
+const SEARCH_CANDIDATE_LIMIT: i64 = 500;
+
+/// Narrow down a game search by what's known about the games
+#[derive(Clone, Copy, Default)]
+pub struct SearchOptions {
+    /// Only games that can be played with other people
+    pub multiplayer: bool,
+    /// Only free games
+    pub free: bool,
+    /// Include DLC, soundtracks, dedicated servers and the like
+    pub include_extras: bool,
+}
+
+/// Search for games by name, allowing for partial words, numerals and typos. Best matches first.
 pub async fn get(
     pool: &PgPool,
     query: String,
+    options: SearchOptions,
     count: i64,
     page: i64,
 ) -> Result<Vec<SteamGameResponse>, Error> {
-    // Build new game Filter
-    let game_filter_values = game::Filter { query, count, page };
-    // Return all games
-    match game::filter(pool, game_filter_values).await {
-        Ok(games) => Ok(games.into_iter().map(SteamGameResponse::from).collect()),
+    let game_filter_values = game::Filter {
+        prefix_query: search::prefix_tsquery(&query),
+        like_prefix: search::like_prefix(&query),
+        query: query.clone(),
+        multiplayer: options.multiplayer,
+        multiplayer_category_ids: MULTIPLAYER_CATEGORY_IDS.to_vec(),
+        free: options.free,
+        include_extras: options.include_extras,
+        limit: SEARCH_CANDIDATE_LIMIT,
+    };
+    let candidates = match game::filter(pool, game_filter_values).await {
+        Ok(games) => games,
+        Err(e) => {
+            return Err(Error::Controller(format!(
+                "Unable to get games due to: {e}"
+            )))
+        }
+    };
+
+    // The database finds anything that might match, then they're ranked by how well they do
+    let mut ranked: Vec<game::Game> = candidates
+        .into_iter()
+        .filter(|game| options.include_extras || !search::looks_like_extra(&game.name))
+        .filter_map(|game| {
+            search::relevance(&query, &game.name).map(|relevance| game::Game {
+                rank: Some(relevance),
+                ..game
+            })
+        })
+        .collect();
+    ranked.sort_by(|a, b| {
+        b.rank
+            .unwrap_or_default()
+            .total_cmp(&a.rank.unwrap_or_default())
+            .then_with(|| a.name.len().cmp(&b.name.len()))
+            .then_with(|| a.appid.cmp(&b.appid))
+    });
+    let games: Vec<game::Game> = ranked
+        .into_iter()
+        .skip(usize::try_from(page * count).unwrap_or(usize::MAX))
+        .take(usize::try_from(count).unwrap_or_default())
+        .collect();
+
+    // Add any cached store details, without fetching them as searches return many games
+    let appids: Vec<i64> = games.iter().map(|game| game.appid).collect();
+    let mut details: HashMap<i64, SteamGameDetails> =
+        match steam_game_details::filter(pool, &appids).await {
+            Ok(details) => details
+                .into_iter()
+                .filter(|details| details.is_available)
+                .map(|details| (details.appid, details.into()))
+                .collect(),
+            Err(e) => {
+                return Err(Error::Controller(format!(
+                    "Unable to get game details due to: {e}"
+                )))
+            }
+        };
+
+    Ok(games
+        .into_iter()
+        .map(|game| {
+            let appid = game.appid;
+            SteamGameResponse {
+                details: details.remove(&appid),
+                ..game.into()
+            }
+        })
+        .collect())
+}
+
+/// Get the Steam store details of a game, fetching them from Steam if they aren't cached or are old.
+/// Returns None if the Steam store has no details for the game.
+pub async fn refresh_details(pool: &PgPool, appid: i64) -> Result<Option<SteamGameDetails>, Error> {
+    // Custom games aren't on the Steam store
+    if is_custom_appid(appid) {
+        return Ok(None);
+    }
+
+    let cached = match steam_game_details::get(pool, appid).await {
+        Ok(cached) => cached,
+        Err(e) => {
+            return Err(Error::Controller(format!(
+                "Unable to get game details due to: {e}"
+            )))
+        }
+    };
+
+    if let Some(cached) = cached {
+        if cached.fetched_at > Utc::now() - Duration::days(DETAILS_MAX_AGE_DAYS) {
+            return Ok(cached.is_available.then(|| cached.into()));
+        }
+    }
+
+    let details = match steam_api::get_app_details(appid).await {
+        Ok(Some(app)) => app.into(),
+        // Remember the store has nothing, so it isn't asked again until the cache expires
+        Ok(None) => steam_game_details::SteamGameDetails {
+            appid,
+            is_available: false,
+            ..Default::default()
+        },
+        Err(e) => {
+            return Err(Error::Controller(format!(
+                "Unable to fetch game details from Steam due to: {e}"
+            )))
+        }
+    };
+
+    let details = match steam_game_details::upsert(pool, details).await {
+        Ok(details) => details,
+        Err(e) => {
+            return Err(Error::Controller(format!(
+                "Unable to save game details due to: {e}"
+            )))
+        }
+    };
+
+    if details.is_available {
+        let players = players_from_categories(&details.category_ids);
+        if let Err(e) = steam_game_players::seed(
+            pool,
+            appid,
+            players.min_players,
+            players.max_players,
+            players.has_lan,
+            players.has_split_screen,
+        )
+        .await
+        {
+            return Err(Error::Controller(format!(
+                "Unable to save game players due to: {e}"
+            )));
+        }
+    }
+
+    Ok(details.is_available.then(|| details.into()))
+}
+
+/// Player counts worked out from Steam store categories
+#[derive(Debug, PartialEq, Eq)]
+pub struct PlayersFromCategories {
+    pub min_players: Option<i32>,
+    pub max_players: Option<i32>,
+    pub has_lan: bool,
+    pub has_split_screen: bool,
+}
+
+/// Work out what we can about player counts from Steam store categories.
+/// Steam doesn't say how many people can play together, only whether a game is
+/// single-player and whether it is multiplayer, so the maximum is left unknown for multiplayer games.
+pub fn players_from_categories(category_ids: &[i32]) -> PlayersFromCategories {
+    let has_category = |ids: &[i32]| category_ids.iter().any(|id| ids.contains(id));
+
+    let is_single_player = category_ids.contains(&SINGLE_PLAYER_CATEGORY_ID);
+    let is_multiplayer = has_category(&MULTIPLAYER_CATEGORY_IDS);
+
+    let (min_players, max_players) = match (is_single_player, is_multiplayer) {
+        (true, false) => (Some(1), Some(1)),
+        (true, true) => (Some(1), None),
+        (false, true) => (Some(2), None),
+        (false, false) => (None, None),
+    };
+
+    PlayersFromCategories {
+        min_players,
+        max_players,
+        has_lan: has_category(&LAN_CATEGORY_IDS),
+        has_split_screen: has_category(&SPLIT_SCREEN_CATEGORY_IDS),
+    }
+}
+
+impl From<steam_game_players::SteamGamePlayers> for GamePlayers {
+    fn from(players: steam_game_players::SteamGamePlayers) -> Self {
+        Self {
+            min_players: players.min_players,
+            max_players: players.max_players,
+            has_lan: players.has_lan,
+            has_split_screen: players.has_split_screen,
+            is_manual: players.is_manual,
+            last_modified: players.last_modified,
+        }
+    }
+}
+
+/// Get player counts for several games, keyed by app ID
+pub async fn get_players(
+    pool: &PgPool,
+    appids: &[i64],
+) -> Result<HashMap<i64, GamePlayers>, Error> {
+    match steam_game_players::filter(pool, appids).await {
+        Ok(players) => Ok(players
+            .into_iter()
+            .map(|players| (players.appid, players.into()))
+            .collect()),
         Err(e) => Err(Error::Controller(format!(
-            "Unable to get games due to: {e}"
+            "Unable to get game players due to: {e}"
         ))),
     }
 }
 
-pub async fn update(pool: &PgPool, steam_api_key: &String) -> Result<(), Error> {
-    let Ok(steam_game_update) = game_update::create(pool).await else {
-        return Err(Error::Controller(
-            "Unable to create game update log".to_string(),
+/// Whether a game can be played by `players` people together. Games with unknown counts are assumed to be.
+pub fn supports_players(game_players: Option<&GamePlayers>, players: i32) -> bool {
+    game_players.is_none_or(|game_players| {
+        game_players.min_players.is_none_or(|min| min <= players)
+            && game_players.max_players.is_none_or(|max| max >= players)
+    })
+}
+
+/// Check player counts make sense, allowing either to be unknown
+pub fn validate_players(min_players: Option<i32>, max_players: Option<i32>) -> Result<(), Error> {
+    if min_players.is_some_and(|min| min < 1) || max_players.is_some_and(|max| max < 1) {
+        return Err(Error::BadInput(
+            "Player counts must be at least 1".to_string(),
         ));
+    }
+
+    if let (Some(min), Some(max)) = (min_players, max_players) {
+        if max < min {
+            return Err(Error::BadInput(
+                "Maximum players can't be less than minimum players".to_string(),
+            ));
+        }
+    }
+
+    Ok(())
+}
+
+pub async fn update_players(
+    pool: &PgPool,
+    appid: i64,
+    players: GamePlayersSubmit,
+    user_email: String,
+) -> Result<GamePlayers, Error> {
+    validate_players(players.min_players, players.max_players)?;
+
+    let result = match steam_game_players::upsert(
+        pool,
+        appid,
+        players.min_players,
+        players.max_players,
+        players.has_lan,
+        players.has_split_screen,
+        user_email.clone(),
+    )
+    .await
+    {
+        Ok(result) => result,
+        Err(sqlx::Error::Database(e)) if e.is_foreign_key_violation() => {
+            return Err(Error::NotFound(format!("Game {appid} not found")))
+        }
+        Err(e) => {
+            return Err(Error::Controller(format!(
+                "Unable to save game players due to: {e}"
+            )))
+        }
+    };
+
+    // Log audit entry
+    let metadata = json!({
+        "appid": appid,
+        "min_players": result.min_players,
+        "max_players": result.max_players,
+        "has_lan": result.has_lan,
+        "has_split_screen": result.has_split_screen,
+    });
+    crate::util::log_audit(
+        pool,
+        Some(user_email),
+        "steam_game_players.update".to_string(),
+        "steam_game".to_string(),
+        Some(appid.to_string()),
+        Some(metadata),
+    )
+    .await;
+
+    Ok(result.into())
+}
+
+/// The longest a game can be scheduled for in one go, a whole day
+const MAX_DURATION_MINUTES: i32 = 24 * 60;
+
+/// Check a game duration is at least a minute and no more than a day
+pub fn validate_duration(duration_minutes: i32) -> Result<(), Error> {
+    if (1..=MAX_DURATION_MINUTES).contains(&duration_minutes) {
+        Ok(())
+    } else {
+        Err(Error::BadInput(format!(
+            "Durations must be between 1 and {MAX_DURATION_MINUTES} minutes"
+        )))
+    }
+}
+
+/// Set how long a game usually takes, or clear it
+pub async fn update_duration(
+    pool: &PgPool,
+    appid: i64,
+    duration_minutes: Option<i32>,
+    user_email: String,
+) -> Result<Option<i32>, Error> {
+    let result = match duration_minutes {
+        Some(duration_minutes) => {
+            validate_duration(duration_minutes)?;
+            steam_game_duration::upsert(pool, appid, duration_minutes, user_email.clone())
+                .await
+                .map(|duration| Some(duration.duration_minutes))
+        }
+        None => steam_game_duration::delete(pool, appid)
+            .await
+            .map(|()| None),
     };
 
-    let steam_games = match steam_api::get_app_list(steam_api_key).await {
-        Ok(steam_games) => steam_games,
+    let duration_minutes = match result {
+        Ok(duration_minutes) => duration_minutes,
+        Err(sqlx::Error::Database(e)) if e.is_foreign_key_violation() => {
+            return Err(Error::NotFound(format!("Game {appid} not found")))
+        }
         Err(e) => {
             return Err(Error::Controller(format!(
-                "Error fetching steam game list: {e}"
+                "Unable to save game duration due to: {e}"
             )))
         }
     };
 
-    log::info!("Retrieved {} games from Steam API", steam_games.len());
+    // Log audit entry
+    crate::util::log_audit(
+        pool,
+        Some(user_email),
+        "steam_game_duration.update".to_string(),
+        "steam_game".to_string(),
+        Some(appid.to_string()),
+        Some(json!({
+            "appid": appid,
+            "duration_minutes": duration_minutes,
+        })),
+    )
+    .await;
 
-    let chunk_size = 20;
+    Ok(duration_minutes)
+}
 
-    for chunk in steam_games.chunks(chunk_size) {
-        let mut insert_promises = vec![];
-        for steam_game in chunk {
-            insert_promises.push(game::create(
-                pool,
-                steam_game.appid,
-                steam_game_update.id,
-                steam_game.name.clone(),
-            ));
+impl From<game_update::UpdateStatus> for SteamGameUpdateStatus {
+    fn from(status: game_update::UpdateStatus) -> Self {
+        match status {
+            game_update::UpdateStatus::Running => Self::Running,
+            game_update::UpdateStatus::Succeeded => Self::Succeeded,
+            game_update::UpdateStatus::Failed => Self::Failed,
+        }
+    }
+}
+
+impl From<game_update::SteamGameUpdate> for SteamGameUpdateResponse {
+    fn from(update: game_update::SteamGameUpdate) -> Self {
+        Self {
+            id: update.id,
+            started_at: update.update_time,
+            status: update.status.into(),
+            triggered_by: update.triggered_by,
+            pages_fetched: update.pages_fetched,
+            apps_fetched: update.apps_fetched,
+            rows_upserted: update.rows_upserted,
+            error_count: update.error_count,
+            last_error: update.last_error,
+            finished_at: update.finished_at,
+            last_modified: update.last_modified,
+            modified_since: update.modified_since,
+            last_appid: update.last_appid,
+            apps_added: update.apps_added,
+            apps_renamed: update.apps_renamed,
+            apps_removed: update.apps_removed,
+        }
+    }
+}
+
+/// A running update that hasn't recorded progress for this long is assumed to have died
+const UPDATE_STALE_AFTER_MINUTES: i32 = 30;
+
+/// Start updating the list of games from the Steam API in the background.
+/// Only one update runs at a time, so this fails if one is already running.
+/// Unless `full` is set, only apps changed since the last successful update are fetched.
+pub async fn start_update(
+    pool: &PgPool,
+    steam_api_key: String,
+    triggered_by: Option<String>,
+    full: bool,
+) -> Result<SteamGameUpdateResponse, Error> {
+    match game_update::fail_stale(pool, UPDATE_STALE_AFTER_MINUTES).await {
+        Ok(0) => {}
+        Ok(count) => log::warn!("Marked {count} stalled game updates as failed"),
+        Err(e) => {
+            return Err(Error::Controller(format!(
+                "Unable to check for stalled game updates due to: {e}"
+            )))
+        }
+    }
+
+    let modified_since = if full {
+        None
+    } else {
+        match game_update::latest_succeeded(pool).await {
+            Ok(latest) => latest.map(|latest| latest.update_time),
+            Err(e) => {
+                return Err(Error::Controller(format!(
+                    "Unable to get the last game update due to: {e}"
+                )))
+            }
+        }
+    };
+
+    let steam_game_update =
+        match game_update::create(pool, triggered_by.clone(), modified_since).await {
+            Ok(Some(steam_game_update)) => steam_game_update,
+            Ok(None) => {
+                return Err(Error::Conflict(
+                    "A game update is already running".to_string(),
+                ))
+            }
+            Err(e) => {
+                return Err(Error::Controller(format!(
+                    "Unable to create game update log due to: {e}"
+                )))
+            }
+        };
+
+    // Log audit entry
+    let metadata = json!({
+        "full": modified_since.is_none(),
+    });
+    crate::util::log_audit(
+        pool,
+        triggered_by,
+        "steam_games.update".to_string(),
+        "steam_games".to_string(),
+        Some(steam_game_update.id.to_string()),
+        Some(metadata),
+    )
+    .await;
+
+    tokio::spawn(run_update(
+        pool.clone(),
+        steam_api_key,
+        steam_game_update.clone(),
+    ));
+
+    Ok(steam_game_update.into())
+}
+
+/// Fetch every page of the Steam app list and save the games, recording progress as it goes
+async fn run_update(
+    pool: PgPool,
+    steam_api_key: String,
+    steam_game_update: game_update::SteamGameUpdate,
+) {
+    let update_id = steam_game_update.id;
+    let if_modified_since = steam_game_update
+        .modified_since
+        .map(|modified_since| modified_since.timestamp());
+
+    let mut last_appid = 0;
+    let mut rows_upserted = 0;
+
+    let result = loop {
+        let page = match steam_api::get_app_list_page(&steam_api_key, last_appid, if_modified_since)
+            .await
+        {
+            Ok(page) => page,
+            Err(e) => break Err(format!("Error fetching steam game list: {e}")),
+        };
+
+        let (appids, names): (Vec<i64>, Vec<String>) = page
+            .apps
+            .into_iter()
+            .map(|steam_game| (steam_game.appid, steam_game.name))
+            .unzip();
+
+        let counts = match game::upsert_many(&pool, update_id, &appids, &names).await {
+            Ok(counts) => counts,
+            Err(e) => break Err(format!("Unable to save games: {e}")),
+        };
+
+        log::info!(
+            "Saved {} games ({} added, {} renamed)",
+            counts.upserted,
+            counts.added,
+            counts.renamed
+        );
+        rows_upserted += counts.upserted;
+
+        if let Err(e) = game_update::record_page(
+            &pool,
+            update_id,
+            page.last_appid.max(last_appid),
+            saturating_i32(appids.len()),
+            saturating_i32(counts.upserted),
+            saturating_i32(counts.added),
+            saturating_i32(counts.renamed),
+        )
+        .await
+        {
+            break Err(format!("Unable to record game update progress: {e}"));
+        }
+
+        if !page.have_more_results {
+            break Ok(());
         }
 
-        let results = join_all(insert_promises).await;
+        last_appid = page.last_appid;
+
+        // Small delay between requests to avoid rate limiting
+        tokio::time::sleep(tokio::time::Duration::from_millis(500)).await;
+    };
 
-        for result in results {
-            if let Err(e) = result {
-                log::error!("Failed to insert game: {e}");
-                break;
+    // Only a full update sees every app, so only it can tell which have been removed.
+    // An empty app list is more likely a Steam API problem than every game being removed.
+    let result = match result {
+        Ok(()) if steam_game_update.modified_since.is_none() && rows_upserted > 0 => {
+            match game::mark_removed(&pool, update_id).await {
+                Ok(apps_removed) => Ok(apps_removed),
+                Err(e) => Err(format!("Unable to mark removed games: {e}")),
             }
         }
+        Ok(()) => Ok(0),
+        Err(e) => Err(e),
+    };
 
-        log::info!("Inserted {chunk_size} games successfully.");
+    let (status, apps_removed, error) = match result {
+        Ok(apps_removed) => {
+            log::info!("Game update {update_id} finished, {apps_removed} games removed");
+            (
+                game_update::UpdateStatus::Succeeded,
+                saturating_i32(apps_removed),
+                None,
+            )
+        }
+        Err(e) => {
+            log::error!("Game update {update_id} failed: {e}");
+            (game_update::UpdateStatus::Failed, 0, Some(e))
+        }
+    };
+
+    if let Err(e) = game_update::finish(&pool, update_id, status, apps_removed, error).await {
+        log::error!("Unable to finish game update {update_id}: {e}");
     }
+}
 
-    Ok(())
+fn saturating_i32<T: TryInto<i32>>(count: T) -> i32 {
+    count.try_into().unwrap_or(i32::MAX)
+}
+
+/// Get the progress of the most recent game update
+pub async fn update_status(pool: &PgPool) -> Result<SteamGameUpdateResponse, Error> {
+    match game_update::latest(pool).await {
+        Ok(Some(steam_game_update)) => Ok(steam_game_update.into()),
+        Ok(None) => Err(Error::NotFound("Games have never been updated".to_string())),
+        Err(e) => Err(Error::Controller(format!(
+            "Unable to get game update status due to: {e}"
+        ))),
+    }
+}
+
+/// Start a game update if the last one started more than `interval` ago
+pub async fn start_update_if_due(
+    pool: &PgPool,
+    steam_api_key: String,
+    interval: Duration,
+) -> Result<Option<SteamGameUpdateResponse>, Error> {
+    let latest = match game_update::latest(pool).await {
+        Ok(latest) => latest,
+        Err(e) => {
+            return Err(Error::Controller(format!(
+                "Unable to get game update status due to: {e}"
+            )))
+        }
+    };
+
+    if latest.is_some_and(|latest| latest.update_time > Utc::now() - interval) {
+        return Ok(None);
+    }
+
+    match start_update(pool, steam_api_key, None, false).await {
+        Ok(update) => Ok(Some(update)),
+        // Another instance got there first
+        Err(Error::Conflict(_)) => Ok(None),
+        Err(e) => Err(e),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use rocket::serde::json::serde_json;
+
+    #[test]
+    fn test_players_from_categories() {
+        assert_eq!(
+            players_from_categories(&[2]),
+            PlayersFromCategories {
+                min_players: Some(1),
+                max_players: Some(1),
+                has_lan: false,
+                has_split_screen: false,
+            }
+        );
+        assert_eq!(
+            players_from_categories(&[2, 1, 48, 39]),
+            PlayersFromCategories {
+                min_players: Some(1),
+                max_players: None,
+                has_lan: true,
+                has_split_screen: true,
+            }
+        );
+        assert_eq!(
+            players_from_categories(&[36]),
+            PlayersFromCategories {
+                min_players: Some(2),
+                max_players: None,
+                has_lan: false,
+                has_split_screen: false,
+            }
+        );
+        assert_eq!(
+            players_from_categories(&[]),
+            PlayersFromCategories {
+                min_players: None,
+                max_players: None,
+                has_lan: false,
+                has_split_screen: false,
+            }
+        );
+    }
+
+    #[test]
+    fn test_supports_players() {
+        let players = GamePlayers {
+            min_players: Some(2),
+            max_players: Some(4),
+            has_lan: true,
+            has_split_screen: false,
+            is_manual: true,
+            last_modified: Utc::now(),
+        };
+        assert!(supports_players(Some(&players), 2));
+        assert!(supports_players(Some(&players), 4));
+        assert!(!supports_players(Some(&players), 1));
+        assert!(!supports_players(Some(&players), 12));
+
+        let unknown_max = GamePlayers {
+            max_players: None,
+            ..players
+        };
+        assert!(supports_players(Some(&unknown_max), 12));
+        assert!(supports_players(None, 12));
+    }
+
+    #[test]
+    fn test_app_details_into_game_details() {
+        let app: steam_api::SteamAPIAppDetails = serde_json::from_value(json!({
+            "type": "game",
+            "name": "Left 4 Dead 2",
+            "steam_appid": 550,
+            "is_free": false,
+            "short_description": "Set in the zombie apocalypse.",
+            "header_image": "https://example.com/550/header.jpg",
+            "platforms": {"windows": true, "mac": true, "linux": true},
+            "categories": [
+                {"id": 2, "description": "Single-player"},
+                {"id": 38, "description": "Online Co-op"},
+                {"id": 48, "description": "LAN Co-op"}
+            ],
+            "genres": [{"id": "1", "description": "Action"}],
+            "price_overview": {
+                "currency": "GBP",
+                "initial": 799,
+                "final": 159,
+                "discount_percent": 80,
+                "initial_formatted": "£7.99",
+                "final_formatted": "£1.59"
+            },
+            "release_date": {"coming_soon": false, "date": "17 Nov, 2009"}
+        }))
+        .unwrap();
+
+        let cached: steam_game_details::SteamGameDetails = app.into();
+        assert_eq!(cached.appid, 550);
+        assert_eq!(cached.category_ids, vec![2, 38, 48]);
+        assert_eq!(cached.price_final, Some(159));
+        assert_eq!(cached.release_date.as_deref(), Some("17 Nov, 2009"));
+
+        let details: SteamGameDetails = cached.into();
+        assert!(details.is_multiplayer);
+        assert!(details.has_lan);
+        assert!(details.platforms.linux);
+        assert_eq!(details.price.as_deref(), Some("£1.59"));
+        assert_eq!(details.genres, vec!["Action".to_string()]);
+    }
+
+    #[test]
+    fn test_free_single_player_app_details() {
+        let app: steam_api::SteamAPIAppDetails = serde_json::from_value(json!({
+            "name": "Free Game",
+            "steam_appid": 1,
+            "is_free": true,
+            "categories": [{"id": 2, "description": "Single-player"}],
+            "release_date": {"coming_soon": true, "date": ""}
+        }))
+        .unwrap();
+
+        let details: SteamGameDetails = steam_game_details::SteamGameDetails::from(app).into();
+        assert!(!details.is_multiplayer);
+        assert!(!details.has_lan);
+        assert!(details.is_free);
+        assert!(details.coming_soon);
+        assert_eq!(details.price, None);
+        assert_eq!(details.release_date, None);
+    }
+
+    /// Needs `DATABASE_URL` to point at a Postgres server: `cargo test -- --ignored`
+    #[sqlx::test]
+    #[ignore = "needs a Postgres database in DATABASE_URL"]
+    async fn test_start_update_already_running(pool: PgPool) -> sqlx::Result<()> {
+        let running = game_update::create(&pool, None, None).await?;
+        assert!(running.is_some());
+        assert!(
+            game_update::create(&pool, None, None).await?.is_none(),
+            "only one update can run at a time"
+        );
+
+        assert!(matches!(
+            start_update(&pool, "steam-api-key".to_string(), None, true).await,
+            Err(Error::Conflict(_))
+        ));
+
+        Ok(())
+    }
 }
@@ -0,0 +1,570 @@
+use std::collections::HashMap;
+
+use sqlx::PgPool;
+
+use crate::{
+    controllers::Error,
+    repositories::{game_comment, game_suggestion},
+    routes::{
+        event_games::Gamer,
+        game_comments::{GameComment, GameCommentReaction, GameCommentSubmit, GameCommentThread},
+    },
+    util::{is_attending_event, is_event_active},
+};
+
+/// The longest a comment can be, in characters
+const MAX_BODY_LENGTH: usize = 2000;
+
+/// The longest a reaction can be, in characters, which allows for emoji made of several characters
+const MAX_EMOJI_LENGTH: usize = 8;
+
+/// Tidy up what a comment says, checking it isn't empty or too long
+fn validate_body(body: &str) -> Result<String, Error> {
+    let body = body.trim();
+    if body.is_empty() {
+        return Err(Error::BadInput("Comments can't be empty".to_string()));
+    }
+    if body.chars().count() > MAX_BODY_LENGTH {
+        return Err(Error::BadInput(format!(
+            "Comments can be at most {MAX_BODY_LENGTH} characters"
+        )));
+    }
+    Ok(body.to_string())
+}
+
+/// Check a reaction looks like an emoji rather than text
+fn validate_emoji(emoji: &str) -> Result<(), Error> {
+    let length = emoji.chars().count();
+    if length == 0
+        || length > MAX_EMOJI_LENGTH
+        || emoji
+            .chars()
+            .any(|c| c.is_ascii_alphanumeric() || c.is_whitespace())
+    {
+        return Err(Error::BadInput(
+            "Reactions must be a single emoji".to_string(),
+        ));
+    }
+    Ok(())
+}
+
+/// Turn a comment into its response, without its replies
+fn to_comment(
+    comment: game_comment::GameComment,
+    reactions: &[game_comment::GameCommentReaction],
+    email: &str,
+) -> GameComment {
+    // Count reactions in the order each emoji was first used
+    let mut counts: Vec<GameCommentReaction> = Vec::new();
+    for reaction in reactions
+        .iter()
+        .filter(|reaction| reaction.comment_id == comment.id)
+    {
+        let reacted = reaction.email.eq_ignore_ascii_case(email);
+        if let Some(count) = counts
+            .iter_mut()
+            .find(|count| count.emoji == reaction.emoji)
+        {
+            count.count += 1;
+            count.reacted |= reacted;
+        } else {
+            counts.push(GameCommentReaction {
+                emoji: reaction.emoji.clone(),
+                count: 1,
+                reacted,
+            });
+        }
+    }
+
+    let deleted = comment.deleted_at.is_some();
+    GameComment {
+        id: comment.id,
+        parent_id: comment.parent_id,
+        author: Gamer {
+            avatar_url: comment.avatar_url,
+            handle: comment.handle,
+        },
+        own: comment.email.eq_ignore_ascii_case(email),
+        body: (!deleted).then_some(comment.body),
+        created_at: comment.created_at,
+        edited_at: comment.edited_at,
+        deleted_at: comment.deleted_at,
+        reactions: if deleted { Vec::new() } else { counts },
+        replies: Vec::new(),
+    }
+}
+
+/// Attach replies to the comments they reply to, dropping deleted comments nobody replied to
+fn attach_replies(
+    mut comment: GameComment,
+    replies: &mut HashMap<i32, Vec<GameComment>>,
+) -> Option<GameComment> {
+    comment.replies = replies
+        .remove(&comment.id)
+        .unwrap_or_default()
+        .into_iter()
+        .filter_map(|reply| attach_replies(reply, replies))
+        .collect();
+
+    (comment.deleted_at.is_none() || !comment.replies.is_empty()).then_some(comment)
+}
+
+/// Build a thread from a game's comments, oldest first, with replies nested under their comments
+fn build_thread(
+    comments: Vec<game_comment::GameComment>,
+    reactions: &[game_comment::GameCommentReaction],
+    email: &str,
+) -> Vec<GameComment> {
+    let ids: Vec<i32> = comments.iter().map(|comment| comment.id).collect();
+
+    let mut top_level = Vec::new();
+    let mut replies: HashMap<i32, Vec<GameComment>> = HashMap::new();
+    for comment in comments {
+        let comment = to_comment(comment, reactions, email);
+        match comment.parent_id {
+            Some(parent_id) if ids.contains(&parent_id) => {
+                replies.entry(parent_id).or_default().push(comment);
+            }
+            _ => top_level.push(comment),
+        }
+    }
+
+    top_level
+        .into_iter()
+        .filter_map(|comment| attach_replies(comment, &mut replies))
+        .collect()
+}
+
+/// Count the comments in a thread that haven't been deleted, including replies
+fn count_comments(comments: &[GameComment]) -> usize {
+    comments
+        .iter()
+        .map(|comment| usize::from(comment.deleted_at.is_none()) + count_comments(&comment.replies))
+        .sum()
+}
+
+/// Check someone can join the discussion about a game, returning the game's suggestion
+async fn ensure_can_comment(
+    pool: &PgPool,
+    event_id: i32,
+    game_id: i64,
+    email: &str,
+) -> Result<game_suggestion::GameSuggestion, Error> {
+    match is_event_active(pool, event_id).await {
+        Err(e) => {
+            return Err(Error::Controller(format!(
+                "Unable to check if event is active, due to: {e}"
+            )))
+        }
+        Ok((false, _)) => {
+            return Err(Error::NotPermitted(
+                "You can only comment on games for active events".to_string(),
+            ))
+        }
+        Ok((true, _event)) => (),
+    }
+
+    match is_attending_event(pool, event_id, email.to_string()).await {
+        Err(e) => {
+            return Err(Error::Controller(format!(
+                "Unable to check if attending event, due to: {e}"
+            )))
+        }
+        Ok(false) => {
+            return Err(Error::NotPermitted(
+                "You can only comment on games for events you are attending".to_string(),
+            ))
+        }
+        Ok(true) => (),
+    }
+
+    match game_suggestion::filter(
+        pool,
+        game_suggestion::Filter {
+            event_id: Some(event_id),
+            game_id: Some(game_id),
+        },
+        email.to_string(),
+    )
+    .await
+    {
+        Ok(game_suggestions) => game_suggestions.into_iter().next().ok_or_else(|| {
+            Error::NotFound(format!(
+                "Game {game_id} hasn't been suggested for this event"
+            ))
+        }),
+        Err(e) => Err(Error::Controller(format!(
+            "Unable to get game suggestion due to: {e}"
+        ))),
+    }
+}
+
+/// Get a comment on a game, checking it belongs to the game
+async fn get_comment(
+    pool: &PgPool,
+    event_id: i32,
+    game_id: i64,
+    comment_id: i32,
+) -> Result<game_comment::GameComment, Error> {
+    match game_comment::get(pool, comment_id).await {
+        Ok(Some(comment)) if comment.event_id == event_id && comment.game_id == game_id => {
+            Ok(comment)
+        }
+        Ok(_) => Err(Error::NotFound(format!("Comment {comment_id} not found"))),
+        Err(e) => Err(Error::Controller(format!(
+            "Unable to get comment due to: {e}"
+        ))),
+    }
+}
+
+/// Get the reactions to a comment
+async fn get_reactions(
+    pool: &PgPool,
+    comment_id: i32,
+) -> Result<Vec<game_comment::GameCommentReaction>, Error> {
+    game_comment::filter_reactions(pool, &[comment_id])
+        .await
+        .map_err(|e| Error::Controller(format!("Unable to get reactions due to: {e}")))
+}
+
+/// Get the discussion about a suggested game
+pub async fn get_thread(
+    pool: &PgPool,
+    event_id: i32,
+    game_id: i64,
+    email: String,
+) -> Result<GameCommentThread, Error> {
+    let comments = match game_comment::filter(pool, event_id, game_id).await {
+        Ok(comments) => comments,
+        Err(e) => {
+            return Err(Error::Controller(format!(
+                "Unable to get comments due to: {e}"
+            )))
+        }
+    };
+
+    let ids: Vec<i32> = comments.iter().map(|comment| comment.id).collect();
+    let reactions = match game_comment::filter_reactions(pool, &ids).await {
+        Ok(reactions) => reactions,
+        Err(e) => {
+            return Err(Error::Controller(format!(
+                "Unable to get reactions due to: {e}"
+            )))
+        }
+    };
+
+    let comments = build_thread(comments, &reactions, &email);
+    Ok(GameCommentThread {
+        comment_count: count_comments(&comments),
+        comments,
+    })
+}
+
+/// Comment on a suggested game, or reply to a comment
+pub async fn create(
+    pool: &PgPool,
+    event_id: i32,
+    game_id: i64,
+    email: String,
+    submit: GameCommentSubmit,
+) -> Result<GameComment, Error> {
+    let game_suggestion = ensure_can_comment(pool, event_id, game_id, &email).await?;
+    let body = validate_body(&submit.body)?;
+
+    if let Some(parent_id) = submit.parent_id {
+        let parent = get_comment(pool, event_id, game_id, parent_id).await?;
+        if parent.deleted_at.is_some() {
+            return Err(Error::BadInput(
+                "You can't reply to a deleted comment".to_string(),
+            ));
+        }
+    }
+
+    let comment = match game_comment::create(
+        pool,
+        event_id,
+        game_id,
+        submit.parent_id,
+        email.clone(),
+        body,
+    )
+    .await
+    {
+        Ok(comment) => comment,
+        Err(e) => {
+            return Err(Error::Controller(format!(
+                "Unable to save comment due to: {e}"
+            )))
+        }
+    };
+
+    // Log audit entry
+    let metadata = rocket::serde::json::serde_json::json!({
+        "event_id": event_id,
+        "game_id": game_id,
+        "game_name": game_suggestion.game_name,
+        "comment_id": comment.id,
+        "parent_id": comment.parent_id,
+        "body": comment.body,
+    });
+    crate::util::log_audit(
+        pool,
+        Some(email.clone()),
+        "game_comment.create".to_string(),
+        "game_comment".to_string(),
+        Some(format!("{event_id}-{game_id}-{}", comment.id)),
+        Some(metadata),
+    )
+    .await;
+
+    Ok(to_comment(comment, &[], &email))
+}
+
+/// Edit one of your comments
+pub async fn update(
+    pool: &PgPool,
+    event_id: i32,
+    game_id: i64,
+    comment_id: i32,
+    email: String,
+    body: String,
+) -> Result<GameComment, Error> {
+    let game_suggestion = ensure_can_comment(pool, event_id, game_id, &email).await?;
+    let comment = get_comment(pool, event_id, game_id, comment_id).await?;
+    if !comment.email.eq_ignore_ascii_case(&email) {
+        return Err(Error::NotPermitted(
+            "You can only edit your own comments".to_string(),
+        ));
+    }
+    let body = validate_body(&body)?;
+
+    let comment = match game_comment::update_body(pool, comment_id, body).await {
+        Ok(Some(comment)) => comment,
+        Ok(None) => return Err(Error::NotFound(format!("Comment {comment_id} not found"))),
+        Err(e) => {
+            return Err(Error::Controller(format!(
+                "Unable to update comment due to: {e}"
+            )))
+        }
+    };
+
+    // Log audit entry
+    let metadata = rocket::serde::json::serde_json::json!({
+        "event_id": event_id,
+        "game_id": game_id,
+        "game_name": game_suggestion.game_name,
+        "comment_id": comment_id,
+        "body": comment.body,
+    });
+    crate::util::log_audit(
+        pool,
+        Some(email.clone()),
+        "game_comment.update".to_string(),
+        "game_comment".to_string(),
+        Some(format!("{event_id}-{game_id}-{comment_id}")),
+        Some(metadata),
+    )
+    .await;
+
+    let reactions = get_reactions(pool, comment_id).await?;
+    Ok(to_comment(comment, &reactions, &email))
+}
+
+/// Delete a comment. Only the person who wrote it can, unless they're an admin.
+pub async fn delete(
+    pool: &PgPool,
+    event_id: i32,
+    game_id: i64,
+    comment_id: i32,
+    email: String,
+    is_admin: bool,
+) -> Result<(), Error> {
+    let comment = get_comment(pool, event_id, game_id, comment_id).await?;
+    let is_author = comment.email.eq_ignore_ascii_case(&email);
+    if !is_admin && !is_author {
+        return Err(Error::NotPermitted(
+            "You can only delete your own comments".to_string(),
+        ));
+    }
+
+    match game_comment::delete(pool, comment_id, email.clone()).await {
+        Ok(Some(_)) => (),
+        Ok(None) => return Err(Error::NotFound(format!("Comment {comment_id} not found"))),
+        Err(e) => {
+            return Err(Error::Controller(format!(
+                "Unable to delete comment due to: {e}"
+            )))
+        }
+    }
+
+    // Log audit entry, keeping what the comment said for moderation
+    let mut metadata = rocket::serde::json::serde_json::json!({
+        "event_id": event_id,
+        "game_id": game_id,
+        "comment_id": comment_id,
+        "author": comment.email,
+        "body": comment.body,
+    });
+    if !is_author {
+        metadata["admin_delete"] = rocket::serde::json::serde_json::json!(true);
+    }
+    crate::util::log_audit(
+        pool,
+        Some(email),
+        "game_comment.delete".to_string(),
+        "game_comment".to_string(),
+        Some(format!("{event_id}-{game_id}-{comment_id}")),
+        Some(metadata),
+    )
+    .await;
+
+    Ok(())
+}
+
+/// React to a comment with an emoji, or take the reaction back
+pub async fn react(
+    pool: &PgPool,
+    event_id: i32,
+    game_id: i64,
+    comment_id: i32,
+    email: String,
+    emoji: String,
+    add: bool,
+) -> Result<GameComment, Error> {
+    ensure_can_comment(pool, event_id, game_id, &email).await?;
+    validate_emoji(&emoji)?;
+
+    let comment = get_comment(pool, event_id, game_id, comment_id).await?;
+    if comment.deleted_at.is_some() {
+        return Err(Error::NotFound(format!("Comment {comment_id} not found")));
+    }
+
+    let result = if add {
+        game_comment::add_reaction(pool, comment_id, email.clone(), emoji).await
+    } else {
+        game_comment::remove_reaction(pool, comment_id, email.clone(), emoji).await
+    };
+    if let Err(e) = result {
+        return Err(Error::Controller(format!(
+            "Unable to save reaction due to: {e}"
+        )));
+    }
+
+    let reactions = get_reactions(pool, comment_id).await?;
+    Ok(to_comment(comment, &reactions, &email))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use chrono::{Duration, Utc};
+
+    fn comment(id: i32, parent_id: Option<i32>, email: &str) -> game_comment::GameComment {
+        game_comment::GameComment {
+            id,
+            event_id: 1,
+            game_id: 730,
+            parent_id,
+            email: email.to_string(),
+            handle: None,
+            avatar_url: None,
+            body: format!("Comment {id}"),
+            created_at: Utc::now() + Duration::minutes(i64::from(id)),
+            edited_at: None,
+            deleted_at: None,
+            deleted_by: None,
+        }
+    }
+
+    fn deleted(mut comment: game_comment::GameComment) -> game_comment::GameComment {
+        comment.deleted_at = Some(Utc::now());
+        comment.deleted_by = Some(comment.email.clone());
+        comment
+    }
+
+    fn reaction(comment_id: i32, email: &str, emoji: &str) -> game_comment::GameCommentReaction {
+        game_comment::GameCommentReaction {
+            comment_id,
+            email: email.to_string(),
+            emoji: emoji.to_string(),
+        }
+    }
+
+    #[test]
+    fn test_replies_are_nested_under_their_comments() {
+        let thread = build_thread(
+            vec![
+                comment(1, None, "a@example.com"),
+                comment(2, Some(1), "b@example.com"),
+                comment(3, None, "b@example.com"),
+                comment(4, Some(2), "a@example.com"),
+            ],
+            &[],
+            "a@example.com",
+        );
+
+        assert_eq!(thread.len(), 2);
+        assert_eq!(thread[0].id, 1);
+        assert_eq!(thread[0].replies[0].id, 2);
+        assert_eq!(thread[0].replies[0].replies[0].id, 4);
+        assert_eq!(thread[1].id, 3);
+        assert!(thread[0].own);
+        assert!(!thread[1].own);
+        assert_eq!(count_comments(&thread), 4);
+    }
+
+    #[test]
+    fn test_deleted_comments_are_kept_only_while_they_have_replies() {
+        let thread = build_thread(
+            vec![
+                deleted(comment(1, None, "a@example.com")),
+                comment(2, Some(1), "b@example.com"),
+                deleted(comment(3, None, "b@example.com")),
+            ],
+            &[],
+            "a@example.com",
+        );
+
+        assert_eq!(thread.len(), 1);
+        assert_eq!(thread[0].body, None);
+        assert_eq!(thread[0].replies[0].body, Some("Comment 2".to_string()));
+        assert_eq!(count_comments(&thread), 1);
+    }
+
+    #[test]
+    fn test_reactions_are_counted_by_emoji() {
+        let reactions = [
+            reaction(1, "a@example.com", "👍"),
+            reaction(1, "b@example.com", "🔥"),
+            reaction(1, "B@example.com", "👍"),
+            reaction(2, "a@example.com", "👍"),
+        ];
+        let comment = to_comment(
+            comment(1, None, "a@example.com"),
+            &reactions,
+            "b@example.com",
+        );
+
+        assert_eq!(comment.reactions.len(), 2);
+        assert_eq!(comment.reactions[0].emoji, "👍");
+        assert_eq!(comment.reactions[0].count, 2);
+        assert!(comment.reactions[0].reacted);
+        assert_eq!(comment.reactions[1].emoji, "🔥");
+        assert_eq!(comment.reactions[1].count, 1);
+    }
+
+    #[test]
+    fn test_comments_and_reactions_are_checked() {
+        assert_eq!(validate_body("  gg  ").ok(), Some("gg".to_string()));
+        assert!(matches!(validate_body("   "), Err(Error::BadInput(_))));
+        assert!(matches!(
+            validate_body(&"a".repeat(MAX_BODY_LENGTH + 1)),
+            Err(Error::BadInput(_))
+        ));
+
+        assert!(validate_emoji("👍").is_ok());
+        assert!(validate_emoji("👍🏽").is_ok());
+        assert!(matches!(validate_emoji("lol"), Err(Error::BadInput(_))));
+        assert!(matches!(validate_emoji(""), Err(Error::BadInput(_))));
+    }
+}
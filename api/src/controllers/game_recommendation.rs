@@ -0,0 +1,390 @@
+use std::collections::{HashMap, HashSet};
+
+use sqlx::PgPool;
+
+use crate::{
+    controllers::{ensure_user_invited, game, Error},
+    repositories::{game_suggestion, invitation, user_games},
+    routes::{event_games::EventGameRecommendation, games::GamePlayers},
+};
+
+/// How many of the group's most owned games to consider
+const CANDIDATE_LIMIT: i64 = 200;
+
+/// How much each signal counts towards a recommendation, adding up to 1
+const OWNERSHIP_WEIGHT: f64 = 0.4;
+const PLAYTIME_WEIGHT: f64 = 0.1;
+const RECENT_PLAYTIME_WEIGHT: f64 = 0.2;
+const PLAYERS_WEIGHT: f64 = 0.2;
+const POPULARITY_WEIGHT: f64 = 0.1;
+
+/// How well a game's player counts fit a group when they aren't known
+const UNKNOWN_PLAYERS_FIT: f64 = 0.5;
+
+/// Everything known about a game that could be recommended
+struct Candidate {
+    appid: i64,
+    name: String,
+    owner_count: i64,
+    playtime_forever: i64,
+    playtime_2weeks: i64,
+    players: Option<GamePlayers>,
+    past_event_count: i64,
+    past_vote_count: i64,
+    is_suggested: bool,
+}
+
+/// The highest values across all candidates, so each signal can be scaled from 0 to 1
+#[derive(Default)]
+struct Maximums {
+    playtime_forever: i64,
+    playtime_2weeks: i64,
+    past_vote_count: i64,
+}
+
+#[allow(clippy::cast_precision_loss)]
+const fn as_float(value: i64) -> f64 {
+    value as f64
+}
+
+/// Scale a value against the maximum on a log scale, so one person's thousands of hours don't drown out everything else
+fn scaled(value: i64, max: i64) -> f64 {
+    if max <= 0 {
+        return 0.0;
+    }
+    as_float(value.max(0)).ln_1p() / as_float(max).ln_1p()
+}
+
+const fn hours(minutes: i64) -> i64 {
+    (minutes + 30) / 60
+}
+
+/// How well a game suits the number of people attending, with why
+fn players_fit(players: Option<&GamePlayers>, attendee_count: i64) -> (f64, String) {
+    let known =
+        players.filter(|players| players.min_players.is_some() || players.max_players.is_some());
+    let Some(players) = known else {
+        return (
+            UNKNOWN_PLAYERS_FIT,
+            "Player counts aren't known".to_string(),
+        );
+    };
+
+    if let Some(min) = players.min_players {
+        if i64::from(min) > attendee_count {
+            return (0.0, format!("Needs at least {min} players"));
+        }
+    }
+
+    match players.max_players {
+        Some(max) if i64::from(max) < attendee_count => (
+            as_float(i64::from(max)) / as_float(attendee_count),
+            format!("Up to {max} of the {attendee_count} attending can play at once"),
+        ),
+        Some(max) => (
+            1.0,
+            format!("Everyone can play together (up to {max} players)"),
+        ),
+        None => (1.0, "Everyone can play together".to_string()),
+    }
+}
+
+/// Score a candidate from 0 to 1, explaining what contributed
+fn score(candidate: &Candidate, attendee_count: i64, maximums: &Maximums) -> (f64, Vec<String>) {
+    let mut reasons = Vec::new();
+
+    let ownership = if attendee_count > 0 {
+        as_float(candidate.owner_count) / as_float(attendee_count)
+    } else {
+        0.0
+    };
+    reasons.push(format!(
+        "Owned by {} of {attendee_count} attending gamers",
+        candidate.owner_count
+    ));
+
+    if candidate.playtime_forever > 0 {
+        reasons.push(format!(
+            "Played for {} hours between them",
+            hours(candidate.playtime_forever)
+        ));
+    }
+
+    if candidate.playtime_2weeks > 0 {
+        reasons.push(format!(
+            "Played for {} hours in the last two weeks",
+            hours(candidate.playtime_2weeks)
+        ));
+    }
+
+    let (fit, players_reason) = players_fit(candidate.players.as_ref(), attendee_count);
+    reasons.push(players_reason);
+
+    if candidate.past_event_count > 0 {
+        reasons.push(format!(
+            "Suggested at {} past events, with {} votes",
+            candidate.past_event_count, candidate.past_vote_count
+        ));
+    }
+
+    let score = [
+        (OWNERSHIP_WEIGHT, ownership),
+        (
+            PLAYTIME_WEIGHT,
+            scaled(candidate.playtime_forever, maximums.playtime_forever),
+        ),
+        (
+            RECENT_PLAYTIME_WEIGHT,
+            scaled(candidate.playtime_2weeks, maximums.playtime_2weeks),
+        ),
+        (PLAYERS_WEIGHT, fit),
+        (
+            POPULARITY_WEIGHT,
+            scaled(candidate.past_vote_count, maximums.past_vote_count),
+        ),
+    ]
+    .iter()
+    .map(|(weight, value)| weight * value)
+    .sum();
+
+    (score, reasons)
+}
+
+/// Rank candidates best first, keeping the top `count`
+fn rank(
+    candidates: Vec<Candidate>,
+    attendee_count: i64,
+    count: usize,
+) -> Vec<EventGameRecommendation> {
+    let maximums = candidates
+        .iter()
+        .fold(Maximums::default(), |maximums, candidate| Maximums {
+            playtime_forever: maximums.playtime_forever.max(candidate.playtime_forever),
+            playtime_2weeks: maximums.playtime_2weeks.max(candidate.playtime_2weeks),
+            past_vote_count: maximums.past_vote_count.max(candidate.past_vote_count),
+        });
+
+    let mut recommendations: Vec<EventGameRecommendation> = candidates
+        .into_iter()
+        .map(|candidate| {
+            let (score, reasons) = score(&candidate, attendee_count, &maximums);
+            EventGameRecommendation {
+                appid: candidate.appid,
+                name: candidate.name,
+                score,
+                owner_count: candidate.owner_count,
+                attendee_count,
+                playtime_forever: candidate.playtime_forever,
+                playtime_2weeks: candidate.playtime_2weeks,
+                players: candidate.players,
+                past_event_count: candidate.past_event_count,
+                past_vote_count: candidate.past_vote_count,
+                is_suggested: candidate.is_suggested,
+                reasons,
+            }
+        })
+        .collect();
+
+    recommendations.sort_by(|a, b| {
+        b.score
+            .total_cmp(&a.score)
+            .then_with(|| a.name.cmp(&b.name))
+    });
+    recommendations.truncate(count);
+    recommendations
+}
+
+/// Recommend games for the people going to an event, best first
+pub async fn get(
+    pool: &PgPool,
+    event_id: i32,
+    email: String,
+    count: usize,
+) -> Result<Vec<EventGameRecommendation>, Error> {
+    ensure_user_invited(pool, event_id, &email).await?;
+
+    let invitations = match invitation::filter(
+        pool,
+        invitation::Filter {
+            event_id: Some(event_id),
+            email: None,
+        },
+    )
+    .await
+    {
+        Ok(invitations) => invitations,
+        Err(e) => {
+            return Err(Error::Controller(format!(
+                "Unable to get event invitations due to: {e}"
+            )))
+        }
+    };
+
+    let attending_emails: Vec<String> = invitations
+        .into_iter()
+        .filter(|i| {
+            i.response == Some(invitation::Response::Yes)
+                || i.response == Some(invitation::Response::Maybe)
+        })
+        .map(|i| i.email)
+        .collect();
+    let attendee_count = i64::try_from(attending_emails.len()).unwrap_or(i64::MAX);
+
+    let group_games =
+        match user_games::owned_by_group(pool, &attending_emails, CANDIDATE_LIMIT).await {
+            Ok(group_games) => group_games,
+            Err(e) => {
+                return Err(Error::Controller(format!(
+                    "Unable to get user games due to: {e}"
+                )))
+            }
+        };
+    let appids: Vec<i64> = group_games.iter().map(|game| game.appid).collect();
+
+    let mut players = game::get_players(pool, &appids).await?;
+
+    let popularity: HashMap<i64, game_suggestion::PastPopularity> =
+        match game_suggestion::get_past_popularity(pool, event_id, &appids).await {
+            Ok(popularity) => popularity
+                .into_iter()
+                .map(|popularity| (popularity.game_id, popularity))
+                .collect(),
+            Err(e) => {
+                return Err(Error::Controller(format!(
+                    "Unable to get past game popularity due to: {e}"
+                )))
+            }
+        };
+
+    let suggested: HashSet<i64> = match game_suggestion::filter(
+        pool,
+        game_suggestion::Filter {
+            event_id: Some(event_id),
+            game_id: None,
+        },
+        email,
+    )
+    .await
+    {
+        Ok(suggestions) => suggestions
+            .into_iter()
+            .map(|suggestion| suggestion.game_id)
+            .collect(),
+        Err(e) => {
+            return Err(Error::Controller(format!(
+                "Unable to get game suggestions due to: {e}"
+            )))
+        }
+    };
+
+    let candidates = group_games
+        .into_iter()
+        .map(|game| {
+            let popularity = popularity.get(&game.appid);
+            Candidate {
+                appid: game.appid,
+                name: game.name,
+                owner_count: game.owner_count,
+                playtime_forever: game.playtime_forever,
+                playtime_2weeks: game.playtime_2weeks,
+                players: players.remove(&game.appid),
+                past_event_count: popularity.map_or(0, |popularity| popularity.event_count),
+                past_vote_count: popularity.map_or(0, |popularity| popularity.vote_count),
+                is_suggested: suggested.contains(&game.appid),
+            }
+        })
+        .collect();
+
+    Ok(rank(candidates, attendee_count, count))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use chrono::Utc;
+
+    fn candidate(appid: i64, owner_count: i64) -> Candidate {
+        Candidate {
+            appid,
+            name: format!("Game {appid}"),
+            owner_count,
+            playtime_forever: 0,
+            playtime_2weeks: 0,
+            players: None,
+            past_event_count: 0,
+            past_vote_count: 0,
+            is_suggested: false,
+        }
+    }
+
+    fn players(min: Option<i32>, max: Option<i32>) -> GamePlayers {
+        GamePlayers {
+            min_players: min,
+            max_players: max,
+            has_lan: false,
+            has_split_screen: false,
+            is_manual: false,
+            last_modified: Utc::now(),
+        }
+    }
+
+    #[test]
+    fn test_more_owners_rank_higher() {
+        let ranked = rank(vec![candidate(1, 2), candidate(2, 5)], 6, 10);
+        assert_eq!(ranked[0].appid, 2);
+        assert_eq!(ranked[0].reasons[0], "Owned by 5 of 6 attending gamers");
+    }
+
+    #[test]
+    fn test_recent_playtime_breaks_ties() {
+        let mut recent = candidate(1, 3);
+        recent.playtime_2weeks = 600;
+        let ranked = rank(vec![candidate(2, 3), recent], 6, 10);
+        assert_eq!(ranked[0].appid, 1);
+        assert!(ranked[0]
+            .reasons
+            .contains(&"Played for 10 hours in the last two weeks".to_string()));
+    }
+
+    #[test]
+    fn test_games_everyone_can_play_rank_higher() {
+        let mut small = candidate(1, 4);
+        small.players = Some(players(Some(1), Some(2)));
+        let mut big = candidate(2, 4);
+        big.players = Some(players(Some(2), Some(16)));
+        let ranked = rank(vec![small, big], 6, 10);
+        assert_eq!(ranked[0].appid, 2);
+        assert!(ranked[1]
+            .reasons
+            .contains(&"Up to 2 of the 6 attending can play at once".to_string()));
+    }
+
+    #[test]
+    fn test_players_fit_handles_unknown_and_minimums() {
+        let fit = |players: Option<&GamePlayers>| players_fit(players, 4).0;
+        assert!((fit(None) - UNKNOWN_PLAYERS_FIT).abs() < 1e-9);
+        assert!((fit(Some(&players(None, None))) - UNKNOWN_PLAYERS_FIT).abs() < 1e-9);
+        assert!(fit(Some(&players(Some(8), None))).abs() < 1e-9);
+        assert!((fit(Some(&players(Some(2), None))) - 1.0).abs() < 1e-9);
+    }
+
+    #[test]
+    fn test_scores_stay_between_zero_and_one() {
+        let mut best = candidate(1, 6);
+        best.playtime_forever = 100_000;
+        best.playtime_2weeks = 1_000;
+        best.past_event_count = 3;
+        best.past_vote_count = 12;
+        best.players = Some(players(Some(1), Some(64)));
+        let ranked = rank(vec![best, candidate(2, 0)], 6, 10);
+        assert!((ranked[0].score - 1.0).abs() < 1e-9);
+        assert!(ranked[1].score >= 0.0);
+    }
+
+    #[test]
+    fn test_keeps_only_the_top_count() {
+        let ranked = rank((1..=5).map(|appid| candidate(appid, appid)).collect(), 5, 2);
+        assert_eq!(ranked.len(), 2);
+        assert_eq!(ranked[0].appid, 5);
+    }
+}
@@ -1,12 +1,26 @@
-use chrono::{DateTime, Datelike, Utc};
+use chrono::{DateTime, Datelike, NaiveTime, Utc};
+use rocket::serde::json::serde_json::json;
 use sqlx::PgPool;
 use std::collections::HashMap;
 
 use crate::{
-    controllers::{ensure_user_invited, Error},
-    repositories::{event, game_schedule, game_suggestion, invitation},
-    routes::game_schedule::{GameScheduleEntry, GameScheduleRequest},
-    scheduler::{self, Game, OccupiedSlot, SchedulerInput, Voter},
+    controllers::{
+        ensure_user_invited, event_voting,
+        game::{self, validate_duration},
+        Error,
+    },
+    repositories::{
+        event, event_blocked_period, event_schedule_config, game_schedule, game_suggestion,
+        invitation, room,
+    },
+    routes::game_schedule::{
+        BlockedPeriod, BlockedPeriodSubmit, EventScheduleConfig, EventScheduleConfigSubmit,
+        GameDuration, GameDurationSource, GameScheduleEntry, GameScheduleExplanation,
+        GameScheduleRequest, RecalculatedSchedule, ScheduleBlock, ScheduleBlockKind,
+        ScheduleSlotScore, UnscheduledGame, UnscheduledReason,
+    },
+    scheduler::{self, Game, OccupiedSlot, OptimiserOptions, QuietHours, SchedulerInput, Voter},
+    voting,
 };
 
 /// Helper function to format date with ordinal suffix (1st, 2nd, 3rd, 4th, etc.)
@@ -30,14 +44,284 @@ impl From<game_schedule::GameSchedule> for GameScheduleEntry {
             game_name: schedule.game_name,
             start_time: schedule.start_time,
             duration_minutes: schedule.duration_minutes,
+            setup_minutes: 0,
+            duration_source: None,
+            group: None,
+            track: schedule.track,
+            room_id: schedule.room_id,
             is_pinned: schedule.is_pinned,
             is_suggested: false,
+            explanation: None,
             created_at: schedule.created_at,
             last_modified: schedule.last_modified,
         }
     }
 }
 
+impl From<scheduler::UnscheduledReason> for UnscheduledReason {
+    fn from(reason: scheduler::UnscheduledReason) -> Self {
+        match reason {
+            scheduler::UnscheduledReason::OutsideEvent => Self::OutsideEvent,
+            scheduler::UnscheduledReason::NoVoterAvailable => Self::NoVoterAvailable,
+            scheduler::UnscheduledReason::NoSlotFits => Self::NoSlotFits,
+        }
+    }
+}
+
+impl From<event_schedule_config::EventScheduleConfig> for EventScheduleConfig {
+    fn from(config: event_schedule_config::EventScheduleConfig) -> Self {
+        Self {
+            event_id: config.event_id,
+            default_duration_minutes: config.default_duration_minutes,
+            setup_buffer_minutes: config.setup_buffer_minutes,
+            use_past_schedules: config.use_past_schedules,
+            allow_parallel_groups: config.allow_parallel_groups,
+            track_count: config.track_count,
+            tracks_from_rooms: config.tracks_from_rooms,
+            use_optimiser: config.use_optimiser,
+            quiet_hours_start: config.quiet_hours_start,
+            quiet_hours_end: config.quiet_hours_end,
+            created_at: config.created_at,
+            last_modified: config.last_modified,
+        }
+    }
+}
+
+/// The most time that can be left between games to set up the next one
+const MAX_SETUP_BUFFER_MINUTES: i32 = 4 * 60;
+
+/// The most games that can be scheduled at the same time
+const MAX_TRACKS: i32 = 16;
+
+/// How long the optimiser can spend searching for a better schedule
+const OPTIMISER_TIME_BUDGET: std::time::Duration = std::time::Duration::from_secs(2);
+
+/// The most changes the optimiser tries, so an event's schedule is the same each time
+/// unless the time budget runs out first
+const OPTIMISER_MAX_ITERATIONS: u32 = 2000;
+
+impl From<event_blocked_period::EventBlockedPeriod> for BlockedPeriod {
+    fn from(blocked_period: event_blocked_period::EventBlockedPeriod) -> Self {
+        Self {
+            id: blocked_period.id,
+            event_id: blocked_period.event_id,
+            name: blocked_period.name,
+            start_time: blocked_period.start_time,
+            end_time: blocked_period.end_time,
+            created_at: blocked_period.created_at,
+            last_modified: blocked_period.last_modified,
+        }
+    }
+}
+
+/// Games aren't played from 1am to 10am unless an event says otherwise
+fn default_quiet_hours_start() -> NaiveTime {
+    NaiveTime::from_hms_opt(1, 0, 0).unwrap_or_default()
+}
+
+fn default_quiet_hours_end() -> NaiveTime {
+    NaiveTime::from_hms_opt(10, 0, 0).unwrap_or_default()
+}
+
+/// Get how games are scheduled for an event, with defaults if it hasn't been configured
+pub async fn get_config(pool: &PgPool, event_id: i32) -> Result<EventScheduleConfig, Error> {
+    match event_schedule_config::get(pool, event_id).await {
+        Ok(Some(config)) => Ok(config.into()),
+        Ok(None) => Ok(EventScheduleConfig {
+            event_id,
+            default_duration_minutes: 120,
+            setup_buffer_minutes: 0,
+            use_past_schedules: true,
+            allow_parallel_groups: false,
+            track_count: 1,
+            tracks_from_rooms: false,
+            use_optimiser: false,
+            quiet_hours_start: default_quiet_hours_start(),
+            quiet_hours_end: default_quiet_hours_end(),
+            created_at: Utc::now(),
+            last_modified: Utc::now(),
+        }),
+        Err(e) => Err(Error::Controller(format!(
+            "Unable to get schedule config due to: {e}"
+        ))),
+    }
+}
+
+pub async fn upsert_config(
+    pool: &PgPool,
+    event_id: i32,
+    config: EventScheduleConfigSubmit,
+    user_email: String,
+) -> Result<EventScheduleConfig, Error> {
+    validate_duration(config.default_duration_minutes)?;
+    if !(0..=MAX_SETUP_BUFFER_MINUTES).contains(&config.setup_buffer_minutes) {
+        return Err(Error::BadInput(format!(
+            "Setup time between games must be between 0 and {MAX_SETUP_BUFFER_MINUTES} minutes"
+        )));
+    }
+    let track_count = config.track_count.unwrap_or(1);
+    if !(1..=MAX_TRACKS).contains(&track_count) {
+        return Err(Error::BadInput(format!(
+            "There must be between 1 and {MAX_TRACKS} tracks"
+        )));
+    }
+    let quiet_hours_start = config
+        .quiet_hours_start
+        .unwrap_or_else(default_quiet_hours_start);
+    let quiet_hours_end = config
+        .quiet_hours_end
+        .unwrap_or_else(default_quiet_hours_end);
+
+    match event_schedule_config::upsert(
+        pool,
+        event_id,
+        config.default_duration_minutes,
+        config.setup_buffer_minutes,
+        config.use_past_schedules,
+        config.allow_parallel_groups,
+        track_count,
+        config.tracks_from_rooms,
+        config.use_optimiser,
+        quiet_hours_start,
+        quiet_hours_end,
+    )
+    .await
+    {
+        Ok(config_result) => {
+            // Log audit entry
+            let metadata = json!({
+                "event_id": event_id,
+                "default_duration_minutes": config.default_duration_minutes,
+                "setup_buffer_minutes": config.setup_buffer_minutes,
+                "use_past_schedules": config.use_past_schedules,
+                "allow_parallel_groups": config.allow_parallel_groups,
+                "track_count": track_count,
+                "tracks_from_rooms": config.tracks_from_rooms,
+                "use_optimiser": config.use_optimiser,
+                "quiet_hours_start": quiet_hours_start,
+                "quiet_hours_end": quiet_hours_end,
+            });
+            crate::util::log_audit(
+                pool,
+                Some(user_email),
+                "event_schedule_config.update".to_string(),
+                "event_schedule_config".to_string(),
+                Some(event_id.to_string()),
+                Some(metadata),
+            )
+            .await;
+
+            Ok(config_result.into())
+        }
+        Err(e) => Err(Error::Controller(format!(
+            "Unable to save schedule config due to: {e}"
+        ))),
+    }
+}
+
+/// Work out how long to schedule a game for. Durations set by an admin for the event win,
+/// then durations set for the game, then how long it was scheduled for at other events.
+const fn estimate_duration(
+    durations: &game_schedule::GameDurations,
+    config: &EventScheduleConfig,
+) -> (i32, GameDurationSource) {
+    if let Some(minutes) = durations.event_minutes {
+        return (minutes, GameDurationSource::Event);
+    }
+    if let Some(minutes) = durations.game_minutes {
+        return (minutes, GameDurationSource::Game);
+    }
+    match durations.past_minutes {
+        Some(minutes) if config.use_past_schedules && minutes > 0 => {
+            (minutes, GameDurationSource::PastSchedules)
+        }
+        _ => (config.default_duration_minutes, GameDurationSource::Default),
+    }
+}
+
+/// Work out how long every game suggested for an event should be scheduled for
+async fn estimate_durations(
+    pool: &PgPool,
+    event_id: i32,
+    config: &EventScheduleConfig,
+) -> Result<Vec<GameDuration>, Error> {
+    let durations = game_schedule::get_durations(pool, event_id)
+        .await
+        .map_err(|e| Error::Controller(format!("Unable to get game durations due to: {e}")))?;
+
+    Ok(durations
+        .into_iter()
+        .map(|durations| {
+            let (duration_minutes, source) = estimate_duration(&durations, config);
+            GameDuration {
+                game_id: durations.game_id,
+                game_name: durations.game_name,
+                duration_minutes,
+                source,
+            }
+        })
+        .collect())
+}
+
+/// Get how long each game suggested for an event will be scheduled for
+pub async fn get_durations(
+    pool: &PgPool,
+    event_id: i32,
+    email: &str,
+) -> Result<Vec<GameDuration>, Error> {
+    ensure_user_invited(pool, event_id, email).await?;
+
+    let config = get_config(pool, event_id).await?;
+    estimate_durations(pool, event_id, &config).await
+}
+
+/// Set how long a suggested game should be scheduled for at an event (admin only)
+pub async fn set_duration(
+    pool: &PgPool,
+    event_id: i32,
+    game_id: i64,
+    duration_minutes: Option<i32>,
+    email: String,
+) -> Result<GameDuration, Error> {
+    if let Some(duration_minutes) = duration_minutes {
+        validate_duration(duration_minutes)?;
+    }
+
+    let updated = game_suggestion::update_duration(pool, event_id, game_id, duration_minutes)
+        .await
+        .map_err(|e| Error::Controller(format!("Unable to save game duration due to: {e}")))?;
+    if !updated {
+        return Err(Error::NotFound(format!(
+            "Game {game_id} hasn't been suggested for this event"
+        )));
+    }
+
+    crate::util::log_audit(
+        pool,
+        Some(email),
+        "game_suggestion.update_duration".to_string(),
+        "game_suggestion".to_string(),
+        Some(format!("{event_id}-{game_id}")),
+        Some(json!({
+            "event_id": event_id,
+            "game_id": game_id,
+            "duration_minutes": duration_minutes,
+        })),
+    )
+    .await;
+
+    let config = get_config(pool, event_id).await?;
+    estimate_durations(pool, event_id, &config)
+        .await?
+        .into_iter()
+        .find(|duration| duration.game_id == game_id)
+        .ok_or_else(|| {
+            Error::NotFound(format!(
+                "Game {game_id} hasn't been suggested for this event"
+            ))
+        })
+}
+
 /// Get all scheduled games for an event (pinned + suggested)
 pub async fn get_all(
     pool: &PgPool,
@@ -59,7 +343,9 @@ pub async fn get_all(
     .map_err(|e| Error::Controller(format!("Unable to get pinned games due to: {e}")))?;
 
     // Get suggested games from scheduling algorithm
-    let suggested_games = schedule_suggested_games(pool, event_id).await?;
+    let suggested_games = schedule_suggested_games(pool, event_id, false)
+        .await?
+        .suggested;
 
     // Combine pinned and suggested games
     let mut all_games: Vec<GameScheduleEntry> = pinned_games
@@ -72,6 +358,234 @@ pub async fn get_all(
     Ok(all_games)
 }
 
+async fn get_event(pool: &PgPool, event_id: i32) -> Result<event::Event, Error> {
+    event::filter(
+        pool,
+        event::Filter {
+            ids: Some(vec![event_id]),
+        },
+    )
+    .await
+    .map_err(|e| Error::Controller(format!("Unable to get event due to: {e}")))?
+    .into_iter()
+    .next()
+    .ok_or_else(|| Error::NotFound(format!("Event {event_id} not found")))
+}
+
+/// Get quiet hours and blocked periods in an event, in the order they start
+pub async fn get_blocks(
+    pool: &PgPool,
+    event_id: i32,
+    email: &str,
+) -> Result<Vec<ScheduleBlock>, Error> {
+    ensure_user_invited(pool, event_id, email).await?;
+
+    let event = get_event(pool, event_id).await?;
+
+    let config = get_config(pool, event_id).await?;
+    let quiet_hours = QuietHours {
+        start: config.quiet_hours_start,
+        end: config.quiet_hours_end,
+    };
+    let mut blocks: Vec<ScheduleBlock> =
+        scheduler::quiet_periods(quiet_hours, event.time_begin, event.time_end)
+            .into_iter()
+            .map(|period| ScheduleBlock {
+                blocked_period_id: None,
+                name: "Quiet hours".to_string(),
+                start_time: period.start_time,
+                end_time: period.end_time,
+                kind: ScheduleBlockKind::QuietHours,
+            })
+            .collect();
+
+    let blocked_periods = event_blocked_period::get_all(pool, event_id)
+        .await
+        .map_err(|e| Error::Controller(format!("Unable to get blocked periods due to: {e}")))?;
+    blocks.extend(blocked_periods.into_iter().map(|period| ScheduleBlock {
+        blocked_period_id: Some(period.id),
+        name: period.name,
+        start_time: period.start_time,
+        end_time: period.end_time,
+        kind: ScheduleBlockKind::Blocked,
+    }));
+
+    blocks.sort_by_key(|block| block.start_time);
+    Ok(blocks)
+}
+
+fn validate_blocked_period(
+    blocked_period: &BlockedPeriodSubmit,
+    event: &event::Event,
+) -> Result<(), Error> {
+    if blocked_period.name.trim().is_empty() {
+        return Err(Error::BadInput(
+            "Blocked period name cannot be empty".to_string(),
+        ));
+    }
+    if blocked_period.end_time <= blocked_period.start_time {
+        return Err(Error::BadInput(
+            "A blocked period must end after it starts".to_string(),
+        ));
+    }
+    if blocked_period.start_time < event.time_begin || blocked_period.end_time > event.time_end {
+        return Err(Error::BadInput(format!(
+            "A blocked period must be during the event, from {} to {}",
+            event.time_begin.format("%a %e %b %Y %H:%M"),
+            event.time_end.format("%a %e %b %Y %H:%M")
+        )));
+    }
+    Ok(())
+}
+
+/// Get a blocked period, making sure it's in the event
+async fn get_blocked_period(
+    pool: &PgPool,
+    event_id: i32,
+    blocked_period_id: i32,
+) -> Result<event_blocked_period::EventBlockedPeriod, Error> {
+    match event_blocked_period::get(pool, blocked_period_id).await {
+        Ok(Some(blocked_period)) if blocked_period.event_id == event_id => Ok(blocked_period),
+        Ok(_) => Err(Error::NotFound(format!(
+            "Blocked period {blocked_period_id} not found in event {event_id}"
+        ))),
+        Err(e) => Err(Error::Controller(format!(
+            "Unable to get blocked period due to: {e}"
+        ))),
+    }
+}
+
+pub async fn create_blocked_period(
+    pool: &PgPool,
+    event_id: i32,
+    blocked_period: BlockedPeriodSubmit,
+    user_email: String,
+) -> Result<BlockedPeriod, Error> {
+    let event = get_event(pool, event_id).await?;
+    validate_blocked_period(&blocked_period, &event)?;
+
+    let created = event_blocked_period::create(
+        pool,
+        event_id,
+        blocked_period.name.trim().to_string(),
+        blocked_period.start_time,
+        blocked_period.end_time,
+    )
+    .await
+    .map_err(|e| Error::Controller(format!("Unable to create blocked period due to: {e}")))?;
+
+    crate::util::log_audit(
+        pool,
+        Some(user_email),
+        "event_blocked_period.create".to_string(),
+        "event_blocked_period".to_string(),
+        Some(created.id.to_string()),
+        Some(json!({
+            "event_id": event_id,
+            "name": created.name,
+            "start_time": created.start_time,
+            "end_time": created.end_time,
+        })),
+    )
+    .await;
+
+    Ok(created.into())
+}
+
+pub async fn update_blocked_period(
+    pool: &PgPool,
+    event_id: i32,
+    blocked_period_id: i32,
+    blocked_period: BlockedPeriodSubmit,
+    user_email: String,
+) -> Result<BlockedPeriod, Error> {
+    get_blocked_period(pool, event_id, blocked_period_id).await?;
+    let event = get_event(pool, event_id).await?;
+    validate_blocked_period(&blocked_period, &event)?;
+
+    let updated = event_blocked_period::update(
+        pool,
+        blocked_period_id,
+        blocked_period.name.trim().to_string(),
+        blocked_period.start_time,
+        blocked_period.end_time,
+    )
+    .await
+    .map_err(|e| Error::Controller(format!("Unable to update blocked period due to: {e}")))?;
+
+    crate::util::log_audit(
+        pool,
+        Some(user_email),
+        "event_blocked_period.update".to_string(),
+        "event_blocked_period".to_string(),
+        Some(blocked_period_id.to_string()),
+        Some(json!({
+            "event_id": event_id,
+            "name": updated.name,
+            "start_time": updated.start_time,
+            "end_time": updated.end_time,
+        })),
+    )
+    .await;
+
+    Ok(updated.into())
+}
+
+pub async fn delete_blocked_period(
+    pool: &PgPool,
+    event_id: i32,
+    blocked_period_id: i32,
+    user_email: String,
+) -> Result<(), Error> {
+    let blocked_period = get_blocked_period(pool, event_id, blocked_period_id).await?;
+
+    event_blocked_period::delete(pool, blocked_period_id)
+        .await
+        .map_err(|e| Error::Controller(format!("Unable to delete blocked period due to: {e}")))?;
+
+    crate::util::log_audit(
+        pool,
+        Some(user_email),
+        "event_blocked_period.delete".to_string(),
+        "event_blocked_period".to_string(),
+        Some(blocked_period_id.to_string()),
+        Some(json!({
+            "event_id": event_id,
+            "name": blocked_period.name,
+        })),
+    )
+    .await;
+
+    Ok(())
+}
+
+/// Check a scheduled game's track, and that its room is in the event. Returns the track.
+async fn validate_track(
+    pool: &PgPool,
+    event_id: i32,
+    request: &GameScheduleRequest,
+) -> Result<i32, Error> {
+    let track = request.track.unwrap_or(1);
+    if !(1..=MAX_TRACKS).contains(&track) {
+        return Err(Error::BadInput(format!(
+            "Tracks are numbered from 1 to {MAX_TRACKS}"
+        )));
+    }
+
+    if let Some(room_id) = request.room_id {
+        let room = room::get(pool, room_id)
+            .await
+            .map_err(|e| Error::Controller(format!("Unable to get room due to: {e}")))?;
+        if room.is_none_or(|room| room.event_id != event_id) {
+            return Err(Error::BadInput(format!(
+                "Room {room_id} isn't part of this event"
+            )));
+        }
+    }
+
+    Ok(track)
+}
+
 /// Create a new scheduled game (admin only)
 pub async fn create(
     pool: &PgPool,
@@ -79,12 +593,16 @@ pub async fn create(
     request: GameScheduleRequest,
     _email: &str,
 ) -> Result<GameScheduleEntry, Error> {
+    let track = validate_track(pool, event_id, &request).await?;
+
     let schedule = game_schedule::create(
         pool,
         event_id,
         request.game_id,
         request.start_time,
         request.duration_minutes,
+        track,
+        request.room_id,
         true, // Always pinned when manually created
     )
     .await
@@ -100,11 +618,19 @@ pub async fn update(
     request: GameScheduleRequest,
     _email: &str,
 ) -> Result<GameScheduleEntry, Error> {
+    let existing = game_schedule::get(pool, schedule_id)
+        .await
+        .map_err(|e| Error::Controller(format!("Unable to get game schedule due to: {e}")))?
+        .ok_or_else(|| Error::NotFound("Game schedule not found".to_string()))?;
+    let track = validate_track(pool, existing.event_id, &request).await?;
+
     let schedule = game_schedule::update(
         pool,
         schedule_id,
         request.start_time,
         request.duration_minutes,
+        track,
+        request.room_id,
     )
     .await
     .map_err(|e| Error::Controller(format!("Unable to update game schedule due to: {e}")))?;
@@ -132,12 +658,13 @@ pub async fn pin(
     create(pool, event_id, request, email).await
 }
 
-/// Schedule games using the scheduling algorithm
+/// Schedule games using the scheduling algorithm, explaining the schedule if asked to
 #[allow(clippy::too_many_lines)]
 pub async fn schedule_suggested_games(
     pool: &PgPool,
     event_id: i32,
-) -> Result<Vec<GameScheduleEntry>, Error> {
+    explain: bool,
+) -> Result<RecalculatedSchedule, Error> {
     // Get event details
     let events = event::filter(
         pool,
@@ -183,9 +710,28 @@ pub async fn schedule_suggested_games(
         .collect();
 
     if games_to_schedule.is_empty() {
-        return Ok(Vec::new());
+        return Ok(RecalculatedSchedule {
+            suggested: Vec::new(),
+            unscheduled: Vec::new(),
+        });
     }
 
+    // Prioritise games the way the event's voting method ranks them
+    let tally = event_voting::tally(pool, event_id).await?;
+    let priorities = voting::priorities(tally.voting_method.into(), &tally.standings);
+
+    // Work out how long each game takes
+    let config = get_config(pool, event_id).await?;
+    let durations: HashMap<i64, GameDuration> = estimate_durations(pool, event_id, &config)
+        .await?
+        .into_iter()
+        .map(|duration| (duration.game_id, duration))
+        .collect();
+
+    // Get how many people can play each game
+    let game_ids: Vec<i64> = games_to_schedule.iter().map(|g| g.game_id).collect();
+    let players = game::get_players(pool, &game_ids).await?;
+
     // Get voters and their availability for each game
     let mut voters_map: HashMap<String, Voter> = HashMap::new();
     let mut games: Vec<Game> = Vec::new();
@@ -218,17 +764,79 @@ pub async fn schedule_suggested_games(
         games.push(Game {
             id: game_record.game_id,
             name: game_record.game_name,
-            votes: game_record.vote_count.unwrap_or(0),
+            votes: priorities
+                .get(&game_record.game_id)
+                .copied()
+                .unwrap_or_else(|| game_record.vote_count.unwrap_or(0)),
             voter_ids,
+            duration_minutes: durations
+                .get(&game_record.game_id)
+                .map_or(config.default_duration_minutes, |duration| {
+                    duration.duration_minutes
+                }),
+            min_players: players
+                .get(&game_record.game_id)
+                .and_then(|players| players.min_players),
+            max_players: players
+                .get(&game_record.game_id)
+                .and_then(|players| players.max_players),
         });
     }
 
-    // Convert pinned games to occupied slots
-    let pinned_slots: Vec<OccupiedSlot> = pinned_games
-        .iter()
-        .map(|schedule| OccupiedSlot {
+    // Work out the tracks, one per room if they come from the event's rooms
+    let rooms = if config.tracks_from_rooms {
+        room::get_all(pool, event_id)
+            .await
+            .map_err(|e| Error::Controller(format!("Unable to get rooms due to: {e}")))?
+    } else {
+        Vec::new()
+    };
+    let tracks = if config.tracks_from_rooms {
+        i32::try_from(rooms.len())
+            .unwrap_or(MAX_TRACKS)
+            .clamp(1, MAX_TRACKS)
+    } else {
+        config.track_count
+    };
+    let room_for_track = |track: i32| {
+        usize::try_from(track - 1)
+            .ok()
+            .and_then(|index| rooms.get(index))
+            .map(|room| room.id)
+    };
+
+    // Convert pinned games to occupied slots, on their room's track if tracks come from rooms.
+    // The people who voted for a pinned game are expected to be playing it.
+    let mut pinned_slots: Vec<OccupiedSlot> = Vec::new();
+    for schedule in &pinned_games {
+        let voter_ids = invitation::get_voters_for_game(pool, event_id, schedule.game_id)
+            .await
+            .map_err(|e| Error::Controller(format!("Unable to get voters due to: {e}")))?
+            .into_iter()
+            .map(|voter| voter.email)
+            .collect();
+        let track = schedule
+            .room_id
+            .and_then(|room_id| rooms.iter().position(|room| room.id == room_id))
+            .and_then(|index| i32::try_from(index + 1).ok())
+            .unwrap_or(schedule.track);
+
+        pinned_slots.push(OccupiedSlot {
             start_time: schedule.start_time,
             duration_minutes: schedule.duration_minutes,
+            track,
+            voter_ids,
+        });
+    }
+
+    // No games in quiet hours or blocked periods
+    let blocked_periods = event_blocked_period::get_all(pool, event_id)
+        .await
+        .map_err(|e| Error::Controller(format!("Unable to get blocked periods due to: {e}")))?
+        .into_iter()
+        .map(|period| scheduler::BlockedPeriod {
+            start_time: period.start_time,
+            end_time: period.end_time,
         })
         .collect();
 
@@ -239,10 +847,40 @@ pub async fn schedule_suggested_games(
         event_start,
         event_end,
         pinned_slots,
-        default_game_duration: 120, // 2 hours default
+        quiet_hours: Some(QuietHours {
+            start: config.quiet_hours_start,
+            end: config.quiet_hours_end,
+        }),
+        blocked_periods,
+        setup_buffer_minutes: config.setup_buffer_minutes,
+        allow_parallel_groups: config.allow_parallel_groups,
+        tracks,
+        explain,
     };
 
-    let scheduler_output = scheduler::schedule_games(&scheduler_input);
+    let scheduler_output = if config.use_optimiser {
+        let options = OptimiserOptions {
+            seed: u64::from(event_id.unsigned_abs()),
+            max_iterations: OPTIMISER_MAX_ITERATIONS,
+            time_budget: OPTIMISER_TIME_BUDGET,
+        };
+        // The optimiser keeps searching for up to its time budget, so run it off the async
+        // workers to not hold up other requests
+        match tokio::task::spawn_blocking(move || {
+            scheduler::optimise_schedule(&scheduler_input, &options)
+        })
+        .await
+        {
+            Ok(output) => output,
+            Err(e) => {
+                return Err(Error::Controller(format!(
+                    "Unable to optimise the game schedule due to: {e}"
+                )))
+            }
+        }
+    } else {
+        scheduler::schedule_games(&scheduler_input)
+    };
 
     // Convert scheduler output to GameScheduleEntry
     let suggested_entries: Vec<GameScheduleEntry> = scheduler_output
@@ -255,8 +893,31 @@ pub async fn schedule_suggested_games(
             game_name: schedule.game_name,
             start_time: schedule.start_time,
             duration_minutes: schedule.duration_minutes,
+            setup_minutes: schedule.setup_minutes,
+            duration_source: durations
+                .get(&schedule.game_id)
+                .map(|duration| duration.source),
+            group: schedule.group,
+            track: schedule.track,
+            room_id: room_for_track(schedule.track),
             is_pinned: false,
             is_suggested: true,
+            explanation: schedule
+                .explanation
+                .map(|explanation| GameScheduleExplanation {
+                    available_voters: explanation.available_voter_ids,
+                    unavailable_voters: explanation.unavailable_voter_ids,
+                    runners_up: explanation
+                        .runners_up
+                        .into_iter()
+                        .map(|slot| ScheduleSlotScore {
+                            start_time: slot.start_time,
+                            track: slot.track,
+                            room_id: room_for_track(slot.track),
+                            availability_score: slot.availability_score,
+                        })
+                        .collect(),
+                }),
             created_at: Utc::now(),
             last_modified: Utc::now(),
         })
@@ -281,5 +942,143 @@ pub async fn schedule_suggested_games(
         );
     }
 
-    Ok(suggested_entries)
+    // The scheduler only gives unscheduled games when asked to explain
+    let unscheduled = scheduler_output
+        .unscheduled
+        .into_iter()
+        .map(|game| UnscheduledGame {
+            game_id: game.game_id,
+            game_name: game.game_name,
+            reason: game.reason.into(),
+        })
+        .collect();
+
+    Ok(RecalculatedSchedule {
+        suggested: suggested_entries,
+        unscheduled,
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn config(use_past_schedules: bool) -> EventScheduleConfig {
+        EventScheduleConfig {
+            event_id: 1,
+            default_duration_minutes: 120,
+            setup_buffer_minutes: 0,
+            use_past_schedules,
+            allow_parallel_groups: false,
+            track_count: 1,
+            tracks_from_rooms: false,
+            use_optimiser: false,
+            quiet_hours_start: default_quiet_hours_start(),
+            quiet_hours_end: default_quiet_hours_end(),
+            created_at: Utc::now(),
+            last_modified: Utc::now(),
+        }
+    }
+
+    fn durations(
+        event_minutes: Option<i32>,
+        game_minutes: Option<i32>,
+        past_minutes: Option<i32>,
+    ) -> game_schedule::GameDurations {
+        game_schedule::GameDurations {
+            game_id: 1,
+            game_name: "Game".to_string(),
+            event_minutes,
+            game_minutes,
+            past_minutes,
+        }
+    }
+
+    #[test]
+    fn test_estimate_duration_prefers_admin_durations() {
+        assert_eq!(
+            estimate_duration(&durations(Some(30), Some(60), Some(90)), &config(true)),
+            (30, GameDurationSource::Event)
+        );
+        assert_eq!(
+            estimate_duration(&durations(None, Some(60), Some(90)), &config(true)),
+            (60, GameDurationSource::Game)
+        );
+        assert_eq!(
+            estimate_duration(&durations(None, None, Some(90)), &config(true)),
+            (90, GameDurationSource::PastSchedules)
+        );
+        assert_eq!(
+            estimate_duration(&durations(None, None, None), &config(true)),
+            (120, GameDurationSource::Default)
+        );
+    }
+
+    fn event() -> event::Event {
+        event::Event {
+            id: 1,
+            created_at: Utc::now(),
+            last_modified: Utc::now(),
+            title: "LAN".to_string(),
+            description: String::new(),
+            image: None,
+            time_begin: "2025-06-06T18:00:00Z".parse().expect("valid time"),
+            time_end: "2025-06-08T18:00:00Z".parse().expect("valid time"),
+        }
+    }
+
+    fn blocked_period(start_time: &str, end_time: &str) -> BlockedPeriodSubmit {
+        BlockedPeriodSubmit {
+            name: "Dinner".to_string(),
+            start_time: start_time.parse().expect("valid time"),
+            end_time: end_time.parse().expect("valid time"),
+        }
+    }
+
+    #[test]
+    fn test_validate_blocked_period() {
+        assert!(validate_blocked_period(
+            &blocked_period("2025-06-06T18:00:00Z", "2025-06-06T19:00:00Z"),
+            &event()
+        )
+        .is_ok());
+        assert!(validate_blocked_period(
+            &blocked_period("2025-06-08T17:00:00Z", "2025-06-08T18:00:00Z"),
+            &event()
+        )
+        .is_ok());
+    }
+
+    #[test]
+    fn test_validate_blocked_period_outside_event() {
+        assert!(matches!(
+            validate_blocked_period(
+                &blocked_period("2025-06-06T17:00:00Z", "2025-06-06T19:00:00Z"),
+                &event()
+            ),
+            Err(Error::BadInput(_))
+        ));
+        assert!(matches!(
+            validate_blocked_period(
+                &blocked_period("2025-06-08T17:00:00Z", "2025-06-08T19:00:00Z"),
+                &event()
+            ),
+            Err(Error::BadInput(_))
+        ));
+        assert!(matches!(
+            validate_blocked_period(
+                &blocked_period("2025-06-07T19:00:00Z", "2025-06-07T18:00:00Z"),
+                &event()
+            ),
+            Err(Error::BadInput(_))
+        ));
+    }
+
+    #[test]
+    fn test_estimate_duration_can_ignore_past_schedules() {
+        assert_eq!(
+            estimate_duration(&durations(None, None, Some(90)), &config(false)),
+            (120, GameDurationSource::Default)
+        );
+    }
 }
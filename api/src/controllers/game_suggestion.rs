@@ -1,17 +1,21 @@
-use std::collections::HashMap;
+use std::collections::{HashMap, HashSet};
 
 use sqlx::PgPool;
 
 use crate::{
-    controllers::Error,
+    controllers::{event_voting, game, Error},
     repositories::{
+        game as game_repository, game_ownership,
         game_suggestion::{self, GameSuggestion},
-        invitation, user_games,
+        invitation, steam_game_details, user_games,
     },
     routes::event_games::{
         EventGameResponse, EventGameSuggestionRequest, EventGameSuggestionResponse, EventGames,
-        GameVote, Gamer,
+        GameOwnership, GameVote, Gamer,
     },
+    routes::event_voting::VotingMethod,
+    routes::games::GameSource,
+    search,
     util::{is_attending_event, is_event_active},
 };
 
@@ -49,12 +53,24 @@ impl From<game_suggestion::GameSuggestion> for EventGameSuggestionResponse {
             requested_at: game_suggestion.requested_at,
             suggestion_last_modified: game_suggestion.last_modified,
             self_vote: game_suggestion.self_vote.map(Into::into),
+            self_score: game_suggestion.self_score,
+            self_rank: game_suggestion.self_rank,
             votes: game_suggestion.votes,
             voters: Vec::new(),
             suggester: None,
             gamer_owned: Vec::new(),
             gamer_unowned: Vec::new(),
             gamer_unknown: Vec::new(),
+            gamer_installed: Vec::new(),
+            details: None,
+            players: None,
+            source: if game::is_custom_appid(game_suggestion.game_id) {
+                GameSource::Custom
+            } else {
+                GameSource::Steam
+            },
+            url: None,
+            image_url: None,
         }
     }
 }
@@ -86,6 +102,7 @@ impl From<crate::repositories::user_games::UserGame> for EventGameResponse {
                 .try_into()
                 .unwrap_or_default(),
             last_modified: game.last_modified.unwrap_or_default(),
+            players: None,
         }
     }
 }
@@ -148,6 +165,9 @@ pub async fn get_all_event_games(
 
     let event_games = match user_games::filter(pool, user_games_filter_values.clone()).await {
         Ok(games) => {
+            let appids: Vec<i64> = games.iter().map(|game| game.appid).collect();
+            let mut players = game::get_players(pool, &appids).await?;
+
             let mut event_games = Vec::new();
             //For each Gamer in each event_games, add the Gamer's handle and avatar_url from invitations
             for game in games {
@@ -165,6 +185,7 @@ pub async fn get_all_event_games(
                     .collect();
 
                 event_games.push(EventGameResponse {
+                    players: players.remove(&game.appid),
                     appid: game.appid,
                     name: game.name,
                     gamer_owned,
@@ -200,10 +221,14 @@ pub async fn get_all_event_games(
     })
 }
 
+/// Get the games suggested for an event, optionally only those that `players` people
+/// can play together, or that can be played over LAN
 pub async fn get(
     pool: &PgPool,
     event_id: i32,
     email: String,
+    players: Option<i32>,
+    lan: Option<bool>,
 ) -> Result<Vec<EventGameSuggestionResponse>, Error> {
     let invitations = match invitation::filter(
         pool,
@@ -235,8 +260,26 @@ pub async fn get(
             let mut game_suggestions_with_gamers = Vec::new();
 
             for game_suggestion in game_suggestions {
-                game_suggestions_with_gamers
-                    .push(add_owners_to_game(pool, game_suggestion, &invitations).await?);
+                let game_suggestion =
+                    add_owners_to_game(pool, game_suggestion, &invitations).await?;
+
+                if players.is_some_and(|players| {
+                    !game::supports_players(game_suggestion.players.as_ref(), players)
+                }) {
+                    continue;
+                }
+
+                if lan.is_some_and(|lan| {
+                    game_suggestion
+                        .players
+                        .as_ref()
+                        .is_some_and(|players| players.has_lan)
+                        != lan
+                }) {
+                    continue;
+                }
+
+                game_suggestions_with_gamers.push(game_suggestion);
             }
             Ok(game_suggestions_with_gamers)
         }
@@ -252,6 +295,9 @@ pub async fn create(
     email: String,
     new_event_game_suggestion: EventGameSuggestionRequest,
 ) -> Result<EventGameSuggestionResponse, Error> {
+    let config = event_voting::get_config(pool, event_id).await?;
+    event_voting::ensure_suggestions_open(&config)?;
+
     match is_event_active(pool, event_id).await {
         Err(e) => {
             return Err(Error::Controller(format!(
@@ -280,6 +326,32 @@ pub async fn create(
         Ok(true) => (),
     }
 
+    // Checked again when saving the suggestion, this saves fetching store details when it's full
+    if let Some(max_suggestions) = config.max_suggestions_per_attendee {
+        match game_suggestion::count_by_user(pool, event_id, &email).await {
+            Ok(count) if count >= i64::from(max_suggestions) => {
+                return Err(Error::NotPermitted(format!(
+                    "You can only suggest {max_suggestions} games for this event"
+                )))
+            }
+            Ok(_) => (),
+            Err(e) => {
+                return Err(Error::Controller(format!(
+                    "Unable to count your game suggestions due to: {e}"
+                )))
+            }
+        }
+    }
+
+    check_for_duplicates(
+        pool,
+        event_id,
+        new_event_game_suggestion.appid,
+        new_event_game_suggestion.allow_similar,
+        &email,
+    )
+    .await?;
+
     let invitations = match invitation::filter(
         pool,
         invitation::Filter {
@@ -304,10 +376,15 @@ pub async fn create(
         new_event_game_suggestion.appid,
         email.clone(),
         new_event_game_suggestion.comment.clone(),
+        config.max_suggestions_per_attendee,
     )
     .await
     {
-        Ok(game_suggestion) => {
+        Ok(None) => Err(Error::NotPermitted(format!(
+            "You can only suggest {} games for this event",
+            config.max_suggestions_per_attendee.unwrap_or_default()
+        ))),
+        Ok(Some(game_suggestion)) => {
             let result = add_owners_to_game(pool, game_suggestion.clone(), &invitations).await?;
 
             // Log audit entry
@@ -335,13 +412,178 @@ pub async fn create(
     }
 }
 
+/// A game, for spotting when it's a version of another
+struct SuggestedApp {
+    appid: i64,
+    name: String,
+    /// The game a DLC, demo or soundtrack belongs to
+    fullgame_appid: Option<i64>,
+}
+
+/// Find a suggested game that's the same as the given game, or looks like another version of it,
+/// such as its DLC, demo, soundtrack or another edition
+fn find_near_duplicate<'a>(
+    game: &SuggestedApp,
+    suggested: &'a [SuggestedApp],
+) -> Option<&'a SuggestedApp> {
+    suggested.iter().find(|other| {
+        other.appid == game.appid
+            || game.fullgame_appid == Some(other.appid)
+            || other.fullgame_appid == Some(game.appid)
+            || search::is_variant(&game.name, &other.name)
+    })
+}
+
+/// Check a game hasn't already been suggested for an event, and unless allowed,
+/// that it isn't a version of a game that has.
+/// Fetches the game's store details once it's otherwise fine to suggest.
+async fn check_for_duplicates(
+    pool: &PgPool,
+    event_id: i32,
+    appid: i64,
+    allow_similar: bool,
+    email: &str,
+) -> Result<(), Error> {
+    let name = match game_repository::get_many(pool, &[appid]).await {
+        Ok(games) => match games.into_iter().next() {
+            Some(game) => game.name,
+            None => return Err(Error::BadInput(format!("Game {appid} doesn't exist"))),
+        },
+        Err(e) => return Err(Error::Controller(format!("Unable to get game due to: {e}"))),
+    };
+
+    let suggestions = match game_suggestion::filter(
+        pool,
+        game_suggestion::Filter {
+            event_id: Some(event_id),
+            game_id: None,
+        },
+        email.to_string(),
+    )
+    .await
+    {
+        Ok(suggestions) => suggestions,
+        Err(e) => {
+            return Err(Error::Controller(format!(
+                "Unable to get game suggestions due to: {e}"
+            )))
+        }
+    };
+
+    if let Some(existing) = suggestions
+        .iter()
+        .find(|suggestion| suggestion.game_id == appid)
+    {
+        return Err(Error::Conflict(format!(
+            "{} has already been suggested for this event",
+            existing.game_name
+        )));
+    }
+
+    // Fetch the game's store details now, so they are ready for spotting other versions of it and
+    // for everyone looking at the suggestions. If the store is down or slow, the game is still
+    // suggested, just without them.
+    if let Err(e) = game::refresh_details(pool, appid).await {
+        log::warn!("Unable to refresh details for game {appid}: {e}");
+    }
+
+    let mut appids: Vec<i64> = suggestions
+        .iter()
+        .map(|suggestion| suggestion.game_id)
+        .collect();
+    appids.push(appid);
+    let fullgames: HashMap<i64, i64> = match steam_game_details::filter(pool, &appids).await {
+        Ok(details) => details
+            .into_iter()
+            .filter_map(|details| {
+                details
+                    .fullgame_appid
+                    .map(|fullgame_appid| (details.appid, fullgame_appid))
+            })
+            .collect(),
+        Err(e) => {
+            return Err(Error::Controller(format!(
+                "Unable to get game details due to: {e}"
+            )))
+        }
+    };
+
+    let game = SuggestedApp {
+        appid,
+        name,
+        fullgame_appid: fullgames.get(&appid).copied(),
+    };
+    let suggested: Vec<SuggestedApp> = suggestions
+        .into_iter()
+        .map(|suggestion| SuggestedApp {
+            appid: suggestion.game_id,
+            fullgame_appid: fullgames.get(&suggestion.game_id).copied(),
+            name: suggestion.game_name,
+        })
+        .collect();
+
+    match find_near_duplicate(&game, &suggested) {
+        Some(existing) if !allow_similar => Err(Error::Conflict(format!(
+            "{} looks like a version of {}, which has already been suggested for this event. \
+            Vote for that instead, or set allow_similar to suggest it anyway",
+            game.name, existing.name
+        ))),
+        _ => Ok(()),
+    }
+}
+
+/// The most stars a game can be given with score voting
+const MAX_SCORE: i16 = 5;
+
+/// Check a vote suits the event's voting method, returning the vote and score to store.
+/// Giving a game any stars counts as voting yes for it.
+fn vote_for_method(
+    method: VotingMethod,
+    vote: Option<GameVote>,
+    score: Option<i16>,
+) -> Result<(GameVote, Option<i16>), Error> {
+    match method {
+        VotingMethod::Approval => match (vote, score) {
+            (_, Some(_)) => Err(Error::BadInput(
+                "This event uses approval voting, so games can't be given stars".to_string(),
+            )),
+            (Some(vote), None) => Ok((vote, None)),
+            (None, None) => Err(Error::BadInput("A vote is required".to_string())),
+        },
+        VotingMethod::Score => match score {
+            Some(score) if (0..=MAX_SCORE).contains(&score) => Ok((
+                if score > 0 {
+                    GameVote::Yes
+                } else {
+                    GameVote::NoVote
+                },
+                Some(score),
+            )),
+            Some(_) => Err(Error::BadInput(format!(
+                "Scores must be from 0 to {MAX_SCORE} stars"
+            ))),
+            None => Err(Error::BadInput(
+                "This event uses score voting, so a score is required".to_string(),
+            )),
+        },
+        VotingMethod::InstantRunoff | VotingMethod::Borda => Err(Error::BadInput(
+            "This event uses ranked choice voting, so games are ranked all at once".to_string(),
+        )),
+    }
+}
+
 pub async fn vote(
     pool: &PgPool,
     event_id: i32,
     game_id: i64,
     email: String,
-    vote: GameVote,
+    vote: Option<GameVote>,
+    score: Option<i16>,
 ) -> Result<EventGameSuggestionResponse, Error> {
+    let config = event_voting::get_config(pool, event_id).await?;
+    event_voting::ensure_voting_open(&config)?;
+    let (vote, score) = vote_for_method(config.voting_method, vote, score)?;
+
     match is_event_active(pool, event_id).await {
         Err(e) => {
             return Err(Error::Controller(format!(
@@ -388,7 +630,16 @@ pub async fn vote(
     };
 
     // Insert game suggestion
-    match game_suggestion::edit(pool, event_id, game_id, email.clone(), vote.clone().into()).await {
+    match game_suggestion::edit(
+        pool,
+        event_id,
+        game_id,
+        email.clone(),
+        vote.clone().into(),
+        score,
+    )
+    .await
+    {
         Ok(game_suggestion) => {
             let result = add_owners_to_game(pool, game_suggestion.clone(), &invitations).await?;
 
@@ -398,6 +649,7 @@ pub async fn vote(
                 "game_id": game_id,
                 "game_name": game_suggestion.game_name,
                 "vote": format!("{:?}", vote),
+                "score": score,
             });
             crate::util::log_audit(
                 pool,
@@ -417,6 +669,31 @@ pub async fn vote(
     }
 }
 
+#[derive(Debug, PartialEq, Eq)]
+enum OwnershipBucket {
+    Owned,
+    Unowned,
+    Unknown,
+}
+
+/// Work out whether an attendee owns a game. What they've said takes priority over Steam,
+/// and with neither we only know they don't own it if Steam has their library.
+fn ownership_bucket(
+    declared: Option<game_ownership::GameOwnership>,
+    owns_on_steam: bool,
+    has_steam_games: bool,
+) -> OwnershipBucket {
+    match declared {
+        Some(game_ownership::GameOwnership::Owned | game_ownership::GameOwnership::Installed) => {
+            OwnershipBucket::Owned
+        }
+        Some(game_ownership::GameOwnership::NotOwned) => OwnershipBucket::Unowned,
+        None if owns_on_steam => OwnershipBucket::Owned,
+        None if has_steam_games => OwnershipBucket::Unowned,
+        None => OwnershipBucket::Unknown,
+    }
+}
+
 async fn add_owners_to_game(
     pool: &PgPool,
     game_suggestion: GameSuggestion,
@@ -424,7 +701,8 @@ async fn add_owners_to_game(
 ) -> Result<EventGameSuggestionResponse, Error> {
     let mut gamer_owned = Vec::new();
     let mut gamer_unowned = Vec::new();
-    let gamer_unknown = Vec::new();
+    let mut gamer_unknown = Vec::new();
+    let mut gamer_installed = Vec::new();
 
     // Filter invitations to only those with yes/maybe responses
     let attending_invitations: Vec<&invitation::Invitation> = invitations
@@ -463,24 +741,58 @@ async fn add_owners_to_game(
     };
 
     // Build a set of emails that own the game for quick lookup
-    let owner_emails: std::collections::HashSet<String> = user_games
+    let owner_emails: HashSet<String> = user_games
         .into_iter()
         .flat_map(|game| game.emails.unwrap_or_default())
         .map(|email| email.to_lowercase())
         .collect();
 
+    // Attendees with no games from Steam at all can't be said not to own it
+    let steam_emails: HashSet<String> =
+        match user_games::emails_with_games(pool, &attending_emails).await {
+            Ok(emails) => emails.into_iter().collect(),
+            Err(e) => {
+                return Err(Error::Controller(format!(
+                    "Unable to get user games due to: {e}"
+                )))
+            }
+        };
+
+    let declarations: HashMap<String, game_ownership::GameOwnership> =
+        match game_ownership::filter(pool, game_suggestion.event_id, game_suggestion.game_id).await
+        {
+            Ok(declarations) => declarations
+                .into_iter()
+                .map(|declaration| (declaration.email.to_lowercase(), declaration.ownership))
+                .collect(),
+            Err(e) => {
+                return Err(Error::Controller(format!(
+                    "Unable to get game ownership due to: {e}"
+                )))
+            }
+        };
+
     // Categorize invitations based on ownership
     for invitation in attending_invitations {
-        if owner_emails.contains(&invitation.email.to_lowercase()) {
-            gamer_owned.push(Gamer {
-                avatar_url: invitation.avatar_url.clone(),
-                handle: invitation.handle.clone(),
-            });
-        } else {
-            gamer_unowned.push(Gamer {
-                avatar_url: invitation.avatar_url.clone(),
-                handle: invitation.handle.clone(),
-            });
+        let email = invitation.email.to_lowercase();
+        let declared = declarations.get(&email).copied();
+        let gamer = Gamer {
+            avatar_url: invitation.avatar_url.clone(),
+            handle: invitation.handle.clone(),
+        };
+
+        if declared == Some(game_ownership::GameOwnership::Installed) {
+            gamer_installed.push(gamer.clone());
+        }
+
+        match ownership_bucket(
+            declared,
+            owner_emails.contains(&email),
+            steam_emails.contains(&email),
+        ) {
+            OwnershipBucket::Owned => gamer_owned.push(gamer),
+            OwnershipBucket::Unowned => gamer_unowned.push(gamer),
+            OwnershipBucket::Unknown => gamer_unknown.push(gamer),
         }
     }
 
@@ -513,6 +825,33 @@ async fn add_owners_to_game(
             handle: i.handle.clone(),
         });
 
+    let details = match steam_game_details::get(pool, game_suggestion.game_id).await {
+        Ok(details) => details
+            .filter(|details| details.is_available)
+            .map(Into::into),
+        Err(e) => {
+            return Err(Error::Controller(format!(
+                "Unable to get game details due to: {e}"
+            )))
+        }
+    };
+
+    let players = game::get_players(pool, &[game_suggestion.game_id])
+        .await?
+        .remove(&game_suggestion.game_id);
+
+    let game = match game_repository::get_many(pool, &[game_suggestion.game_id]).await {
+        Ok(games) => games.into_iter().next(),
+        Err(e) => return Err(Error::Controller(format!("Unable to get game due to: {e}"))),
+    };
+    let (source, url, image_url) = game.map_or((GameSource::Steam, None, None), |game| {
+        (
+            game.source.into(),
+            game::game_url(game.source, game.appid, game.url),
+            game.image_url,
+        )
+    });
+
     Ok(EventGameSuggestionResponse {
         appid: game_suggestion.game_id,
         name: game_suggestion.game_name,
@@ -522,12 +861,20 @@ async fn add_owners_to_game(
         requested_at: game_suggestion.requested_at,
         suggestion_last_modified: game_suggestion.last_modified,
         self_vote: game_suggestion.self_vote.map(Into::into),
+        self_score: game_suggestion.self_score,
+        self_rank: game_suggestion.self_rank,
         votes: game_suggestion.votes,
         voters,
         suggester,
         gamer_owned,
         gamer_unowned,
         gamer_unknown,
+        gamer_installed,
+        details,
+        players,
+        source,
+        url,
+        image_url,
     })
 }
 
@@ -615,3 +962,377 @@ pub async fn update_comment(
         ))),
     }
 }
+
+// Implement From for game_ownership::GameOwnership from GameOwnership
+impl From<GameOwnership> for game_ownership::GameOwnership {
+    fn from(ownership: GameOwnership) -> Self {
+        match ownership {
+            GameOwnership::Owned => Self::Owned,
+            GameOwnership::NotOwned => Self::NotOwned,
+            GameOwnership::Installed => Self::Installed,
+        }
+    }
+}
+
+/// Say whether you own a suggested game for an event, overriding your Steam library.
+/// Clearing it with `None` goes back to using Steam.
+pub async fn set_ownership(
+    pool: &PgPool,
+    event_id: i32,
+    game_id: i64,
+    email: String,
+    ownership: Option<GameOwnership>,
+) -> Result<EventGameSuggestionResponse, Error> {
+    match is_attending_event(pool, event_id, email.clone()).await {
+        Err(e) => {
+            return Err(Error::Controller(format!(
+                "Unable to check if attending event, due to: {e}"
+            )))
+        }
+        Ok(false) => {
+            return Err(Error::NotPermitted(
+                "You can only say which games you own for events you are attending".to_string(),
+            ))
+        }
+        Ok(true) => (),
+    }
+
+    let game_suggestion = match game_suggestion::filter(
+        pool,
+        game_suggestion::Filter {
+            event_id: Some(event_id),
+            game_id: Some(game_id),
+        },
+        email.clone(),
+    )
+    .await
+    {
+        Ok(game_suggestions) => match game_suggestions.into_iter().next() {
+            Some(game_suggestion) => game_suggestion,
+            None => {
+                return Err(Error::NotFound(format!(
+                    "Game {game_id} hasn't been suggested for this event"
+                )))
+            }
+        },
+        Err(e) => {
+            return Err(Error::Controller(format!(
+                "Unable to get game suggestion due to: {e}"
+            )))
+        }
+    };
+
+    let result = match ownership {
+        Some(ownership) => {
+            game_ownership::upsert(pool, event_id, game_id, email.clone(), ownership.into())
+                .await
+                .map(|_| ())
+        }
+        None => game_ownership::delete(pool, event_id, game_id, email.clone())
+            .await
+            .map(|_| ()),
+    };
+    if let Err(e) = result {
+        return Err(Error::Controller(format!(
+            "Unable to save game ownership due to: {e}"
+        )));
+    }
+
+    let invitations = match invitation::filter(
+        pool,
+        invitation::Filter {
+            event_id: Some(event_id),
+            email: None,
+        },
+    )
+    .await
+    {
+        Ok(invitations) => invitations,
+        Err(e) => {
+            return Err(Error::Controller(format!(
+                "Unable to get event invitations due to: {e}"
+            )))
+        }
+    };
+
+    // Log audit entry for ownership update
+    let metadata = rocket::serde::json::serde_json::json!({
+        "event_id": event_id,
+        "game_id": game_id,
+        "game_name": game_suggestion.game_name,
+        "ownership": ownership.map(|ownership| format!("{ownership:?}")),
+    });
+    crate::util::log_audit(
+        pool,
+        Some(email),
+        "game_ownership.update".to_string(),
+        "game_suggestion".to_string(),
+        Some(format!("{event_id}-{game_id}")),
+        Some(metadata),
+    )
+    .await;
+
+    add_owners_to_game(pool, game_suggestion, &invitations).await
+}
+
+/// Merge a suggested game into another, such as a DLC into its game.
+/// Votes and comments carry over, then the merged game is no longer suggested.
+pub async fn merge(
+    pool: &PgPool,
+    event_id: i32,
+    game_id: i64,
+    into_game_id: i64,
+    email: String,
+) -> Result<EventGameSuggestionResponse, Error> {
+    if game_id == into_game_id {
+        return Err(Error::BadInput(
+            "A game can't be merged into itself".to_string(),
+        ));
+    }
+
+    let suggestions = match game_suggestion::filter(
+        pool,
+        game_suggestion::Filter {
+            event_id: Some(event_id),
+            game_id: None,
+        },
+        email.clone(),
+    )
+    .await
+    {
+        Ok(suggestions) => suggestions,
+        Err(e) => {
+            return Err(Error::Controller(format!(
+                "Unable to get game suggestions due to: {e}"
+            )))
+        }
+    };
+    let Some(merged) = suggestions
+        .iter()
+        .find(|suggestion| suggestion.game_id == game_id)
+    else {
+        return Err(Error::NotFound(format!(
+            "Game {game_id} hasn't been suggested for this event"
+        )));
+    };
+    if !suggestions
+        .iter()
+        .any(|suggestion| suggestion.game_id == into_game_id)
+    {
+        return Err(Error::NotFound(format!(
+            "Game {into_game_id} hasn't been suggested for this event"
+        )));
+    }
+
+    match game_suggestion::merge(pool, event_id, game_id, into_game_id).await {
+        Ok(true) => (),
+        Ok(false) => {
+            return Err(Error::NotFound(
+                "Both games need to have been suggested for this event".to_string(),
+            ))
+        }
+        Err(e) => {
+            return Err(Error::Controller(format!(
+                "Unable to merge game suggestions due to: {e}"
+            )))
+        }
+    }
+
+    let game_suggestion = match game_suggestion::filter(
+        pool,
+        game_suggestion::Filter {
+            event_id: Some(event_id),
+            game_id: Some(into_game_id),
+        },
+        email.clone(),
+    )
+    .await
+    {
+        Ok(game_suggestions) => match game_suggestions.into_iter().next() {
+            Some(game_suggestion) => game_suggestion,
+            None => {
+                return Err(Error::NotFound(format!(
+                    "Game {into_game_id} hasn't been suggested for this event"
+                )))
+            }
+        },
+        Err(e) => {
+            return Err(Error::Controller(format!(
+                "Unable to get game suggestion due to: {e}"
+            )))
+        }
+    };
+
+    let invitations = match invitation::filter(
+        pool,
+        invitation::Filter {
+            event_id: Some(event_id),
+            email: None,
+        },
+    )
+    .await
+    {
+        Ok(invitations) => invitations,
+        Err(e) => {
+            return Err(Error::Controller(format!(
+                "Unable to get event invitations due to: {e}"
+            )))
+        }
+    };
+
+    // Log audit entry for the merge
+    let metadata = rocket::serde::json::serde_json::json!({
+        "event_id": event_id,
+        "game_id": game_id,
+        "game_name": merged.game_name,
+        "into_game_id": into_game_id,
+        "into_game_name": game_suggestion.game_name,
+    });
+    crate::util::log_audit(
+        pool,
+        Some(email),
+        "game_suggestion.merge".to_string(),
+        "game_suggestion".to_string(),
+        Some(format!("{event_id}-{into_game_id}")),
+        Some(metadata),
+    )
+    .await;
+
+    add_owners_to_game(pool, game_suggestion, &invitations).await
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use game_ownership::GameOwnership::{Installed, NotOwned, Owned};
+
+    #[test]
+    fn test_declarations_take_priority_over_steam() {
+        assert_eq!(
+            ownership_bucket(Some(Owned), false, true),
+            OwnershipBucket::Owned
+        );
+        assert_eq!(
+            ownership_bucket(Some(Installed), false, false),
+            OwnershipBucket::Owned
+        );
+        assert_eq!(
+            ownership_bucket(Some(NotOwned), true, true),
+            OwnershipBucket::Unowned
+        );
+    }
+
+    #[test]
+    fn test_steam_library_decides_without_a_declaration() {
+        assert_eq!(ownership_bucket(None, true, true), OwnershipBucket::Owned);
+        assert_eq!(
+            ownership_bucket(None, false, true),
+            OwnershipBucket::Unowned
+        );
+    }
+
+    #[test]
+    fn test_approval_votes_need_a_vote_and_no_score() {
+        assert!(matches!(
+            vote_for_method(VotingMethod::Approval, Some(GameVote::Yes), None),
+            Ok((GameVote::Yes, None))
+        ));
+        assert!(matches!(
+            vote_for_method(VotingMethod::Approval, None, None),
+            Err(Error::BadInput(_))
+        ));
+        assert!(matches!(
+            vote_for_method(VotingMethod::Approval, Some(GameVote::Yes), Some(3)),
+            Err(Error::BadInput(_))
+        ));
+    }
+
+    #[test]
+    fn test_score_votes_need_a_score_in_range() {
+        assert!(matches!(
+            vote_for_method(VotingMethod::Score, None, Some(4)),
+            Ok((GameVote::Yes, Some(4)))
+        ));
+        assert!(matches!(
+            vote_for_method(VotingMethod::Score, Some(GameVote::Yes), Some(0)),
+            Ok((GameVote::NoVote, Some(0)))
+        ));
+        assert!(matches!(
+            vote_for_method(VotingMethod::Score, Some(GameVote::Yes), None),
+            Err(Error::BadInput(_))
+        ));
+        assert!(matches!(
+            vote_for_method(VotingMethod::Score, None, Some(6)),
+            Err(Error::BadInput(_))
+        ));
+    }
+
+    #[test]
+    fn test_ranked_events_dont_take_single_votes() {
+        for method in [VotingMethod::InstantRunoff, VotingMethod::Borda] {
+            assert!(matches!(
+                vote_for_method(method, Some(GameVote::Yes), None),
+                Err(Error::BadInput(_))
+            ));
+        }
+    }
+
+    fn app(appid: i64, name: &str, fullgame_appid: Option<i64>) -> SuggestedApp {
+        SuggestedApp {
+            appid,
+            name: name.to_string(),
+            fullgame_appid,
+        }
+    }
+
+    #[test]
+    fn test_versions_of_suggested_games_are_near_duplicates() {
+        let suggested = [
+            app(730, "Counter-Strike 2", None),
+            app(252_950, "Rocket League", None),
+        ];
+
+        let soundtrack = app(2_000_000, "Counter-Strike 2 Soundtrack", Some(730));
+        assert_eq!(
+            find_near_duplicate(&soundtrack, &suggested).map(|game| game.appid),
+            Some(730)
+        );
+
+        // DLC with a name that doesn't give it away
+        let dlc = app(2_000_001, "Triton", Some(252_950));
+        assert_eq!(
+            find_near_duplicate(&dlc, &suggested).map(|game| game.appid),
+            Some(252_950)
+        );
+
+        let same = app(730, "Counter-Strike 2", None);
+        assert_eq!(
+            find_near_duplicate(&same, &suggested).map(|game| game.appid),
+            Some(730)
+        );
+    }
+
+    #[test]
+    fn test_games_suggested_after_their_dlc_are_near_duplicates() {
+        let suggested = [app(2_000_001, "Triton", Some(252_950))];
+        let game = app(252_950, "Rocket League", None);
+        assert!(find_near_duplicate(&game, &suggested).is_some());
+    }
+
+    #[test]
+    fn test_different_games_are_not_near_duplicates() {
+        let suggested = [app(400, "Portal", None), app(730, "Counter-Strike 2", None)];
+        assert!(find_near_duplicate(&app(620, "Portal 2", None), &suggested).is_none());
+        assert!(
+            find_near_duplicate(&app(240, "Counter-Strike: Source", None), &suggested).is_none()
+        );
+    }
+
+    #[test]
+    fn test_unknown_without_steam_games_or_a_declaration() {
+        assert_eq!(
+            ownership_bucket(None, false, false),
+            OwnershipBucket::Unknown
+        );
+    }
+}
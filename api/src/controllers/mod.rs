@@ -6,17 +6,26 @@ use crate::repositories::invitation;
 
 pub mod activity_ticker;
 pub mod audit_log;
+pub mod custom_game;
+pub mod digest;
+pub mod discord;
 pub mod event;
 pub mod event_invitation;
 pub mod event_seating_config;
+pub mod event_voting;
 pub mod game;
+pub mod game_comment;
+pub mod game_recommendation;
 pub mod game_schedule;
 pub mod game_suggestion;
 pub mod gamer;
+pub mod notification_preference;
+pub mod presence;
 pub mod profile;
 pub mod room;
 pub mod seat;
 pub mod seat_reservation;
+pub mod webhook;
 
 // Custom controller error
 #[derive(Debug)]
@@ -28,6 +37,8 @@ pub enum Error {
     NotPermitted(String),
     Conflict(String),
     NotFound(String),
+    /// An API we depend on is rate limiting us, so try again later
+    RateLimited(String),
 }
 
 pub async fn ensure_user_invited(pool: &PgPool, event_id: i32, email: &str) -> Result<(), Error> {
@@ -58,7 +69,8 @@ impl Display for Error {
             | Error::NoData(reason)
             | Error::NotPermitted(reason)
             | Error::Conflict(reason)
-            | Error::NotFound(reason) => reason.fmt(f),
+            | Error::NotFound(reason)
+            | Error::RateLimited(reason) => reason.fmt(f),
         }
     }
 }
@@ -0,0 +1,147 @@
+use sqlx::PgPool;
+
+use crate::{
+    controllers::Error,
+    repositories::notification_preference::{self, NotificationCategory, NotificationPreference},
+    routes::notification_preferences::{NotificationPreferences, NotificationPreferencesSubmit},
+};
+
+impl From<NotificationPreference> for NotificationPreferences {
+    fn from(preference: NotificationPreference) -> Self {
+        Self {
+            email: preference.email,
+            invitations: preference.invitations,
+            reminders: preference.reminders,
+            event_updates: preference.event_updates,
+            game_schedule_changes: preference.game_schedule_changes,
+            digests: preference.digests,
+            last_modified: preference.last_modified,
+        }
+    }
+}
+
+async fn get_or_default(pool: &PgPool, email: &str) -> Result<NotificationPreference, Error> {
+    match notification_preference::get(pool, email).await {
+        Ok(preference) => {
+            Ok(preference.unwrap_or_else(|| NotificationPreference::default_for(email)))
+        }
+        Err(e) => Err(Error::Controller(format!(
+            "Unable to get notification preferences due to: {e}"
+        ))),
+    }
+}
+
+pub async fn get(pool: &PgPool, email: &str) -> Result<NotificationPreferences, Error> {
+    Ok(get_or_default(pool, email).await?.into())
+}
+
+pub async fn update(
+    pool: &PgPool,
+    email: &str,
+    preferences: NotificationPreferencesSubmit,
+) -> Result<NotificationPreferences, Error> {
+    let mut preference = get_or_default(pool, email).await?;
+    preference.invitations = preferences.invitations;
+    preference.reminders = preferences.reminders;
+    preference.event_updates = preferences.event_updates;
+    preference.game_schedule_changes = preferences.game_schedule_changes;
+    preference.digests = preferences.digests;
+
+    match notification_preference::upsert(pool, preference).await {
+        Ok(preference) => {
+            // Log audit entry
+            let metadata = rocket::serde::json::serde_json::json!({
+                "invitations": preference.invitations,
+                "reminders": preference.reminders,
+                "event_updates": preference.event_updates,
+                "game_schedule_changes": preference.game_schedule_changes,
+                "digests": preference.digests,
+            });
+            crate::util::log_audit(
+                pool,
+                Some(email.to_string()),
+                "notification_preference.update".to_string(),
+                "notification_preference".to_string(),
+                Some(preference.email.clone()),
+                Some(metadata),
+            )
+            .await;
+
+            Ok(preference.into())
+        }
+        Err(e) => Err(Error::Controller(format!(
+            "Unable to save notification preferences due to: {e}"
+        ))),
+    }
+}
+
+/// Turn off a single category of email, as requested from an unsubscribe link
+pub async fn unsubscribe(
+    pool: &PgPool,
+    email: &str,
+    category: NotificationCategory,
+) -> Result<NotificationPreferences, Error> {
+    let mut preference = get_or_default(pool, email).await?;
+    preference.set_subscribed(category, false);
+
+    match notification_preference::upsert(pool, preference).await {
+        Ok(preference) => {
+            // Log audit entry
+            let metadata = rocket::serde::json::serde_json::json!({
+                "category": category.as_str(),
+            });
+            crate::util::log_audit(
+                pool,
+                Some(email.to_string()),
+                "notification_preference.unsubscribe".to_string(),
+                "notification_preference".to_string(),
+                Some(preference.email.clone()),
+                Some(metadata),
+            )
+            .await;
+
+            Ok(preference.into())
+        }
+        Err(e) => Err(Error::Controller(format!(
+            "Unable to unsubscribe due to: {e}"
+        ))),
+    }
+}
+
+/// Check whether someone wants to receive a category of email
+pub async fn is_subscribed(
+    pool: &PgPool,
+    email: &str,
+    category: NotificationCategory,
+) -> Result<bool, Error> {
+    Ok(get_or_default(pool, email).await?.is_subscribed(category))
+}
+
+/// Reduce a list of recipients down to those who want to receive a category of email
+pub async fn filter_subscribed(
+    pool: &PgPool,
+    emails: Vec<String>,
+    category: NotificationCategory,
+) -> Result<Vec<String>, Error> {
+    let preferences = match notification_preference::filter(pool, &emails).await {
+        Ok(preferences) => preferences,
+        Err(e) => {
+            return Err(Error::Controller(format!(
+                "Unable to get notification preferences due to: {e}"
+            )))
+        }
+    };
+
+    Ok(emails
+        .into_iter()
+        .filter(|email| {
+            preferences
+                .iter()
+                .find(|preference| preference.email.eq_ignore_ascii_case(email))
+                .map_or_else(
+                    || NotificationPreference::default_for(email).is_subscribed(category),
+                    |preference| preference.is_subscribed(category),
+                )
+        })
+        .collect())
+}
@@ -0,0 +1,473 @@
+use std::collections::{HashMap, HashSet};
+
+use chrono::{Duration, Utc};
+use rocket::serde::json::serde_json::json;
+use sqlx::PgPool;
+
+use crate::{
+    controllers::Error,
+    repositories::{
+        steam_api::{self, PlayerSummary},
+        steam_presence,
+    },
+    routes::{
+        event_games::Gamer,
+        now_playing::{NowPlayingGame, NowPlayingGamer},
+    },
+};
+
+/// How long after someone was last seen to stop showing what they were playing,
+/// so presence doesn't linger if polling stops
+const STALE_AFTER_MINUTES: i64 = 10;
+
+/// How many attendees need to be playing the same game for it to show on the activity ticker
+const GROUP_SIZE: usize = 3;
+
+/// A game someone is playing. Steam doesn't give an app ID for non-Steam games, only a name.
+#[derive(Clone, Debug, PartialEq, Eq, Hash)]
+pub struct Playing {
+    pub game_id: Option<i64>,
+    pub game_name: Option<String>,
+}
+
+impl Playing {
+    fn from_summary(summary: &PlayerSummary) -> Option<Self> {
+        let game_id = summary
+            .gameid
+            .as_ref()
+            .and_then(|gameid| gameid.parse::<i64>().ok());
+        let game_name = summary
+            .gameextrainfo
+            .as_ref()
+            .map(|name| name.trim().to_string())
+            .filter(|name| !name.is_empty());
+
+        if game_id.is_none() && game_name.is_none() {
+            return None;
+        }
+        Some(Self { game_id, game_name })
+    }
+
+    fn from_presence(presence: &steam_presence::SteamPresence) -> Option<Self> {
+        if presence.game_id.is_none() && presence.game_name.is_none() {
+            return None;
+        }
+        Some(Self {
+            game_id: presence.game_id,
+            game_name: presence.game_name.clone(),
+        })
+    }
+}
+
+/// Something worth showing on an event's activity ticker
+#[derive(Debug, PartialEq, Eq)]
+enum PresenceChange {
+    /// Someone started playing a game
+    Started { email: String, game: Playing },
+    /// Enough people are now playing the same game to call it out
+    Group { game: Playing, player_count: usize },
+}
+
+/// Work out what changed for the attendees of an event between two polls.
+/// Both maps are keyed by lowercase email.
+fn notable_changes(
+    attendees: &[String],
+    before: &HashMap<String, Playing>,
+    after: &HashMap<String, Playing>,
+) -> Vec<PresenceChange> {
+    let mut changes = Vec::new();
+
+    for email in attendees {
+        if let Some(game) = after.get(email) {
+            if before.get(email) != Some(game) {
+                changes.push(PresenceChange::Started {
+                    email: email.clone(),
+                    game: game.clone(),
+                });
+            }
+        }
+    }
+
+    let count_players = |playing: &HashMap<String, Playing>| {
+        let mut counts: HashMap<Playing, usize> = HashMap::new();
+        for email in attendees {
+            if let Some(game) = playing.get(email) {
+                *counts.entry(game.clone()).or_default() += 1;
+            }
+        }
+        counts
+    };
+    let counts_before = count_players(before);
+    let mut counts_after: Vec<(Playing, usize)> = count_players(after).into_iter().collect();
+    counts_after.sort_by(|a, b| b.1.cmp(&a.1).then_with(|| a.0.game_id.cmp(&b.0.game_id)));
+
+    for (game, player_count) in counts_after {
+        let player_count_before = counts_before.get(&game).copied().unwrap_or(0);
+        if player_count >= GROUP_SIZE && player_count_before < GROUP_SIZE {
+            changes.push(PresenceChange::Group { game, player_count });
+        }
+    }
+
+    changes
+}
+
+async fn log_change(pool: &PgPool, event_id: i32, change: PresenceChange) {
+    let (user_email, action, game, player_count) = match change {
+        PresenceChange::Started { email, game } => (Some(email), "presence.start", game, None),
+        PresenceChange::Group { game, player_count } => {
+            (None, "presence.group", game, Some(player_count))
+        }
+    };
+
+    let metadata = json!({
+        "event_id": event_id,
+        "game_id": game.game_id,
+        "game_name": game.game_name,
+        "player_count": player_count,
+    });
+    crate::util::log_audit(
+        pool,
+        user_email,
+        action.to_string(),
+        "presence".to_string(),
+        Some(format!("{event_id}-{}", game.game_id.unwrap_or_default())),
+        Some(metadata),
+    )
+    .await;
+}
+
+/// What happened when polling Steam for what attendees are playing
+#[derive(Default)]
+pub struct PollSummary {
+    pub polled: usize,
+    pub playing: usize,
+    pub changes: usize,
+    pub rate_limited: bool,
+}
+
+/// Poll Steam for what everyone going to a running event is playing, storing it and
+/// adding notable changes to the event's activity ticker
+pub async fn poll_running_events(
+    pool: &PgPool,
+    steam_api_key: &String,
+) -> Result<PollSummary, Error> {
+    let attendees = match steam_presence::running_event_attendees(pool).await {
+        Ok(attendees) => attendees,
+        Err(e) => {
+            return Err(Error::Controller(format!(
+                "Unable to get attendees of running events due to: {e}"
+            )))
+        }
+    };
+
+    let mut summary = PollSummary::default();
+    if attendees.is_empty() {
+        return Ok(summary);
+    }
+
+    // People can be at more than one event at once, but only need polling once
+    let mut steam_ids: HashMap<String, i64> = HashMap::new();
+    for attendee in &attendees {
+        steam_ids.insert(attendee.email.to_lowercase(), attendee.steam_id);
+    }
+    let emails: Vec<String> = steam_ids.keys().cloned().collect();
+
+    let before: HashMap<String, Playing> = match steam_presence::filter(pool, &emails).await {
+        Ok(presences) => presences
+            .iter()
+            .filter_map(|presence| {
+                Playing::from_presence(presence)
+                    .map(|playing| (presence.email.to_lowercase(), playing))
+            })
+            .collect(),
+        Err(e) => {
+            return Err(Error::Controller(format!(
+                "Unable to get Steam presence due to: {e}"
+            )))
+        }
+    };
+
+    let unique_steam_ids: Vec<i64> = steam_ids
+        .values()
+        .copied()
+        .collect::<HashSet<_>>()
+        .into_iter()
+        .collect();
+    let mut summaries: HashMap<i64, PlayerSummary> = HashMap::new();
+    for batch in unique_steam_ids.chunks(steam_api::MAX_PLAYER_SUMMARIES) {
+        match steam_api::get_player_summaries(steam_api_key, batch).await {
+            Ok(players) => {
+                for player in players {
+                    if let Ok(steam_id) = player.steamid.parse::<i64>() {
+                        summaries.insert(steam_id, player);
+                    }
+                }
+            }
+            Err(e) if e.status() == Some(reqwest::StatusCode::TOO_MANY_REQUESTS) => {
+                summary.rate_limited = true;
+                break;
+            }
+            Err(e) => {
+                return Err(Error::Controller(format!(
+                    "Unable to get player summaries from Steam due to: {e}"
+                )))
+            }
+        }
+    }
+
+    let mut after: HashMap<String, Playing> = HashMap::new();
+    for (email, steam_id) in &steam_ids {
+        // Leave presence as it was for anyone Steam didn't tell us about
+        let Some(player) = summaries.get(steam_id) else {
+            if let Some(playing) = before.get(email) {
+                after.insert(email.clone(), playing.clone());
+            }
+            continue;
+        };
+        summary.polled += 1;
+
+        let playing = Playing::from_summary(player);
+        if let Err(e) = steam_presence::upsert(
+            pool,
+            email.clone(),
+            *steam_id,
+            playing.as_ref().and_then(|playing| playing.game_id),
+            playing
+                .as_ref()
+                .and_then(|playing| playing.game_name.clone()),
+        )
+        .await
+        {
+            return Err(Error::Controller(format!(
+                "Unable to save Steam presence for {email} due to: {e}"
+            )));
+        }
+
+        if let Some(playing) = playing {
+            summary.playing += 1;
+            after.insert(email.clone(), playing);
+        }
+    }
+
+    let mut attendees_by_event: HashMap<i32, Vec<String>> = HashMap::new();
+    for attendee in attendees {
+        attendees_by_event
+            .entry(attendee.event_id)
+            .or_default()
+            .push(attendee.email.to_lowercase());
+    }
+
+    for (event_id, event_attendees) in attendees_by_event {
+        for change in notable_changes(&event_attendees, &before, &after) {
+            summary.changes += 1;
+            log_change(pool, event_id, change).await;
+        }
+    }
+
+    Ok(summary)
+}
+
+/// Group the people playing at an event by game, with the most players first
+fn group_by_game(presences: Vec<steam_presence::EventPresence>) -> Vec<NowPlayingGame> {
+    let mut games: Vec<NowPlayingGame> = Vec::new();
+
+    for presence in presences {
+        let name = presence
+            .game_name
+            .clone()
+            .or_else(|| presence.game_id.map(|game_id| format!("App {game_id}")))
+            .unwrap_or_else(|| "Unknown game".to_string());
+        let player = NowPlayingGamer {
+            gamer: Gamer {
+                avatar_url: presence.avatar_url,
+                handle: presence.handle,
+            },
+            since: presence.started_at,
+        };
+
+        // Steam games are grouped by app ID, anything else by name
+        let existing = games.iter_mut().find(|game| {
+            presence.game_id.map_or_else(
+                || game.appid.is_none() && game.name.eq_ignore_ascii_case(&name),
+                |game_id| game.appid == Some(game_id),
+            )
+        });
+        match existing {
+            Some(game) => game.gamers.push(player),
+            None => games.push(NowPlayingGame {
+                appid: presence.game_id,
+                name,
+                gamers: vec![player],
+            }),
+        }
+    }
+
+    games.sort_by(|a, b| {
+        b.gamers
+            .len()
+            .cmp(&a.gamers.len())
+            .then_with(|| a.name.cmp(&b.name))
+    });
+    games
+}
+
+/// Get what attendees of an event are playing right now, grouped by game
+pub async fn get_now_playing(pool: &PgPool, event_id: i32) -> Result<Vec<NowPlayingGame>, Error> {
+    let seen_since = Utc::now() - Duration::minutes(STALE_AFTER_MINUTES);
+
+    match steam_presence::filter_playing_at_event(pool, event_id, seen_since).await {
+        Ok(presences) => Ok(group_by_game(presences)),
+        Err(e) => Err(Error::Controller(format!(
+            "Unable to get Steam presence due to: {e}"
+        ))),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn playing(game_id: i64) -> Playing {
+        Playing {
+            game_id: Some(game_id),
+            game_name: Some(format!("Game {game_id}")),
+        }
+    }
+
+    fn attendees(count: usize) -> Vec<String> {
+        (1..=count)
+            .map(|n| format!("gamer{n}@example.com"))
+            .collect()
+    }
+
+    fn presence(
+        email: &str,
+        game_id: Option<i64>,
+        game_name: Option<&str>,
+    ) -> steam_presence::EventPresence {
+        steam_presence::EventPresence {
+            email: email.to_string(),
+            avatar_url: None,
+            handle: Some(email.to_string()),
+            game_id,
+            game_name: game_name.map(ToString::to_string),
+            started_at: None,
+        }
+    }
+
+    #[test]
+    fn test_playing_comes_from_game_id_or_name() {
+        let mut summary = PlayerSummary::default();
+        assert_eq!(Playing::from_summary(&summary), None);
+
+        summary.gameid = Some("730".to_string());
+        summary.gameextrainfo = Some("Counter-Strike 2".to_string());
+        assert_eq!(
+            Playing::from_summary(&summary),
+            Some(Playing {
+                game_id: Some(730),
+                game_name: Some("Counter-Strike 2".to_string()),
+            })
+        );
+
+        // Non-Steam games have IDs too big to be app IDs
+        summary.gameid = Some("15190414816125648896".to_string());
+        summary.gameextrainfo = Some("Minecraft".to_string());
+        assert_eq!(
+            Playing::from_summary(&summary),
+            Some(Playing {
+                game_id: None,
+                game_name: Some("Minecraft".to_string()),
+            })
+        );
+    }
+
+    #[test]
+    fn test_starting_a_game_is_notable_but_carrying_on_isnt() {
+        let attendees = attendees(2);
+        let before = HashMap::from([(attendees[0].clone(), playing(1))]);
+        let after = HashMap::from([
+            (attendees[0].clone(), playing(1)),
+            (attendees[1].clone(), playing(1)),
+        ]);
+
+        assert_eq!(
+            notable_changes(&attendees, &before, &after),
+            vec![PresenceChange::Started {
+                email: attendees[1].clone(),
+                game: playing(1),
+            }]
+        );
+    }
+
+    #[test]
+    fn test_switching_games_is_notable() {
+        let attendees = attendees(1);
+        let before = HashMap::from([(attendees[0].clone(), playing(1))]);
+        let after = HashMap::from([(attendees[0].clone(), playing(2))]);
+
+        assert_eq!(notable_changes(&attendees, &before, &after).len(), 1);
+        assert!(notable_changes(&attendees, &after, &HashMap::new()).is_empty());
+    }
+
+    #[test]
+    fn test_groups_are_notable_once() {
+        let attendees = attendees(4);
+        let playing_together = |count: usize| -> HashMap<String, Playing> {
+            attendees
+                .iter()
+                .take(count)
+                .map(|email| (email.clone(), playing(1)))
+                .collect()
+        };
+
+        let changes = notable_changes(&attendees, &playing_together(2), &playing_together(3));
+        assert!(changes.contains(&PresenceChange::Group {
+            game: playing(1),
+            player_count: 3,
+        }));
+
+        let changes = notable_changes(&attendees, &playing_together(3), &playing_together(4));
+        assert!(!changes
+            .iter()
+            .any(|change| matches!(change, PresenceChange::Group { .. })));
+    }
+
+    #[test]
+    fn test_only_the_events_attendees_count() {
+        let everyone = attendees(3);
+        let after: HashMap<String, Playing> = everyone
+            .iter()
+            .map(|email| (email.clone(), playing(1)))
+            .collect();
+
+        let changes = notable_changes(&everyone[..2], &HashMap::new(), &after);
+        assert_eq!(changes.len(), 2);
+    }
+
+    #[test]
+    fn test_games_are_grouped_with_most_players_first() {
+        let games = group_by_game(vec![
+            presence("a@example.com", Some(1), Some("Portal 2")),
+            presence("b@example.com", Some(2), Some("Rocket League")),
+            presence("c@example.com", None, Some("Minecraft")),
+            presence("d@example.com", Some(2), Some("Rocket League")),
+            presence("e@example.com", None, Some("minecraft")),
+            presence("f@example.com", Some(3), None),
+        ]);
+
+        let summary: Vec<(&str, usize)> = games
+            .iter()
+            .map(|game| (game.name.as_str(), game.gamers.len()))
+            .collect();
+        assert_eq!(
+            summary,
+            vec![
+                ("Minecraft", 2),
+                ("Rocket League", 2),
+                ("App 3", 1),
+                ("Portal 2", 1),
+            ]
+        );
+    }
+}
@@ -14,6 +14,8 @@ impl From<crate::repositories::profile::Profile> for Profile {
             steam_id: profile.steam_id.to_string(),
             games: vec![],
             game_count: 0,
+            last_refreshed: profile.last_refreshed,
+            refresh_error: profile.refresh_error,
         }
     }
 }
@@ -124,33 +126,132 @@ pub async fn edit(
     }
 }
 
-pub async fn update_user_games(
+/// What happened when refreshing someone's games from their Steam library
+#[derive(Debug, PartialEq, Eq)]
+pub enum LibraryRefresh {
+    Refreshed {
+        games_count: i64,
+        games_removed: i64,
+    },
+    /// The user's game details are private, so their games were left as they were
+    Private,
+    /// Steam is rate limiting requests, so try again later
+    RateLimited,
+}
+
+const PRIVATE_LIBRARY_ERROR: &str = "Steam game details are private";
+
+/// Refresh someone's games from their Steam library, removing any they no longer own.
+/// Failures are recorded against their profile, except for rate limiting which isn't their fault.
+pub async fn refresh_library(
     pool: &PgPool,
-    email: String,
     steam_api_key: &String,
-) -> Result<Profile, Error> {
-    let profile = get(pool, email.clone(), 1, 1).await?;
+    email: String,
+    steam_id: i64,
+) -> Result<LibraryRefresh, Error> {
+    let owned_games = match steam_api::get_owned_games(steam_api_key, &steam_id.to_string()).await {
+        Ok(owned_games) => owned_games,
+        Err(e) if e.status() == Some(reqwest::StatusCode::TOO_MANY_REQUESTS) => {
+            return Ok(LibraryRefresh::RateLimited)
+        }
+        Err(e) => {
+            record_refresh_failure(pool, email, format!("Unable to get games from Steam: {e}"))
+                .await?;
+            return Err(e.into());
+        }
+    };
 
-    let user_games = steam_api::get_owned_games(steam_api_key, &profile.steam_id).await?;
+    let Some(games) = library_games(owned_games.response) else {
+        record_refresh_failure(pool, email, PRIVATE_LIBRARY_ERROR.to_string()).await?;
+        return Ok(LibraryRefresh::Private);
+    };
 
-    let games_count = user_games.response.games.len();
+    let mut appids = Vec::with_capacity(games.len());
+    let mut playtimes = Vec::with_capacity(games.len());
+    let mut playtimes_2weeks = Vec::with_capacity(games.len());
+    for game in games {
+        appids.push(game.appid);
+        playtimes.push(game.playtime_forever);
+        playtimes_2weeks.push(game.playtime_2weeks.unwrap_or_default());
+    }
 
-    // Create each user game in the user_games.rs repository
-    for game in user_games.response.games {
-        match user_games::create(pool, email.clone(), game.appid, game.playtime_forever).await {
-            Ok(_) => (),
+    let counts =
+        match user_games::sync(pool, email.clone(), &appids, &playtimes, &playtimes_2weeks).await {
+            Ok(counts) => counts,
             Err(e) => {
                 return Err(Error::Controller(format!(
-                    "Unable to create user game due to: {e}"
+                    "Unable to save user games due to: {e}"
                 )))
             }
-        }
+        };
+
+    if let Err(e) = profile::record_refresh(pool, email).await {
+        return Err(Error::Controller(format!(
+            "Unable to update profile due to: {e}"
+        )));
     }
 
+    Ok(LibraryRefresh::Refreshed {
+        games_count: counts.upserted,
+        games_removed: counts.removed,
+    })
+}
+
+/// The games in someone's Steam library, or None if their game details are private.
+/// Steam leaves out the games both for private libraries and public ones with no games,
+/// but only says how many there are for public ones.
+fn library_games(
+    response: steam_api::SteamAPIOwnedGamesListResponse,
+) -> Option<Vec<steam_api::SteamAPIOwnedGame>> {
+    response
+        .games
+        .or_else(|| response.game_count.map(|_| vec![]))
+}
+
+async fn record_refresh_failure(pool: &PgPool, email: String, error: String) -> Result<(), Error> {
+    profile::record_refresh_failure(pool, email, error)
+        .await
+        .map_err(|e| Error::Controller(format!("Unable to update profile due to: {e}")))
+}
+
+pub async fn update_user_games(
+    pool: &PgPool,
+    email: String,
+    steam_api_key: &String,
+) -> Result<Profile, Error> {
+    let steam_id = match profile::read(pool, email.clone()).await {
+        Ok(Some(profile)) => profile.steam_id,
+        Ok(None) => return Err(Error::NoData(format!("Profile for {email} not found"))),
+        Err(e) => {
+            return Err(Error::Controller(format!(
+                "Unable to get profile due to: {e}"
+            )))
+        }
+    };
+
+    let (games_count, games_removed) =
+        match refresh_library(pool, steam_api_key, email.clone(), steam_id).await? {
+            LibraryRefresh::Refreshed {
+                games_count,
+                games_removed,
+            } => (games_count, games_removed),
+            LibraryRefresh::Private => {
+                return Err(Error::BadInput(format!(
+                    "{PRIVATE_LIBRARY_ERROR}, so your games can't be updated"
+                )))
+            }
+            LibraryRefresh::RateLimited => {
+                return Err(Error::RateLimited(
+                    "Steam is busy, try updating your games again later".to_string(),
+                ))
+            }
+        };
+
     // Log audit entry for games refresh
     let metadata = rocket::serde::json::serde_json::json!({
         "games_count": games_count,
-        "steam_id": &profile.steam_id,
+        "games_removed": games_removed,
+        "steam_id": steam_id.to_string(),
     });
     crate::util::log_audit(
         pool,
@@ -171,3 +272,197 @@ pub async fn update_user_games(
         ))),
     }
 }
+
+/// Refresh libraries that were last refreshed longer ago than this
+const LIBRARY_REFRESH_AFTER_HOURS: i64 = 24;
+
+/// Wait this long before retrying a library that failed to refresh
+const LIBRARY_RETRY_AFTER_HOURS: i64 = 6;
+
+/// Most libraries to refresh in one go, to stay well within Steam's rate limits
+const LIBRARY_REFRESH_BATCH_SIZE: i64 = 25;
+
+/// Time between requests to Steam while refreshing libraries
+const LIBRARY_REFRESH_DELAY_SECONDS: u64 = 2;
+
+/// How a batch of library refreshes went
+#[derive(Debug, Default, PartialEq, Eq)]
+pub struct LibraryRefreshSummary {
+    pub refreshed: usize,
+    pub private: usize,
+    pub failed: usize,
+    pub rate_limited: bool,
+}
+
+impl LibraryRefreshSummary {
+    /// Count how refreshing someone's library went, returning false if Steam is rate limiting
+    /// so the rest of the batch should be left for next time
+    fn record(&mut self, email: &str, result: Result<LibraryRefresh, Error>) -> bool {
+        match result {
+            Ok(LibraryRefresh::Refreshed { games_removed, .. }) => {
+                if games_removed > 0 {
+                    log::info!("Removed {games_removed} games no longer owned by {email}");
+                }
+                self.refreshed += 1;
+            }
+            Ok(LibraryRefresh::Private) => self.private += 1,
+            Ok(LibraryRefresh::RateLimited) => {
+                self.rate_limited = true;
+                return false;
+            }
+            Err(e) => {
+                log::warn!("Unable to refresh games for {email}: {e}");
+                self.failed += 1;
+            }
+        }
+
+        true
+    }
+}
+
+/// Refresh the Steam libraries of people going to upcoming events, if they're due.
+/// Stops early if Steam starts rate limiting, leaving the rest for next time.
+pub async fn refresh_due_libraries(
+    pool: &PgPool,
+    steam_api_key: &String,
+) -> Result<LibraryRefreshSummary, Error> {
+    let now = chrono::Utc::now();
+    let profiles = match profile::due_for_refresh(
+        pool,
+        now - chrono::Duration::hours(LIBRARY_REFRESH_AFTER_HOURS),
+        now - chrono::Duration::hours(LIBRARY_RETRY_AFTER_HOURS),
+        LIBRARY_REFRESH_BATCH_SIZE,
+    )
+    .await
+    {
+        Ok(profiles) => profiles,
+        Err(e) => {
+            return Err(Error::Controller(format!(
+                "Unable to get profiles to refresh due to: {e}"
+            )))
+        }
+    };
+
+    let mut summary = LibraryRefreshSummary::default();
+    for (index, profile) in profiles.into_iter().enumerate() {
+        if index > 0 {
+            tokio::time::sleep(std::time::Duration::from_secs(
+                LIBRARY_REFRESH_DELAY_SECONDS,
+            ))
+            .await;
+        }
+
+        let result =
+            refresh_library(pool, steam_api_key, profile.email.clone(), profile.steam_id).await;
+        if !summary.record(&profile.email, result) {
+            break;
+        }
+    }
+
+    Ok(summary)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use rocket::serde::json::serde_json::{self, json};
+
+    fn owned_games(response: serde_json::Value) -> steam_api::SteamAPIOwnedGamesListResponse {
+        serde_json::from_value::<steam_api::SteamAPIOwnedGamesList>(json!({ "response": response }))
+            .expect("the response should parse")
+            .response
+    }
+
+    #[test]
+    fn test_private_library() {
+        assert!(library_games(owned_games(json!({}))).is_none());
+    }
+
+    #[test]
+    fn test_public_library_without_games() {
+        let games = library_games(owned_games(json!({ "game_count": 0 })))
+            .expect("a public library with no games isn't private");
+        assert!(games.is_empty());
+    }
+
+    #[test]
+    fn test_public_library() {
+        let games = library_games(owned_games(json!({
+            "game_count": 2,
+            "games": [
+                {"appid": 550, "playtime_forever": 120, "playtime_2weeks": 30},
+                {"appid": 730, "playtime_forever": 0}
+            ]
+        })))
+        .expect("the library is public");
+        let appids: Vec<i64> = games.iter().map(|game| game.appid).collect();
+        assert_eq!(appids, vec![550, 730]);
+    }
+
+    #[test]
+    fn test_refresh_summary_counts_each_outcome() {
+        let mut summary = LibraryRefreshSummary::default();
+        assert!(summary.record(
+            "a@example.com",
+            Ok(LibraryRefresh::Refreshed {
+                games_count: 10,
+                games_removed: 2,
+            })
+        ));
+        assert!(summary.record("b@example.com", Ok(LibraryRefresh::Private)));
+        assert!(summary.record(
+            "c@example.com",
+            Err(Error::Controller("Steam is down".to_string()))
+        ));
+        assert!(summary.record(
+            "d@example.com",
+            Ok(LibraryRefresh::Refreshed {
+                games_count: 0,
+                games_removed: 0,
+            })
+        ));
+
+        assert_eq!(
+            summary,
+            LibraryRefreshSummary {
+                refreshed: 2,
+                private: 1,
+                failed: 1,
+                rate_limited: false,
+            }
+        );
+    }
+
+    #[test]
+    fn test_refresh_summary_stops_when_rate_limited() {
+        let results = [
+            Ok(LibraryRefresh::Private),
+            Ok(LibraryRefresh::RateLimited),
+            Ok(LibraryRefresh::Refreshed {
+                games_count: 1,
+                games_removed: 0,
+            }),
+        ];
+
+        // The same as the batch loop, which leaves the rest for the next check
+        let mut summary = LibraryRefreshSummary::default();
+        let mut attempted = 0;
+        for result in results {
+            attempted += 1;
+            if !summary.record("a@example.com", result) {
+                break;
+            }
+        }
+
+        assert_eq!(attempted, 2);
+        assert_eq!(
+            summary,
+            LibraryRefreshSummary {
+                refreshed: 0,
+                private: 1,
+                failed: 0,
+                rate_limited: true,
+            }
+        );
+    }
+}
@@ -0,0 +1,640 @@
+use chrono::{DateTime, Duration, Utc};
+use rocket::serde::json::serde_json::{json, Value as JsonValue};
+use sqlx::PgPool;
+
+use crate::{
+    controllers::Error,
+    repositories::{
+        audit_log::AuditLog,
+        webhook,
+        webhook_delivery::{self, DeliveryStatus, DueDelivery},
+    },
+    routes::webhooks::{Webhook, WebhookDelivery, WebhookDeliveryStatus, WebhookSubmit},
+    util::webhook_signature,
+};
+
+/// Give up on a delivery after this many attempts
+pub const MAX_DELIVERY_ATTEMPTS: i32 = 6;
+
+/// How long a claimed delivery is reserved for one sender before it can be retried by another
+const DELIVERY_LEASE_SECONDS: f64 = 60.0;
+
+/// Secrets shorter than this are too easy to guess
+const MIN_SECRET_LENGTH: usize = 16;
+
+impl From<webhook::Webhook> for Webhook {
+    fn from(webhook: webhook::Webhook) -> Self {
+        Self {
+            id: webhook.id,
+            url: webhook.url,
+            event_types: webhook.event_types,
+            is_active: webhook.is_active,
+            created_at: webhook.created_at,
+            last_modified: webhook.last_modified,
+        }
+    }
+}
+
+impl From<DeliveryStatus> for WebhookDeliveryStatus {
+    fn from(status: DeliveryStatus) -> Self {
+        match status {
+            DeliveryStatus::Pending => Self::Pending,
+            DeliveryStatus::Succeeded => Self::Succeeded,
+            DeliveryStatus::Failed => Self::Failed,
+        }
+    }
+}
+
+impl From<webhook_delivery::WebhookDelivery> for WebhookDelivery {
+    fn from(delivery: webhook_delivery::WebhookDelivery) -> Self {
+        Self {
+            id: delivery.id,
+            webhook_id: delivery.webhook_id,
+            event_type: delivery.event_type,
+            payload: delivery.payload,
+            status: delivery.status.into(),
+            attempts: delivery.attempts,
+            next_attempt_at: delivery.next_attempt_at,
+            response_status: delivery.response_status,
+            error: delivery.error,
+            created_at: delivery.created_at,
+            last_modified: delivery.last_modified,
+        }
+    }
+}
+
+/// Check whether a webhook's event type patterns include an event type.
+/// Patterns are an exact event type, a prefix ending in `.*`, or `*` for everything.
+pub fn subscribes_to(event_types: &[String], event_type: &str) -> bool {
+    event_types.iter().any(|pattern| {
+        pattern == "*"
+            || pattern == event_type
+            || pattern
+                .strip_suffix('*')
+                .is_some_and(|prefix| prefix.ends_with('.') && event_type.starts_with(prefix))
+    })
+}
+
+/// How long to wait before retrying after a number of failed attempts.
+/// Starts at 30 seconds and quadruples each time, so the last retry is around two hours later.
+pub fn retry_delay(attempts: i32) -> Duration {
+    let exponent = u32::try_from(attempts.saturating_sub(1).clamp(0, 8)).unwrap_or_default();
+    Duration::seconds(30 * 4_i64.pow(exponent))
+}
+
+fn validate(webhook_submit: &WebhookSubmit) -> Result<(), Error> {
+    match reqwest::Url::parse(&webhook_submit.url) {
+        Ok(url) if url.scheme() == "https" || url.scheme() == "http" => {}
+        Ok(_) => {
+            return Err(Error::BadInput(
+                "Webhook URL must use http or https".to_string(),
+            ))
+        }
+        Err(e) => return Err(Error::BadInput(format!("Webhook URL is invalid: {e}"))),
+    }
+
+    if webhook_submit.event_types.is_empty() {
+        return Err(Error::BadInput(
+            "Webhook must subscribe to at least one event type".to_string(),
+        ));
+    }
+
+    if webhook_submit
+        .event_types
+        .iter()
+        .any(|event_type| event_type.trim().is_empty())
+    {
+        return Err(Error::BadInput("Event types cannot be empty".to_string()));
+    }
+
+    if let Some(secret) = &webhook_submit.secret {
+        if secret.len() < MIN_SECRET_LENGTH {
+            return Err(Error::BadInput(format!(
+                "Webhook secret must be at least {MIN_SECRET_LENGTH} characters"
+            )));
+        }
+    }
+
+    Ok(())
+}
+
+pub async fn index(pool: &PgPool) -> Result<Vec<Webhook>, Error> {
+    match webhook::index(pool).await {
+        Ok(webhooks) => Ok(webhooks.into_iter().map(Webhook::from).collect()),
+        Err(e) => Err(Error::Controller(format!(
+            "Unable to get webhooks due to: {e}"
+        ))),
+    }
+}
+
+pub async fn get(pool: &PgPool, webhook_id: i32) -> Result<Webhook, Error> {
+    match webhook::get(pool, webhook_id).await {
+        Ok(Some(webhook)) => Ok(webhook.into()),
+        Ok(None) => Err(Error::NotFound(format!(
+            "Webhook with ID {webhook_id} not found"
+        ))),
+        Err(e) => Err(Error::Controller(format!(
+            "Unable to get webhook due to: {e}"
+        ))),
+    }
+}
+
+pub async fn create(
+    pool: &PgPool,
+    webhook_submit: WebhookSubmit,
+    user_email: String,
+) -> Result<Webhook, Error> {
+    validate(&webhook_submit)?;
+
+    let Some(secret) = webhook_submit.secret else {
+        return Err(Error::BadInput(
+            "A secret is required to sign webhook payloads".to_string(),
+        ));
+    };
+
+    match webhook::create(
+        pool,
+        webhook_submit.url,
+        secret,
+        webhook_submit.event_types,
+        webhook_submit.is_active,
+    )
+    .await
+    {
+        Ok(webhook) => {
+            // Log audit entry
+            let metadata = json!({
+                "webhook_id": webhook.id,
+                "url": webhook.url,
+                "event_types": webhook.event_types,
+                "is_active": webhook.is_active,
+            });
+            crate::util::log_audit(
+                pool,
+                Some(user_email),
+                "webhook.create".to_string(),
+                "webhook".to_string(),
+                Some(webhook.id.to_string()),
+                Some(metadata),
+            )
+            .await;
+
+            Ok(webhook.into())
+        }
+        Err(e) => Err(Error::Controller(format!(
+            "Unable to create webhook due to: {e}"
+        ))),
+    }
+}
+
+pub async fn update(
+    pool: &PgPool,
+    webhook_id: i32,
+    webhook_submit: WebhookSubmit,
+    user_email: String,
+) -> Result<Webhook, Error> {
+    validate(&webhook_submit)?;
+
+    let secret_changed = webhook_submit.secret.is_some();
+
+    match webhook::update(
+        pool,
+        webhook_id,
+        webhook_submit.url,
+        webhook_submit.secret,
+        webhook_submit.event_types,
+        webhook_submit.is_active,
+    )
+    .await
+    {
+        Ok(Some(webhook)) => {
+            // Log audit entry
+            let metadata = json!({
+                "webhook_id": webhook.id,
+                "url": webhook.url,
+                "event_types": webhook.event_types,
+                "is_active": webhook.is_active,
+                "secret_changed": secret_changed,
+            });
+            crate::util::log_audit(
+                pool,
+                Some(user_email),
+                "webhook.update".to_string(),
+                "webhook".to_string(),
+                Some(webhook.id.to_string()),
+                Some(metadata),
+            )
+            .await;
+
+            Ok(webhook.into())
+        }
+        Ok(None) => Err(Error::NotFound(format!(
+            "Webhook with ID {webhook_id} not found"
+        ))),
+        Err(e) => Err(Error::Controller(format!(
+            "Unable to update webhook due to: {e}"
+        ))),
+    }
+}
+
+pub async fn delete(pool: &PgPool, webhook_id: i32, user_email: String) -> Result<(), Error> {
+    match webhook::delete(pool, webhook_id).await {
+        Ok(()) => {
+            // Log audit entry
+            let metadata = json!({
+                "webhook_id": webhook_id,
+            });
+            crate::util::log_audit(
+                pool,
+                Some(user_email),
+                "webhook.delete".to_string(),
+                "webhook".to_string(),
+                Some(webhook_id.to_string()),
+                Some(metadata),
+            )
+            .await;
+
+            Ok(())
+        }
+        Err(e) => Err(Error::Controller(format!(
+            "Unable to delete webhook due to: {e}"
+        ))),
+    }
+}
+
+pub async fn deliveries(
+    pool: &PgPool,
+    webhook_id: i32,
+    limit: i64,
+    offset: i64,
+) -> Result<Vec<WebhookDelivery>, Error> {
+    // Distinguish an unknown webhook from one that hasn't sent anything yet
+    get(pool, webhook_id).await?;
+
+    match webhook_delivery::filter(pool, webhook_id, limit, offset).await {
+        Ok(deliveries) => Ok(deliveries.into_iter().map(WebhookDelivery::from).collect()),
+        Err(e) => Err(Error::Controller(format!(
+            "Unable to get webhook deliveries due to: {e}"
+        ))),
+    }
+}
+
+/// Build the JSON body posted to webhooks
+pub fn payload(
+    id: Option<i64>,
+    event_type: &str,
+    timestamp: DateTime<Utc>,
+    actor: Option<&str>,
+    entity_type: &str,
+    entity_id: Option<&str>,
+    data: Option<&JsonValue>,
+) -> JsonValue {
+    json!({
+        "id": id,
+        "type": event_type,
+        "timestamp": timestamp,
+        "actor": actor,
+        "entityType": entity_type,
+        "entityId": entity_id,
+        "data": data,
+    })
+}
+
+/// Queue an audit log entry for delivery to every active webhook subscribed to its action.
+/// Called from `util::log_audit`, so like it this fails softly.
+pub async fn enqueue(pool: &PgPool, audit_log: &AuditLog) {
+    let webhook_ids: Vec<i32> = match webhook::index_active(pool).await {
+        Ok(webhooks) => webhooks
+            .into_iter()
+            .filter(|webhook| subscribes_to(&webhook.event_types, &audit_log.action))
+            .map(|webhook| webhook.id)
+            .collect(),
+        Err(e) => {
+            log::error!("Failed to get webhooks for {}: {e}", audit_log.action);
+            return;
+        }
+    };
+
+    if webhook_ids.is_empty() {
+        return;
+    }
+
+    let body = payload(
+        Some(audit_log.id),
+        &audit_log.action,
+        audit_log.timestamp,
+        audit_log.user_id.as_deref(),
+        &audit_log.entity_type,
+        audit_log.entity_id.as_deref(),
+        audit_log.metadata.as_ref(),
+    );
+
+    if let Err(e) = webhook_delivery::create_many(pool, &webhook_ids, &audit_log.action, body).await
+    {
+        log::error!(
+            "Failed to queue webhook deliveries for {}: {e}",
+            audit_log.action
+        );
+    }
+}
+
+/// Queue a test event for a single webhook, whatever it is subscribed to
+pub async fn send_test(
+    pool: &PgPool,
+    webhook_id: i32,
+    user_email: String,
+) -> Result<WebhookDelivery, Error> {
+    get(pool, webhook_id).await?;
+
+    let body = payload(
+        None,
+        "webhook.test",
+        Utc::now(),
+        Some(&user_email),
+        "webhook",
+        Some(&webhook_id.to_string()),
+        Some(&json!({
+            "message": "This is a test event from caLANdar",
+        })),
+    );
+
+    match webhook_delivery::create_many(pool, &[webhook_id], "webhook.test", body).await {
+        Ok(deliveries) => deliveries.into_iter().next().map_or_else(
+            || {
+                Err(Error::Controller(
+                    "Test delivery was not created".to_string(),
+                ))
+            },
+            |delivery| Ok(delivery.into()),
+        ),
+        Err(e) => Err(Error::Controller(format!(
+            "Unable to queue test delivery due to: {e}"
+        ))),
+    }
+}
+
+/// Post a payload to a webhook URL, signed with its secret.
+/// Returns the response status code, or why the request couldn't be made.
+pub async fn post(
+    client: &reqwest::Client,
+    url: &str,
+    secret: &str,
+    delivery_id: i64,
+    event_type: &str,
+    payload: &JsonValue,
+) -> Result<u16, String> {
+    let body = payload.to_string();
+    let timestamp = Utc::now().timestamp();
+    let signature = webhook_signature(secret, timestamp, &body);
+
+    match client
+        .post(url)
+        .header("Content-Type", "application/json")
+        .header("X-Calandar-Event", event_type)
+        .header("X-Calandar-Delivery", delivery_id.to_string())
+        .header("X-Calandar-Timestamp", timestamp.to_string())
+        .header("X-Calandar-Signature", format!("sha256={signature}"))
+        .body(body)
+        .send()
+        .await
+    {
+        Ok(response) => Ok(response.status().as_u16()),
+        Err(e) => Err(e.to_string()),
+    }
+}
+
+async fn attempt(pool: &PgPool, client: &reqwest::Client, delivery: DueDelivery) {
+    let attempts = delivery.attempts + 1;
+
+    let (response_status, error) = match post(
+        client,
+        &delivery.url,
+        &delivery.secret,
+        delivery.id,
+        &delivery.event_type,
+        &delivery.payload,
+    )
+    .await
+    {
+        Ok(status) if (200..300).contains(&status) => (Some(i32::from(status)), None),
+        Ok(status) => (
+            Some(i32::from(status)),
+            Some(format!("Webhook responded with status {status}")),
+        ),
+        Err(e) => (None, Some(e)),
+    };
+
+    let (status, next_attempt_at) = if error.is_none() {
+        (DeliveryStatus::Succeeded, Utc::now())
+    } else if attempts >= MAX_DELIVERY_ATTEMPTS {
+        (DeliveryStatus::Failed, Utc::now())
+    } else {
+        (DeliveryStatus::Pending, Utc::now() + retry_delay(attempts))
+    };
+
+    if let Some(error) = &error {
+        log::warn!(
+            "Webhook delivery {} to webhook {} failed on attempt {attempts}: {error}",
+            delivery.id,
+            delivery.webhook_id
+        );
+    }
+
+    if let Err(e) = webhook_delivery::record_attempt(
+        pool,
+        delivery.id,
+        status,
+        attempts,
+        next_attempt_at,
+        response_status,
+        error,
+    )
+    .await
+    {
+        log::error!(
+            "Failed to record webhook delivery {} attempt: {e}",
+            delivery.id
+        );
+    }
+}
+
+/// Send deliveries that are due, returning how many were attempted
+pub async fn deliver_due(
+    pool: &PgPool,
+    client: &reqwest::Client,
+    limit: i64,
+) -> Result<usize, Error> {
+    let deliveries = match webhook_delivery::claim_due(pool, limit, DELIVERY_LEASE_SECONDS).await {
+        Ok(deliveries) => deliveries,
+        Err(e) => {
+            return Err(Error::Controller(format!(
+                "Unable to get due webhook deliveries due to: {e}"
+            )))
+        }
+    };
+
+    let count = deliveries.len();
+    futures::future::join_all(
+        deliveries
+            .into_iter()
+            .map(|delivery| attempt(pool, client, delivery)),
+    )
+    .await;
+
+    Ok(count)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use tokio::io::{AsyncReadExt, AsyncWriteExt};
+    use tokio::net::TcpListener;
+
+    fn patterns(patterns: &[&str]) -> Vec<String> {
+        patterns.iter().map(ToString::to_string).collect()
+    }
+
+    #[test]
+    fn test_subscribes_to_exact_prefix_and_wildcard() {
+        assert!(subscribes_to(&patterns(&["rsvp.update"]), "rsvp.update"));
+        assert!(!subscribes_to(&patterns(&["rsvp.update"]), "rsvp.delete"));
+        assert!(subscribes_to(
+            &patterns(&["game_suggestion.*"]),
+            "game_suggestion.create"
+        ));
+        assert!(!subscribes_to(
+            &patterns(&["game.*"]),
+            "game_suggestion.create"
+        ));
+        assert!(subscribes_to(&patterns(&["*"]), "seat_reservation.create"));
+        assert!(!subscribes_to(&patterns(&[]), "rsvp.update"));
+    }
+
+    #[test]
+    fn test_retry_delay_backs_off() {
+        assert_eq!(retry_delay(1), Duration::seconds(30));
+        assert_eq!(retry_delay(2), Duration::minutes(2));
+        assert_eq!(retry_delay(3), Duration::minutes(8));
+        assert_eq!(retry_delay(5), Duration::seconds(30 * 256));
+    }
+
+    /// Accept a single request on a local listener, respond with `status`,
+    /// and return the raw request that was received
+    async fn serve_once(status: u16) -> (String, tokio::task::JoinHandle<String>) {
+        let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let url = format!("http://{}/hook", listener.local_addr().unwrap());
+
+        let handle = tokio::spawn(async move {
+            let (mut socket, _) = listener.accept().await.unwrap();
+            let mut request = Vec::new();
+            let mut buffer = [0; 4096];
+
+            // Read until the headers and the full body have arrived
+            loop {
+                let read = socket.read(&mut buffer).await.unwrap();
+                request.extend_from_slice(&buffer[..read]);
+                let text = String::from_utf8_lossy(&request).to_string();
+                if let Some(header_end) = text.find("\r\n\r\n") {
+                    let content_length = text[..header_end]
+                        .lines()
+                        .find_map(|line| {
+                            line.to_lowercase()
+                                .strip_prefix("content-length:")
+                                .map(|length| length.trim().parse::<usize>().unwrap())
+                        })
+                        .unwrap_or(0);
+                    if request.len() >= header_end + 4 + content_length {
+                        break;
+                    }
+                }
+                if read == 0 {
+                    break;
+                }
+            }
+
+            socket
+                .write_all(
+                    format!("HTTP/1.1 {status} Status\r\ncontent-length: 0\r\n\r\n").as_bytes(),
+                )
+                .await
+                .unwrap();
+
+            String::from_utf8(request).unwrap()
+        });
+
+        (url, handle)
+    }
+
+    fn header<'a>(request: &'a str, name: &str) -> Option<&'a str> {
+        request.lines().find_map(|line| {
+            let (key, value) = line.split_once(':')?;
+            key.eq_ignore_ascii_case(name).then(|| value.trim())
+        })
+    }
+
+    #[tokio::test]
+    async fn test_post_signs_payload_for_local_listener() {
+        let (url, handle) = serve_once(200).await;
+        let body = payload(
+            Some(7),
+            "rsvp.update",
+            Utc::now(),
+            Some("test@test.invalid"),
+            "event",
+            Some("1"),
+            Some(&json!({"response": "Yes"})),
+        );
+
+        let status = post(
+            &reqwest::Client::new(),
+            &url,
+            "a-long-random-shared-secret",
+            42,
+            "rsvp.update",
+            &body,
+        )
+        .await
+        .unwrap();
+        assert_eq!(status, 200);
+
+        let request = handle.await.unwrap();
+        assert!(request.starts_with("POST /hook HTTP/1.1"));
+        assert_eq!(header(&request, "x-calandar-event"), Some("rsvp.update"));
+        assert_eq!(header(&request, "x-calandar-delivery"), Some("42"));
+
+        let timestamp: i64 = header(&request, "x-calandar-timestamp")
+            .unwrap()
+            .parse()
+            .unwrap();
+        let received_body = request.split_once("\r\n\r\n").unwrap().1;
+        assert_eq!(received_body, body.to_string());
+
+        let expected = format!(
+            "sha256={}",
+            webhook_signature("a-long-random-shared-secret", timestamp, received_body)
+        );
+        assert_eq!(
+            header(&request, "x-calandar-signature"),
+            Some(expected.as_str())
+        );
+    }
+
+    #[tokio::test]
+    async fn test_post_returns_error_status() {
+        let (url, handle) = serve_once(500).await;
+
+        let status = post(
+            &reqwest::Client::new(),
+            &url,
+            "a-long-random-shared-secret",
+            1,
+            "webhook.test",
+            &json!({}),
+        )
+        .await
+        .unwrap();
+        assert_eq!(status, 500);
+
+        handle.await.unwrap();
+    }
+}
@@ -0,0 +1,165 @@
+use std::sync::Arc;
+
+use chrono::{Duration, Utc};
+use rocket_dyn_templates::tera::Context;
+use tokio::time::MissedTickBehavior;
+
+use crate::{
+    controllers::{digest, Error},
+    jobs::JobContext,
+    repositories::{event::Event, notification_preference::NotificationCategory},
+    util::{add_unsubscribe_link, event_ics_attachment, render_email, send_email},
+};
+
+/// How often to check for digests that are due
+const CHECK_INTERVAL_MINUTES: i64 = 60;
+
+/// Periodically send the weekly activity digest for each upcoming event
+pub async fn run(context: Arc<JobContext>) {
+    let period = Duration::minutes(CHECK_INTERVAL_MINUTES)
+        .to_std()
+        .unwrap_or(std::time::Duration::from_secs(3600));
+
+    let mut interval = tokio::time::interval(period);
+    interval.set_missed_tick_behavior(MissedTickBehavior::Delay);
+
+    loop {
+        interval.tick().await;
+
+        match send_due_digests(&context).await {
+            Ok(0) => {}
+            Ok(sent) => log::info!("Sent {sent} event digests"),
+            Err(e) => log::error!("Error sending event digests: {e}"),
+        }
+    }
+}
+
+async fn send_due_digests(context: &JobContext) -> Result<usize, Error> {
+    let mut sent = 0;
+
+    for event in digest::upcoming_events(&context.pool).await? {
+        // Don't let one event stop digests for the others
+        match send_event_digests(context, &event).await {
+            Ok(count) => sent += count,
+            Err(e) => log::error!("Error sending digests for event {}: {e}", event.id),
+        }
+    }
+
+    Ok(sent)
+}
+
+async fn send_event_digests(context: &JobContext, event: &Event) -> Result<usize, Error> {
+    // Digests are due at most one check early, so they don't drift later each week
+    let recipients = digest::due_recipients(
+        &context.pool,
+        event.id,
+        Duration::minutes(CHECK_INTERVAL_MINUTES),
+    )
+    .await?;
+
+    if recipients.is_empty() {
+        return Ok(0);
+    }
+
+    let event_digest = digest::build(
+        &context.pool,
+        event.id,
+        Utc::now() - Duration::days(digest::DIGEST_INTERVAL_DAYS),
+    )
+    .await?;
+
+    // Nothing new this week, so check again later rather than sending an empty digest
+    if !event_digest.has_activity() {
+        return Ok(0);
+    }
+
+    let mut email_context = Context::new();
+    email_context.insert("title", &event.title);
+    email_context.insert(
+        "time_begin",
+        &event.time_begin.format("%a %e %b %Y %H:%M").to_string(),
+    );
+    email_context.insert(
+        "time_end",
+        &event.time_end.format("%a %e %b %Y %H:%M").to_string(),
+    );
+    email_context.insert("event_id", &event.id);
+    email_context.insert("new_rsvps", &event_digest.new_rsvps);
+    email_context.insert("new_games", &event_digest.new_games);
+    email_context.insert("vote_leaders", &event_digest.vote_leaders);
+    email_context.insert("schedule", &event_digest.schedule);
+
+    let subject = format!("This week at {}", event.title);
+
+    let mut sent = 0;
+    let mut failed = 0;
+    for (index, recipient_email) in recipients.iter().enumerate() {
+        let mut recipient_context = email_context.clone();
+        let unsubscribe_url = add_unsubscribe_link(
+            &mut recipient_context,
+            &context.key,
+            recipient_email,
+            NotificationCategory::Digests,
+        )
+        .map_err(Error::Controller)?;
+
+        let body = render_email(&context.tera, "email_digest.html.tera", &recipient_context)
+            .map_err(Error::Controller)?;
+
+        // Another instance may have sent it since the recipients were checked
+        if !digest::claim(
+            &context.pool,
+            event.id,
+            recipient_email,
+            Duration::minutes(CHECK_INTERVAL_MINUTES),
+        )
+        .await?
+        {
+            continue;
+        }
+
+        if index > 0 {
+            // Stay under the email provider's rate limit
+            tokio::time::sleep(std::time::Duration::from_millis(500)).await;
+        }
+
+        if let Err(e) = send_email(
+            &context.email_sender,
+            vec![recipient_email.as_str()],
+            &subject,
+            &body,
+            vec![event_ics_attachment(event)],
+            Some(unsubscribe_url.as_str()),
+        )
+        .await
+        {
+            // Release the claim so it is retried on the next check
+            log::error!(
+                "Error sending digest for event {} to {recipient_email}: {e}",
+                event.id
+            );
+            digest::release(&context.pool, event.id, recipient_email).await?;
+            failed += 1;
+            continue;
+        }
+
+        sent += 1;
+    }
+
+    // Log audit entry
+    let metadata = rocket::serde::json::serde_json::json!({
+        "recipient_count": sent,
+        "failed_recipient_count": failed,
+    });
+    crate::util::log_audit(
+        &context.pool,
+        None,
+        "event_digest.send".to_string(),
+        "event".to_string(),
+        Some(event.id.to_string()),
+        Some(metadata),
+    )
+    .await;
+
+    Ok(sent)
+}
@@ -0,0 +1,25 @@
+use std::sync::Arc;
+
+use tokio::time::MissedTickBehavior;
+
+use crate::{controllers::discord, jobs::JobContext};
+
+/// How often to look for scheduled games that are about to start
+const CHECK_INTERVAL_SECONDS: u64 = 60;
+
+/// Post to Discord when scheduled games are about to start
+pub async fn run(context: Arc<JobContext>) {
+    let mut interval =
+        tokio::time::interval(std::time::Duration::from_secs(CHECK_INTERVAL_SECONDS));
+    interval.set_missed_tick_behavior(MissedTickBehavior::Delay);
+
+    loop {
+        interval.tick().await;
+
+        match discord::post_starting_games(&context.pool).await {
+            Ok(0) => {}
+            Ok(count) => log::info!("Posted {count} starting games to Discord"),
+            Err(e) => log::error!("Error posting starting games to Discord: {e}"),
+        }
+    }
+}
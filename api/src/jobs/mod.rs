@@ -0,0 +1,39 @@
+use std::sync::Arc;
+
+use resend_rs::Resend;
+use rocket_dyn_templates::tera::Tera;
+use rusty_paseto::prelude::*;
+use sqlx::PgPool;
+
+pub mod digest;
+pub mod discord;
+pub mod steam_games;
+pub mod steam_libraries;
+pub mod steam_presence;
+pub mod webhook;
+
+/// The shared state background jobs need, the same as what Rocket manages for the routes
+pub struct JobContext {
+    pub pool: PgPool,
+    pub key: PasetoSymmetricKey<V4, Local>,
+    pub email_sender: Resend,
+    pub tera: Tera,
+    pub steam_api_key: String,
+    /// How often to update the list of games from the Steam API, if at all
+    pub steam_game_update_interval_hours: Option<u32>,
+}
+
+/// Start all background jobs on the current runtime.
+/// Jobs run for as long as the API instance is up.
+pub fn spawn(context: JobContext) {
+    let context = Arc::new(context);
+
+    tokio::spawn(digest::run(context.clone()));
+    tokio::spawn(discord::run(context.clone()));
+    if let Some(interval_hours) = context.steam_game_update_interval_hours {
+        tokio::spawn(steam_games::run(context.clone(), interval_hours));
+    }
+    tokio::spawn(steam_libraries::run(context.clone()));
+    tokio::spawn(steam_presence::run(context.clone()));
+    tokio::spawn(webhook::run(context));
+}
@@ -0,0 +1,31 @@
+use std::sync::Arc;
+
+use tokio::time::MissedTickBehavior;
+
+use crate::{controllers::game, jobs::JobContext};
+
+/// How often to check whether the list of games is due an update
+const CHECK_INTERVAL_MINUTES: u64 = 60;
+
+/// Keep the list of games from the Steam API up to date.
+/// Updates are due once the last one, scheduled or started by an admin, is `interval_hours` old,
+/// so restarting the API doesn't start a new update each time.
+pub async fn run(context: Arc<JobContext>, interval_hours: u32) {
+    let interval = chrono::Duration::hours(i64::from(interval_hours));
+
+    let mut check_interval =
+        tokio::time::interval(std::time::Duration::from_secs(CHECK_INTERVAL_MINUTES * 60));
+    check_interval.set_missed_tick_behavior(MissedTickBehavior::Delay);
+
+    loop {
+        check_interval.tick().await;
+
+        match game::start_update_if_due(&context.pool, context.steam_api_key.clone(), interval)
+            .await
+        {
+            Ok(Some(update)) => log::info!("Started scheduled game update {}", update.id),
+            Ok(None) => {}
+            Err(e) => log::error!("Error starting scheduled game update: {e}"),
+        }
+    }
+}
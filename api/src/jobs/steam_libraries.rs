@@ -0,0 +1,39 @@
+use std::sync::Arc;
+
+use tokio::time::MissedTickBehavior;
+
+use crate::{controllers::profile, jobs::JobContext};
+
+/// How often to look for libraries that are due a refresh
+const CHECK_INTERVAL_MINUTES: u64 = 15;
+
+/// Keep the Steam libraries of people going to upcoming events up to date,
+/// so who owns each suggested game isn't months out of date
+pub async fn run(context: Arc<JobContext>) {
+    let mut interval =
+        tokio::time::interval(std::time::Duration::from_secs(CHECK_INTERVAL_MINUTES * 60));
+    interval.set_missed_tick_behavior(MissedTickBehavior::Delay);
+
+    loop {
+        interval.tick().await;
+
+        match profile::refresh_due_libraries(&context.pool, &context.steam_api_key).await {
+            Ok(summary) => {
+                if summary.refreshed + summary.private + summary.failed > 0 {
+                    log::info!(
+                        "Refreshed {} Steam libraries ({} private, {} failed)",
+                        summary.refreshed,
+                        summary.private,
+                        summary.failed
+                    );
+                }
+                if summary.rate_limited {
+                    log::warn!(
+                        "Steam is rate limiting library refreshes, stopping until the next check"
+                    );
+                }
+            }
+            Err(e) => log::error!("Error refreshing Steam libraries: {e}"),
+        }
+    }
+}
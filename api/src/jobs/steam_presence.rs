@@ -0,0 +1,39 @@
+use std::sync::Arc;
+
+use tokio::time::MissedTickBehavior;
+
+use crate::{controllers::presence, jobs::JobContext};
+
+/// How often to ask Steam what attendees are playing
+const POLL_INTERVAL_MINUTES: u64 = 2;
+
+/// Keep track of what attendees of running events are playing on Steam,
+/// for the event dashboard and activity ticker
+pub async fn run(context: Arc<JobContext>) {
+    let mut interval =
+        tokio::time::interval(std::time::Duration::from_secs(POLL_INTERVAL_MINUTES * 60));
+    interval.set_missed_tick_behavior(MissedTickBehavior::Delay);
+
+    loop {
+        interval.tick().await;
+
+        match presence::poll_running_events(&context.pool, &context.steam_api_key).await {
+            Ok(summary) => {
+                if summary.polled > 0 {
+                    log::debug!(
+                        "Polled Steam presence for {} attendees ({} playing, {} changes)",
+                        summary.polled,
+                        summary.playing,
+                        summary.changes
+                    );
+                }
+                if summary.rate_limited {
+                    log::warn!(
+                        "Steam is rate limiting presence polling, stopping until the next poll"
+                    );
+                }
+            }
+            Err(e) => log::error!("Error polling Steam presence: {e}"),
+        }
+    }
+}
@@ -0,0 +1,49 @@
+use std::sync::Arc;
+
+use tokio::time::MissedTickBehavior;
+
+use crate::{controllers::webhook, jobs::JobContext};
+
+/// How often to look for webhook deliveries that are due
+const CHECK_INTERVAL_SECONDS: u64 = 5;
+
+/// The most deliveries to send at once
+const BATCH_SIZE: i64 = 20;
+
+/// Don't let a slow receiver hold up other deliveries
+const REQUEST_TIMEOUT_SECONDS: u64 = 10;
+
+/// Send queued webhook deliveries, including retries of earlier failures
+pub async fn run(context: Arc<JobContext>) {
+    let client = match reqwest::Client::builder()
+        .timeout(std::time::Duration::from_secs(REQUEST_TIMEOUT_SECONDS))
+        .user_agent("caLANdar-Webhooks")
+        .build()
+    {
+        Ok(client) => client,
+        Err(e) => {
+            log::error!("Unable to create webhook HTTP client, webhooks will not be sent: {e}");
+            return;
+        }
+    };
+
+    let mut interval =
+        tokio::time::interval(std::time::Duration::from_secs(CHECK_INTERVAL_SECONDS));
+    interval.set_missed_tick_behavior(MissedTickBehavior::Delay);
+
+    loop {
+        interval.tick().await;
+
+        // Keep going while there is a backlog, rather than waiting for the next tick
+        loop {
+            match webhook::deliver_due(&context.pool, &client, BATCH_SIZE).await {
+                Ok(count) if usize::try_from(BATCH_SIZE).is_ok_and(|size| count >= size) => {}
+                Ok(_) => break,
+                Err(e) => {
+                    log::error!("Error sending webhook deliveries: {e}");
+                    break;
+                }
+            }
+        }
+    }
+}
@@ -29,10 +29,13 @@ mod error;
 
 mod auth;
 mod controllers;
+mod jobs;
 mod repositories;
 mod routes;
 mod scheduler;
+mod search;
 mod util;
+mod voting;
 
 #[derive(Serialize, Deserialize, JsonSchema, Debug)]
 #[serde(crate = "rocket::serde", rename_all = "camelCase")]
@@ -93,7 +96,7 @@ impl Fairing for CORS {
     }
 }
 
-const EMAIL_TEMPLATES: [(&str, &str); 4] = [
+const EMAIL_TEMPLATES: [(&str, &str); 10] = [
     (
         "email_base.html.tera",
         r#"
@@ -104,6 +107,9 @@ const EMAIL_TEMPLATES: [(&str, &str); 4] = [
       </head>
       <body>
         {% block content %}{% endblock content %}
+        {% if unsubscribe_url %}
+        <p style="font-size: small; color: #666666;">Don't want to receive {{ unsubscribe_label }}? <a href="{{ unsubscribe_url }}">Unsubscribe</a></p>
+        {% endif %}
       </body>
     </html>
     "#,
@@ -180,6 +186,160 @@ const EMAIL_TEMPLATES: [(&str, &str); 4] = [
     {% endblock content %}
     "#,
     ),
+    (
+        "email_digest.html.tera",
+        r#"
+    {% extends "email_base.html.tera" %}
+
+    {% block content %}
+        <p>Hello,</p>
+
+        <p>Here's what has been happening for <strong>{{ title }}</strong>, which starts at {{ time_begin }} and runs until {{ time_end }}.</p>
+
+        {% if new_rsvps %}
+        <p><strong>New RSVPs</strong></p>
+        <ul>
+            {% for rsvp in new_rsvps %}
+            <li>{{ rsvp.handle }} {{ rsvp.response }}</li>
+            {% endfor %}
+        </ul>
+        {% endif %}
+
+        {% if new_games %}
+        <p><strong>Newly suggested games</strong></p>
+        <ul>
+            {% for game in new_games %}
+            <li>{{ game.name }}, suggested by {{ game.suggested_by }}{% if game.comment %}: "{{ game.comment }}"{% endif %}</li>
+            {% endfor %}
+        </ul>
+        {% endif %}
+
+        {% if vote_leaders %}
+        <p><strong>Most voted games</strong></p>
+        <ol>
+            {% for game in vote_leaders %}
+            <li>{{ game.name }} ({{ game.votes }} vote{{ game.votes | pluralize }})</li>
+            {% endfor %}
+        </ol>
+        {% endif %}
+
+        {% if schedule %}
+        <p><strong>Current game schedule</strong></p>
+        <ul>
+            {% for game in schedule %}
+            <li>{{ game.start_time }}: {{ game.name }} ({{ game.duration_minutes }} minutes)</li>
+            {% endfor %}
+        </ul>
+        {% endif %}
+
+        <p>Suggest and vote for games at <a href="https://calandar.org/events/{{ event_id }}">calandar.org</a>.</p>
+
+        <p>See you there,</p>
+
+        <p>Lewis</p>
+    {% endblock content %}
+    "#,
+    ),
+    (
+        "email_base.txt.tera",
+        r#"{% block content %}{% endblock content %}
+{% if unsubscribe_url %}
+--
+Don't want to receive {{ unsubscribe_label }}? Unsubscribe: {{ unsubscribe_url }}
+{% endif %}"#,
+    ),
+    (
+        "email_invitation.txt.tera",
+        r#"{% extends "email_base.txt.tera" %}
+{% block content %}
+Dear {{ name }},
+
+I'm pleased to invite you to the {{ title }}
+
+It starts at {{ time_begin }} and runs until {{ time_end }}.
+
+{{ description }}
+
+I've created a new website for these events where you can RSVP and see details about the event. In the future, you will also be able to let me know which times you can attend, reserve seats, suggest and vote for games, as well as a live dashboard for during the event itself. It's a constant work in progress, so please let me know if you have any issues or feedback.
+
+To RSVP, please visit the following link within the next 24 hours. After this time you will have to log in again at calandar.org using this email address.
+
+https://calandar.org/verify_email?token={{ token }}
+
+See you there,
+
+Lewis
+{% endblock content %}"#,
+    ),
+    (
+        "email_verification.txt.tera",
+        r#"{% extends "email_base.txt.tera" %}
+{% block content %}
+Dear {{ name }},
+
+Please confirm your email by visiting the following link in the next 30 minutes:
+
+https://calandar.org/verify_email?token={{ token }}
+
+Alternatively, go to https://calandar.org/verify_email and enter the following token:
+
+{{ token }}
+
+If you did not request this email, please ignore it.
+
+Happy hunting,
+
+Lewis
+{% endblock content %}"#,
+    ),
+    (
+        "email_custom.txt.tera",
+        r#"{% extends "email_base.txt.tera" %}
+{% block content %}
+Hello,
+
+This is an update about {{ title }}.
+
+Event details:
+- Starts: {{ time_begin }}
+- Ends: {{ time_end }}
+
+{{ message }}
+
+You can view more details and manage your RSVP at https://calandar.org.
+
+See you there,
+
+Lewis
+{% endblock content %}"#,
+    ),
+    (
+        "email_digest.txt.tera",
+        r#"{% extends "email_base.txt.tera" %}
+{% block content %}
+Hello,
+
+Here's what has been happening for {{ title }}, which starts at {{ time_begin }} and runs until {{ time_end }}.
+{% if new_rsvps %}
+New RSVPs:
+{% for rsvp in new_rsvps %}- {{ rsvp.handle }} {{ rsvp.response }}
+{% endfor %}{% endif %}{% if new_games %}
+Newly suggested games:
+{% for game in new_games %}- {{ game.name }}, suggested by {{ game.suggested_by }}{% if game.comment %}: "{{ game.comment }}"{% endif %}
+{% endfor %}{% endif %}{% if vote_leaders %}
+Most voted games:
+{% for game in vote_leaders %}{{ loop.index }}. {{ game.name }} ({{ game.votes }} vote{{ game.votes | pluralize }})
+{% endfor %}{% endif %}{% if schedule %}
+Current game schedule:
+{% for game in schedule %}- {{ game.start_time }}: {{ game.name }} ({{ game.duration_minutes }} minutes)
+{% endfor %}{% endif %}
+Suggest and vote for games at https://calandar.org/events/{{ event_id }}
+
+See you there,
+
+Lewis
+{% endblock content %}"#,
+    ),
 ];
 
 // Helper function to load secrets and create configuration objects
@@ -233,6 +393,7 @@ fn build_rocket(
             openapi_get_routes![
                 healthz,
                 routes::activity_ticker::get_activity_ticker,
+                routes::now_playing::get,
                 routes::auth::login,
                 routes::auth::verify_email,
                 routes::events::get_all,
@@ -278,25 +439,70 @@ fn build_rocket(
                 routes::seat_reservations::delete_admin,
                 routes::seat_reservations::check_availability,
                 routes::games::steam_game_update_v2,
+                routes::games::steam_game_update_status,
                 routes::games::get_steam_game,
+                routes::games::put_players,
+                routes::games::put_duration,
+                routes::custom_games::post,
+                routes::custom_games::put_admin,
+                routes::custom_games::put,
+                routes::custom_games::delete,
+                routes::event_discord::get,
+                routes::event_discord::put,
+                routes::event_discord::delete,
+                routes::event_discord::announce,
                 routes::event_games::get_all,
                 routes::event_games::get_all_suggested,
+                routes::event_games::get_recommended,
                 routes::event_games::post,
                 routes::event_games::patch,
                 routes::event_games::update_comment,
+                routes::event_games::update_ownership,
+                routes::event_games::merge,
+                routes::game_comments::get_all,
+                routes::game_comments::post,
+                routes::game_comments::put,
+                routes::game_comments::delete_admin,
+                routes::game_comments::delete,
+                routes::game_comments::put_reaction,
+                routes::game_comments::delete_reaction,
+                routes::event_voting::get,
+                routes::event_voting::put,
+                routes::event_voting::reopen,
+                routes::event_voting::put_ranking,
+                routes::event_voting::get_results,
                 routes::game_schedule::get_all,
                 routes::game_schedule::create,
                 routes::game_schedule::update,
                 routes::game_schedule::delete,
                 routes::game_schedule::pin,
                 routes::game_schedule::recalculate_suggested_schedule,
+                routes::game_schedule::get_config,
+                routes::game_schedule::put_config,
+                routes::game_schedule::get_durations,
+                routes::game_schedule::put_duration,
+                routes::game_schedule::get_blocks,
+                routes::game_schedule::create_blocked_period,
+                routes::game_schedule::update_blocked_period,
+                routes::game_schedule::delete_blocked_period,
                 routes::profiles::get,
                 routes::profiles::put,
                 routes::profiles::put_admin,
                 routes::profiles::post_games_update,
+                routes::notification_preferences::get,
+                routes::notification_preferences::put,
+                routes::notification_preferences::get_unsubscribe,
+                routes::notification_preferences::post_unsubscribe,
                 routes::gamers::get_all_paginated,
                 routes::gamers::get_all,
                 routes::audit_logs::get_audit_logs,
+                routes::webhooks::get_all,
+                routes::webhooks::get,
+                routes::webhooks::post,
+                routes::webhooks::put,
+                routes::webhooks::delete,
+                routes::webhooks::get_deliveries,
+                routes::webhooks::test,
             ],
         )
         .mount(
@@ -351,9 +557,32 @@ async fn main() -> Result<(), Box<dyn std::error::Error>> {
     let steam_api_key = std::env::var("STEAM_API_KEY")
         .map_err(|_| "STEAM_API_KEY environment variable must be set")?;
 
+    // Optionally keep the list of games from the Steam API up to date on a schedule
+    let steam_game_update_interval_hours = match std::env::var("STEAM_GAME_UPDATE_INTERVAL_HOURS") {
+        Ok(hours) => Some(
+            hours
+                .parse::<u32>()
+                .map_err(|_| "STEAM_GAME_UPDATE_INTERVAL_HOURS must be a whole number of hours")?,
+        ),
+        Err(_) => None,
+    };
+
+    // Background jobs get their own key and email sender, built from the same secrets
+    let job_key = PasetoSymmetricKey::<V4, Local>::from(Key::from(paseto_secret_key.as_bytes()));
+    let job_email_sender = Resend::new(&resend_api_key);
+
     let (paseto_symmetric_key, email_sender, steam_api_key, tera) =
         load_secrets_and_config(paseto_secret_key, resend_api_key, steam_api_key)?;
 
+    jobs::spawn(jobs::JobContext {
+        pool: pool.clone(),
+        key: job_key,
+        email_sender: job_email_sender,
+        tera: tera.clone(),
+        steam_api_key: steam_api_key.clone(),
+        steam_game_update_interval_hours,
+    });
+
     // Configure Rocket
     let mut config = rocket::Config::default();
 
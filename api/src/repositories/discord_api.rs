@@ -0,0 +1,66 @@
+use chrono::{DateTime, Utc};
+use rocket::serde::Serialize;
+
+/// A message posted through a Discord webhook
+/// https://discord.com/developers/docs/resources/webhook#execute-webhook
+#[derive(Serialize, Clone, Debug)]
+#[serde(crate = "rocket::serde")]
+pub struct WebhookMessage {
+    pub username: String,
+    pub embeds: Vec<Embed>,
+}
+
+/// https://discord.com/developers/docs/resources/message#embed-object
+#[derive(Serialize, Clone, Debug, Default)]
+#[serde(crate = "rocket::serde")]
+pub struct Embed {
+    pub title: String,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub description: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub url: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub color: Option<u32>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub timestamp: Option<DateTime<Utc>>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub author: Option<EmbedAuthor>,
+    #[serde(skip_serializing_if = "Vec::is_empty")]
+    pub fields: Vec<EmbedField>,
+}
+
+#[derive(Serialize, Clone, Debug)]
+#[serde(crate = "rocket::serde")]
+pub struct EmbedAuthor {
+    pub name: String,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub icon_url: Option<String>,
+}
+
+#[derive(Serialize, Clone, Debug)]
+#[serde(crate = "rocket::serde")]
+pub struct EmbedField {
+    pub name: String,
+    pub value: String,
+    pub inline: bool,
+}
+
+/// Don't let a slow Discord hold up the request or job posting to it
+const REQUEST_TIMEOUT_SECONDS: u64 = 10;
+
+/// Post a message to a Discord webhook URL
+pub async fn execute_webhook(
+    webhook_url: &str,
+    message: &WebhookMessage,
+) -> Result<(), reqwest::Error> {
+    reqwest::Client::builder()
+        .timeout(std::time::Duration::from_secs(REQUEST_TIMEOUT_SECONDS))
+        .build()?
+        .post(webhook_url)
+        .json(message)
+        .send()
+        .await?
+        .error_for_status()?;
+
+    Ok(())
+}
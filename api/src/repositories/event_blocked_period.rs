@@ -0,0 +1,142 @@
+use chrono::{DateTime, Utc};
+use sqlx::PgPool;
+
+#[derive(Clone)]
+pub struct EventBlockedPeriod {
+    pub id: i32,
+    pub event_id: i32,
+    pub name: String,
+    pub start_time: DateTime<Utc>,
+    pub end_time: DateTime<Utc>,
+    pub created_at: DateTime<Utc>,
+    pub last_modified: DateTime<Utc>,
+}
+
+pub async fn get_all(pool: &PgPool, event_id: i32) -> Result<Vec<EventBlockedPeriod>, sqlx::Error> {
+    sqlx::query_as!(
+        EventBlockedPeriod,
+        r#"
+        SELECT
+            id,
+            event_id,
+            name,
+            start_time,
+            end_time,
+            created_at,
+            last_modified
+        FROM event_blocked_period
+        WHERE event_id = $1
+        ORDER BY start_time, id
+        "#,
+        event_id
+    )
+    .fetch_all(pool)
+    .await
+}
+
+pub async fn get(
+    pool: &PgPool,
+    blocked_period_id: i32,
+) -> Result<Option<EventBlockedPeriod>, sqlx::Error> {
+    sqlx::query_as!(
+        EventBlockedPeriod,
+        r#"
+        SELECT
+            id,
+            event_id,
+            name,
+            start_time,
+            end_time,
+            created_at,
+            last_modified
+        FROM event_blocked_period
+        WHERE id = $1
+        "#,
+        blocked_period_id
+    )
+    .fetch_optional(pool)
+    .await
+}
+
+pub async fn create(
+    pool: &PgPool,
+    event_id: i32,
+    name: String,
+    start_time: DateTime<Utc>,
+    end_time: DateTime<Utc>,
+) -> Result<EventBlockedPeriod, sqlx::Error> {
+    sqlx::query_as!(
+        EventBlockedPeriod,
+        r#"
+        INSERT INTO event_blocked_period (
+            event_id,
+            name,
+            start_time,
+            end_time
+        )
+        VALUES ($1, $2, $3, $4)
+        RETURNING
+            id,
+            event_id,
+            name,
+            start_time,
+            end_time,
+            created_at,
+            last_modified
+        "#,
+        event_id,
+        name,
+        start_time,
+        end_time
+    )
+    .fetch_one(pool)
+    .await
+}
+
+pub async fn update(
+    pool: &PgPool,
+    blocked_period_id: i32,
+    name: String,
+    start_time: DateTime<Utc>,
+    end_time: DateTime<Utc>,
+) -> Result<EventBlockedPeriod, sqlx::Error> {
+    sqlx::query_as!(
+        EventBlockedPeriod,
+        r#"
+        UPDATE event_blocked_period
+        SET
+            name = $2,
+            start_time = $3,
+            end_time = $4,
+            last_modified = NOW()
+        WHERE id = $1
+        RETURNING
+            id,
+            event_id,
+            name,
+            start_time,
+            end_time,
+            created_at,
+            last_modified
+        "#,
+        blocked_period_id,
+        name,
+        start_time,
+        end_time
+    )
+    .fetch_one(pool)
+    .await
+}
+
+pub async fn delete(pool: &PgPool, blocked_period_id: i32) -> Result<(), sqlx::Error> {
+    sqlx::query!(
+        r#"
+        DELETE FROM event_blocked_period
+        WHERE id = $1
+        "#,
+        blocked_period_id
+    )
+    .execute(pool)
+    .await?;
+    Ok(())
+}
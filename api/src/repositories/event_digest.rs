@@ -0,0 +1,66 @@
+use chrono::{DateTime, Utc};
+use sqlx::PgPool;
+
+/// The last time a digest for an event was sent to a recipient
+#[allow(dead_code)]
+#[derive(Clone)]
+pub struct EventDigest {
+    pub event_id: i32,
+    pub email: String,
+    pub sent_at: DateTime<Utc>,
+}
+
+pub async fn filter(pool: &PgPool, event_id: i32) -> Result<Vec<EventDigest>, sqlx::Error> {
+    sqlx::query_as!(
+        EventDigest,
+        r#"
+        SELECT event_id, email, sent_at
+        FROM event_digest
+        WHERE event_id = $1
+        "#,
+        event_id
+    )
+    .fetch_all(pool)
+    .await
+}
+
+/// Atomically record that a digest is being sent, unless one was already sent after `due_before`.
+/// Returns `None` when the recipient isn't due, including when another run has just claimed it.
+pub async fn claim(
+    pool: &PgPool,
+    event_id: i32,
+    email: &str,
+    due_before: DateTime<Utc>,
+) -> Result<Option<EventDigest>, sqlx::Error> {
+    sqlx::query_as!(
+        EventDigest,
+        r#"
+        INSERT INTO event_digest (event_id, email, sent_at)
+        VALUES ($1, LOWER($2), NOW())
+        ON CONFLICT (event_id, email)
+        DO UPDATE SET sent_at = NOW()
+        WHERE event_digest.sent_at <= $3
+        RETURNING event_id, email, sent_at
+        "#,
+        event_id,
+        email,
+        due_before
+    )
+    .fetch_optional(pool)
+    .await
+}
+
+pub async fn delete(pool: &PgPool, event_id: i32, email: &str) -> Result<(), sqlx::Error> {
+    sqlx::query!(
+        r#"
+        DELETE FROM event_digest
+        WHERE event_id = $1 AND email = LOWER($2)
+        "#,
+        event_id,
+        email
+    )
+    .execute(pool)
+    .await?;
+
+    Ok(())
+}
@@ -0,0 +1,212 @@
+use chrono::{DateTime, Utc};
+use sqlx::PgPool;
+
+#[derive(Clone)]
+pub struct EventDiscordConfig {
+    pub event_id: i32,
+    pub webhook_url: String,
+    pub is_enabled: bool,
+    pub notify_rsvps: bool,
+    pub notify_top_game: bool,
+    pub notify_schedule: bool,
+    pub schedule_lead_minutes: i32,
+    pub top_game_id: Option<i64>,
+    pub created_at: DateTime<Utc>,
+    pub last_modified: DateTime<Utc>,
+}
+
+/// A scheduled game that is about to start at an event with Discord notifications
+pub struct StartingGame {
+    pub schedule_id: i32,
+    pub event_id: i32,
+    pub game_id: i64,
+    pub game_name: String,
+    pub start_time: DateTime<Utc>,
+    pub duration_minutes: i32,
+    pub webhook_url: String,
+}
+
+pub async fn get(pool: &PgPool, event_id: i32) -> Result<Option<EventDiscordConfig>, sqlx::Error> {
+    sqlx::query_as!(
+        EventDiscordConfig,
+        r#"
+        SELECT
+            event_id,
+            webhook_url,
+            is_enabled,
+            notify_rsvps,
+            notify_top_game,
+            notify_schedule,
+            schedule_lead_minutes,
+            top_game_id,
+            created_at,
+            last_modified
+        FROM event_discord_config
+        WHERE event_id = $1
+        "#,
+        event_id
+    )
+    .fetch_optional(pool)
+    .await
+}
+
+#[allow(clippy::too_many_arguments)]
+pub async fn upsert(
+    pool: &PgPool,
+    event_id: i32,
+    webhook_url: String,
+    is_enabled: bool,
+    notify_rsvps: bool,
+    notify_top_game: bool,
+    notify_schedule: bool,
+    schedule_lead_minutes: i32,
+) -> Result<EventDiscordConfig, sqlx::Error> {
+    sqlx::query_as!(
+        EventDiscordConfig,
+        r#"
+        INSERT INTO event_discord_config (
+            event_id,
+            webhook_url,
+            is_enabled,
+            notify_rsvps,
+            notify_top_game,
+            notify_schedule,
+            schedule_lead_minutes
+        )
+        VALUES ($1, $2, $3, $4, $5, $6, $7)
+        ON CONFLICT (event_id)
+        DO UPDATE SET
+            webhook_url = $2,
+            is_enabled = $3,
+            notify_rsvps = $4,
+            notify_top_game = $5,
+            notify_schedule = $6,
+            schedule_lead_minutes = $7,
+            last_modified = NOW()
+        RETURNING
+            event_id,
+            webhook_url,
+            is_enabled,
+            notify_rsvps,
+            notify_top_game,
+            notify_schedule,
+            schedule_lead_minutes,
+            top_game_id,
+            created_at,
+            last_modified
+        "#,
+        event_id,
+        webhook_url,
+        is_enabled,
+        notify_rsvps,
+        notify_top_game,
+        notify_schedule,
+        schedule_lead_minutes,
+    )
+    .fetch_one(pool)
+    .await
+}
+
+pub async fn delete(pool: &PgPool, event_id: i32) -> Result<(), sqlx::Error> {
+    sqlx::query!(
+        r#"
+        DELETE FROM event_discord_config
+        WHERE event_id = $1
+        "#,
+        event_id
+    )
+    .execute(pool)
+    .await?;
+
+    Ok(())
+}
+
+/// Remember which game was most voted when Discord was last told about it
+pub async fn set_top_game(
+    pool: &PgPool,
+    event_id: i32,
+    top_game_id: Option<i64>,
+) -> Result<(), sqlx::Error> {
+    sqlx::query!(
+        r#"
+        UPDATE event_discord_config
+        SET top_game_id = $2
+        WHERE event_id = $1
+        "#,
+        event_id,
+        top_game_id
+    )
+    .execute(pool)
+    .await?;
+
+    Ok(())
+}
+
+/// Record that a notice is being posted, returning false if it already has been
+pub async fn claim_notice(
+    pool: &PgPool,
+    event_id: i32,
+    notice_key: &str,
+) -> Result<bool, sqlx::Error> {
+    let claimed = sqlx::query!(
+        r#"
+        INSERT INTO event_discord_notice (event_id, notice_key)
+        VALUES ($1, $2)
+        ON CONFLICT (event_id, notice_key) DO NOTHING
+        RETURNING event_id
+        "#,
+        event_id,
+        notice_key
+    )
+    .fetch_optional(pool)
+    .await?;
+
+    Ok(claimed.is_some())
+}
+
+/// Forget a notice that couldn't be posted, so it is tried again
+pub async fn release_notice(
+    pool: &PgPool,
+    event_id: i32,
+    notice_key: &str,
+) -> Result<(), sqlx::Error> {
+    sqlx::query!(
+        r#"
+        DELETE FROM event_discord_notice
+        WHERE event_id = $1 AND notice_key = $2
+        "#,
+        event_id,
+        notice_key
+    )
+    .execute(pool)
+    .await?;
+
+    Ok(())
+}
+
+/// Get scheduled games starting within each event's notice period
+pub async fn starting_games(pool: &PgPool) -> Result<Vec<StartingGame>, sqlx::Error> {
+    sqlx::query_as!(
+        StartingGame,
+        r#"
+        SELECT
+            egs.id AS schedule_id,
+            egs.event_id,
+            egs.game_id,
+            sg.name AS game_name,
+            egs.start_time,
+            egs.duration_minutes,
+            edc.webhook_url
+        FROM event_game_schedule egs
+        INNER JOIN steam_game sg ON egs.game_id = sg.appid
+        INNER JOIN event_discord_config edc ON egs.event_id = edc.event_id
+        WHERE edc.is_enabled
+        AND edc.notify_schedule
+        AND egs.start_time > NOW()
+        AND egs.start_time <= NOW() + make_interval(mins => edc.schedule_lead_minutes)
+        ORDER BY egs.start_time ASC
+        "#
+    )
+    .fetch_all(pool)
+    .await
+}
@@ -0,0 +1,115 @@
+use chrono::{DateTime, NaiveTime, Utc};
+use sqlx::PgPool;
+
+#[derive(Clone)]
+pub struct EventScheduleConfig {
+    pub event_id: i32,
+    pub default_duration_minutes: i32,
+    pub setup_buffer_minutes: i32,
+    pub use_past_schedules: bool,
+    pub allow_parallel_groups: bool,
+    pub track_count: i32,
+    pub tracks_from_rooms: bool,
+    pub use_optimiser: bool,
+    pub quiet_hours_start: NaiveTime,
+    pub quiet_hours_end: NaiveTime,
+    pub created_at: DateTime<Utc>,
+    pub last_modified: DateTime<Utc>,
+}
+
+pub async fn get(pool: &PgPool, event_id: i32) -> Result<Option<EventScheduleConfig>, sqlx::Error> {
+    sqlx::query_as!(
+        EventScheduleConfig,
+        r#"
+        SELECT
+            event_id,
+            default_duration_minutes,
+            setup_buffer_minutes,
+            use_past_schedules,
+            allow_parallel_groups,
+            track_count,
+            tracks_from_rooms,
+            use_optimiser,
+            quiet_hours_start,
+            quiet_hours_end,
+            created_at,
+            last_modified
+        FROM event_schedule_config
+        WHERE event_id = $1
+        "#,
+        event_id
+    )
+    .fetch_optional(pool)
+    .await
+}
+
+#[allow(clippy::too_many_arguments)]
+pub async fn upsert(
+    pool: &PgPool,
+    event_id: i32,
+    default_duration_minutes: i32,
+    setup_buffer_minutes: i32,
+    use_past_schedules: bool,
+    allow_parallel_groups: bool,
+    track_count: i32,
+    tracks_from_rooms: bool,
+    use_optimiser: bool,
+    quiet_hours_start: NaiveTime,
+    quiet_hours_end: NaiveTime,
+) -> Result<EventScheduleConfig, sqlx::Error> {
+    sqlx::query_as!(
+        EventScheduleConfig,
+        r#"
+        INSERT INTO event_schedule_config (
+            event_id,
+            default_duration_minutes,
+            setup_buffer_minutes,
+            use_past_schedules,
+            allow_parallel_groups,
+            track_count,
+            tracks_from_rooms,
+            use_optimiser,
+            quiet_hours_start,
+            quiet_hours_end
+        )
+        VALUES ($1, $2, $3, $4, $5, $6, $7, $8, $9, $10)
+        ON CONFLICT (event_id)
+        DO UPDATE SET
+            default_duration_minutes = $2,
+            setup_buffer_minutes = $3,
+            use_past_schedules = $4,
+            allow_parallel_groups = $5,
+            track_count = $6,
+            tracks_from_rooms = $7,
+            use_optimiser = $8,
+            quiet_hours_start = $9,
+            quiet_hours_end = $10,
+            last_modified = NOW()
+        RETURNING
+            event_id,
+            default_duration_minutes,
+            setup_buffer_minutes,
+            use_past_schedules,
+            allow_parallel_groups,
+            track_count,
+            tracks_from_rooms,
+            use_optimiser,
+            quiet_hours_start,
+            quiet_hours_end,
+            created_at,
+            last_modified
+        "#,
+        event_id,
+        default_duration_minutes,
+        setup_buffer_minutes,
+        use_past_schedules,
+        allow_parallel_groups,
+        track_count,
+        tracks_from_rooms,
+        use_optimiser,
+        quiet_hours_start,
+        quiet_hours_end,
+    )
+    .fetch_one(pool)
+    .await
+}
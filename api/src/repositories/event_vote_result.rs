@@ -0,0 +1,109 @@
+use chrono::{DateTime, Utc};
+use sqlx::PgPool;
+
+use crate::repositories::event_voting_config::VotingMethod;
+
+/// Where a game finished when voting closed
+#[derive(Clone)]
+pub struct EventVoteResult {
+    pub event_id: i32,
+    pub game_id: i64,
+    pub position: i32,
+    pub points: i64,
+    pub voter_count: i64,
+    pub eliminated_in_round: Option<i32>,
+    pub voting_method: VotingMethod,
+    pub frozen_at: DateTime<Utc>,
+}
+
+/// A game's result, to be frozen
+pub struct ResultInsert {
+    pub game_id: i64,
+    pub points: i64,
+    pub voter_count: i64,
+    pub eliminated_in_round: Option<i32>,
+}
+
+/// Get the frozen results for an event, best first
+pub async fn filter(pool: &PgPool, event_id: i32) -> Result<Vec<EventVoteResult>, sqlx::Error> {
+    sqlx::query_as!(
+        EventVoteResult,
+        r#"
+        SELECT
+            event_id,
+            game_id,
+            position,
+            points,
+            voter_count,
+            eliminated_in_round,
+            voting_method AS "voting_method: _",
+            frozen_at
+        FROM event_vote_result
+        WHERE event_id = $1
+        ORDER BY position
+        "#,
+        event_id
+    )
+    .fetch_all(pool)
+    .await
+}
+
+/// Freeze an event's results, best first. Does nothing if they've already been frozen.
+pub async fn create_many(
+    pool: &PgPool,
+    event_id: i32,
+    voting_method: VotingMethod,
+    results: &[ResultInsert],
+) -> Result<(), sqlx::Error> {
+    let game_ids: Vec<i64> = results.iter().map(|result| result.game_id).collect();
+    let points: Vec<i64> = results.iter().map(|result| result.points).collect();
+    let voter_counts: Vec<i64> = results.iter().map(|result| result.voter_count).collect();
+    let eliminated_in_rounds: Vec<Option<i32>> = results
+        .iter()
+        .map(|result| result.eliminated_in_round)
+        .collect();
+
+    sqlx::query!(
+        r#"
+        INSERT INTO event_vote_result (
+            event_id,
+            game_id,
+            position,
+            points,
+            voter_count,
+            eliminated_in_round,
+            voting_method
+        )
+        SELECT $1, result.game_id, result.position::INT, result.points, result.voter_count, result.eliminated_in_round, $2
+        FROM UNNEST($3::BIGINT[], $4::BIGINT[], $5::BIGINT[], $6::INT[])
+            WITH ORDINALITY AS result(game_id, points, voter_count, eliminated_in_round, position)
+        WHERE NOT EXISTS (SELECT 1 FROM event_vote_result WHERE event_id = $1)
+        ON CONFLICT (event_id, game_id) DO NOTHING
+        "#,
+        event_id,
+        voting_method as _,
+        &game_ids,
+        &points,
+        &voter_counts,
+        &eliminated_in_rounds,
+    )
+    .execute(pool)
+    .await?;
+
+    Ok(())
+}
+
+/// Throw away an event's frozen results, so they're counted again when voting next closes
+pub async fn delete(pool: &PgPool, event_id: i32) -> Result<(), sqlx::Error> {
+    sqlx::query!(
+        r#"
+        DELETE FROM event_vote_result
+        WHERE event_id = $1
+        "#,
+        event_id
+    )
+    .execute(pool)
+    .await?;
+
+    Ok(())
+}
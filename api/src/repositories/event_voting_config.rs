@@ -0,0 +1,139 @@
+use chrono::{DateTime, Utc};
+use sqlx::PgPool;
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq, sqlx::Type)]
+#[sqlx(type_name = "voting_method", rename_all = "lowercase")]
+pub enum VotingMethod {
+    Approval,
+    Score,
+    InstantRunoff,
+    Borda,
+}
+
+#[derive(Clone)]
+pub struct EventVotingConfig {
+    pub event_id: i32,
+    pub voting_method: VotingMethod,
+    pub suggestions_open_at: Option<DateTime<Utc>>,
+    pub suggestions_close_at: Option<DateTime<Utc>>,
+    pub voting_open_at: Option<DateTime<Utc>>,
+    pub voting_close_at: Option<DateTime<Utc>>,
+    pub max_suggestions_per_attendee: Option<i32>,
+    pub created_at: DateTime<Utc>,
+    pub last_modified: DateTime<Utc>,
+}
+
+/// When games can be suggested and voted on, if there are limits
+#[derive(Clone, Copy)]
+pub struct Windows {
+    pub suggestions_open_at: Option<DateTime<Utc>>,
+    pub suggestions_close_at: Option<DateTime<Utc>>,
+    pub voting_open_at: Option<DateTime<Utc>>,
+    pub voting_close_at: Option<DateTime<Utc>>,
+}
+
+pub async fn get(pool: &PgPool, event_id: i32) -> Result<Option<EventVotingConfig>, sqlx::Error> {
+    sqlx::query_as!(
+        EventVotingConfig,
+        r#"
+        SELECT
+            event_id,
+            voting_method AS "voting_method: _",
+            suggestions_open_at,
+            suggestions_close_at,
+            voting_open_at,
+            voting_close_at,
+            max_suggestions_per_attendee,
+            created_at,
+            last_modified
+        FROM event_voting_config
+        WHERE event_id = $1
+        "#,
+        event_id
+    )
+    .fetch_optional(pool)
+    .await
+}
+
+pub async fn upsert(
+    pool: &PgPool,
+    event_id: i32,
+    voting_method: VotingMethod,
+    windows: Windows,
+    max_suggestions_per_attendee: Option<i32>,
+) -> Result<EventVotingConfig, sqlx::Error> {
+    sqlx::query_as!(
+        EventVotingConfig,
+        r#"
+        INSERT INTO event_voting_config (
+            event_id,
+            voting_method,
+            suggestions_open_at,
+            suggestions_close_at,
+            voting_open_at,
+            voting_close_at,
+            max_suggestions_per_attendee
+        )
+        VALUES ($1, $2, $3, $4, $5, $6, $7)
+        ON CONFLICT (event_id)
+        DO UPDATE SET
+            voting_method = $2,
+            suggestions_open_at = $3,
+            suggestions_close_at = $4,
+            voting_open_at = $5,
+            voting_close_at = $6,
+            max_suggestions_per_attendee = $7,
+            last_modified = NOW()
+        RETURNING
+            event_id,
+            voting_method AS "voting_method: _",
+            suggestions_open_at,
+            suggestions_close_at,
+            voting_open_at,
+            voting_close_at,
+            max_suggestions_per_attendee,
+            created_at,
+            last_modified
+        "#,
+        event_id,
+        voting_method as _,
+        windows.suggestions_open_at,
+        windows.suggestions_close_at,
+        windows.voting_open_at,
+        windows.voting_close_at,
+        max_suggestions_per_attendee,
+    )
+    .fetch_one(pool)
+    .await
+}
+
+/// Change when voting closes, leaving the rest of the config alone.
+/// Returns None if the event has no voting config.
+pub async fn update_voting_close(
+    pool: &PgPool,
+    event_id: i32,
+    voting_close_at: Option<DateTime<Utc>>,
+) -> Result<Option<EventVotingConfig>, sqlx::Error> {
+    sqlx::query_as!(
+        EventVotingConfig,
+        r#"
+        UPDATE event_voting_config
+        SET voting_close_at = $2, last_modified = NOW()
+        WHERE event_id = $1
+        RETURNING
+            event_id,
+            voting_method AS "voting_method: _",
+            suggestions_open_at,
+            suggestions_close_at,
+            voting_open_at,
+            voting_close_at,
+            max_suggestions_per_attendee,
+            created_at,
+            last_modified
+        "#,
+        event_id,
+        voting_close_at,
+    )
+    .fetch_optional(pool)
+    .await
+}
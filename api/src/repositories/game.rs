@@ -1,5 +1,12 @@
 use chrono::{DateTime, Utc};
-use sqlx::{postgres::PgQueryResult, PgPool};
+use sqlx::PgPool;
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq, sqlx::Type)]
+#[sqlx(type_name = "game_source", rename_all = "lowercase")]
+pub enum GameSource {
+    Steam,
+    Custom,
+}
 
 #[derive(Clone)]
 pub struct Game {
@@ -7,48 +14,359 @@ pub struct Game {
     pub name: String,
     pub last_modified: DateTime<Utc>,
     pub rank: Option<f32>,
+    pub source: GameSource,
+    pub url: Option<String>,
+    pub image_url: Option<String>,
+    pub created_by: Option<String>,
 }
 
 pub struct Filter {
     pub query: String,
-    pub count: i64,
-    pub page: i64,
+    /// Full text search query matching words starting with each word of the query
+    pub prefix_query: String,
+    /// `LIKE` pattern matching names starting with the query
+    pub like_prefix: String,
+    pub multiplayer: bool,
+    /// Steam store categories that mean a game can be played with other people
+    pub multiplayer_category_ids: Vec<i32>,
+    pub free: bool,
+    /// Include apps the store says aren't games, such as DLC and soundtracks
+    pub include_extras: bool,
+    pub limit: i64,
 }
 
+/// Find games that might match a search, by prefix, by words starting with the query's words,
+/// or by being similar allowing for typos. Most similar first, with `rank` set to how similar.
+/// Multiplayer and free filters rely on store details, so only match games they've been fetched for.
 pub async fn filter(pool: &PgPool, filter: Filter) -> Result<Vec<Game>, sqlx::Error> {
     sqlx::query_as!(
         Game,
         r#"
-        SELECT appid, name, last_modified, ts_rank_cd(to_tsvector('english', name), query, 32 /* rank/(rank+1) */) AS rank
-        FROM steam_game, plainto_tsquery('english', $1) query
-        WHERE query @@ to_tsvector('english', name)
-        ORDER BY lower(name) LIKE lower($1) DESC, rank DESC
-        LIMIT $2
-        OFFSET $3
+        SELECT
+            sg.appid,
+            sg.name,
+            sg.last_modified,
+            word_similarity(LOWER($1), LOWER(sg.name)) AS rank,
+            sg.source AS "source: _",
+            sg.url,
+            sg.image_url,
+            sg.created_by
+        FROM steam_game sg
+        LEFT JOIN steam_game_details d ON d.appid = sg.appid
+        LEFT JOIN steam_game_players p ON p.appid = sg.appid
+        WHERE sg.removed_at IS NULL
+        AND (
+            LOWER(sg.name) LIKE $2
+            OR ($3 <> '' AND to_tsvector('simple', sg.name) @@ to_tsquery('simple', $3))
+            OR LOWER($1) <% LOWER(sg.name)
+        )
+        AND ($4 OR d.app_type IS NULL OR d.app_type = 'game')
+        AND (NOT $5 OR p.max_players > 1 OR d.category_ids && $6)
+        AND (NOT $7 OR d.is_free)
+        ORDER BY rank DESC, LENGTH(sg.name)
+        LIMIT $8
         "#,
         filter.query,
-        filter.count,
-        filter.page * filter.count,
+        filter.like_prefix,
+        filter.prefix_query,
+        filter.include_extras,
+        filter.multiplayer,
+        &filter.multiplayer_category_ids,
+        filter.free,
+        filter.limit,
     )
     .fetch_all(pool)
     .await
 }
 
-pub async fn create(
+/// How the games in a bulk upsert compared with what was already saved
+pub struct UpsertCounts {
+    pub added: i64,
+    pub renamed: i64,
+    pub upserted: i64,
+}
+
+/// Insert or update many games in one statement, recording which update last saw them.
+/// Games that had been removed from the Steam app list are restored.
+pub async fn upsert_many(
     pool: &PgPool,
-    appid: i64,
     update_id: i32,
+    appids: &[i64],
+    names: &[String],
+) -> Result<UpsertCounts, sqlx::Error> {
+    // All CTEs see the table as it was before the insert, so `existing` holds the old names
+    sqlx::query_as!(
+        UpsertCounts,
+        r#"
+        WITH incoming AS (
+            SELECT DISTINCT ON (appid) appid, name
+            FROM UNNEST($2::BIGINT[], $3::TEXT[]) AS t(appid, name)
+            ORDER BY appid
+        ),
+        existing AS (
+            SELECT sg.appid, sg.name, sg.removed_at
+            FROM steam_game sg
+            INNER JOIN incoming USING (appid)
+        ),
+        upserted AS (
+            INSERT INTO steam_game (appid, update_id, name, last_modified)
+            SELECT appid, $1, name, NOW()
+            FROM incoming
+            ON CONFLICT (appid) DO UPDATE SET
+                update_id = EXCLUDED.update_id,
+                name = EXCLUDED.name,
+                removed_at = NULL,
+                last_modified = CASE
+                    WHEN steam_game.name = EXCLUDED.name AND steam_game.removed_at IS NULL
+                    THEN steam_game.last_modified
+                    ELSE NOW()
+                END
+            RETURNING appid, name
+        )
+        SELECT
+            COUNT(*) FILTER (WHERE e.appid IS NULL OR e.removed_at IS NOT NULL) AS "added!",
+            COUNT(*) FILTER (WHERE e.removed_at IS NULL AND e.name <> u.name) AS "renamed!",
+            COUNT(*) AS "upserted!"
+        FROM upserted u
+        LEFT JOIN existing e USING (appid)
+        "#,
+        update_id,
+        appids,
+        names,
+    )
+    .fetch_one(pool)
+    .await
+}
+
+/// Mark games that weren't seen by a full update as removed from the Steam app list
+pub async fn mark_removed(pool: &PgPool, update_id: i32) -> Result<u64, sqlx::Error> {
+    let result = sqlx::query!(
+        r#"
+        UPDATE steam_game
+        SET removed_at = NOW(), last_modified = NOW()
+        WHERE update_id <> $1
+        AND source = 'steam'
+        AND removed_at IS NULL
+        "#,
+        update_id,
+    )
+    .execute(pool)
+    .await?;
+
+    Ok(result.rows_affected())
+}
+
+/// Get games by app ID, whether from Steam or custom
+pub async fn get_many(pool: &PgPool, appids: &[i64]) -> Result<Vec<Game>, sqlx::Error> {
+    sqlx::query_as!(
+        Game,
+        r#"
+        SELECT
+            appid,
+            name,
+            last_modified,
+            NULL::REAL AS rank,
+            source AS "source: _",
+            url,
+            image_url,
+            created_by
+        FROM steam_game
+        WHERE appid = ANY($1)
+        "#,
+        appids,
+    )
+    .fetch_all(pool)
+    .await
+}
+
+/// Find a custom game with the same name, ignoring case
+pub async fn find_custom_by_name(pool: &PgPool, name: &str) -> Result<Option<Game>, sqlx::Error> {
+    sqlx::query_as!(
+        Game,
+        r#"
+        SELECT
+            appid,
+            name,
+            last_modified,
+            NULL::REAL AS rank,
+            source AS "source: _",
+            url,
+            image_url,
+            created_by
+        FROM steam_game
+        WHERE source = 'custom'
+        AND LOWER(name) = LOWER($1)
+        "#,
+        name,
+    )
+    .fetch_optional(pool)
+    .await
+}
+
+pub async fn create_custom(
+    executor: impl sqlx::PgExecutor<'_>,
     name: String,
-) -> Result<PgQueryResult, sqlx::Error> {
-    // Insert/replace new game
-    sqlx::query!(
-        "INSERT INTO steam_game (appid, update_id, name, last_modified)
-        VALUES ($1, $2, $3, NOW())
-        ON CONFLICT (appid) DO UPDATE SET update_id = $2, name = $3, last_modified = NOW()",
+    url: Option<String>,
+    image_url: Option<String>,
+    created_by: String,
+) -> Result<Game, sqlx::Error> {
+    sqlx::query_as!(
+        Game,
+        r#"
+        INSERT INTO steam_game (appid, name, source, url, image_url, created_by)
+        VALUES (nextval('custom_game_appid_seq'), $1, 'custom', $2, $3, $4)
+        RETURNING
+            appid,
+            name,
+            last_modified,
+            NULL::REAL AS rank,
+            source AS "source: _",
+            url,
+            image_url,
+            created_by
+        "#,
+        name,
+        url,
+        image_url,
+        created_by,
+    )
+    .fetch_one(executor)
+    .await
+}
+
+/// Update a custom game, returning None if there isn't a custom game with the app ID
+pub async fn update_custom(
+    executor: impl sqlx::PgExecutor<'_>,
+    appid: i64,
+    name: String,
+    url: Option<String>,
+    image_url: Option<String>,
+) -> Result<Option<Game>, sqlx::Error> {
+    sqlx::query_as!(
+        Game,
+        r#"
+        UPDATE steam_game
+        SET name = $2, url = $3, image_url = $4, last_modified = NOW()
+        WHERE appid = $1
+        AND source = 'custom'
+        RETURNING
+            appid,
+            name,
+            last_modified,
+            NULL::REAL AS rank,
+            source AS "source: _",
+            url,
+            image_url,
+            created_by
+        "#,
         appid,
-        update_id,
         name,
+        url,
+        image_url,
     )
-    .execute(pool)
+    .fetch_optional(executor)
     .await
 }
+
+/// Delete a custom game, along with any suggestions, votes and schedule entries for it
+pub async fn delete_custom(pool: &PgPool, appid: i64) -> Result<bool, sqlx::Error> {
+    let result = sqlx::query!(
+        r#"
+        DELETE FROM steam_game
+        WHERE appid = $1
+        AND source = 'custom'
+        "#,
+        appid,
+    )
+    .execute(pool)
+    .await?;
+
+    Ok(result.rows_affected() > 0)
+}
+
+/// These run against a fresh database with the migrations applied, so they need
+/// `DATABASE_URL` to point at a Postgres server: `cargo test -- --ignored`
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::repositories::game_update;
+
+    async fn start_update(pool: &PgPool) -> Result<i32, sqlx::Error> {
+        let update = game_update::create(pool, None, None)
+            .await?
+            .expect("no other update should be running");
+        Ok(update.id)
+    }
+
+    async fn finish_update(pool: &PgPool, update_id: i32) -> Result<(), sqlx::Error> {
+        game_update::finish(
+            pool,
+            update_id,
+            game_update::UpdateStatus::Succeeded,
+            0,
+            None,
+        )
+        .await
+    }
+
+    async fn is_removed(pool: &PgPool, appid: i64) -> Result<bool, sqlx::Error> {
+        sqlx::query_scalar("SELECT removed_at IS NOT NULL FROM steam_game WHERE appid = $1")
+            .bind(appid)
+            .fetch_one(pool)
+            .await
+    }
+
+    fn names(names: &[&str]) -> Vec<String> {
+        names.iter().map(ToString::to_string).collect()
+    }
+
+    #[sqlx::test]
+    #[ignore = "needs a Postgres database in DATABASE_URL"]
+    async fn test_games_missing_from_a_full_update_are_removed(pool: PgPool) -> sqlx::Result<()> {
+        let first = start_update(&pool).await?;
+        let counts = upsert_many(&pool, first, &[10, 20], &names(&["Ten", "Twenty"])).await?;
+        assert_eq!((counts.added, counts.renamed, counts.upserted), (2, 0, 2));
+        assert_eq!(mark_removed(&pool, first).await?, 0);
+        finish_update(&pool, first).await?;
+
+        let second = start_update(&pool).await?;
+        let counts = upsert_many(&pool, second, &[10], &names(&["Ten Remastered"])).await?;
+        assert_eq!((counts.added, counts.renamed, counts.upserted), (0, 1, 1));
+        assert_eq!(mark_removed(&pool, second).await?, 1);
+        finish_update(&pool, second).await?;
+
+        assert!(!is_removed(&pool, 10).await?);
+        assert!(is_removed(&pool, 20).await?);
+
+        Ok(())
+    }
+
+    #[sqlx::test]
+    #[ignore = "needs a Postgres database in DATABASE_URL"]
+    async fn test_removed_games_are_restored_when_seen_again(pool: PgPool) -> sqlx::Result<()> {
+        let first = start_update(&pool).await?;
+        upsert_many(&pool, first, &[10, 20], &names(&["Ten", "Twenty"])).await?;
+        finish_update(&pool, first).await?;
+
+        let second = start_update(&pool).await?;
+        upsert_many(&pool, second, &[10], &names(&["Ten"])).await?;
+        assert_eq!(mark_removed(&pool, second).await?, 1);
+        finish_update(&pool, second).await?;
+
+        // A restored game counts as added, not renamed, even when its name changed
+        let third = start_update(&pool).await?;
+        let counts =
+            upsert_many(&pool, third, &[10, 20], &names(&["Ten", "Twenty Returns"])).await?;
+        assert_eq!((counts.added, counts.renamed, counts.upserted), (1, 0, 2));
+        assert_eq!(mark_removed(&pool, third).await?, 0);
+        finish_update(&pool, third).await?;
+
+        assert!(!is_removed(&pool, 20).await?);
+        assert_eq!(
+            get_many(&pool, &[20]).await?[0].name,
+            "Twenty Returns".to_string()
+        );
+
+        Ok(())
+    }
+}
@@ -0,0 +1,287 @@
+use chrono::{DateTime, Utc};
+use sqlx::PgPool;
+
+/// A comment on a game suggested for an event
+#[derive(Clone)]
+pub struct GameComment {
+    pub id: i32,
+    pub event_id: i32,
+    pub game_id: i64,
+    /// The comment this is a reply to
+    pub parent_id: Option<i32>,
+    pub email: String,
+    pub handle: Option<String>,
+    pub avatar_url: Option<String>,
+    pub body: String,
+    pub created_at: DateTime<Utc>,
+    pub edited_at: Option<DateTime<Utc>>,
+    pub deleted_at: Option<DateTime<Utc>>,
+    /// Who deleted the comment, which is an admin if it isn't the author
+    pub deleted_by: Option<String>,
+}
+
+/// Someone's emoji reaction to a comment
+#[derive(Clone)]
+pub struct GameCommentReaction {
+    pub comment_id: i32,
+    pub email: String,
+    pub emoji: String,
+}
+
+/// Get the comments on a suggested game, oldest first
+pub async fn filter(
+    pool: &PgPool,
+    event_id: i32,
+    game_id: i64,
+) -> Result<Vec<GameComment>, sqlx::Error> {
+    sqlx::query_as!(
+        GameComment,
+        r#"
+        SELECT
+            c.id,
+            c.event_id,
+            c.game_id,
+            c.parent_id,
+            c.email,
+            i.handle AS "handle?",
+            'https://www.gravatar.com/avatar/' || MD5(LOWER(c.email)) || '?d=robohash' AS avatar_url,
+            c.body,
+            c.created_at,
+            c.edited_at,
+            c.deleted_at,
+            c.deleted_by
+        FROM event_game_comment c
+        LEFT JOIN invitation i
+            ON i.event_id = c.event_id
+            AND LOWER(i.email) = LOWER(c.email)
+        WHERE c.event_id = $1
+        AND c.game_id = $2
+        ORDER BY c.created_at, c.id
+        "#,
+        event_id,
+        game_id,
+    )
+    .fetch_all(pool)
+    .await
+}
+
+pub async fn get(pool: &PgPool, id: i32) -> Result<Option<GameComment>, sqlx::Error> {
+    sqlx::query_as!(
+        GameComment,
+        r#"
+        SELECT
+            c.id,
+            c.event_id,
+            c.game_id,
+            c.parent_id,
+            c.email,
+            i.handle AS "handle?",
+            'https://www.gravatar.com/avatar/' || MD5(LOWER(c.email)) || '?d=robohash' AS avatar_url,
+            c.body,
+            c.created_at,
+            c.edited_at,
+            c.deleted_at,
+            c.deleted_by
+        FROM event_game_comment c
+        LEFT JOIN invitation i
+            ON i.event_id = c.event_id
+            AND LOWER(i.email) = LOWER(c.email)
+        WHERE c.id = $1
+        "#,
+        id,
+    )
+    .fetch_optional(pool)
+    .await
+}
+
+pub async fn create(
+    pool: &PgPool,
+    event_id: i32,
+    game_id: i64,
+    parent_id: Option<i32>,
+    email: String,
+    body: String,
+) -> Result<GameComment, sqlx::Error> {
+    sqlx::query_as!(
+        GameComment,
+        r#"
+        WITH created AS (
+            INSERT INTO event_game_comment (event_id, game_id, parent_id, email, body)
+                VALUES ($1, $2, $3, $4, $5)
+                RETURNING *
+        )
+        SELECT
+            c.id,
+            c.event_id,
+            c.game_id,
+            c.parent_id,
+            c.email,
+            i.handle AS "handle?",
+            'https://www.gravatar.com/avatar/' || MD5(LOWER(c.email)) || '?d=robohash' AS avatar_url,
+            c.body,
+            c.created_at,
+            c.edited_at,
+            c.deleted_at,
+            c.deleted_by
+        FROM created c
+        LEFT JOIN invitation i
+            ON i.event_id = c.event_id
+            AND LOWER(i.email) = LOWER(c.email)
+        "#,
+        event_id,
+        game_id,
+        parent_id,
+        email,
+        body,
+    )
+    .fetch_one(pool)
+    .await
+}
+
+/// Change what a comment says. Returns None if the comment doesn't exist or has been deleted.
+pub async fn update_body(
+    pool: &PgPool,
+    id: i32,
+    body: String,
+) -> Result<Option<GameComment>, sqlx::Error> {
+    sqlx::query_as!(
+        GameComment,
+        r#"
+        WITH updated AS (
+            UPDATE event_game_comment
+            SET body = $2, edited_at = NOW()
+            WHERE id = $1
+            AND deleted_at IS NULL
+            RETURNING *
+        )
+        SELECT
+            c.id,
+            c.event_id,
+            c.game_id,
+            c.parent_id,
+            c.email,
+            i.handle AS "handle?",
+            'https://www.gravatar.com/avatar/' || MD5(LOWER(c.email)) || '?d=robohash' AS avatar_url,
+            c.body,
+            c.created_at,
+            c.edited_at,
+            c.deleted_at,
+            c.deleted_by
+        FROM updated c
+        LEFT JOIN invitation i
+            ON i.event_id = c.event_id
+            AND LOWER(i.email) = LOWER(c.email)
+        "#,
+        id,
+        body,
+    )
+    .fetch_optional(pool)
+    .await
+}
+
+/// Mark a comment as deleted, keeping it so replies to it still make sense.
+/// Returns None if the comment doesn't exist or was already deleted.
+pub async fn delete(
+    pool: &PgPool,
+    id: i32,
+    deleted_by: String,
+) -> Result<Option<GameComment>, sqlx::Error> {
+    sqlx::query_as!(
+        GameComment,
+        r#"
+        WITH deleted AS (
+            UPDATE event_game_comment
+            SET deleted_at = NOW(), deleted_by = $2
+            WHERE id = $1
+            AND deleted_at IS NULL
+            RETURNING *
+        )
+        SELECT
+            c.id,
+            c.event_id,
+            c.game_id,
+            c.parent_id,
+            c.email,
+            i.handle AS "handle?",
+            'https://www.gravatar.com/avatar/' || MD5(LOWER(c.email)) || '?d=robohash' AS avatar_url,
+            c.body,
+            c.created_at,
+            c.edited_at,
+            c.deleted_at,
+            c.deleted_by
+        FROM deleted c
+        LEFT JOIN invitation i
+            ON i.event_id = c.event_id
+            AND LOWER(i.email) = LOWER(c.email)
+        "#,
+        id,
+        deleted_by,
+    )
+    .fetch_optional(pool)
+    .await
+}
+
+/// Get the reactions to some comments
+pub async fn filter_reactions(
+    pool: &PgPool,
+    comment_ids: &[i32],
+) -> Result<Vec<GameCommentReaction>, sqlx::Error> {
+    sqlx::query_as!(
+        GameCommentReaction,
+        r#"
+        SELECT comment_id, email, emoji
+        FROM event_game_comment_reaction
+        WHERE comment_id = ANY($1)
+        ORDER BY created_at
+        "#,
+        comment_ids,
+    )
+    .fetch_all(pool)
+    .await
+}
+
+/// React to a comment. Does nothing if the person has already reacted with the emoji.
+pub async fn add_reaction(
+    pool: &PgPool,
+    comment_id: i32,
+    email: String,
+    emoji: String,
+) -> Result<(), sqlx::Error> {
+    sqlx::query!(
+        r#"
+        INSERT INTO event_game_comment_reaction (comment_id, email, emoji)
+        VALUES ($1, $2, $3)
+        ON CONFLICT (comment_id, LOWER(email), emoji) DO NOTHING
+        "#,
+        comment_id,
+        email,
+        emoji,
+    )
+    .execute(pool)
+    .await?;
+
+    Ok(())
+}
+
+pub async fn remove_reaction(
+    pool: &PgPool,
+    comment_id: i32,
+    email: String,
+    emoji: String,
+) -> Result<(), sqlx::Error> {
+    sqlx::query!(
+        r#"
+        DELETE FROM event_game_comment_reaction
+        WHERE comment_id = $1
+        AND LOWER(email) = LOWER($2)
+        AND emoji = $3
+        "#,
+        comment_id,
+        email,
+        emoji,
+    )
+    .execute(pool)
+    .await?;
+
+    Ok(())
+}
@@ -0,0 +1,99 @@
+use chrono::{DateTime, Utc};
+use sqlx::PgPool;
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq, sqlx::Type)]
+#[sqlx(type_name = "game_ownership", rename_all = "lowercase")]
+pub enum GameOwnership {
+    Owned,
+    NotOwned,
+    Installed,
+}
+
+/// Whether an attendee says they own a game for an event
+#[derive(Clone)]
+pub struct OwnershipDeclaration {
+    pub event_id: i32,
+    pub game_id: i64,
+    pub email: String,
+    pub ownership: GameOwnership,
+    pub last_modified: DateTime<Utc>,
+}
+
+pub async fn filter(
+    pool: &PgPool,
+    event_id: i32,
+    game_id: i64,
+) -> Result<Vec<OwnershipDeclaration>, sqlx::Error> {
+    sqlx::query_as!(
+        OwnershipDeclaration,
+        r#"
+        SELECT
+            event_id,
+            game_id,
+            email,
+            ownership AS "ownership: _",
+            last_modified
+        FROM event_game_ownership
+        WHERE event_id = $1
+        AND game_id = $2
+        "#,
+        event_id,
+        game_id,
+    )
+    .fetch_all(pool)
+    .await
+}
+
+pub async fn upsert(
+    pool: &PgPool,
+    event_id: i32,
+    game_id: i64,
+    email: String,
+    ownership: GameOwnership,
+) -> Result<OwnershipDeclaration, sqlx::Error> {
+    sqlx::query_as!(
+        OwnershipDeclaration,
+        r#"
+        INSERT INTO event_game_ownership (event_id, game_id, email, ownership)
+        VALUES ($1, $2, $3, $4)
+        ON CONFLICT (event_id, game_id, LOWER(email))
+        DO UPDATE SET ownership = $4, last_modified = NOW()
+        RETURNING
+            event_id,
+            game_id,
+            email,
+            ownership AS "ownership: _",
+            last_modified
+        "#,
+        event_id,
+        game_id,
+        email,
+        ownership as _,
+    )
+    .fetch_one(pool)
+    .await
+}
+
+/// Remove a declaration, so ownership comes from Steam again. Returns false if there wasn't one.
+pub async fn delete(
+    pool: &PgPool,
+    event_id: i32,
+    game_id: i64,
+    email: String,
+) -> Result<bool, sqlx::Error> {
+    let result = sqlx::query!(
+        r#"
+        DELETE FROM event_game_ownership
+        WHERE event_id = $1
+        AND game_id = $2
+        AND LOWER(email) = LOWER($3)
+        "#,
+        event_id,
+        game_id,
+        email,
+    )
+    .execute(pool)
+    .await?;
+
+    Ok(result.rows_affected() > 0)
+}
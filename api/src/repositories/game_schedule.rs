@@ -9,6 +9,9 @@ pub struct GameSchedule {
     pub game_name: String,
     pub start_time: DateTime<Utc>,
     pub duration_minutes: i32,
+    /// The track the game is on, counting from 1
+    pub track: i32,
+    pub room_id: Option<i32>,
     pub is_pinned: bool,
     pub created_at: DateTime<Utc>,
     pub last_modified: DateTime<Utc>,
@@ -39,6 +42,8 @@ pub async fn filter(pool: &PgPool, filter: Filter) -> Result<Vec<GameSchedule>,
             sg.name AS game_name,
             egs.start_time,
             egs.duration_minutes,
+            egs.track,
+            egs.room_id,
             egs.is_pinned,
             egs.created_at,
             egs.last_modified
@@ -58,7 +63,6 @@ pub async fn filter(pool: &PgPool, filter: Filter) -> Result<Vec<GameSchedule>,
 }
 
 /// Get a specific scheduled game by ID
-#[allow(dead_code)]
 pub async fn get(pool: &PgPool, schedule_id: i32) -> Result<Option<GameSchedule>, sqlx::Error> {
     sqlx::query_as!(
         GameSchedule,
@@ -70,6 +74,8 @@ pub async fn get(pool: &PgPool, schedule_id: i32) -> Result<Option<GameSchedule>
             sg.name AS game_name,
             egs.start_time,
             egs.duration_minutes,
+            egs.track,
+            egs.room_id,
             egs.is_pinned,
             egs.created_at,
             egs.last_modified
@@ -84,12 +90,15 @@ pub async fn get(pool: &PgPool, schedule_id: i32) -> Result<Option<GameSchedule>
 }
 
 /// Create a new scheduled game
+#[allow(clippy::too_many_arguments)]
 pub async fn create(
     pool: &PgPool,
     event_id: i32,
     game_id: i64,
     start_time: DateTime<Utc>,
     duration_minutes: i32,
+    track: i32,
+    room_id: Option<i32>,
     is_pinned: bool,
 ) -> Result<GameSchedule, sqlx::Error> {
     sqlx::query_as!(
@@ -101,15 +110,19 @@ pub async fn create(
                 game_id,
                 start_time,
                 duration_minutes,
+                track,
+                room_id,
                 is_pinned
             )
-            VALUES ($1, $2, $3, $4, $5)
+            VALUES ($1, $2, $3, $4, $5, $6, $7)
             RETURNING
                 id,
                 event_id,
                 game_id,
                 start_time,
                 duration_minutes,
+                track,
+                room_id,
                 is_pinned,
                 created_at,
                 last_modified
@@ -121,6 +134,8 @@ pub async fn create(
             sg.name AS game_name,
             inserted.start_time,
             inserted.duration_minutes,
+            inserted.track,
+            inserted.room_id,
             inserted.is_pinned,
             inserted.created_at,
             inserted.last_modified
@@ -131,18 +146,22 @@ pub async fn create(
         game_id,
         start_time,
         duration_minutes,
+        track,
+        room_id,
         is_pinned
     )
     .fetch_one(pool)
     .await
 }
 
-/// Update a scheduled game's time and duration
+/// Update a scheduled game's time, duration and track
 pub async fn update(
     pool: &PgPool,
     schedule_id: i32,
     start_time: DateTime<Utc>,
     duration_minutes: i32,
+    track: i32,
+    room_id: Option<i32>,
 ) -> Result<GameSchedule, sqlx::Error> {
     sqlx::query_as!(
         GameSchedule,
@@ -152,6 +171,8 @@ pub async fn update(
             SET
                 start_time = $2,
                 duration_minutes = $3,
+                track = $4,
+                room_id = $5,
                 last_modified = NOW()
             WHERE id = $1
             RETURNING
@@ -160,6 +181,8 @@ pub async fn update(
                 game_id,
                 start_time,
                 duration_minutes,
+                track,
+                room_id,
                 is_pinned,
                 created_at,
                 last_modified
@@ -171,6 +194,8 @@ pub async fn update(
             sg.name AS game_name,
             updated.start_time,
             updated.duration_minutes,
+            updated.track,
+            updated.room_id,
             updated.is_pinned,
             updated.created_at,
             updated.last_modified
@@ -179,7 +204,9 @@ pub async fn update(
         "#,
         schedule_id,
         start_time,
-        duration_minutes
+        duration_minutes,
+        track,
+        room_id
     )
     .fetch_one(pool)
     .await
@@ -240,3 +267,49 @@ pub async fn has_overlap(
 
     Ok(result.exists)
 }
+
+/// What's known about how long each game suggested for an event takes
+#[derive(Clone)]
+pub struct GameDurations {
+    pub game_id: i64,
+    pub game_name: String,
+    /// Set for this event by an admin
+    pub event_minutes: Option<i32>,
+    /// Set for the game by an admin
+    pub game_minutes: Option<i32>,
+    /// The median time the game was scheduled for at other events
+    pub past_minutes: Option<i32>,
+}
+
+pub async fn get_durations(
+    pool: &PgPool,
+    event_id: i32,
+) -> Result<Vec<GameDurations>, sqlx::Error> {
+    sqlx::query_as!(
+        GameDurations,
+        r#"
+        SELECT
+            eg.game_id,
+            sg.name AS game_name,
+            eg.duration_minutes AS event_minutes,
+            sgd.duration_minutes AS "game_minutes?",
+            past.minutes AS "past_minutes?"
+        FROM event_game eg
+        INNER JOIN steam_game sg ON eg.game_id = sg.appid
+        LEFT JOIN steam_game_duration sgd ON eg.game_id = sgd.appid
+        LEFT JOIN (
+            SELECT
+                game_id,
+                CAST(PERCENTILE_CONT(0.5) WITHIN GROUP (ORDER BY duration_minutes) AS INT) AS minutes
+            FROM event_game_schedule
+            WHERE event_id <> $1
+            GROUP BY game_id
+        ) past ON eg.game_id = past.game_id
+        WHERE eg.event_id = $1
+        ORDER BY sg.name
+        "#,
+        event_id,
+    )
+    .fetch_all(pool)
+    .await
+}
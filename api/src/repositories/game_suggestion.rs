@@ -18,6 +18,10 @@ pub struct GameSuggestion {
     pub user_email: String,
     pub comment: Option<String>,
     pub self_vote: Option<GameVote>,
+    /// The user's stars for the game, for events using score voting
+    pub self_score: Option<i16>,
+    /// Where the user ranked the game, for events using ranked choice voting
+    pub self_rank: Option<i16>,
     pub votes: Option<i64>,
     pub requested_at: DateTime<Utc>,
     pub last_modified: DateTime<Utc>,
@@ -28,15 +32,41 @@ pub struct Filter {
     pub game_id: Option<i64>,
 }
 
+/// Suggest a game, returning None if the user already has `max_suggestions` for the event
 pub async fn create(
     pool: &PgPool,
     event_id: i32,
     game_id: i64,
     email: String,
     comment: Option<String>,
-) -> Result<GameSuggestion, sqlx::Error> {
+    max_suggestions: Option<i32>,
+) -> Result<Option<GameSuggestion>, sqlx::Error> {
+    let mut transaction = pool.begin().await?;
+
+    // Lock the user's invitation until the suggestion is saved, so suggestions made at the
+    // same time are counted one after the other and can't both fit in the last space
+    sqlx::query!(
+        r#"
+        SELECT event_id
+        FROM invitation
+        WHERE event_id = $1
+        AND LOWER(email) = LOWER($2)
+        FOR UPDATE
+        "#,
+        event_id,
+        email,
+    )
+    .fetch_optional(&mut *transaction)
+    .await?;
+
+    if let Some(max_suggestions) = max_suggestions {
+        if count_by_user(&mut *transaction, event_id, &email).await? >= i64::from(max_suggestions) {
+            return Ok(None);
+        }
+    }
+
     // Insert new game suggestion
-    sqlx::query_as!(
+    let game_suggestion = sqlx::query_as!(
         GameSuggestion,
         r#"
         WITH event_game_suggestion_response AS (
@@ -55,6 +85,8 @@ pub async fn create(
             event_game_suggestion_response.user_email AS user_email,
             event_game_suggestion_response.comment AS comment,
             'yes'::vote AS "self_vote: _",
+            NULL::SMALLINT AS self_score,
+            NULL::SMALLINT AS self_rank,
             1 AS "votes: i64",
             event_game_suggestion_response.requested_at AS requested_at,
             event_game_suggestion_response.last_modified AS last_modified
@@ -67,8 +99,12 @@ pub async fn create(
         email,
         comment,
     )
-    .fetch_one(pool)
-    .await
+    .fetch_one(&mut *transaction)
+    .await?;
+
+    transaction.commit().await?;
+
+    Ok(Some(game_suggestion))
 }
 
 pub async fn filter(
@@ -92,6 +128,8 @@ pub async fn filter(
             event_game.user_email AS user_email,
             event_game.comment AS comment,
             self_votes.vote AS "self_vote: _",
+            self_votes.score AS self_score,
+            self_votes.rank AS self_rank,
             count(all_votes.*) AS votes,
             event_game.requested_at AS requested_at,
             event_game.last_modified AS last_modified
@@ -108,7 +146,7 @@ pub async fn filter(
             AND all_votes.vote = 'yes'::vote
         WHERE (event_game.event_id = $1 OR $2)
         AND (event_game.game_id = $3 OR $4)
-        GROUP BY event_game.event_id, event_game.game_id, steam_game.name, event_game.user_email, event_game.comment, self_votes.vote, event_game.requested_at, event_game.last_modified
+        GROUP BY event_game.event_id, event_game.game_id, steam_game.name, event_game.user_email, event_game.comment, self_votes.vote, self_votes.score, self_votes.rank, event_game.requested_at, event_game.last_modified
         "#,
         event_id.0,
         event_id.1,
@@ -126,15 +164,16 @@ pub async fn edit(
     game_id: i64,
     email: String,
     vote: GameVote,
+    score: Option<i16>,
 ) -> Result<GameSuggestion, sqlx::Error> {
     // Update vote on game suggestion
     sqlx::query_as!(
         GameSuggestion,
         r#"WITH event_game_patch_response AS (
-            INSERT INTO event_game_vote (event_id, game_id, email, vote)
-                VALUES ($1, $2, $3, $4)
-                ON CONFLICT (event_id, game_id, email) DO UPDATE SET vote = $4, last_modified = NOW()
-                RETURNING event_id, game_id, email, vote, vote_date, last_modified
+            INSERT INTO event_game_vote (event_id, game_id, email, vote, score)
+                VALUES ($1, $2, $3, $4, $5)
+                ON CONFLICT (event_id, game_id, email) DO UPDATE SET vote = $4, score = $5, last_modified = NOW()
+                RETURNING event_id, game_id, email, vote, score, rank, vote_date, last_modified
         ) SELECT
             event_game.event_id AS event_id,
             event_game.game_id AS game_id,
@@ -142,6 +181,8 @@ pub async fn edit(
             event_game.user_email AS user_email,
             event_game.comment AS comment,
             self_vote.vote AS "self_vote: _",
+            self_vote.score AS self_score,
+            self_vote.rank AS self_rank,
             CASE
                 WHEN self_vote.vote = 'yes'::vote THEN count(all_votes.*) + 1
                 ELSE count(all_votes.*) - 1
@@ -160,11 +201,12 @@ pub async fn edit(
             AND all_votes.vote = 'yes'::vote
         WHERE event_game.event_id = $1
         AND event_game.game_id = $2
-        GROUP BY event_game.event_id, event_game.game_id, steam_game.name, steam_game.last_modified, event_game.user_email, event_game.comment, event_game.requested_at, event_game.last_modified, self_vote.vote"#,
+        GROUP BY event_game.event_id, event_game.game_id, steam_game.name, steam_game.last_modified, event_game.user_email, event_game.comment, event_game.requested_at, event_game.last_modified, self_vote.vote, self_vote.score, self_vote.rank"#,
         event_id,
         game_id,
         email,
         vote as _,
+        score,
     )
     .fetch_one(pool)
     .await
@@ -212,6 +254,8 @@ pub async fn update_comment(
             event_game_update_response.user_email AS user_email,
             event_game_update_response.comment AS comment,
             self_vote.vote AS "self_vote: _",
+            self_vote.score AS self_score,
+            self_vote.rank AS self_rank,
             count(all_votes.*) AS votes,
             event_game_update_response.requested_at AS requested_at,
             event_game_update_response.last_modified AS last_modified
@@ -226,7 +270,7 @@ pub async fn update_comment(
             ON event_game_update_response.event_id = all_votes.event_id
             AND event_game_update_response.game_id = all_votes.game_id
             AND all_votes.vote = 'yes'::vote
-        GROUP BY event_game_update_response.event_id, event_game_update_response.game_id, steam_game.name, event_game_update_response.user_email, event_game_update_response.comment, event_game_update_response.requested_at, event_game_update_response.last_modified, self_vote.vote
+        GROUP BY event_game_update_response.event_id, event_game_update_response.game_id, steam_game.name, event_game_update_response.user_email, event_game_update_response.comment, event_game_update_response.requested_at, event_game_update_response.last_modified, self_vote.vote, self_vote.score, self_vote.rank
         "#,
         event_id,
         game_id,
@@ -269,3 +313,291 @@ pub async fn get_games_with_votes(
     .fetch_all(pool)
     .await
 }
+
+/// How popular a game was at other events that have finished
+pub struct PastPopularity {
+    pub game_id: i64,
+    pub event_count: i64,
+    pub vote_count: i64,
+}
+
+pub async fn get_past_popularity(
+    pool: &PgPool,
+    event_id: i32,
+    game_ids: &[i64],
+) -> Result<Vec<PastPopularity>, sqlx::Error> {
+    sqlx::query_as!(
+        PastPopularity,
+        r#"
+        SELECT
+            eg.game_id,
+            COUNT(DISTINCT eg.event_id) AS "event_count!",
+            COUNT(egv.email) AS "vote_count!"
+        FROM event_game eg
+        INNER JOIN event e ON eg.event_id = e.id
+        LEFT JOIN event_game_vote egv ON eg.event_id = egv.event_id AND eg.game_id = egv.game_id AND egv.vote = 'yes'
+        WHERE eg.game_id = ANY($2)
+        AND eg.event_id <> $1
+        AND e.time_end < NOW()
+        GROUP BY eg.game_id
+        "#,
+        event_id,
+        game_ids,
+    )
+    .fetch_all(pool)
+    .await
+}
+
+/// Get the IDs of the games suggested for an event
+pub async fn get_game_ids(pool: &PgPool, event_id: i32) -> Result<Vec<i64>, sqlx::Error> {
+    sqlx::query_scalar!(
+        r#"
+        SELECT game_id
+        FROM event_game
+        WHERE event_id = $1
+        ORDER BY game_id
+        "#,
+        event_id
+    )
+    .fetch_all(pool)
+    .await
+}
+
+/// Someone's vote for a game suggested for an event
+#[derive(Clone)]
+pub struct EventVote {
+    pub game_id: i64,
+    pub email: String,
+    pub vote: GameVote,
+    pub score: Option<i16>,
+    pub rank: Option<i16>,
+}
+
+/// Get every vote for the games suggested for an event
+pub async fn get_votes(pool: &PgPool, event_id: i32) -> Result<Vec<EventVote>, sqlx::Error> {
+    sqlx::query_as!(
+        EventVote,
+        r#"
+        SELECT
+            egv.game_id,
+            egv.email,
+            egv.vote AS "vote: _",
+            egv.score,
+            egv.rank
+        FROM event_game_vote egv
+        INNER JOIN event_game eg ON eg.event_id = egv.event_id AND eg.game_id = egv.game_id
+        WHERE egv.event_id = $1
+        "#,
+        event_id
+    )
+    .fetch_all(pool)
+    .await
+}
+
+/// Replace someone's ranking of an event's suggested games, favourite first.
+/// Ranked games count as yes votes, and games left out of the ranking lose their rank and vote.
+pub async fn set_ranking(
+    pool: &PgPool,
+    event_id: i32,
+    email: String,
+    game_ids: &[i64],
+) -> Result<(), sqlx::Error> {
+    sqlx::query!(
+        r#"
+        WITH unranked AS (
+            UPDATE event_game_vote
+            SET rank = NULL, vote = 'novote'::vote, last_modified = NOW()
+            WHERE event_id = $1
+            AND LOWER(email) = LOWER($2)
+            AND rank IS NOT NULL
+            AND NOT (game_id = ANY($3))
+        )
+        INSERT INTO event_game_vote (event_id, game_id, email, vote, rank)
+            SELECT $1, ranking.game_id, $2, 'yes'::vote, ranking.position::SMALLINT
+            FROM UNNEST($3::BIGINT[]) WITH ORDINALITY AS ranking(game_id, position)
+            ON CONFLICT (event_id, game_id, email) DO UPDATE SET vote = 'yes'::vote, rank = EXCLUDED.rank, last_modified = NOW()
+        "#,
+        event_id,
+        email,
+        game_ids,
+    )
+    .execute(pool)
+    .await?;
+
+    Ok(())
+}
+
+/// Count how many games someone has suggested for an event
+pub async fn count_by_user(
+    executor: impl sqlx::PgExecutor<'_>,
+    event_id: i32,
+    email: &str,
+) -> Result<i64, sqlx::Error> {
+    sqlx::query_scalar!(
+        r#"
+        SELECT COUNT(*) AS "count!"
+        FROM event_game
+        WHERE event_id = $1
+        AND LOWER(user_email) = LOWER($2)
+        "#,
+        event_id,
+        email,
+    )
+    .fetch_one(executor)
+    .await
+}
+
+/// Merge one suggested game into another. Votes carry over, keeping the strongest vote of anyone
+/// who voted for both, the suggesters' comments are joined and the discussion moves across. Ownership declarations for the merged game are dropped.
+/// Returns false if either game hasn't been suggested for the event.
+pub async fn merge(
+    pool: &PgPool,
+    event_id: i32,
+    game_id: i64,
+    into_game_id: i64,
+) -> Result<bool, sqlx::Error> {
+    let result = sqlx::query!(
+        r#"
+        WITH source AS (
+            DELETE FROM event_game
+            WHERE event_id = $1
+            AND game_id = $2
+            AND EXISTS (SELECT 1 FROM event_game WHERE event_id = $1 AND game_id = $3)
+            RETURNING comment
+        ), moved_votes AS (
+            DELETE FROM event_game_vote
+            WHERE event_id = $1
+            AND game_id = $2
+            AND EXISTS (SELECT 1 FROM source)
+            RETURNING email, vote, score, rank
+        ), merged_votes AS (
+            INSERT INTO event_game_vote (event_id, game_id, email, vote, score, rank)
+                SELECT $1, $3, email, vote, score, rank FROM moved_votes
+                ON CONFLICT (event_id, game_id, LOWER(email)) DO UPDATE SET
+                    vote = CASE WHEN EXCLUDED.vote = 'yes'::vote THEN 'yes'::vote ELSE event_game_vote.vote END,
+                    score = GREATEST(event_game_vote.score, EXCLUDED.score),
+                    rank = LEAST(event_game_vote.rank, EXCLUDED.rank),
+                    last_modified = NOW()
+        ), moved_comments AS (
+            UPDATE event_game_comment
+            SET game_id = $3
+            WHERE event_id = $1
+            AND game_id = $2
+            AND EXISTS (SELECT 1 FROM source)
+        ), dropped_ownership AS (
+            DELETE FROM event_game_ownership
+            WHERE event_id = $1
+            AND game_id = $2
+            AND EXISTS (SELECT 1 FROM source)
+        )
+        UPDATE event_game
+        SET comment = NULLIF(LEFT(CONCAT_WS(E'\n\n', event_game.comment, source.comment), 500), ''), last_modified = NOW()
+        FROM source
+        WHERE event_game.event_id = $1
+        AND event_game.game_id = $3
+        "#,
+        event_id,
+        game_id,
+        into_game_id,
+    )
+    .execute(pool)
+    .await?;
+
+    Ok(result.rows_affected() > 0)
+}
+
+/// Set how long a suggested game should be scheduled for at an event, or clear it to use the usual time.
+/// Returns false if the game hasn't been suggested for the event.
+pub async fn update_duration(
+    pool: &PgPool,
+    event_id: i32,
+    game_id: i64,
+    duration_minutes: Option<i32>,
+) -> Result<bool, sqlx::Error> {
+    let result = sqlx::query!(
+        r#"
+        UPDATE event_game
+        SET duration_minutes = $3, last_modified = NOW()
+        WHERE event_id = $1 AND game_id = $2
+        "#,
+        event_id,
+        game_id,
+        duration_minutes,
+    )
+    .execute(pool)
+    .await?;
+
+    Ok(result.rows_affected() > 0)
+}
+
+/// These run against a fresh database with the migrations applied, so they need
+/// `DATABASE_URL` to point at a Postgres server: `cargo test -- --ignored`
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[sqlx::test]
+    #[ignore = "needs a Postgres database in DATABASE_URL"]
+    async fn test_suggestions_made_at_once_stay_within_the_quota(pool: PgPool) -> sqlx::Result<()> {
+        let event_id: i32 = sqlx::query_scalar(
+            "INSERT INTO event (title, description, time_begin, time_end) VALUES ('LAN', '', NOW(), NOW() + INTERVAL '1 day') RETURNING id",
+        )
+        .fetch_one(&pool)
+        .await?;
+        sqlx::query(
+            "INSERT INTO invitation (event_id, email, response) VALUES ($1, 'gamer@example.com', 'yes')",
+        )
+        .bind(event_id)
+        .execute(&pool)
+        .await?;
+        let update_id: i32 =
+            sqlx::query_scalar("INSERT INTO steam_game_update DEFAULT VALUES RETURNING id")
+                .fetch_one(&pool)
+                .await?;
+        for appid in 1..=10_i64 {
+            sqlx::query(
+                "INSERT INTO steam_game (appid, name, update_id) VALUES ($1, 'Game ' || $1, $2)",
+            )
+            .bind(appid)
+            .bind(update_id)
+            .execute(&pool)
+            .await?;
+        }
+
+        let suggestions: Vec<_> = (1..=10_i64)
+            .map(|appid| {
+                let pool = pool.clone();
+                tokio::spawn(async move {
+                    create(
+                        &pool,
+                        event_id,
+                        appid,
+                        "gamer@example.com".to_string(),
+                        None,
+                        Some(3),
+                    )
+                    .await
+                })
+            })
+            .collect();
+
+        let mut created = 0;
+        for suggestion in suggestions {
+            if suggestion
+                .await
+                .expect("the suggestion task shouldn't panic")?
+                .is_some()
+            {
+                created += 1;
+            }
+        }
+
+        assert_eq!(created, 3);
+        assert_eq!(
+            count_by_user(&pool, event_id, "Gamer@example.com").await?,
+            3
+        );
+
+        Ok(())
+    }
+}
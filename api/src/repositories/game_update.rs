@@ -1,19 +1,221 @@
 use chrono::{DateTime, Utc};
 use sqlx::PgPool;
 
+#[derive(Clone, Copy, Debug, PartialEq, Eq, sqlx::Type)]
+#[sqlx(type_name = "steam_game_update_status", rename_all = "lowercase")]
+pub enum UpdateStatus {
+    Running,
+    Succeeded,
+    Failed,
+}
+
 #[derive(Clone)]
-#[allow(dead_code)]
 pub struct SteamGameUpdate {
     pub id: i32,
     pub update_time: DateTime<Utc>,
+    pub status: UpdateStatus,
+    pub triggered_by: Option<String>,
+    pub pages_fetched: i32,
+    pub apps_fetched: i32,
+    pub rows_upserted: i32,
+    pub error_count: i32,
+    pub last_error: Option<String>,
+    pub finished_at: Option<DateTime<Utc>>,
+    pub last_modified: DateTime<Utc>,
+    pub modified_since: Option<DateTime<Utc>>,
+    pub last_appid: i64,
+    pub apps_added: i32,
+    pub apps_renamed: i32,
+    pub apps_removed: i32,
+}
+
+/// Start a new update, returning None if another update is already running.
+/// Updates with `modified_since` only fetch apps changed since then.
+pub async fn create(
+    pool: &PgPool,
+    triggered_by: Option<String>,
+    modified_since: Option<DateTime<Utc>>,
+) -> Result<Option<SteamGameUpdate>, sqlx::Error> {
+    sqlx::query_as!(
+        SteamGameUpdate,
+        r#"
+        INSERT INTO steam_game_update (triggered_by, modified_since)
+        VALUES ($1, $2)
+        ON CONFLICT (status) WHERE status = 'running' DO NOTHING
+        RETURNING
+            id,
+            update_time,
+            status AS "status: _",
+            triggered_by,
+            pages_fetched,
+            apps_fetched,
+            rows_upserted,
+            error_count,
+            last_error,
+            finished_at,
+            last_modified,
+            modified_since,
+            last_appid,
+            apps_added,
+            apps_renamed,
+            apps_removed
+        "#,
+        triggered_by,
+        modified_since,
+    )
+    .fetch_optional(pool)
+    .await
+}
+
+/// Get the most recently started update
+pub async fn latest(pool: &PgPool) -> Result<Option<SteamGameUpdate>, sqlx::Error> {
+    sqlx::query_as!(
+        SteamGameUpdate,
+        r#"
+        SELECT
+            id,
+            update_time,
+            status AS "status: _",
+            triggered_by,
+            pages_fetched,
+            apps_fetched,
+            rows_upserted,
+            error_count,
+            last_error,
+            finished_at,
+            last_modified,
+            modified_since,
+            last_appid,
+            apps_added,
+            apps_renamed,
+            apps_removed
+        FROM steam_game_update
+        ORDER BY update_time DESC, id DESC
+        LIMIT 1
+        "#
+    )
+    .fetch_optional(pool)
+    .await
 }
 
-pub async fn create(pool: &PgPool) -> Result<SteamGameUpdate, sqlx::Error> {
-    // Insert new game suggestion
+/// Get the most recently started update that finished successfully
+pub async fn latest_succeeded(pool: &PgPool) -> Result<Option<SteamGameUpdate>, sqlx::Error> {
     sqlx::query_as!(
         SteamGameUpdate,
-        "INSERT INTO steam_game_update DEFAULT VALUES RETURNING id, update_time",
+        r#"
+        SELECT
+            id,
+            update_time,
+            status AS "status: _",
+            triggered_by,
+            pages_fetched,
+            apps_fetched,
+            rows_upserted,
+            error_count,
+            last_error,
+            finished_at,
+            last_modified,
+            modified_since,
+            last_appid,
+            apps_added,
+            apps_renamed,
+            apps_removed
+        FROM steam_game_update
+        WHERE status = 'succeeded'
+        ORDER BY update_time DESC, id DESC
+        LIMIT 1
+        "#
     )
-    .fetch_one(pool)
+    .fetch_optional(pool)
     .await
 }
+
+/// Add the progress from one page of the Steam app list to an update
+pub async fn record_page(
+    pool: &PgPool,
+    id: i32,
+    last_appid: i64,
+    apps_fetched: i32,
+    rows_upserted: i32,
+    apps_added: i32,
+    apps_renamed: i32,
+) -> Result<(), sqlx::Error> {
+    sqlx::query!(
+        r#"
+        UPDATE steam_game_update
+        SET
+            pages_fetched = pages_fetched + 1,
+            last_appid = $2,
+            apps_fetched = apps_fetched + $3,
+            rows_upserted = rows_upserted + $4,
+            apps_added = apps_added + $5,
+            apps_renamed = apps_renamed + $6,
+            last_modified = NOW()
+        WHERE id = $1
+        "#,
+        id,
+        last_appid,
+        apps_fetched,
+        rows_upserted,
+        apps_added,
+        apps_renamed,
+    )
+    .execute(pool)
+    .await?;
+
+    Ok(())
+}
+
+/// Mark an update as finished, recording the error that stopped it if it failed
+pub async fn finish(
+    pool: &PgPool,
+    id: i32,
+    status: UpdateStatus,
+    apps_removed: i32,
+    error: Option<String>,
+) -> Result<(), sqlx::Error> {
+    sqlx::query!(
+        r#"
+        UPDATE steam_game_update
+        SET
+            status = $2,
+            apps_removed = $3,
+            error_count = error_count + CASE WHEN $4::TEXT IS NULL THEN 0 ELSE 1 END,
+            last_error = COALESCE($4, last_error),
+            finished_at = NOW(),
+            last_modified = NOW()
+        WHERE id = $1
+        "#,
+        id,
+        status as _,
+        apps_removed,
+        error,
+    )
+    .execute(pool)
+    .await?;
+
+    Ok(())
+}
+
+/// Fail running updates that have made no progress for a while, such as when the
+/// instance running them was shut down, so they don't block new updates forever
+pub async fn fail_stale(pool: &PgPool, stale_after_minutes: i32) -> Result<u64, sqlx::Error> {
+    let result = sqlx::query!(
+        r#"
+        UPDATE steam_game_update
+        SET
+            status = 'failed',
+            error_count = error_count + 1,
+            last_error = 'Update stopped making progress',
+            finished_at = NOW(),
+            last_modified = NOW()
+        WHERE status = 'running'
+        AND last_modified < NOW() - make_interval(mins => $1)
+        "#,
+        stale_after_minutes,
+    )
+    .execute(pool)
+    .await?;
+
+    Ok(result.rows_affected())
+}
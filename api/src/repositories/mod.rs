@@ -1,15 +1,31 @@
 pub mod audit_log;
+pub mod discord_api;
 pub mod event;
+pub mod event_blocked_period;
+pub mod event_digest;
+pub mod event_discord_config;
+pub mod event_schedule_config;
 pub mod event_seating_config;
+pub mod event_vote_result;
+pub mod event_voting_config;
 pub mod game;
+pub mod game_comment;
+pub mod game_ownership;
 pub mod game_schedule;
 pub mod game_suggestion;
 pub mod game_update;
 pub mod gamer;
 pub mod invitation;
+pub mod notification_preference;
 pub mod profile;
 pub mod room;
 pub mod seat;
 pub mod seat_reservation;
 pub mod steam_api;
+pub mod steam_game_details;
+pub mod steam_game_duration;
+pub mod steam_game_players;
+pub mod steam_presence;
 pub mod user_games;
+pub mod webhook;
+pub mod webhook_delivery;
@@ -0,0 +1,194 @@
+use chrono::{DateTime, Utc};
+use sqlx::PgPool;
+
+/// The categories of email a user can opt in or out of
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum NotificationCategory {
+    Invitations,
+    Reminders,
+    EventUpdates,
+    GameScheduleChanges,
+    Digests,
+}
+
+impl NotificationCategory {
+    pub const fn as_str(self) -> &'static str {
+        match self {
+            Self::Invitations => "invitations",
+            Self::Reminders => "reminders",
+            Self::EventUpdates => "event_updates",
+            Self::GameScheduleChanges => "game_schedule_changes",
+            Self::Digests => "digests",
+        }
+    }
+
+    pub fn parse(category: &str) -> Option<Self> {
+        match category {
+            "invitations" => Some(Self::Invitations),
+            "reminders" => Some(Self::Reminders),
+            "event_updates" => Some(Self::EventUpdates),
+            "game_schedule_changes" => Some(Self::GameScheduleChanges),
+            "digests" => Some(Self::Digests),
+            _ => None,
+        }
+    }
+
+    /// Human readable name, used in email footers and the unsubscribe page
+    pub const fn label(self) -> &'static str {
+        match self {
+            Self::Invitations => "event invitations",
+            Self::Reminders => "event reminders",
+            Self::EventUpdates => "event updates",
+            Self::GameScheduleChanges => "game schedule changes",
+            Self::Digests => "activity digests",
+        }
+    }
+}
+
+#[allow(dead_code)]
+#[derive(Clone)]
+pub struct NotificationPreference {
+    pub email: String,
+    pub invitations: bool,
+    pub reminders: bool,
+    pub event_updates: bool,
+    pub game_schedule_changes: bool,
+    pub digests: bool,
+    pub created_at: DateTime<Utc>,
+    pub last_modified: DateTime<Utc>,
+}
+
+impl NotificationPreference {
+    /// Preferences for someone who has never changed them
+    pub fn default_for(email: &str) -> Self {
+        Self {
+            email: email.to_lowercase(),
+            invitations: true,
+            reminders: true,
+            event_updates: true,
+            game_schedule_changes: true,
+            digests: false,
+            created_at: Utc::now(),
+            last_modified: Utc::now(),
+        }
+    }
+
+    pub const fn is_subscribed(&self, category: NotificationCategory) -> bool {
+        match category {
+            NotificationCategory::Invitations => self.invitations,
+            NotificationCategory::Reminders => self.reminders,
+            NotificationCategory::EventUpdates => self.event_updates,
+            NotificationCategory::GameScheduleChanges => self.game_schedule_changes,
+            NotificationCategory::Digests => self.digests,
+        }
+    }
+
+    pub const fn set_subscribed(&mut self, category: NotificationCategory, subscribed: bool) {
+        match category {
+            NotificationCategory::Invitations => self.invitations = subscribed,
+            NotificationCategory::Reminders => self.reminders = subscribed,
+            NotificationCategory::EventUpdates => self.event_updates = subscribed,
+            NotificationCategory::GameScheduleChanges => self.game_schedule_changes = subscribed,
+            NotificationCategory::Digests => self.digests = subscribed,
+        }
+    }
+}
+
+pub async fn get(
+    pool: &PgPool,
+    email: &str,
+) -> Result<Option<NotificationPreference>, sqlx::Error> {
+    sqlx::query_as!(
+        NotificationPreference,
+        r#"
+        SELECT
+            email,
+            invitations,
+            reminders,
+            event_updates,
+            game_schedule_changes,
+            digests,
+            created_at,
+            last_modified
+        FROM notification_preference
+        WHERE email = LOWER($1)
+        "#,
+        email
+    )
+    .fetch_optional(pool)
+    .await
+}
+
+/// Get the stored preferences for a set of email addresses.
+/// Addresses without stored preferences are not returned.
+pub async fn filter(
+    pool: &PgPool,
+    emails: &[String],
+) -> Result<Vec<NotificationPreference>, sqlx::Error> {
+    let emails: Vec<String> = emails.iter().map(|email| email.to_lowercase()).collect();
+
+    sqlx::query_as!(
+        NotificationPreference,
+        r#"
+        SELECT
+            email,
+            invitations,
+            reminders,
+            event_updates,
+            game_schedule_changes,
+            digests,
+            created_at,
+            last_modified
+        FROM notification_preference
+        WHERE email = ANY($1)
+        "#,
+        &emails
+    )
+    .fetch_all(pool)
+    .await
+}
+
+pub async fn upsert(
+    pool: &PgPool,
+    preference: NotificationPreference,
+) -> Result<NotificationPreference, sqlx::Error> {
+    sqlx::query_as!(
+        NotificationPreference,
+        r#"
+        INSERT INTO notification_preference (
+            email,
+            invitations,
+            reminders,
+            event_updates,
+            game_schedule_changes,
+            digests
+        )
+        VALUES (LOWER($1), $2, $3, $4, $5, $6)
+        ON CONFLICT (email)
+        DO UPDATE SET
+            invitations = $2,
+            reminders = $3,
+            event_updates = $4,
+            game_schedule_changes = $5,
+            digests = $6,
+            last_modified = NOW()
+        RETURNING
+            email,
+            invitations,
+            reminders,
+            event_updates,
+            game_schedule_changes,
+            digests,
+            created_at,
+            last_modified
+        "#,
+        preference.email,
+        preference.invitations,
+        preference.reminders,
+        preference.event_updates,
+        preference.game_schedule_changes,
+        preference.digests,
+    )
+    .fetch_one(pool)
+    .await
+}
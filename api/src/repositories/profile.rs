@@ -5,6 +5,11 @@ pub struct Profile {
     pub email: String,
     pub steam_id: i64,
     pub last_refreshed: Option<DateTime<Utc>>,
+    /// Why the last attempt to refresh the user's games failed, cleared once one succeeds
+    pub refresh_error: Option<String>,
+    pub refresh_failed_at: Option<DateTime<Utc>>,
+    /// How many attempts in a row have failed
+    pub refresh_failure_count: i32,
 }
 
 pub async fn read(pool: &PgPool, email: String) -> Result<Option<Profile>, sqlx::Error> {
@@ -30,6 +35,9 @@ pub async fn update(
             email: email.clone(),
             steam_id: 0,
             last_refreshed: None,
+            refresh_error: None,
+            refresh_failed_at: None,
+            refresh_failure_count: 0,
         },
         |old_profile| old_profile,
     );
@@ -68,3 +76,213 @@ pub async fn update(
     .fetch_one(pool)
     .await
 }
+
+/// Get profiles of people going to upcoming events whose games haven't been refreshed since
+/// `refreshed_before`, skipping any that failed since `failed_before`. Oldest first.
+pub async fn due_for_refresh(
+    pool: &PgPool,
+    refreshed_before: DateTime<Utc>,
+    failed_before: DateTime<Utc>,
+    limit: i64,
+) -> Result<Vec<Profile>, sqlx::Error> {
+    sqlx::query_as!(
+        Profile,
+        r#"
+        SELECT
+            profiles.email,
+            profiles.steam_id,
+            profiles.last_refreshed,
+            profiles.refresh_error,
+            profiles.refresh_failed_at,
+            profiles.refresh_failure_count
+        FROM profiles
+        WHERE profiles.steam_id <> 0
+        AND (profiles.last_refreshed IS NULL OR profiles.last_refreshed < $1)
+        AND (profiles.refresh_failed_at IS NULL OR profiles.refresh_failed_at < $2)
+        AND EXISTS (
+            SELECT 1
+            FROM invitation
+            INNER JOIN event ON event.id = invitation.event_id
+            WHERE LOWER(invitation.email) = LOWER(profiles.email)
+            AND invitation.response IN ('yes', 'maybe')
+            AND event.time_end > NOW()
+        )
+        ORDER BY profiles.last_refreshed ASC NULLS FIRST
+        LIMIT $3
+        "#,
+        refreshed_before,
+        failed_before,
+        limit,
+    )
+    .fetch_all(pool)
+    .await
+}
+
+/// Record that the user's games were refreshed, clearing any earlier failure
+pub async fn record_refresh(pool: &PgPool, email: String) -> Result<(), sqlx::Error> {
+    sqlx::query!(
+        r#"
+        UPDATE profiles
+        SET
+            last_refreshed = NOW(),
+            refresh_error = NULL,
+            refresh_failed_at = NULL,
+            refresh_failure_count = 0
+        WHERE LOWER(email) = LOWER($1)
+        "#,
+        email,
+    )
+    .execute(pool)
+    .await?;
+
+    Ok(())
+}
+
+/// Record why refreshing the user's games failed
+pub async fn record_refresh_failure(
+    pool: &PgPool,
+    email: String,
+    error: String,
+) -> Result<(), sqlx::Error> {
+    sqlx::query!(
+        r#"
+        UPDATE profiles
+        SET
+            refresh_error = $2,
+            refresh_failed_at = NOW(),
+            refresh_failure_count = refresh_failure_count + 1
+        WHERE LOWER(email) = LOWER($1)
+        "#,
+        email,
+        error,
+    )
+    .execute(pool)
+    .await?;
+
+    Ok(())
+}
+
+/// These run against a fresh database with the migrations applied, so they need
+/// `DATABASE_URL` to point at a Postgres server: `cargo test -- --ignored`
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use chrono::Duration;
+
+    async fn add_profile(
+        pool: &PgPool,
+        email: &str,
+        last_refreshed: Option<DateTime<Utc>>,
+        refresh_failed_at: Option<DateTime<Utc>>,
+    ) -> Result<(), sqlx::Error> {
+        sqlx::query(
+            "INSERT INTO profiles (email, steam_id, last_refreshed, refresh_failed_at) VALUES ($1, 76561197990048341, $2, $3)",
+        )
+        .bind(email)
+        .bind(last_refreshed)
+        .bind(refresh_failed_at)
+        .execute(pool)
+        .await?;
+        Ok(())
+    }
+
+    async fn add_event(pool: &PgPool, time_end: DateTime<Utc>) -> Result<i32, sqlx::Error> {
+        sqlx::query_scalar(
+            "INSERT INTO event (title, description, time_begin, time_end) VALUES ('LAN', '', $1, $1) RETURNING id",
+        )
+        .bind(time_end)
+        .fetch_one(pool)
+        .await
+    }
+
+    async fn rsvp(
+        pool: &PgPool,
+        event_id: i32,
+        email: &str,
+        response: &str,
+    ) -> Result<(), sqlx::Error> {
+        sqlx::query(
+            "INSERT INTO invitation (event_id, email, response) VALUES ($1, $2, $3::invitation_response)",
+        )
+        .bind(event_id)
+        .bind(email)
+        .bind(response)
+        .execute(pool)
+        .await?;
+        Ok(())
+    }
+
+    #[sqlx::test]
+    #[ignore = "needs a Postgres database in DATABASE_URL"]
+    async fn test_libraries_are_refreshed_in_batches_oldest_first(
+        pool: PgPool,
+    ) -> sqlx::Result<()> {
+        let now = Utc::now();
+        let upcoming = add_event(&pool, now + Duration::days(7)).await?;
+        let finished = add_event(&pool, now - Duration::days(7)).await?;
+
+        add_profile(&pool, "never@example.com", None, None).await?;
+        add_profile(
+            &pool,
+            "oldest@example.com",
+            Some(now - Duration::days(3)),
+            None,
+        )
+        .await?;
+        add_profile(
+            &pool,
+            "older@example.com",
+            Some(now - Duration::days(2)),
+            None,
+        )
+        .await?;
+        add_profile(
+            &pool,
+            "fresh@example.com",
+            Some(now - Duration::hours(1)),
+            None,
+        )
+        .await?;
+        add_profile(
+            &pool,
+            "failing@example.com",
+            Some(now - Duration::days(3)),
+            Some(now - Duration::hours(1)),
+        )
+        .await?;
+        add_profile(&pool, "declined@example.com", None, None).await?;
+        add_profile(&pool, "finished@example.com", None, None).await?;
+
+        for email in [
+            "never@example.com",
+            "oldest@example.com",
+            "older@example.com",
+            "fresh@example.com",
+            "failing@example.com",
+        ] {
+            rsvp(&pool, upcoming, email, "yes").await?;
+        }
+        rsvp(&pool, upcoming, "declined@example.com", "no").await?;
+        rsvp(&pool, finished, "finished@example.com", "yes").await?;
+
+        let refreshed_before = now - Duration::hours(24);
+        let failed_before = now - Duration::hours(6);
+
+        let batch = due_for_refresh(&pool, refreshed_before, failed_before, 2).await?;
+        let emails: Vec<&str> = batch.iter().map(|p| p.email.as_str()).collect();
+        assert_eq!(emails, vec!["never@example.com", "oldest@example.com"]);
+
+        let batch = due_for_refresh(&pool, refreshed_before, failed_before, 10).await?;
+        let emails: Vec<&str> = batch.iter().map(|p| p.email.as_str()).collect();
+        assert_eq!(
+            emails,
+            vec![
+                "never@example.com",
+                "oldest@example.com",
+                "older@example.com"
+            ]
+        );
+
+        Ok(())
+    }
+}
@@ -12,8 +12,12 @@ pub struct SteamAPIApp {
 #[derive(Clone, Deserialize)]
 #[serde(crate = "rocket::serde")]
 pub struct SteamAPIAppListResponse {
+    // Steam leaves these out when there are no apps, such as when nothing has changed
+    #[serde(default)]
     pub apps: Vec<SteamAPIApp>,
+    #[serde(default)]
     pub have_more_results: bool,
+    #[serde(default)]
     pub last_appid: i64,
 }
 
@@ -23,38 +27,59 @@ pub struct SteamAPIAppListWrapper {
     pub response: SteamAPIAppListResponse,
 }
 
-/// Fetch all apps from the Steam Store API using pagination
-/// Uses IStoreService/GetAppList/v1 endpoint which supports pagination
+/// Fetch one page of apps from the Steam Store API, starting after `last_appid`
+/// Uses IStoreService/GetAppList/v1 endpoint which supports pagination,
+/// and only returns apps changed since `if_modified_since` when it is set
 #[allow(clippy::too_many_lines)]
-pub async fn get_app_list(steam_api_key: &String) -> Result<Vec<SteamAPIApp>, String> {
-    let mut all_apps = Vec::new();
-    let mut last_appid = 0;
+pub async fn get_app_list_page(
+    steam_api_key: &String,
+    last_appid: i64,
+    if_modified_since: Option<i64>,
+) -> Result<SteamAPIAppListResponse, String> {
     let max_results = 50000; // Maximum allowed by the API
 
-    loop {
-        let request_url = format!(
-            "https://api.steampowered.com/IStoreService/GetAppList/v1/?key={steam_api_key}&max_results={max_results}&last_appid={last_appid}&include_games=true&include_dlc=true&include_software=true&include_videos=false&include_hardware=false"
-        );
+    let mut request_url = format!(
+        "https://api.steampowered.com/IStoreService/GetAppList/v1/?key={steam_api_key}&max_results={max_results}&last_appid={last_appid}&include_games=true&include_dlc=true&include_software=true&include_videos=false&include_hardware=false"
+    );
+    if let Some(if_modified_since) = if_modified_since {
+        request_url.push_str(&format!("&if_modified_since={if_modified_since}"));
+    }
+
+    log::info!(
+        "Requesting games from Steam API using IStoreService/GetAppList (last_appid: {last_appid})"
+    );
+
+    // Retry logic for transient failures
+    let mut attempts = 0;
+    let wrapper = loop {
+        attempts += 1;
 
-        log::info!(
-            "Requesting games from Steam API using IStoreService/GetAppList (last_appid: {last_appid})"
-        );
+        match reqwest::get(&request_url).await {
+            Ok(response) => {
+                let status = response.status();
 
-        // Retry logic for transient failures
-        let mut attempts = 0;
-        let wrapper = loop {
-            attempts += 1;
+                if !status.is_success() {
+                    let error_text = response
+                        .text()
+                        .await
+                        .unwrap_or_else(|_| "Unable to read error response".to_string());
+                    log::error!("Steam API returned status {status}: {error_text}");
 
-            match reqwest::get(&request_url).await {
-                Ok(response) => {
-                    let status = response.status();
+                    if attempts < MAX_RETRIES {
+                        log::warn!("Retrying request (attempt {attempts}/{MAX_RETRIES})");
+                        tokio::time::sleep(tokio::time::Duration::from_secs(2)).await;
+                        continue;
+                    }
+                    return Err(format!(
+                        "Steam API error after {MAX_RETRIES} attempts (status {status}): {error_text}"
+                    ));
+                }
 
-                    if !status.is_success() {
-                        let error_text = response
-                            .text()
-                            .await
-                            .unwrap_or_else(|_| "Unable to read error response".to_string());
-                        log::error!("Steam API returned status {status}: {error_text}");
+                // Get the response body as text first for debugging
+                let response_text = match response.text().await {
+                    Ok(text) => text,
+                    Err(e) => {
+                        log::error!("Failed to read response body: {e}");
 
                         if attempts < MAX_RETRIES {
                             log::warn!("Retrying request (attempt {attempts}/{MAX_RETRIES})");
@@ -62,100 +87,60 @@ pub async fn get_app_list(steam_api_key: &String) -> Result<Vec<SteamAPIApp>, St
                             continue;
                         }
                         return Err(format!(
-                            "Steam API error after {MAX_RETRIES} attempts (status {status}): {error_text}"
+                            "Failed to read response body after {MAX_RETRIES} attempts: {e}"
                         ));
                     }
-
-                    // Get the response body as text first for debugging
-                    let response_text = match response.text().await {
-                        Ok(text) => text,
-                        Err(e) => {
-                            log::error!("Failed to read response body: {e}");
-
-                            if attempts < MAX_RETRIES {
-                                log::warn!("Retrying request (attempt {attempts}/{MAX_RETRIES})");
-                                tokio::time::sleep(tokio::time::Duration::from_secs(2)).await;
-                                continue;
-                            }
-                            return Err(format!(
-                                "Failed to read response body after {MAX_RETRIES} attempts: {e}"
-                            ));
-                        }
-                    };
-
-                    // Try to parse the JSON response
-                    match serde_json::from_str::<SteamAPIAppListWrapper>(&response_text) {
-                        Ok(wrapper) => break wrapper,
-                        Err(e) => {
-                            log::error!("Failed to parse Steam API response: {e}");
-                            log::error!(
-                                "Response body (first 500 chars): {}",
-                                if response_text.len() > 500 {
-                                    &response_text[..500]
-                                } else {
-                                    &response_text
-                                }
-                            );
-
-                            if attempts < MAX_RETRIES {
-                                log::warn!("Retrying request (attempt {attempts}/{MAX_RETRIES})");
-                                tokio::time::sleep(tokio::time::Duration::from_secs(2)).await;
-                                continue;
-                            }
-                            // If we've collected some apps, log a warning and continue with what we have
-                            if !all_apps.is_empty() {
-                                log::warn!(
-                                    "Failed to parse page after {} apps collected. Stopping pagination and returning {} apps.",
-                                    all_apps.len(), all_apps.len()
-                                );
-                                log::info!("Total apps retrieved (partial): {}", all_apps.len());
-                                return Ok(all_apps);
+                };
+
+                // Try to parse the JSON response
+                match serde_json::from_str::<SteamAPIAppListWrapper>(&response_text) {
+                    Ok(wrapper) => break wrapper,
+                    Err(e) => {
+                        log::error!("Failed to parse Steam API response: {e}");
+                        log::error!(
+                            "Response body (first 500 chars): {}",
+                            if response_text.len() > 500 {
+                                &response_text[..500]
+                            } else {
+                                &response_text
                             }
-                            return Err(format!("Failed to parse Steam API response after {} attempts: {}. Response: {}",
-                                MAX_RETRIES, e,
-                                if response_text.len() > 200 {
-                                    &response_text[..200]
-                                } else {
-                                    &response_text
-                                }));
+                        );
+
+                        if attempts < MAX_RETRIES {
+                            log::warn!("Retrying request (attempt {attempts}/{MAX_RETRIES})");
+                            tokio::time::sleep(tokio::time::Duration::from_secs(2)).await;
+                            continue;
                         }
+                        return Err(format!("Failed to parse Steam API response after {} attempts: {}. Response: {}",
+                            MAX_RETRIES, e,
+                            if response_text.len() > 200 {
+                                &response_text[..200]
+                            } else {
+                                &response_text
+                            }));
                     }
                 }
-                Err(e) => {
-                    log::error!("Network error requesting Steam API: {e}");
+            }
+            Err(e) => {
+                log::error!("Network error requesting Steam API: {e}");
 
-                    if attempts < MAX_RETRIES {
-                        log::warn!("Retrying request (attempt {attempts}/{MAX_RETRIES})");
-                        tokio::time::sleep(tokio::time::Duration::from_secs(2)).await;
-                        continue;
-                    }
-                    return Err(format!("Network error after {MAX_RETRIES} attempts: {e}"));
+                if attempts < MAX_RETRIES {
+                    log::warn!("Retrying request (attempt {attempts}/{MAX_RETRIES})");
+                    tokio::time::sleep(tokio::time::Duration::from_secs(2)).await;
+                    continue;
                 }
+                return Err(format!("Network error after {MAX_RETRIES} attempts: {e}"));
             }
-        };
-
-        let app_count = wrapper.response.apps.len();
-        log::info!(
-            "Retrieved {} apps from Steam API (page total: {})",
-            app_count,
-            all_apps.len() + app_count
-        );
-
-        all_apps.extend(wrapper.response.apps);
-
-        if !wrapper.response.have_more_results {
-            log::info!("No more results to fetch from Steam API");
-            break;
         }
+    };
 
-        last_appid = wrapper.response.last_appid;
-
-        // Small delay between requests to avoid rate limiting
-        tokio::time::sleep(tokio::time::Duration::from_millis(500)).await;
-    }
+    log::info!(
+        "Retrieved {} apps from Steam API (more results: {})",
+        wrapper.response.apps.len(),
+        wrapper.response.have_more_results
+    );
 
-    log::info!("Total apps retrieved: {}", all_apps.len());
-    Ok(all_apps)
+    Ok(wrapper.response)
 }
 
 #[derive(Clone, Deserialize)]
@@ -176,8 +161,10 @@ pub struct SteamAPIOwnedGame {
 #[serde(crate = "rocket::serde")]
 #[allow(dead_code)]
 pub struct SteamAPIOwnedGamesListResponse {
-    pub game_count: i64,
-    pub games: Vec<SteamAPIOwnedGame>,
+    /// Missing when the user's game details are private
+    pub game_count: Option<i64>,
+    /// Missing when the user's game details are private, or they have no games
+    pub games: Option<Vec<SteamAPIOwnedGame>>,
 }
 
 #[derive(Clone, Deserialize)]
@@ -200,7 +187,11 @@ pub async fn get_owned_games(
 
     log::info!("Requesting owned games from steam API using url: {request_url}");
 
-    let response = match reqwest::get(&request_url).await {
+    // Fail on error statuses, so callers can tell when Steam is rate limiting them
+    let response = match reqwest::get(&request_url)
+        .await
+        .and_then(reqwest::Response::error_for_status)
+    {
         Ok(response) => response,
         Err(e) => return Err(e),
     };
@@ -273,8 +264,9 @@ pub struct PlayerSummariesResponse {
 //       ]
 //     }
 //   }
-#[derive(Clone, Deserialize)]
-#[serde(crate = "rocket::serde")]
+/// Which fields are returned depends on the player's privacy settings, so anything missing is left empty
+#[derive(Clone, Default, Deserialize)]
+#[serde(crate = "rocket::serde", default)]
 #[allow(dead_code)]
 pub struct PlayerSummary {
     pub steamid: String,
@@ -292,23 +284,154 @@ pub struct PlayerSummary {
     pub primaryclanid: String,
     pub timecreated: i64,
     pub personastateflags: i32,
+    /// The app ID of the game being played, only present while in game
     pub gameid: Option<String>,
+    /// The name of the game being played, which is also set for non-Steam games
+    pub gameextrainfo: Option<String>,
 }
 
-#[allow(dead_code)]
-pub async fn get_current_game(
+/// The most Steam IDs `GetPlayerSummaries` accepts in one request
+pub const MAX_PLAYER_SUMMARIES: usize = 100;
+
+/// Get the summaries of up to `MAX_PLAYER_SUMMARIES` players, including what they're playing.
+/// Players that don't exist are left out.
+pub async fn get_player_summaries(
     steam_api_key: &String,
-    steam_id: &String,
-) -> Result<Option<String>, reqwest::Error> {
+    steam_ids: &[i64],
+) -> Result<Vec<PlayerSummary>, reqwest::Error> {
+    let steam_ids = steam_ids
+        .iter()
+        .map(ToString::to_string)
+        .collect::<Vec<_>>()
+        .join(",");
+
     let request_url = format!(
-        "http://api.steampowered.com/ISteamUser/GetPlayerSummaries/v0002/?key={steam_api_key}&steamids={steam_id}",
+        "https://api.steampowered.com/ISteamUser/GetPlayerSummaries/v0002/?key={steam_api_key}&steamids={steam_ids}",
     );
 
-    log::info!("Requesting current game from steam API using url: {request_url}");
-
-    let response = reqwest::get(&request_url).await?;
+    // Fail on error statuses, so callers can tell when Steam is rate limiting them
+    let response = reqwest::get(&request_url)
+        .await
+        .and_then(reqwest::Response::error_for_status)?;
 
     let player_summaries: PlayerSummaries = response.json().await?;
 
-    Ok(player_summaries.response.players[0].gameid.clone())
+    Ok(player_summaries.response.players)
+}
+
+/// The Steam store country prices are shown in
+const STORE_COUNTRY_CODE: &str = "gb";
+
+/// Details are fetched while a user waits on a game suggestion, so don't wait long for the store
+const STORE_REQUEST_TIMEOUT_SECONDS: u64 = 5;
+
+#[derive(Clone, Deserialize)]
+#[serde(crate = "rocket::serde")]
+pub struct SteamAPIAppDetailsWrapper {
+    pub success: bool,
+    pub data: Option<SteamAPIAppDetails>,
+}
+
+#[derive(Clone, Deserialize)]
+#[serde(crate = "rocket::serde")]
+#[allow(dead_code)]
+pub struct SteamAPIAppDetails {
+    pub steam_appid: i64,
+    pub name: String,
+    /// Such as "game", "dlc", "music" or "demo"
+    #[serde(rename = "type")]
+    pub app_type: Option<String>,
+    /// The game a DLC, demo or soundtrack belongs to
+    pub fullgame: Option<SteamAPIFullGame>,
+    #[serde(default)]
+    pub is_free: bool,
+    pub short_description: Option<String>,
+    pub header_image: Option<String>,
+    pub platforms: Option<SteamAPIPlatforms>,
+    #[serde(default)]
+    pub categories: Vec<SteamAPICategory>,
+    #[serde(default)]
+    pub genres: Vec<SteamAPIGenre>,
+    pub price_overview: Option<SteamAPIPriceOverview>,
+    pub release_date: Option<SteamAPIReleaseDate>,
+}
+
+#[derive(Clone, Deserialize)]
+#[serde(crate = "rocket::serde")]
+#[allow(dead_code)]
+pub struct SteamAPIFullGame {
+    /// The store sends the app ID as a string
+    pub appid: String,
+    pub name: String,
+}
+
+#[derive(Clone, Deserialize)]
+#[serde(crate = "rocket::serde")]
+pub struct SteamAPIPlatforms {
+    pub windows: bool,
+    pub mac: bool,
+    pub linux: bool,
+}
+
+#[derive(Clone, Deserialize)]
+#[serde(crate = "rocket::serde")]
+pub struct SteamAPICategory {
+    pub id: i32,
+    pub description: String,
+}
+
+#[derive(Clone, Deserialize)]
+#[serde(crate = "rocket::serde")]
+#[allow(dead_code)]
+pub struct SteamAPIGenre {
+    pub id: String,
+    pub description: String,
+}
+
+#[derive(Clone, Deserialize)]
+#[serde(crate = "rocket::serde")]
+#[allow(dead_code)]
+pub struct SteamAPIPriceOverview {
+    pub currency: String,
+    pub initial: i32,
+    #[serde(rename = "final")]
+    pub final_price: i32,
+    pub discount_percent: i32,
+    pub final_formatted: String,
+}
+
+#[derive(Clone, Deserialize)]
+#[serde(crate = "rocket::serde")]
+pub struct SteamAPIReleaseDate {
+    pub coming_soon: bool,
+    pub date: String,
+}
+
+/// Fetch the store page details of an app, or None if the store has none, such as for delisted apps
+/// Uses the undocumented store appdetails endpoint, which is limited to around 200 requests per 5 minutes
+pub async fn get_app_details(appid: i64) -> Result<Option<SteamAPIAppDetails>, reqwest::Error> {
+    let request_url = format!(
+        "https://store.steampowered.com/api/appdetails?appids={appid}&cc={STORE_COUNTRY_CODE}&l=english"
+    );
+
+    log::info!("Requesting app details from Steam store using url: {request_url}");
+
+    let response = reqwest::Client::builder()
+        .timeout(std::time::Duration::from_secs(
+            STORE_REQUEST_TIMEOUT_SECONDS,
+        ))
+        .build()?
+        .get(&request_url)
+        .send()
+        .await?
+        .error_for_status()?;
+
+    // The response is keyed by app ID, as the endpoint used to accept several
+    let mut app_details: std::collections::HashMap<String, SteamAPIAppDetailsWrapper> =
+        response.json().await?;
+
+    Ok(app_details
+        .remove(&appid.to_string())
+        .filter(|wrapper| wrapper.success)
+        .and_then(|wrapper| wrapper.data))
 }
@@ -0,0 +1,160 @@
+use chrono::{DateTime, Utc};
+use sqlx::PgPool;
+
+/// Details of a game from the Steam store, cached as they're slow and rate limited to fetch
+#[derive(Clone, Default)]
+pub struct SteamGameDetails {
+    pub appid: i64,
+    /// False if the Steam store has no details for the app, such as for delisted games
+    pub is_available: bool,
+    pub header_image: Option<String>,
+    pub short_description: Option<String>,
+    pub category_ids: Vec<i32>,
+    pub categories: Vec<String>,
+    pub genres: Vec<String>,
+    pub is_windows: bool,
+    pub is_mac: bool,
+    pub is_linux: bool,
+    pub is_free: bool,
+    pub price_currency: Option<String>,
+    pub price_final: Option<i32>,
+    pub price_formatted: Option<String>,
+    pub release_date: Option<String>,
+    pub is_coming_soon: bool,
+    pub fetched_at: DateTime<Utc>,
+    /// What kind of app the store says it is, such as "game", "dlc" or "music"
+    pub app_type: Option<String>,
+    /// The game a DLC, demo or soundtrack belongs to
+    pub fullgame_appid: Option<i64>,
+}
+
+pub async fn get(pool: &PgPool, appid: i64) -> Result<Option<SteamGameDetails>, sqlx::Error> {
+    Ok(filter(pool, &[appid]).await?.into_iter().next())
+}
+
+pub async fn filter(pool: &PgPool, appids: &[i64]) -> Result<Vec<SteamGameDetails>, sqlx::Error> {
+    sqlx::query_as!(
+        SteamGameDetails,
+        r#"
+        SELECT
+            appid,
+            is_available,
+            header_image,
+            short_description,
+            category_ids,
+            categories,
+            genres,
+            is_windows,
+            is_mac,
+            is_linux,
+            is_free,
+            price_currency,
+            price_final,
+            price_formatted,
+            release_date,
+            is_coming_soon,
+            fetched_at,
+            app_type,
+            fullgame_appid
+        FROM steam_game_details
+        WHERE appid = ANY($1)
+        "#,
+        appids,
+    )
+    .fetch_all(pool)
+    .await
+}
+
+/// Save freshly fetched details, replacing any that were cached
+pub async fn upsert(
+    pool: &PgPool,
+    details: SteamGameDetails,
+) -> Result<SteamGameDetails, sqlx::Error> {
+    sqlx::query_as!(
+        SteamGameDetails,
+        r#"
+        INSERT INTO steam_game_details (
+            appid,
+            is_available,
+            header_image,
+            short_description,
+            category_ids,
+            categories,
+            genres,
+            is_windows,
+            is_mac,
+            is_linux,
+            is_free,
+            price_currency,
+            price_final,
+            price_formatted,
+            release_date,
+            is_coming_soon,
+            fetched_at,
+            app_type,
+            fullgame_appid
+        )
+        VALUES ($1, $2, $3, $4, $5, $6, $7, $8, $9, $10, $11, $12, $13, $14, $15, $16, NOW(), $17, $18)
+        ON CONFLICT (appid)
+        DO UPDATE SET
+            is_available = $2,
+            header_image = $3,
+            short_description = $4,
+            category_ids = $5,
+            categories = $6,
+            genres = $7,
+            is_windows = $8,
+            is_mac = $9,
+            is_linux = $10,
+            is_free = $11,
+            price_currency = $12,
+            price_final = $13,
+            price_formatted = $14,
+            release_date = $15,
+            is_coming_soon = $16,
+            fetched_at = NOW(),
+            app_type = $17,
+            fullgame_appid = $18
+        RETURNING
+            appid,
+            is_available,
+            header_image,
+            short_description,
+            category_ids,
+            categories,
+            genres,
+            is_windows,
+            is_mac,
+            is_linux,
+            is_free,
+            price_currency,
+            price_final,
+            price_formatted,
+            release_date,
+            is_coming_soon,
+            fetched_at,
+            app_type,
+            fullgame_appid
+        "#,
+        details.appid,
+        details.is_available,
+        details.header_image,
+        details.short_description,
+        &details.category_ids,
+        &details.categories,
+        &details.genres,
+        details.is_windows,
+        details.is_mac,
+        details.is_linux,
+        details.is_free,
+        details.price_currency,
+        details.price_final,
+        details.price_formatted,
+        details.release_date,
+        details.is_coming_soon,
+        details.app_type,
+        details.fullgame_appid,
+    )
+    .fetch_one(pool)
+    .await
+}
@@ -0,0 +1,55 @@
+use chrono::{DateTime, Utc};
+use sqlx::PgPool;
+
+/// How long a game usually takes, set by an admin
+#[derive(Clone)]
+pub struct SteamGameDuration {
+    pub appid: i64,
+    pub duration_minutes: i32,
+    pub modified_by: Option<String>,
+    pub last_modified: DateTime<Utc>,
+}
+
+pub async fn upsert(
+    pool: &PgPool,
+    appid: i64,
+    duration_minutes: i32,
+    modified_by: String,
+) -> Result<SteamGameDuration, sqlx::Error> {
+    sqlx::query_as!(
+        SteamGameDuration,
+        r#"
+        INSERT INTO steam_game_duration (appid, duration_minutes, modified_by)
+        VALUES ($1, $2, $3)
+        ON CONFLICT (appid)
+        DO UPDATE SET
+            duration_minutes = $2,
+            modified_by = $3,
+            last_modified = NOW()
+        RETURNING
+            appid,
+            duration_minutes,
+            modified_by,
+            last_modified
+        "#,
+        appid,
+        duration_minutes,
+        modified_by,
+    )
+    .fetch_one(pool)
+    .await
+}
+
+pub async fn delete(pool: &PgPool, appid: i64) -> Result<(), sqlx::Error> {
+    sqlx::query!(
+        r#"
+        DELETE FROM steam_game_duration
+        WHERE appid = $1
+        "#,
+        appid,
+    )
+    .execute(pool)
+    .await?;
+
+    Ok(())
+}
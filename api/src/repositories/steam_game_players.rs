@@ -0,0 +1,124 @@
+use chrono::{DateTime, Utc};
+use sqlx::PgPool;
+
+/// How many people can play a game together, and how
+#[derive(Clone)]
+pub struct SteamGamePlayers {
+    pub appid: i64,
+    pub min_players: Option<i32>,
+    pub max_players: Option<i32>,
+    pub has_lan: bool,
+    pub has_split_screen: bool,
+    pub is_manual: bool,
+    pub modified_by: Option<String>,
+    pub last_modified: DateTime<Utc>,
+}
+
+pub async fn filter(pool: &PgPool, appids: &[i64]) -> Result<Vec<SteamGamePlayers>, sqlx::Error> {
+    sqlx::query_as!(
+        SteamGamePlayers,
+        r#"
+        SELECT
+            appid,
+            min_players,
+            max_players,
+            has_lan,
+            has_split_screen,
+            is_manual,
+            modified_by,
+            last_modified
+        FROM steam_game_players
+        WHERE appid = ANY($1)
+        "#,
+        appids,
+    )
+    .fetch_all(pool)
+    .await
+}
+
+/// Save player counts worked out from the Steam store, unless an admin has already set them
+pub async fn seed(
+    pool: &PgPool,
+    appid: i64,
+    min_players: Option<i32>,
+    max_players: Option<i32>,
+    has_lan: bool,
+    has_split_screen: bool,
+) -> Result<(), sqlx::Error> {
+    sqlx::query!(
+        r#"
+        INSERT INTO steam_game_players (appid, min_players, max_players, has_lan, has_split_screen)
+        VALUES ($1, $2, $3, $4, $5)
+        ON CONFLICT (appid)
+        DO UPDATE SET
+            min_players = $2,
+            max_players = $3,
+            has_lan = $4,
+            has_split_screen = $5,
+            last_modified = NOW()
+        WHERE NOT steam_game_players.is_manual
+        "#,
+        appid,
+        min_players,
+        max_players,
+        has_lan,
+        has_split_screen,
+    )
+    .execute(pool)
+    .await?;
+
+    Ok(())
+}
+
+/// Save player counts set by an admin
+pub async fn upsert(
+    executor: impl sqlx::PgExecutor<'_>,
+    appid: i64,
+    min_players: Option<i32>,
+    max_players: Option<i32>,
+    has_lan: bool,
+    has_split_screen: bool,
+    modified_by: String,
+) -> Result<SteamGamePlayers, sqlx::Error> {
+    sqlx::query_as!(
+        SteamGamePlayers,
+        r#"
+        INSERT INTO steam_game_players (
+            appid,
+            min_players,
+            max_players,
+            has_lan,
+            has_split_screen,
+            is_manual,
+            modified_by
+        )
+        VALUES ($1, $2, $3, $4, $5, true, $6)
+        ON CONFLICT (appid)
+        DO UPDATE SET
+            min_players = $2,
+            max_players = $3,
+            has_lan = $4,
+            has_split_screen = $5,
+            is_manual = true,
+            modified_by = $6,
+            last_modified = NOW()
+        RETURNING
+            appid,
+            min_players,
+            max_players,
+            has_lan,
+            has_split_screen,
+            is_manual,
+            modified_by,
+            last_modified
+        "#,
+        appid,
+        min_players,
+        max_players,
+        has_lan,
+        has_split_screen,
+        modified_by,
+    )
+    .fetch_one(executor)
+    .await
+}
@@ -0,0 +1,159 @@
+use chrono::{DateTime, Utc};
+use sqlx::PgPool;
+
+/// Someone with a Steam ID going to an event that is happening now
+#[derive(Clone)]
+pub struct RunningEventAttendee {
+    pub event_id: i32,
+    pub email: String,
+    pub steam_id: i64,
+}
+
+/// What someone was last seen playing on Steam, if anything
+#[derive(Clone)]
+pub struct SteamPresence {
+    pub email: String,
+    pub steam_id: i64,
+    pub game_id: Option<i64>,
+    pub game_name: Option<String>,
+    pub started_at: Option<DateTime<Utc>>,
+    pub last_seen: DateTime<Utc>,
+}
+
+/// Someone at an event playing a game
+#[derive(Clone)]
+pub struct EventPresence {
+    pub email: String,
+    pub avatar_url: Option<String>,
+    pub handle: Option<String>,
+    pub game_id: Option<i64>,
+    pub game_name: Option<String>,
+    pub started_at: Option<DateTime<Utc>>,
+}
+
+/// Get everyone with a Steam ID who has said yes or maybe to an event that is happening now
+pub async fn running_event_attendees(
+    pool: &PgPool,
+) -> Result<Vec<RunningEventAttendee>, sqlx::Error> {
+    sqlx::query_as!(
+        RunningEventAttendee,
+        r#"
+        SELECT
+            invitation.event_id,
+            profiles.email,
+            profiles.steam_id
+        FROM invitation
+        INNER JOIN event ON event.id = invitation.event_id
+        INNER JOIN profiles ON LOWER(profiles.email) = LOWER(invitation.email)
+        WHERE invitation.response IN ('yes', 'maybe')
+        AND event.time_begin <= NOW()
+        AND event.time_end > NOW()
+        AND profiles.steam_id <> 0
+        ORDER BY invitation.event_id, profiles.email
+        "#,
+    )
+    .fetch_all(pool)
+    .await
+}
+
+/// Get what people were last seen playing, by their lowercase emails
+pub async fn filter(pool: &PgPool, emails: &[String]) -> Result<Vec<SteamPresence>, sqlx::Error> {
+    sqlx::query_as!(
+        SteamPresence,
+        r#"
+        SELECT
+            email,
+            steam_id,
+            game_id,
+            game_name,
+            started_at,
+            last_seen
+        FROM steam_presence
+        WHERE LOWER(email) = ANY($1)
+        "#,
+        emails,
+    )
+    .fetch_all(pool)
+    .await
+}
+
+/// Record what someone is playing, keeping when they started if it's the same game as before
+pub async fn upsert(
+    pool: &PgPool,
+    email: String,
+    steam_id: i64,
+    game_id: Option<i64>,
+    game_name: Option<String>,
+) -> Result<SteamPresence, sqlx::Error> {
+    sqlx::query_as!(
+        SteamPresence,
+        r#"
+        INSERT INTO steam_presence (email, steam_id, game_id, game_name, started_at)
+        VALUES (
+            $1,
+            $2,
+            $3,
+            $4,
+            CASE WHEN $3::BIGINT IS NULL AND $4::TEXT IS NULL THEN NULL ELSE NOW() END
+        )
+        ON CONFLICT (LOWER(email))
+        DO UPDATE SET
+            steam_id = $2,
+            game_id = $3,
+            game_name = $4,
+            started_at = CASE
+                WHEN $3::BIGINT IS NULL AND $4::TEXT IS NULL THEN NULL
+                WHEN steam_presence.game_id IS NOT DISTINCT FROM $3
+                    AND steam_presence.game_name IS NOT DISTINCT FROM $4
+                    THEN steam_presence.started_at
+                ELSE NOW()
+            END,
+            last_seen = NOW()
+        RETURNING
+            email,
+            steam_id,
+            game_id,
+            game_name,
+            started_at,
+            last_seen
+        "#,
+        email,
+        steam_id,
+        game_id,
+        game_name,
+    )
+    .fetch_one(pool)
+    .await
+}
+
+/// Get who is playing something at an event, leaving out anyone not seen since `seen_since`
+pub async fn filter_playing_at_event(
+    pool: &PgPool,
+    event_id: i32,
+    seen_since: DateTime<Utc>,
+) -> Result<Vec<EventPresence>, sqlx::Error> {
+    sqlx::query_as!(
+        EventPresence,
+        r#"
+        SELECT
+            steam_presence.email,
+            'https://www.gravatar.com/avatar/' || MD5(LOWER(steam_presence.email)) || '?d=robohash' AS avatar_url,
+            invitation.handle,
+            steam_presence.game_id,
+            COALESCE(steam_presence.game_name, steam_game.name) AS game_name,
+            steam_presence.started_at
+        FROM steam_presence
+        INNER JOIN invitation ON LOWER(invitation.email) = LOWER(steam_presence.email)
+        LEFT JOIN steam_game ON steam_game.appid = steam_presence.game_id
+        WHERE invitation.event_id = $1
+        AND invitation.response IN ('yes', 'maybe')
+        AND steam_presence.last_seen >= $2
+        AND (steam_presence.game_id IS NOT NULL OR steam_presence.game_name IS NOT NULL)
+        ORDER BY steam_presence.started_at ASC
+        "#,
+        event_id,
+        seen_since,
+    )
+    .fetch_all(pool)
+    .await
+}
@@ -1,5 +1,5 @@
 use chrono::{DateTime, Utc};
-use sqlx::{postgres::PgQueryResult, PgPool};
+use sqlx::PgPool;
 
 pub struct UserGame {
     pub emails: Option<Vec<String>>,
@@ -17,23 +17,54 @@ pub struct Filter {
     pub page: i64,
 }
 
-pub async fn create(
+pub struct SyncCounts {
+    pub upserted: i64,
+    pub removed: i64,
+}
+
+/// Replace a user's games with the ones now in their Steam library,
+/// removing any they no longer own
+pub async fn sync(
     pool: &PgPool,
     email: String,
-    appid: i64,
-    playtime_forever: i32,
-) -> Result<PgQueryResult, sqlx::Error> {
-    sqlx::query!(
+    appids: &[i64],
+    playtimes: &[i32],
+    playtimes_2weeks: &[i32],
+) -> Result<SyncCounts, sqlx::Error> {
+    sqlx::query_as!(
+        SyncCounts,
         r#"
-        INSERT INTO user_game (email, appid, playtime_forever, last_modified)
-        VALUES ($1, $2, $3, NOW())
-        ON CONFLICT (LOWER(email), appid) DO UPDATE SET playtime_forever = $3, last_modified = NOW()
+        WITH incoming AS (
+            SELECT DISTINCT ON (appid) appid, playtime_forever, playtime_2weeks
+            FROM UNNEST($2::BIGINT[], $3::INT[], $4::INT[]) AS t(appid, playtime_forever, playtime_2weeks)
+            ORDER BY appid
+        ),
+        upserted AS (
+            INSERT INTO user_game (email, appid, playtime_forever, playtime_2weeks, last_modified)
+            SELECT $1, appid, playtime_forever, playtime_2weeks, NOW()
+            FROM incoming
+            ON CONFLICT (LOWER(email), appid) DO UPDATE SET
+                playtime_forever = EXCLUDED.playtime_forever,
+                playtime_2weeks = EXCLUDED.playtime_2weeks,
+                last_modified = NOW()
+            RETURNING appid
+        ),
+        removed AS (
+            DELETE FROM user_game
+            WHERE LOWER(email) = LOWER($1)
+            AND appid NOT IN (SELECT appid FROM incoming)
+            RETURNING appid
+        )
+        SELECT
+            (SELECT COUNT(*) FROM upserted) AS "upserted!",
+            (SELECT COUNT(*) FROM removed) AS "removed!"
         "#,
         email,
-        appid,
-        playtime_forever,
+        appids,
+        playtimes,
+        playtimes_2weeks,
     )
-    .execute(pool)
+    .fetch_one(pool)
     .await
 }
 
@@ -109,3 +140,124 @@ pub async fn count(pool: &PgPool, filter: Filter) -> Result<Option<i64>, sqlx::E
     .fetch_one(pool)
     .await
 }
+
+/// Get which of these emails have any games from Steam, in lowercase.
+/// Anyone else hasn't linked Steam, or has a private library.
+pub async fn emails_with_games(
+    pool: &PgPool,
+    emails: &[String],
+) -> Result<Vec<String>, sqlx::Error> {
+    let emails: Vec<String> = emails.iter().map(|s| s.to_lowercase()).collect();
+
+    sqlx::query_scalar!(
+        r#"
+        SELECT DISTINCT LOWER(email) AS "email!"
+        FROM user_game
+        WHERE LOWER(email) = ANY($1)
+        "#,
+        &emails[..],
+    )
+    .fetch_all(pool)
+    .await
+}
+
+/// How much a group of people own and play a game
+pub struct GroupGame {
+    pub appid: i64,
+    pub name: String,
+    pub owner_count: i64,
+    pub playtime_forever: i64,
+    pub playtime_2weeks: i64,
+}
+
+/// Get the games most owned by a group of people, with their combined playtime
+pub async fn owned_by_group(
+    pool: &PgPool,
+    emails: &[String],
+    limit: i64,
+) -> Result<Vec<GroupGame>, sqlx::Error> {
+    let emails: Vec<String> = emails.iter().map(|s| s.to_lowercase()).collect();
+
+    sqlx::query_as!(
+        GroupGame,
+        r#"
+        SELECT
+            appid,
+            name,
+            COUNT(DISTINCT LOWER(email)) AS "owner_count!",
+            SUM(playtime_forever)::BIGINT AS "playtime_forever!",
+            SUM(playtime_2weeks)::BIGINT AS "playtime_2weeks!"
+        FROM user_game
+        INNER JOIN steam_game USING(appid)
+        WHERE LOWER(email) = ANY($1)
+        AND steam_game.removed_at IS NULL
+        GROUP BY
+            appid,
+            name
+        ORDER BY
+            3 DESC,
+            5 DESC,
+            4 DESC
+        LIMIT $2
+        "#,
+        &emails[..],
+        limit,
+    )
+    .fetch_all(pool)
+    .await
+}
+
+/// These run against a fresh database with the migrations applied, so they need
+/// `DATABASE_URL` to point at a Postgres server: `cargo test -- --ignored`
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    async fn owned_appids(pool: &PgPool, email: &str) -> Result<Vec<i64>, sqlx::Error> {
+        sqlx::query_scalar(
+            "SELECT appid FROM user_game WHERE LOWER(email) = LOWER($1) ORDER BY appid",
+        )
+        .bind(email)
+        .fetch_all(pool)
+        .await
+    }
+
+    #[sqlx::test]
+    #[ignore = "needs a Postgres database in DATABASE_URL"]
+    async fn test_games_no_longer_owned_are_removed(pool: PgPool) -> sqlx::Result<()> {
+        let email = "gamer@example.com".to_string();
+
+        let counts = sync(&pool, email.clone(), &[10, 20, 30], &[60, 0, 5], &[0, 0, 5]).await?;
+        assert_eq!((counts.upserted, counts.removed), (3, 0));
+
+        // Someone else's games are left alone
+        sync(&pool, "other@example.com".to_string(), &[20], &[1], &[0]).await?;
+
+        let counts = sync(
+            &pool,
+            "Gamer@Example.com".to_string(),
+            &[10, 40],
+            &[90, 1],
+            &[30, 1],
+        )
+        .await?;
+        assert_eq!((counts.upserted, counts.removed), (2, 2));
+        assert_eq!(owned_appids(&pool, &email).await?, vec![10, 40]);
+        assert_eq!(owned_appids(&pool, "other@example.com").await?, vec![20]);
+
+        Ok(())
+    }
+
+    #[sqlx::test]
+    #[ignore = "needs a Postgres database in DATABASE_URL"]
+    async fn test_an_empty_library_removes_every_game(pool: PgPool) -> sqlx::Result<()> {
+        let email = "gamer@example.com".to_string();
+        sync(&pool, email.clone(), &[10, 20], &[1, 2], &[0, 0]).await?;
+
+        let counts = sync(&pool, email.clone(), &[], &[], &[]).await?;
+        assert_eq!((counts.upserted, counts.removed), (0, 2));
+        assert!(owned_appids(&pool, &email).await?.is_empty());
+
+        Ok(())
+    }
+}
@@ -0,0 +1,164 @@
+use chrono::{DateTime, Utc};
+use sqlx::PgPool;
+
+#[derive(Clone)]
+pub struct Webhook {
+    pub id: i32,
+    pub url: String,
+    pub secret: String,
+    pub event_types: Vec<String>,
+    pub is_active: bool,
+    pub created_at: DateTime<Utc>,
+    pub last_modified: DateTime<Utc>,
+}
+
+pub async fn index(pool: &PgPool) -> Result<Vec<Webhook>, sqlx::Error> {
+    sqlx::query_as!(
+        Webhook,
+        r#"
+        SELECT
+            id,
+            url,
+            secret,
+            event_types,
+            is_active,
+            created_at,
+            last_modified
+        FROM webhook
+        ORDER BY id
+        "#
+    )
+    .fetch_all(pool)
+    .await
+}
+
+/// Get the webhooks that are switched on, for fanning out new events
+pub async fn index_active(pool: &PgPool) -> Result<Vec<Webhook>, sqlx::Error> {
+    sqlx::query_as!(
+        Webhook,
+        r#"
+        SELECT
+            id,
+            url,
+            secret,
+            event_types,
+            is_active,
+            created_at,
+            last_modified
+        FROM webhook
+        WHERE is_active
+        ORDER BY id
+        "#
+    )
+    .fetch_all(pool)
+    .await
+}
+
+pub async fn get(pool: &PgPool, webhook_id: i32) -> Result<Option<Webhook>, sqlx::Error> {
+    sqlx::query_as!(
+        Webhook,
+        r#"
+        SELECT
+            id,
+            url,
+            secret,
+            event_types,
+            is_active,
+            created_at,
+            last_modified
+        FROM webhook
+        WHERE id = $1
+        "#,
+        webhook_id
+    )
+    .fetch_optional(pool)
+    .await
+}
+
+pub async fn create(
+    pool: &PgPool,
+    url: String,
+    secret: String,
+    event_types: Vec<String>,
+    is_active: bool,
+) -> Result<Webhook, sqlx::Error> {
+    sqlx::query_as!(
+        Webhook,
+        r#"
+        INSERT INTO webhook (
+            url,
+            secret,
+            event_types,
+            is_active
+        )
+        VALUES ($1, $2, $3, $4)
+        RETURNING
+            id,
+            url,
+            secret,
+            event_types,
+            is_active,
+            created_at,
+            last_modified
+        "#,
+        url,
+        secret,
+        &event_types,
+        is_active,
+    )
+    .fetch_one(pool)
+    .await
+}
+
+/// Update a webhook, keeping the existing secret if a new one isn't given
+pub async fn update(
+    pool: &PgPool,
+    webhook_id: i32,
+    url: String,
+    secret: Option<String>,
+    event_types: Vec<String>,
+    is_active: bool,
+) -> Result<Option<Webhook>, sqlx::Error> {
+    sqlx::query_as!(
+        Webhook,
+        r#"
+        UPDATE webhook
+        SET
+            url = $2,
+            secret = COALESCE($3, secret),
+            event_types = $4,
+            is_active = $5,
+            last_modified = NOW()
+        WHERE id = $1
+        RETURNING
+            id,
+            url,
+            secret,
+            event_types,
+            is_active,
+            created_at,
+            last_modified
+        "#,
+        webhook_id,
+        url,
+        secret,
+        &event_types,
+        is_active,
+    )
+    .fetch_optional(pool)
+    .await
+}
+
+pub async fn delete(pool: &PgPool, webhook_id: i32) -> Result<(), sqlx::Error> {
+    sqlx::query!(
+        r#"
+        DELETE FROM webhook
+        WHERE id = $1
+        "#,
+        webhook_id
+    )
+    .execute(pool)
+    .await?;
+
+    Ok(())
+}
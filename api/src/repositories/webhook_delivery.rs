@@ -0,0 +1,183 @@
+use chrono::{DateTime, Utc};
+use rocket::serde::json::serde_json::Value as JsonValue;
+use sqlx::PgPool;
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq, sqlx::Type)]
+#[sqlx(type_name = "webhook_delivery_status", rename_all = "lowercase")]
+pub enum DeliveryStatus {
+    Pending,
+    Succeeded,
+    Failed,
+}
+
+#[derive(Clone)]
+pub struct WebhookDelivery {
+    pub id: i64,
+    pub webhook_id: i32,
+    pub event_type: String,
+    pub payload: JsonValue,
+    pub status: DeliveryStatus,
+    pub attempts: i32,
+    pub next_attempt_at: DateTime<Utc>,
+    pub response_status: Option<i32>,
+    pub error: Option<String>,
+    pub created_at: DateTime<Utc>,
+    pub last_modified: DateTime<Utc>,
+}
+
+/// A delivery that has been claimed for sending, along with where to send it
+pub struct DueDelivery {
+    pub id: i64,
+    pub webhook_id: i32,
+    pub event_type: String,
+    pub payload: JsonValue,
+    pub attempts: i32,
+    pub url: String,
+    pub secret: String,
+}
+
+/// Queue the same payload for delivery to several webhooks
+pub async fn create_many(
+    pool: &PgPool,
+    webhook_ids: &[i32],
+    event_type: &str,
+    payload: JsonValue,
+) -> Result<Vec<WebhookDelivery>, sqlx::Error> {
+    sqlx::query_as!(
+        WebhookDelivery,
+        r#"
+        INSERT INTO webhook_delivery (webhook_id, event_type, payload)
+        SELECT webhook_id, $2, $3
+        FROM UNNEST($1::INTEGER[]) AS webhook_id
+        RETURNING
+            id,
+            webhook_id,
+            event_type,
+            payload,
+            status AS "status: _",
+            attempts,
+            next_attempt_at,
+            response_status,
+            error,
+            created_at,
+            last_modified
+        "#,
+        webhook_ids,
+        event_type,
+        payload,
+    )
+    .fetch_all(pool)
+    .await
+}
+
+/// Get the delivery log for a webhook, newest first
+pub async fn filter(
+    pool: &PgPool,
+    webhook_id: i32,
+    limit: i64,
+    offset: i64,
+) -> Result<Vec<WebhookDelivery>, sqlx::Error> {
+    sqlx::query_as!(
+        WebhookDelivery,
+        r#"
+        SELECT
+            id,
+            webhook_id,
+            event_type,
+            payload,
+            status AS "status: _",
+            attempts,
+            next_attempt_at,
+            response_status,
+            error,
+            created_at,
+            last_modified
+        FROM webhook_delivery
+        WHERE webhook_id = $1
+        ORDER BY created_at DESC, id DESC
+        LIMIT $2 OFFSET $3
+        "#,
+        webhook_id,
+        limit,
+        offset,
+    )
+    .fetch_all(pool)
+    .await
+}
+
+/// Claim pending deliveries that are due by pushing their next attempt back by `lease_seconds`.
+/// Rows being claimed by another instance are skipped, so each attempt only happens once.
+pub async fn claim_due(
+    pool: &PgPool,
+    limit: i64,
+    lease_seconds: f64,
+) -> Result<Vec<DueDelivery>, sqlx::Error> {
+    sqlx::query_as!(
+        DueDelivery,
+        r#"
+        WITH claimed AS (
+            UPDATE webhook_delivery
+            SET next_attempt_at = NOW() + make_interval(secs => $2), last_modified = NOW()
+            WHERE id IN (
+                SELECT id
+                FROM webhook_delivery
+                WHERE status = 'pending'
+                AND next_attempt_at <= NOW()
+                ORDER BY next_attempt_at
+                LIMIT $1
+                FOR UPDATE SKIP LOCKED
+            )
+            RETURNING id, webhook_id, event_type, payload, attempts
+        ) SELECT
+            claimed.id,
+            claimed.webhook_id,
+            claimed.event_type,
+            claimed.payload,
+            claimed.attempts,
+            webhook.url,
+            webhook.secret
+        FROM claimed
+        INNER JOIN webhook ON claimed.webhook_id = webhook.id
+        ORDER BY claimed.id
+        "#,
+        limit,
+        lease_seconds,
+    )
+    .fetch_all(pool)
+    .await
+}
+
+/// Record the outcome of a delivery attempt
+pub async fn record_attempt(
+    pool: &PgPool,
+    delivery_id: i64,
+    status: DeliveryStatus,
+    attempts: i32,
+    next_attempt_at: DateTime<Utc>,
+    response_status: Option<i32>,
+    error: Option<String>,
+) -> Result<(), sqlx::Error> {
+    sqlx::query!(
+        r#"
+        UPDATE webhook_delivery
+        SET
+            status = $2,
+            attempts = $3,
+            next_attempt_at = $4,
+            response_status = $5,
+            error = $6,
+            last_modified = NOW()
+        WHERE id = $1
+        "#,
+        delivery_id,
+        status as _,
+        attempts,
+        next_attempt_at,
+        response_status,
+        error,
+    )
+    .execute(pool)
+    .await?;
+
+    Ok(())
+}
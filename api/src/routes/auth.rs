@@ -46,6 +46,8 @@ pub async fn login(
         address: login_request.email.to_string(),
         subject: "Calandar Email Verification".to_string(),
         template: "email_verification.html.tera".to_string(),
+        attachments: vec![],
+        category: None,
     };
 
     match send_preauth_email(
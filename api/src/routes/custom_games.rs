@@ -0,0 +1,199 @@
+use crate::{
+    auth::{AdminUser, User},
+    controllers::{custom_game, Error},
+    routes::games::GamePlayers,
+};
+use chrono::{prelude::Utc, DateTime};
+use rocket::{
+    delete, post, put,
+    response::status,
+    serde::{json::Json, Deserialize, Serialize},
+    State,
+};
+use rocket_okapi::okapi::schemars;
+use rocket_okapi::okapi::schemars::JsonSchema;
+use rocket_okapi::openapi;
+use sqlx::postgres::PgPool;
+
+use super::SchemaExample;
+
+/// A game that isn't from Steam, such as one from another store or a board game.
+/// Custom games have negative app IDs, and can be suggested, voted on and scheduled like Steam games.
+#[derive(Clone, Serialize, JsonSchema)]
+#[serde(crate = "rocket::serde", rename_all = "camelCase")]
+#[schemars(example = "Self::example")]
+pub struct CustomGame {
+    pub appid: i64,
+    pub name: String,
+
+    /// Where to find out more about the game, or buy it.
+    pub url: Option<String>,
+
+    pub image_url: Option<String>,
+
+    /// How many people can play the game together, if known.
+    pub players: Option<GamePlayers>,
+
+    pub last_modified: DateTime<Utc>,
+}
+
+impl SchemaExample for CustomGame {
+    fn example() -> Self {
+        Self {
+            appid: -1,
+            name: "Minecraft: Java Edition".to_string(),
+            url: Some("https://www.minecraft.net/".to_string()),
+            image_url: Some("https://example.com/minecraft.jpg".to_string()),
+            players: Some(GamePlayers {
+                min_players: Some(1),
+                max_players: None,
+                has_lan: true,
+                has_split_screen: false,
+                is_manual: true,
+                last_modified: Utc::now(),
+            }),
+            last_modified: Utc::now(),
+        }
+    }
+}
+
+/// The request body for creating or updating a custom game.
+#[derive(Deserialize, JsonSchema)]
+#[serde(crate = "rocket::serde", rename_all = "camelCase")]
+#[schemars(example = "Self::example")]
+pub struct CustomGameSubmit {
+    pub name: String,
+    pub url: Option<String>,
+    pub image_url: Option<String>,
+    pub min_players: Option<i32>,
+    pub max_players: Option<i32>,
+    #[serde(default)]
+    pub has_lan: bool,
+    #[serde(default)]
+    pub has_split_screen: bool,
+}
+
+impl SchemaExample for CustomGameSubmit {
+    fn example() -> Self {
+        Self {
+            name: "Minecraft: Java Edition".to_string(),
+            url: Some("https://www.minecraft.net/".to_string()),
+            image_url: Some("https://example.com/minecraft.jpg".to_string()),
+            min_players: Some(1),
+            max_players: None,
+            has_lan: true,
+            has_split_screen: false,
+        }
+    }
+}
+
+custom_errors!(
+    CustomGamePostError,
+    Unauthorized,
+    BadRequest,
+    Conflict,
+    InternalServerError
+);
+
+/// Add a game that isn't on Steam, so it can be suggested for events.
+#[openapi(tag = "Custom Games")]
+#[post("/custom-games", format = "json", data = "<game_submit>")]
+pub async fn post(
+    game_submit: Json<CustomGameSubmit>,
+    pool: &State<PgPool>,
+    user: User,
+) -> Result<status::Created<Json<CustomGame>>, CustomGamePostError> {
+    match custom_game::create(pool, game_submit.into_inner(), user.email).await {
+        Ok(game) => Ok(status::Created::new("/custom-games").body(Json(game))),
+        Err(Error::BadInput(e)) => Err(CustomGamePostError::BadRequest(e)),
+        Err(Error::Conflict(e)) => Err(CustomGamePostError::Conflict(e)),
+        Err(e) => Err(CustomGamePostError::InternalServerError(format!(
+            "Error creating custom game, due to: {e}"
+        ))),
+    }
+}
+
+custom_errors!(
+    CustomGamePutError,
+    Unauthorized,
+    BadRequest,
+    NotFound,
+    Conflict,
+    InternalServerError
+);
+
+/// Update any custom game (admin only).
+#[openapi(tag = "Custom Games")]
+#[put(
+    "/custom-games/<appid>?<_as_admin>",
+    format = "json",
+    data = "<game_submit>"
+)]
+pub async fn put_admin(
+    appid: i64,
+    game_submit: Json<CustomGameSubmit>,
+    pool: &State<PgPool>,
+    _as_admin: Option<bool>,
+    user: AdminUser,
+) -> Result<Json<CustomGame>, CustomGamePutError> {
+    put_response(custom_game::update(pool, appid, game_submit.into_inner(), user.email, true).await)
+}
+
+/// Update a custom game added by the current user.
+///
+/// Rank 2: Lower priority than the admin route (rank 1) for the same path.
+#[openapi(tag = "Custom Games")]
+#[put(
+    "/custom-games/<appid>",
+    format = "json",
+    data = "<game_submit>",
+    rank = 2
+)]
+pub async fn put(
+    appid: i64,
+    game_submit: Json<CustomGameSubmit>,
+    pool: &State<PgPool>,
+    user: User,
+) -> Result<Json<CustomGame>, CustomGamePutError> {
+    put_response(
+        custom_game::update(pool, appid, game_submit.into_inner(), user.email, false).await,
+    )
+}
+
+fn put_response(result: Result<CustomGame, Error>) -> Result<Json<CustomGame>, CustomGamePutError> {
+    match result {
+        Ok(game) => Ok(Json(game)),
+        Err(Error::NotPermitted(e)) => Err(CustomGamePutError::Unauthorized(e)),
+        Err(Error::BadInput(e)) => Err(CustomGamePutError::BadRequest(e)),
+        Err(Error::NotFound(e)) => Err(CustomGamePutError::NotFound(e)),
+        Err(Error::Conflict(e)) => Err(CustomGamePutError::Conflict(e)),
+        Err(e) => Err(CustomGamePutError::InternalServerError(format!(
+            "Error updating custom game, due to: {e}"
+        ))),
+    }
+}
+
+custom_errors!(
+    CustomGameDeleteError,
+    Unauthorized,
+    NotFound,
+    InternalServerError
+);
+
+/// Delete a custom game, along with any suggestions, votes and schedule entries for it (admin only).
+#[openapi(tag = "Custom Games")]
+#[delete("/custom-games/<appid>?<_as_admin>")]
+pub async fn delete(
+    appid: i64,
+    pool: &State<PgPool>,
+    _as_admin: Option<bool>,
+    user: AdminUser,
+) -> Result<status::NoContent, CustomGameDeleteError> {
+    match custom_game::delete(pool, appid, user.email).await {
+        Ok(()) => Ok(status::NoContent),
+        Err(Error::NotFound(e)) => Err(CustomGameDeleteError::NotFound(e)),
+        Err(e) => Err(CustomGameDeleteError::InternalServerError(format!(
+            "Error deleting custom game, due to: {e}"
+        ))),
+    }
+}
@@ -0,0 +1,199 @@
+use crate::{
+    auth::AdminUser,
+    controllers::{discord, Error},
+};
+use chrono::{prelude::Utc, DateTime};
+use rocket::{
+    delete, get, post, put,
+    serde::{json::Json, Deserialize, Serialize},
+    State,
+};
+use rocket_okapi::okapi::schemars;
+use rocket_okapi::okapi::schemars::JsonSchema;
+use rocket_okapi::openapi;
+use sqlx::postgres::PgPool;
+
+use super::SchemaExample;
+
+/// The response for the `GET /events/{eventId}/discord` endpoint.
+#[derive(Clone, Serialize, JsonSchema)]
+#[serde(crate = "rocket::serde", rename_all = "camelCase")]
+#[schemars(example = "Self::example")]
+pub struct EventDiscordConfig {
+    /// The event ID this configuration belongs to.
+    pub event_id: i32,
+
+    /// The Discord webhook URL messages are posted to.
+    pub webhook_url: String,
+
+    /// Whether anything is posted to Discord for this event.
+    pub is_enabled: bool,
+
+    /// Post when someone RSVPs yes.
+    pub notify_rsvps: bool,
+
+    /// Post when a different game becomes the most voted.
+    pub notify_top_game: bool,
+
+    /// Post when a scheduled game is about to start.
+    pub notify_schedule: bool,
+
+    /// How many minutes before a scheduled game starts to post about it.
+    pub schedule_lead_minutes: i32,
+
+    /// The date the configuration was created.
+    pub created_at: DateTime<Utc>,
+
+    /// The last time this configuration was modified.
+    pub last_modified: DateTime<Utc>,
+}
+
+impl SchemaExample for EventDiscordConfig {
+    fn example() -> Self {
+        Self {
+            event_id: 1,
+            webhook_url: "https://discord.com/api/webhooks/123456789/abcdef".to_string(),
+            is_enabled: true,
+            notify_rsvps: true,
+            notify_top_game: true,
+            notify_schedule: true,
+            schedule_lead_minutes: 15,
+            created_at: Utc::now(),
+            last_modified: Utc::now(),
+        }
+    }
+}
+
+/// The request body for creating/updating Discord configuration.
+#[derive(Deserialize, Serialize, JsonSchema)]
+#[serde(crate = "rocket::serde", rename_all = "camelCase")]
+#[schemars(example = "Self::example")]
+pub struct EventDiscordConfigSubmit {
+    pub webhook_url: String,
+    pub is_enabled: bool,
+    pub notify_rsvps: bool,
+    pub notify_top_game: bool,
+    pub notify_schedule: bool,
+    pub schedule_lead_minutes: i32,
+}
+
+impl SchemaExample for EventDiscordConfigSubmit {
+    fn example() -> Self {
+        Self {
+            webhook_url: "https://discord.com/api/webhooks/123456789/abcdef".to_string(),
+            is_enabled: true,
+            notify_rsvps: true,
+            notify_top_game: true,
+            notify_schedule: true,
+            schedule_lead_minutes: 15,
+        }
+    }
+}
+
+custom_errors!(
+    EventDiscordConfigGetError,
+    Unauthorized,
+    NotFound,
+    InternalServerError
+);
+
+/// Get the Discord configuration for an event (admin only).
+#[openapi(tag = "Event Discord")]
+#[get("/events/<event_id>/discord?<_as_admin>", format = "json")]
+pub async fn get(
+    event_id: i32,
+    pool: &State<PgPool>,
+    _as_admin: Option<bool>,
+    _user: AdminUser,
+) -> Result<Json<EventDiscordConfig>, EventDiscordConfigGetError> {
+    match discord::get(pool, event_id).await {
+        Ok(config) => Ok(Json(config)),
+        Err(Error::NotFound(e)) => Err(EventDiscordConfigGetError::NotFound(e)),
+        Err(e) => Err(EventDiscordConfigGetError::InternalServerError(format!(
+            "Error getting Discord config, due to: {e}"
+        ))),
+    }
+}
+
+custom_errors!(
+    EventDiscordConfigPutError,
+    Unauthorized,
+    BadRequest,
+    InternalServerError
+);
+
+/// Update the Discord configuration for an event (admin only).
+/// The event is announced on Discord the first time it is enabled.
+#[openapi(tag = "Event Discord")]
+#[put(
+    "/events/<event_id>/discord?<_as_admin>",
+    format = "json",
+    data = "<config_submit>"
+)]
+pub async fn put(
+    event_id: i32,
+    config_submit: Json<EventDiscordConfigSubmit>,
+    pool: &State<PgPool>,
+    _as_admin: Option<bool>,
+    user: AdminUser,
+) -> Result<Json<EventDiscordConfig>, EventDiscordConfigPutError> {
+    match discord::upsert(pool, event_id, config_submit.into_inner(), user.email).await {
+        Ok(config) => Ok(Json(config)),
+        Err(Error::BadInput(e)) => Err(EventDiscordConfigPutError::BadRequest(format!(
+            "Invalid request, due to {e}"
+        ))),
+        Err(e) => Err(EventDiscordConfigPutError::InternalServerError(format!(
+            "Error saving Discord config, due to: {e}"
+        ))),
+    }
+}
+
+custom_errors!(
+    EventDiscordConfigDeleteError,
+    Unauthorized,
+    InternalServerError
+);
+
+/// Stop posting to Discord for an event and remove its configuration (admin only).
+#[openapi(tag = "Event Discord")]
+#[delete("/events/<event_id>/discord?<_as_admin>")]
+pub async fn delete(
+    event_id: i32,
+    pool: &State<PgPool>,
+    _as_admin: Option<bool>,
+    user: AdminUser,
+) -> Result<rocket::response::status::NoContent, EventDiscordConfigDeleteError> {
+    match discord::delete(pool, event_id, user.email).await {
+        Ok(()) => Ok(rocket::response::status::NoContent),
+        Err(e) => Err(EventDiscordConfigDeleteError::InternalServerError(format!(
+            "Error deleting Discord config, due to: {e}"
+        ))),
+    }
+}
+
+custom_errors!(
+    EventDiscordAnnounceError,
+    Unauthorized,
+    BadRequest,
+    NotFound,
+    InternalServerError
+);
+
+/// Post the event announcement to Discord again, such as after the event details change (admin only).
+#[openapi(tag = "Event Discord")]
+#[post("/events/<event_id>/discord/announce?<_as_admin>")]
+pub async fn announce(
+    event_id: i32,
+    pool: &State<PgPool>,
+    _as_admin: Option<bool>,
+    _user: AdminUser,
+) -> Result<rocket::response::status::NoContent, EventDiscordAnnounceError> {
+    match discord::announce(pool, event_id).await {
+        Ok(()) => Ok(rocket::response::status::NoContent),
+        Err(Error::NotFound(e)) => Err(EventDiscordAnnounceError::NotFound(e)),
+        Err(Error::BadInput(e)) => Err(EventDiscordAnnounceError::BadRequest(e)),
+        Err(e) => Err(EventDiscordAnnounceError::InternalServerError(format!(
+            "Error announcing event on Discord, due to: {e}"
+        ))),
+    }
+}
@@ -1,6 +1,7 @@
 use crate::{
-    auth::User,
-    controllers::{game_suggestion, Error},
+    auth::{AdminUser, User},
+    controllers::{game_recommendation, game_suggestion, Error},
+    routes::games::{GamePlayers, GameSource, SteamGameDetails},
 };
 use chrono::{prelude::Utc, DateTime};
 use rocket::{
@@ -29,6 +30,8 @@ pub struct EventGameResponse {
     pub gamer_owned: Vec<Gamer>,
     pub playtime_forever: i32,
     pub last_modified: DateTime<Utc>,
+    /// How many people can play the game together, if known.
+    pub players: Option<GamePlayers>,
 }
 
 #[derive(Serialize, JsonSchema)]
@@ -72,25 +75,48 @@ pub struct EventGameSuggestionResponse {
     pub requested_at: DateTime<Utc>,
     pub suggestion_last_modified: DateTime<Utc>,
     pub self_vote: Option<GameVote>,
+    /// The current user's stars for the game, from 0 to 5, for events using score voting.
+    pub self_score: Option<i16>,
+    /// Where the current user ranked the game, 1 being their favourite, for events using ranked choice voting.
+    pub self_rank: Option<i16>,
+    /// How many people voted for the game, or gave it stars or ranked it.
     pub votes: Option<i64>,
     pub voters: Vec<Gamer>,
     pub suggester: Option<Gamer>,
     pub gamer_owned: Vec<Gamer>,
     pub gamer_unowned: Vec<Gamer>,
+    /// Attendees with no Steam library who haven't said whether they own the game.
     pub gamer_unknown: Vec<Gamer>,
+    /// Attendees who have said the game is installed and ready to play. They're also in `gamer_owned`.
+    pub gamer_installed: Vec<Gamer>,
+    /// Details from the Steam store, if they have been fetched.
+    pub details: Option<SteamGameDetails>,
+    /// How many people can play the game together, if known.
+    pub players: Option<GamePlayers>,
+    /// Whether the game is from Steam or was added as a custom game.
+    pub source: GameSource,
+    /// The Steam store page, or the link given for a custom game.
+    pub url: Option<String>,
+    /// The image given for a custom game.
+    pub image_url: Option<String>,
 }
 
 custom_errors!(EventGameSuggestedError, Unauthorized, InternalServerError);
 
+/// Get the games suggested for an event.
+/// Use `players` to only get games that many people can play together, and `lan` to only get games with LAN play.
+/// Games with unknown player counts are included when filtering by `players`.
 #[openapi(tag = "Event Games")]
-#[get("/events/<event_id>/suggested_games", format = "json")]
+#[get("/events/<event_id>/suggested_games?<players>&<lan>", format = "json")]
 pub async fn get_all_suggested(
     event_id: i32,
+    players: Option<i32>,
+    lan: Option<bool>,
     pool: &State<PgPool>,
     user: User,
 ) -> Result<Json<Vec<EventGameSuggestionResponse>>, EventGameSuggestedError> {
     // Return all games
-    match game_suggestion::get(pool, event_id, user.email).await {
+    match game_suggestion::get(pool, event_id, user.email, players, lan).await {
         Ok(game_suggestions) => Ok(Json(game_suggestions)),
         Err(Error::NotPermitted(e)) => Err(EventGameSuggestedError::Unauthorized(e)),
         Err(e) => Err(EventGameSuggestedError::InternalServerError(format!(
@@ -99,15 +125,28 @@ pub async fn get_all_suggested(
     }
 }
 
-custom_errors!(EventGameSuggestionError, InternalServerError);
+custom_errors!(
+    EventGameSuggestionError,
+    Unauthorized,
+    BadRequest,
+    Conflict,
+    InternalServerError
+);
 
 #[derive(Deserialize, JsonSchema)]
 #[serde(crate = "rocket::serde")]
 pub struct EventGameSuggestionRequest {
     pub appid: i64,
     pub comment: Option<String>,
+    /// Suggest the game even if it looks like a version of a game that's already been suggested,
+    /// such as its DLC, soundtrack or another edition.
+    #[serde(default)]
+    pub allow_similar: bool,
 }
 
+/// Suggest a game for an event.
+/// Events can limit how many games each attendee suggests, and games that look like versions
+/// of an already suggested game are turned away unless `allow_similar` is set.
 #[openapi(tag = "Event Games")]
 #[post(
     "/events/<event_id>/suggested_games",
@@ -126,6 +165,9 @@ pub async fn post(
             event_id, event_game_suggestion.appid
         ))
         .body(Json(event_game_suggestion))),
+        Err(Error::NotPermitted(e)) => Err(EventGameSuggestionError::Unauthorized(e)),
+        Err(Error::BadInput(e)) => Err(EventGameSuggestionError::BadRequest(e)),
+        Err(Error::Conflict(e)) => Err(EventGameSuggestionError::Conflict(e)),
         Err(e) => Err(EventGameSuggestionError::InternalServerError(format!(
             "Error creating event, due to: {e}"
         ))),
@@ -141,12 +183,24 @@ pub enum GameVote {
     No, // Not used for now
 }
 
+/// A vote for a suggested game. Events using approval voting need a `vote`,
+/// and events using score voting need a `score` from 0 to 5 stars.
 #[derive(Deserialize, JsonSchema)]
 #[serde(crate = "rocket::serde")]
 pub struct EventGameSuggestionPatch {
-    vote: GameVote,
+    pub vote: Option<GameVote>,
+    pub score: Option<i16>,
 }
 
+custom_errors!(
+    EventGameVoteError,
+    Unauthorized,
+    BadRequest,
+    InternalServerError
+);
+
+/// Vote for a suggested game.
+/// Events using ranked choice voting rank all their games at once with `PUT /events/{eventId}/ranking` instead.
 #[openapi(tag = "Event Games")]
 #[patch(
     "/events/<event_id>/suggested_games/<game_id>",
@@ -159,21 +213,24 @@ pub async fn patch(
     game_patch: Json<EventGameSuggestionPatch>,
     pool: &State<PgPool>,
     user: User,
-) -> Result<Json<EventGameSuggestionResponse>, rocket::response::status::Unauthorized<String>> {
-    #[allow(clippy::option_if_let_else)]
+) -> Result<Json<EventGameSuggestionResponse>, EventGameVoteError> {
+    let game_patch = game_patch.into_inner();
     match game_suggestion::vote(
         pool,
         event_id,
         game_id,
         user.email,
-        game_patch.into_inner().vote,
+        game_patch.vote,
+        game_patch.score,
     )
     .await
     {
         Ok(updated_game_suggestion) => Ok(Json(updated_game_suggestion)),
-        Err(_) => Err(rocket::response::status::Unauthorized(
-            "Error updating game vote in the database".to_string(),
-        )),
+        Err(Error::BadInput(e)) => Err(EventGameVoteError::BadRequest(e)),
+        Err(Error::NotPermitted(e)) => Err(EventGameVoteError::Unauthorized(e)),
+        Err(e) => Err(EventGameVoteError::InternalServerError(format!(
+            "Error updating game vote in the database, due to: {e}"
+        ))),
     }
 }
 
@@ -218,3 +275,161 @@ pub async fn update_comment(
         ))),
     }
 }
+
+/// Whether an attendee owns a game, as they've said themselves.
+#[derive(Debug, Clone, Copy, Deserialize, Serialize, JsonSchema, PartialEq, Eq)]
+#[serde(crate = "rocket::serde", rename_all = "camelCase")]
+pub enum GameOwnership {
+    Owned,
+    NotOwned,
+    Installed,
+}
+
+#[derive(Deserialize, JsonSchema)]
+#[serde(crate = "rocket::serde")]
+pub struct EventGameOwnershipUpdate {
+    /// Leave empty to go back to using your Steam library.
+    pub ownership: Option<GameOwnership>,
+}
+
+custom_errors!(
+    EventGameOwnershipUpdateError,
+    Unauthorized,
+    NotFound,
+    InternalServerError
+);
+
+/// Say whether you own a suggested game, for when it isn't in your Steam library
+/// (such as owning it on another store) or your Steam profile is private.
+#[openapi(tag = "Event Games")]
+#[put(
+    "/events/<event_id>/suggested_games/<game_id>/ownership",
+    format = "json",
+    data = "<ownership_update>"
+)]
+pub async fn update_ownership(
+    event_id: i32,
+    game_id: i64,
+    ownership_update: Json<EventGameOwnershipUpdate>,
+    pool: &State<PgPool>,
+    user: User,
+) -> Result<Json<EventGameSuggestionResponse>, EventGameOwnershipUpdateError> {
+    match game_suggestion::set_ownership(
+        pool,
+        event_id,
+        game_id,
+        user.email,
+        ownership_update.into_inner().ownership,
+    )
+    .await
+    {
+        Ok(updated_game_suggestion) => Ok(Json(updated_game_suggestion)),
+        Err(Error::NotPermitted(e)) => Err(EventGameOwnershipUpdateError::Unauthorized(e)),
+        Err(Error::NotFound(e)) => Err(EventGameOwnershipUpdateError::NotFound(e)),
+        Err(e) => Err(EventGameOwnershipUpdateError::InternalServerError(format!(
+            "Error updating game ownership: {e}"
+        ))),
+    }
+}
+
+#[derive(Deserialize, JsonSchema)]
+#[serde(crate = "rocket::serde", rename_all = "camelCase")]
+pub struct EventGameSuggestionMerge {
+    /// The suggested game to merge into.
+    pub into_appid: i64,
+}
+
+custom_errors!(
+    EventGameSuggestionMergeError,
+    Unauthorized,
+    BadRequest,
+    NotFound,
+    InternalServerError
+);
+
+/// Merge a suggested game into another, such as a DLC into its game (admin only).
+/// Votes and comments carry over, then the merged game is no longer suggested.
+#[openapi(tag = "Event Games")]
+#[post(
+    "/events/<event_id>/suggested_games/<game_id>/merge?<_as_admin>",
+    format = "json",
+    data = "<merge>"
+)]
+pub async fn merge(
+    event_id: i32,
+    game_id: i64,
+    merge: Json<EventGameSuggestionMerge>,
+    pool: &State<PgPool>,
+    _as_admin: Option<bool>,
+    user: AdminUser,
+) -> Result<Json<EventGameSuggestionResponse>, EventGameSuggestionMergeError> {
+    match game_suggestion::merge(
+        pool,
+        event_id,
+        game_id,
+        merge.into_inner().into_appid,
+        user.email,
+    )
+    .await
+    {
+        Ok(merged_game_suggestion) => Ok(Json(merged_game_suggestion)),
+        Err(Error::BadInput(e)) => Err(EventGameSuggestionMergeError::BadRequest(e)),
+        Err(Error::NotFound(e)) => Err(EventGameSuggestionMergeError::NotFound(e)),
+        Err(e) => Err(EventGameSuggestionMergeError::InternalServerError(format!(
+            "Error merging game suggestions: {e}"
+        ))),
+    }
+}
+
+/// A game the people going to an event might enjoy, with why it was recommended.
+#[derive(Serialize, JsonSchema)]
+#[serde(crate = "rocket::serde", rename_all = "camelCase")]
+pub struct EventGameRecommendation {
+    pub appid: i64,
+    pub name: String,
+    /// How strongly the game is recommended, from 0 to 1.
+    pub score: f64,
+    /// How many attending gamers own the game on Steam.
+    pub owner_count: i64,
+    pub attendee_count: i64,
+    /// Combined playtime of the attending gamers, in minutes.
+    pub playtime_forever: i64,
+    /// Combined playtime of the attending gamers over the last two weeks, in minutes.
+    pub playtime_2weeks: i64,
+    /// How many people can play the game together, if known.
+    pub players: Option<GamePlayers>,
+    /// How many past events the game was suggested for.
+    pub past_event_count: i64,
+    /// How many votes the game got at past events.
+    pub past_vote_count: i64,
+    /// Whether the game has already been suggested for this event.
+    pub is_suggested: bool,
+    /// Why the game was recommended.
+    pub reasons: Vec<String>,
+}
+
+custom_errors!(
+    EventGameRecommendationError,
+    Unauthorized,
+    InternalServerError
+);
+
+/// Recommend games for an event, ranked by how many attending gamers own them, how much
+/// they play them (especially recently), whether everyone can play together, and how popular
+/// they were at past events.
+#[openapi(tag = "Event Games")]
+#[get("/events/<event_id>/recommended_games?<count>", format = "json")]
+pub async fn get_recommended(
+    event_id: i32,
+    count: Option<usize>,
+    pool: &State<PgPool>,
+    user: User,
+) -> Result<Json<Vec<EventGameRecommendation>>, EventGameRecommendationError> {
+    match game_recommendation::get(pool, event_id, user.email, count.unwrap_or(10)).await {
+        Ok(recommendations) => Ok(Json(recommendations)),
+        Err(Error::NotPermitted(e)) => Err(EventGameRecommendationError::Unauthorized(e)),
+        Err(e) => Err(EventGameRecommendationError::InternalServerError(format!(
+            "Error getting game recommendations, due to: {e}"
+        ))),
+    }
+}
@@ -1,7 +1,12 @@
 use crate::{
     auth::{AdminUser, User},
+    controllers::notification_preference,
     controllers::{ensure_user_invited, event_invitation, Error},
-    util::{send_email_bcc, send_preauth_email, PreauthEmailDetails},
+    repositories::notification_preference::NotificationCategory,
+    util::{
+        add_unsubscribe_link, event_ics_attachment, render_email, send_email, send_preauth_email,
+        EmailBody, PreauthEmailDetails,
+    },
 };
 use chrono::{prelude::Utc, DateTime, Duration};
 use resend_rs::Resend;
@@ -129,24 +134,41 @@ pub async fn post(
         address: invitation_request.email.to_string(),
         subject: format!("{} - caLANdar Invitation", event.title),
         template: "email_invitation.html.tera".to_string(),
+        attachments: vec![event_ics_attachment(&event)],
+        category: Some(NotificationCategory::Invitations),
     };
 
-    match send_preauth_email(
-        email_details,
-        &mut context,
-        format!("/events/{}", event.id).as_str(),
-        Duration::hours(24),
-        key,
-        sender,
-        tera,
+    // The invitation still stands if they've unsubscribed, they just won't be emailed about it
+    let Ok(wants_invitation_email) = notification_preference::is_subscribed(
+        pool.inner(),
+        &invitation_request.email,
+        NotificationCategory::Invitations,
     )
     .await
-    {
-        Ok(()) => (),
-        Err(_) => {
-            return Err(rocket::response::status::BadRequest(
-                "Error sending invitation email".to_string(),
-            ))
+    else {
+        return Err(rocket::response::status::BadRequest(
+            "Error getting notification preferences".to_string(),
+        ));
+    };
+
+    if wants_invitation_email {
+        match send_preauth_email(
+            email_details,
+            &mut context,
+            format!("/events/{}", event.id).as_str(),
+            Duration::hours(24),
+            key,
+            sender,
+            tera,
+        )
+        .await
+        {
+            Ok(()) => (),
+            Err(_) => {
+                return Err(rocket::response::status::BadRequest(
+                    "Error sending invitation email".to_string(),
+                ))
+            }
         }
     }
 
@@ -256,8 +278,30 @@ pub async fn resend(
         address: email.to_string(),
         subject: format!("{} - caLANdar Invitation", event.title),
         template: "email_invitation.html.tera".to_string(),
+        attachments: vec![event_ics_attachment(&event)],
+        category: Some(NotificationCategory::Invitations),
     };
 
+    match notification_preference::is_subscribed(
+        pool.inner(),
+        &email,
+        NotificationCategory::Invitations,
+    )
+    .await
+    {
+        Ok(true) => (),
+        Ok(false) => {
+            return Err(rocket::response::status::BadRequest(
+                "Recipient has unsubscribed from invitation emails".to_string(),
+            ))
+        }
+        Err(_) => {
+            return Err(rocket::response::status::BadRequest(
+                "Error getting notification preferences".to_string(),
+            ))
+        }
+    }
+
     match send_preauth_email(
         email_details,
         &mut context,
@@ -589,22 +633,25 @@ custom_errors!(
     InternalServerError
 );
 
+/// Email a message to an event's invitees (admin only).
+/// The emails are sent in the background, so this returns once they're ready to send.
 #[openapi(tag = "Event Invitations")]
 #[post(
     "/events/<event_id>/email?<_as_admin>",
     format = "json",
     data = "<email_request>"
 )]
-#[allow(clippy::too_many_lines)]
+#[allow(clippy::too_many_arguments, clippy::too_many_lines)]
 pub async fn send_custom_email(
     event_id: i32,
     email_request: Json<SendCustomEmailRequest>,
     pool: &State<PgPool>,
+    key: &State<PasetoSymmetricKey<V4, Local>>,
     sender: &State<Resend>,
     tera: &State<Tera>,
     _as_admin: Option<bool>,
     user: AdminUser,
-) -> Result<rocket::response::status::NoContent, SendCustomEmailError> {
+) -> Result<status::Accepted<()>, SendCustomEmailError> {
     // Get the event details
     let event = match event::filter(
         pool.inner(),
@@ -662,9 +709,28 @@ pub async fn send_custom_email(
         })
         .collect();
 
-    if filtered_invitations.is_empty() {
+    // Only send to people who haven't unsubscribed from event updates
+    let recipient_emails = match notification_preference::filter_subscribed(
+        pool.inner(),
+        filtered_invitations
+            .iter()
+            .map(|invitation| invitation.email.clone())
+            .collect(),
+        NotificationCategory::EventUpdates,
+    )
+    .await
+    {
+        Ok(recipient_emails) => recipient_emails,
+        Err(e) => {
+            return Err(SendCustomEmailError::InternalServerError(format!(
+                "Error getting notification preferences: {e}"
+            )))
+        }
+    };
+
+    if recipient_emails.is_empty() {
         return Err(SendCustomEmailError::BadRequest(format!(
-            "No invitations match the selected filter: {:?}",
+            "No subscribed invitations match the selected filter: {:?}",
             email_request.filter
         )));
     }
@@ -683,56 +749,109 @@ pub async fn send_custom_email(
     context.insert("message", &email_request.message);
     context.insert("subject", &email_request.subject);
 
-    // Render the email body
-    let body = match tera.render("email_custom.html.tera", &context) {
-        Ok(body) => body,
-        Err(e) => {
-            return Err(SendCustomEmailError::InternalServerError(format!(
-                "Error rendering email: {e}"
-            )))
+    // Render a separate email for each recipient, so each gets their own unsubscribe link
+    // and recipients don't see each other's email addresses
+    let mut emails = vec![];
+    for recipient_email in recipient_emails {
+        let mut recipient_context = context.clone();
+        let unsubscribe_url = match add_unsubscribe_link(
+            &mut recipient_context,
+            key,
+            &recipient_email,
+            NotificationCategory::EventUpdates,
+        ) {
+            Ok(unsubscribe_url) => unsubscribe_url,
+            Err(e) => {
+                return Err(SendCustomEmailError::InternalServerError(format!(
+                    "Error creating unsubscribe link: {e}"
+                )))
+            }
+        };
+
+        // Render the email body
+        let body = match render_email(tera, "email_custom.html.tera", &recipient_context) {
+            Ok(body) => body,
+            Err(e) => {
+                return Err(SendCustomEmailError::InternalServerError(format!(
+                    "Error rendering email: {e}"
+                )))
+            }
+        };
+
+        emails.push((recipient_email, body, unsubscribe_url));
+    }
+
+    // Sending is paced to stay under the email provider's rate limit, which takes too long
+    // for large events to wait for, so send in the background
+    tokio::spawn(send_custom_emails(
+        pool.inner().clone(),
+        sender.inner().clone(),
+        event,
+        emails,
+        email_request.into_inner(),
+        user.email,
+    ));
+
+    Ok(status::Accepted(()))
+}
+
+/// Send rendered custom emails one at a time, then record how many were sent
+async fn send_custom_emails(
+    pool: PgPool,
+    sender: Resend,
+    event: event::Event,
+    emails: Vec<(String, EmailBody, String)>,
+    email_request: SendCustomEmailRequest,
+    admin_email: String,
+) {
+    let mut failures = vec![];
+    for (index, (recipient_email, body, unsubscribe_url)) in emails.iter().enumerate() {
+        if index > 0 {
+            // Stay under the email provider's rate limit
+            tokio::time::sleep(std::time::Duration::from_millis(500)).await;
         }
-    };
 
-    // Send emails to all filtered recipients using BCC
-    // This ensures recipients don't see each other's email addresses
-    let recipient_emails: Vec<&str> = filtered_invitations
-        .iter()
-        .map(|inv| inv.email.as_str())
-        .collect();
+        if let Err(e) = send_email(
+            &sender,
+            vec![recipient_email.as_str()],
+            email_request.subject.as_str(),
+            body,
+            vec![event_ics_attachment(&event)],
+            Some(unsubscribe_url.as_str()),
+        )
+        .await
+        {
+            log::error!("Error sending custom email to {recipient_email}: {e}");
+            failures.push(recipient_email.clone());
+        }
+    }
 
-    let recipient_count = recipient_emails.len();
+    let recipient_count = emails.len() - failures.len();
 
-    match send_email_bcc(
-        sender,
-        recipient_emails,
-        email_request.subject.as_str(),
-        body.as_str(),
+    // Log audit entry
+    let metadata = rocket::serde::json::serde_json::json!({
+        "event_id": event.id,
+        "recipient_count": recipient_count,
+        "failed_recipient_count": failures.len(),
+        "filter": format!("{:?}", email_request.filter),
+        "subject": email_request.subject,
+        "email_type": "custom",
+    });
+    crate::util::log_audit(
+        &pool,
+        Some(admin_email),
+        "email.send".to_string(),
+        "email".to_string(),
+        Some(format!("{}", event.id)),
+        Some(metadata),
     )
-    .await
-    {
-        Ok(()) => {
-            // Log audit entry
-            let metadata = rocket::serde::json::serde_json::json!({
-                "event_id": event_id,
-                "recipient_count": recipient_count,
-                "filter": format!("{:?}", email_request.filter),
-                "subject": email_request.subject,
-                "email_type": "custom",
-            });
-            crate::util::log_audit(
-                pool.inner(),
-                Some(user.email),
-                "email.send".to_string(),
-                "email".to_string(),
-                Some(format!("{event_id}")),
-                Some(metadata),
-            )
-            .await;
+    .await;
 
-            Ok(rocket::response::status::NoContent)
-        }
-        Err(e) => Err(SendCustomEmailError::InternalServerError(format!(
-            "Error sending email: {e}"
-        ))),
+    if !failures.is_empty() {
+        log::error!(
+            "Error sending custom email for event {} to: {}",
+            event.id,
+            failures.join(", ")
+        );
     }
 }
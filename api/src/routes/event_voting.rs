@@ -0,0 +1,373 @@
+use crate::{
+    auth::{AdminUser, User},
+    controllers::{ensure_user_invited, event_voting, Error},
+};
+use chrono::{prelude::Utc, DateTime};
+use rocket::{
+    get, post, put,
+    serde::{json::Json, Deserialize, Serialize},
+    State,
+};
+use rocket_okapi::okapi::schemars;
+use rocket_okapi::okapi::schemars::JsonSchema;
+use rocket_okapi::openapi;
+use sqlx::postgres::PgPool;
+
+use super::SchemaExample;
+
+/// How votes on an event's suggested games are counted.
+#[derive(Debug, Clone, Copy, Deserialize, Serialize, JsonSchema, PartialEq, Eq)]
+#[serde(crate = "rocket::serde", rename_all = "camelCase")]
+pub enum VotingMethod {
+    /// Vote yes for any number of games. The most yes votes wins.
+    Approval,
+    /// Give games 0 to 5 stars. The most stars in total wins.
+    Score,
+    /// Rank games in order of preference. The game with the fewest first preferences is knocked out
+    /// and its votes go to each voter's next choice, until one game is left.
+    InstantRunoff,
+    /// Rank games in order of preference. Games get a point for every game ranked below them.
+    Borda,
+}
+
+/// The response for the `GET /events/{eventId}/voting-config` endpoint.
+#[derive(Clone, Serialize, JsonSchema)]
+#[serde(crate = "rocket::serde", rename_all = "camelCase")]
+#[schemars(example = "Self::example")]
+pub struct EventVotingConfig {
+    /// The event ID this configuration belongs to.
+    pub event_id: i32,
+
+    /// How votes are counted. Events use approval voting unless set otherwise.
+    pub voting_method: VotingMethod,
+
+    /// When games can first be suggested, if there's a limit.
+    pub suggestions_open_at: Option<DateTime<Utc>>,
+
+    /// When games can no longer be suggested, if there's a limit.
+    pub suggestions_close_at: Option<DateTime<Utc>>,
+
+    /// When voting starts, if there's a limit.
+    pub voting_open_at: Option<DateTime<Utc>>,
+
+    /// When votes are frozen and the final results counted, if there's a limit.
+    pub voting_close_at: Option<DateTime<Utc>>,
+
+    /// The most games each attendee can suggest, if there's a limit.
+    pub max_suggestions_per_attendee: Option<i32>,
+
+    /// Whether games can be suggested right now.
+    pub suggestions_open: bool,
+
+    /// Whether votes can be changed right now.
+    pub voting_open: bool,
+
+    /// The date the configuration was created.
+    pub created_at: DateTime<Utc>,
+
+    /// The last time this configuration was modified.
+    pub last_modified: DateTime<Utc>,
+}
+
+impl SchemaExample for EventVotingConfig {
+    fn example() -> Self {
+        Self {
+            event_id: 1,
+            voting_method: VotingMethod::InstantRunoff,
+            suggestions_open_at: None,
+            suggestions_close_at: Some(Utc::now()),
+            voting_open_at: None,
+            voting_close_at: Some(Utc::now() + chrono::Duration::days(2)),
+            max_suggestions_per_attendee: Some(3),
+            suggestions_open: false,
+            voting_open: true,
+            created_at: Utc::now(),
+            last_modified: Utc::now(),
+        }
+    }
+}
+
+/// The request body for updating voting configuration.
+#[derive(Deserialize, Serialize, JsonSchema)]
+#[serde(crate = "rocket::serde", rename_all = "camelCase")]
+#[schemars(example = "Self::example")]
+pub struct EventVotingConfigSubmit {
+    pub voting_method: VotingMethod,
+    #[serde(default)]
+    pub suggestions_open_at: Option<DateTime<Utc>>,
+    #[serde(default)]
+    pub suggestions_close_at: Option<DateTime<Utc>>,
+    #[serde(default)]
+    pub voting_open_at: Option<DateTime<Utc>>,
+    #[serde(default)]
+    pub voting_close_at: Option<DateTime<Utc>>,
+    #[serde(default)]
+    pub max_suggestions_per_attendee: Option<i32>,
+}
+
+impl SchemaExample for EventVotingConfigSubmit {
+    fn example() -> Self {
+        Self {
+            voting_method: VotingMethod::Score,
+            suggestions_open_at: None,
+            suggestions_close_at: Some(Utc::now() + chrono::Duration::days(7)),
+            voting_open_at: None,
+            voting_close_at: Some(Utc::now() + chrono::Duration::days(9)),
+            max_suggestions_per_attendee: Some(3),
+        }
+    }
+}
+
+/// The request body for reopening voting.
+#[derive(Deserialize, Serialize, JsonSchema)]
+#[serde(crate = "rocket::serde", rename_all = "camelCase")]
+#[schemars(example = "Self::example")]
+pub struct VotingReopen {
+    /// When voting should close again, or none to leave it open.
+    #[serde(default)]
+    pub voting_close_at: Option<DateTime<Utc>>,
+}
+
+impl SchemaExample for VotingReopen {
+    fn example() -> Self {
+        Self {
+            voting_close_at: Some(Utc::now() + chrono::Duration::days(1)),
+        }
+    }
+}
+
+/// Someone's ranking of an event's suggested games, for events using ranked choice voting.
+#[derive(Deserialize, Serialize, JsonSchema)]
+#[serde(crate = "rocket::serde", rename_all = "camelCase")]
+#[schemars(example = "Self::example")]
+pub struct EventGameRanking {
+    /// App IDs of suggested games, favourite first. Games left out aren't ranked.
+    pub games: Vec<i64>,
+}
+
+impl SchemaExample for EventGameRanking {
+    fn example() -> Self {
+        Self {
+            games: vec![730, 252_950, 550],
+        }
+    }
+}
+
+/// How a suggested game did in the vote.
+#[derive(Clone, Serialize, JsonSchema)]
+#[serde(crate = "rocket::serde", rename_all = "camelCase")]
+pub struct VoteStanding {
+    pub appid: i64,
+    pub name: String,
+
+    /// Where the game came, starting at 1.
+    pub position: usize,
+
+    /// Yes votes, total stars or Borda points, depending on the voting method.
+    /// For instant-runoff, the first preferences the game had in the last round it was in.
+    pub points: i64,
+
+    /// How many people voted for, gave stars to or ranked the game.
+    pub voter_count: i64,
+
+    /// For instant-runoff, the round the game was knocked out in, starting at 1.
+    pub eliminated_in_round: Option<usize>,
+}
+
+/// The response for the `GET /events/{eventId}/vote-results` endpoint.
+#[derive(Serialize, JsonSchema)]
+#[serde(crate = "rocket::serde", rename_all = "camelCase")]
+#[schemars(example = "Self::example")]
+pub struct VoteResults {
+    pub voting_method: VotingMethod,
+
+    /// When the results were frozen, if voting has closed.
+    pub frozen_at: Option<DateTime<Utc>>,
+
+    /// Every suggested game, best first.
+    pub standings: Vec<VoteStanding>,
+}
+
+impl SchemaExample for VoteResults {
+    fn example() -> Self {
+        Self {
+            voting_method: VotingMethod::InstantRunoff,
+            frozen_at: None,
+            standings: vec![
+                VoteStanding {
+                    appid: 252_950,
+                    name: "Rocket League".to_string(),
+                    position: 1,
+                    points: 4,
+                    voter_count: 6,
+                    eliminated_in_round: None,
+                },
+                VoteStanding {
+                    appid: 730,
+                    name: "Counter-Strike 2".to_string(),
+                    position: 2,
+                    points: 3,
+                    voter_count: 4,
+                    eliminated_in_round: Some(1),
+                },
+            ],
+        }
+    }
+}
+
+custom_errors!(EventVotingConfigGetError, Unauthorized, InternalServerError);
+
+/// Get how votes are counted for an event.
+#[openapi(tag = "Event Games")]
+#[get("/events/<event_id>/voting-config", format = "json")]
+pub async fn get(
+    event_id: i32,
+    pool: &State<PgPool>,
+    user: User,
+) -> Result<Json<EventVotingConfig>, EventVotingConfigGetError> {
+    match ensure_user_invited(pool, event_id, &user.email).await {
+        Ok(()) => (),
+        Err(Error::NotPermitted(e)) => return Err(EventVotingConfigGetError::Unauthorized(e)),
+        Err(e) => {
+            return Err(EventVotingConfigGetError::InternalServerError(format!(
+                "Error checking invitation, due to: {e}"
+            )))
+        }
+    }
+
+    match event_voting::get_config(pool, event_id).await {
+        Ok(config) => Ok(Json(config)),
+        Err(e) => Err(EventVotingConfigGetError::InternalServerError(format!(
+            "Error getting voting config, due to: {e}"
+        ))),
+    }
+}
+
+custom_errors!(
+    EventVotingConfigPutError,
+    Unauthorized,
+    BadRequest,
+    InternalServerError
+);
+
+/// Change how votes are counted for an event (admin only).
+/// Votes made with another method are kept, but only count if they suit the new method,
+/// so attendees may need to vote again.
+#[openapi(tag = "Event Games")]
+#[put(
+    "/events/<event_id>/voting-config?<_as_admin>",
+    format = "json",
+    data = "<config_submit>"
+)]
+pub async fn put(
+    event_id: i32,
+    config_submit: Json<EventVotingConfigSubmit>,
+    pool: &State<PgPool>,
+    _as_admin: Option<bool>,
+    user: AdminUser,
+) -> Result<Json<EventVotingConfig>, EventVotingConfigPutError> {
+    match event_voting::upsert_config(pool, event_id, config_submit.into_inner(), user.email).await
+    {
+        Ok(config) => Ok(Json(config)),
+        Err(Error::BadInput(e)) => Err(EventVotingConfigPutError::BadRequest(e)),
+        Err(e) => Err(EventVotingConfigPutError::InternalServerError(format!(
+            "Error saving voting config, due to: {e}"
+        ))),
+    }
+}
+
+custom_errors!(
+    VotingReopenError,
+    Unauthorized,
+    BadRequest,
+    InternalServerError
+);
+
+/// Reopen voting for an event after it has closed (admin only).
+/// The frozen results are thrown away, and counted again when voting next closes.
+#[openapi(tag = "Event Games")]
+#[post(
+    "/events/<event_id>/voting-config/reopen?<_as_admin>",
+    format = "json",
+    data = "<reopen>"
+)]
+pub async fn reopen(
+    event_id: i32,
+    reopen: Json<VotingReopen>,
+    pool: &State<PgPool>,
+    _as_admin: Option<bool>,
+    user: AdminUser,
+) -> Result<Json<EventVotingConfig>, VotingReopenError> {
+    match event_voting::reopen_voting(
+        pool,
+        event_id,
+        reopen.into_inner().voting_close_at,
+        user.email,
+    )
+    .await
+    {
+        Ok(config) => Ok(Json(config)),
+        Err(Error::BadInput(e)) => Err(VotingReopenError::BadRequest(e)),
+        Err(e) => Err(VotingReopenError::InternalServerError(format!(
+            "Error reopening voting, due to: {e}"
+        ))),
+    }
+}
+
+custom_errors!(
+    EventGameRankingPutError,
+    Unauthorized,
+    BadRequest,
+    InternalServerError
+);
+
+/// Rank the games suggested for an event, for events using ranked choice voting.
+/// Replaces any earlier ranking. Rankings can't be changed once voting has closed.
+#[openapi(tag = "Event Games")]
+#[put("/events/<event_id>/ranking", format = "json", data = "<ranking>")]
+pub async fn put_ranking(
+    event_id: i32,
+    ranking: Json<EventGameRanking>,
+    pool: &State<PgPool>,
+    user: User,
+) -> Result<Json<EventGameRanking>, EventGameRankingPutError> {
+    match event_voting::set_ranking(pool, event_id, user.email, ranking.into_inner().games).await {
+        Ok(ranking) => Ok(Json(ranking)),
+        Err(Error::BadInput(e)) => Err(EventGameRankingPutError::BadRequest(e)),
+        Err(Error::NotPermitted(e)) => Err(EventGameRankingPutError::Unauthorized(e)),
+        Err(e) => Err(EventGameRankingPutError::InternalServerError(format!(
+            "Error saving ranking, due to: {e}"
+        ))),
+    }
+}
+
+custom_errors!(
+    VoteResultsGetError,
+    Unauthorized,
+    Forbidden,
+    InternalServerError
+);
+
+/// Get how the games suggested for an event are doing in the vote, using the event's voting method.
+/// Once voting has closed, these are the frozen final results.
+#[openapi(tag = "Event Games")]
+#[get("/events/<event_id>/vote-results", format = "json")]
+pub async fn get_results(
+    event_id: i32,
+    pool: &State<PgPool>,
+    user: User,
+) -> Result<Json<VoteResults>, VoteResultsGetError> {
+    // Ensure user is invited to the event
+    if let Err(e) = ensure_user_invited(pool, event_id, &user.email).await {
+        return Err(VoteResultsGetError::Forbidden(format!(
+            "You are not invited to this event: {e}"
+        )));
+    }
+
+    match event_voting::get_results(pool, event_id, user.email).await {
+        Ok(results) => Ok(Json(results)),
+        Err(e) => Err(VoteResultsGetError::InternalServerError(format!(
+            "Error getting vote results, due to: {e}"
+        ))),
+    }
+}
@@ -0,0 +1,375 @@
+use crate::{
+    auth::{AdminUser, User},
+    controllers::{ensure_user_invited, game_comment, Error},
+    routes::event_games::Gamer,
+};
+use chrono::{prelude::Utc, DateTime};
+use rocket::{
+    delete, get, post, put,
+    response::status,
+    serde::{json::Json, Deserialize, Serialize},
+    State,
+};
+use rocket_okapi::okapi::schemars;
+use rocket_okapi::okapi::schemars::JsonSchema;
+use rocket_okapi::openapi;
+use sqlx::postgres::PgPool;
+
+use super::SchemaExample;
+
+/// How many people reacted to a comment with an emoji.
+#[derive(Clone, Serialize, JsonSchema)]
+#[serde(crate = "rocket::serde", rename_all = "camelCase")]
+pub struct GameCommentReaction {
+    pub emoji: String,
+    pub count: usize,
+    /// Whether you reacted with this emoji.
+    pub reacted: bool,
+}
+
+/// A comment on a suggested game, with its replies.
+#[derive(Clone, Serialize, JsonSchema)]
+#[serde(crate = "rocket::serde", rename_all = "camelCase")]
+#[schemars(example = "Self::example")]
+pub struct GameComment {
+    pub id: i32,
+    /// The comment this is a reply to.
+    pub parent_id: Option<i32>,
+    pub author: Gamer,
+    /// Whether you wrote the comment, so can edit or delete it.
+    pub own: bool,
+    /// Empty once the comment has been deleted.
+    pub body: Option<String>,
+    pub created_at: DateTime<Utc>,
+    pub edited_at: Option<DateTime<Utc>>,
+    pub deleted_at: Option<DateTime<Utc>>,
+    pub reactions: Vec<GameCommentReaction>,
+    /// Replies, oldest first. Deleted comments are only kept while they have replies.
+    pub replies: Vec<Self>,
+}
+
+impl SchemaExample for GameComment {
+    fn example() -> Self {
+        Self {
+            id: 1,
+            parent_id: None,
+            author: Gamer {
+                avatar_url: Some(
+                    "https://www.gravatar.com/avatar/27205e5c51cb03f862138b22bcb5dc20?d=robohash"
+                        .to_string(),
+                ),
+                handle: Some("Lewis".to_string()),
+            },
+            own: false,
+            body: Some("We played this last time and it was great with 8.".to_string()),
+            created_at: Utc::now(),
+            edited_at: None,
+            deleted_at: None,
+            reactions: vec![GameCommentReaction {
+                emoji: "👍".to_string(),
+                count: 3,
+                reacted: true,
+            }],
+            replies: vec![Self {
+                id: 2,
+                parent_id: Some(1),
+                author: Gamer {
+                    avatar_url: None,
+                    handle: Some("Sam".to_string()),
+                },
+                own: true,
+                body: Some("Agreed, it's better than the sequel.".to_string()),
+                created_at: Utc::now(),
+                edited_at: Some(Utc::now()),
+                deleted_at: None,
+                reactions: Vec::new(),
+                replies: Vec::new(),
+            }],
+        }
+    }
+}
+
+/// The response for the `GET /events/{eventId}/suggested_games/{gameId}/comments` endpoint.
+#[derive(Serialize, JsonSchema)]
+#[serde(crate = "rocket::serde", rename_all = "camelCase")]
+pub struct GameCommentThread {
+    /// How many comments there are, including replies but not deleted comments.
+    pub comment_count: usize,
+    /// Top level comments, oldest first.
+    pub comments: Vec<GameComment>,
+}
+
+/// The request body for commenting on a suggested game.
+#[derive(Deserialize, Serialize, JsonSchema)]
+#[serde(crate = "rocket::serde", rename_all = "camelCase")]
+#[schemars(example = "Self::example")]
+pub struct GameCommentSubmit {
+    pub body: String,
+    /// The comment this is a reply to.
+    #[serde(default)]
+    pub parent_id: Option<i32>,
+}
+
+impl SchemaExample for GameCommentSubmit {
+    fn example() -> Self {
+        Self {
+            body: "Does anyone have the DLC?".to_string(),
+            parent_id: None,
+        }
+    }
+}
+
+/// The request body for editing a comment.
+#[derive(Deserialize, Serialize, JsonSchema)]
+#[serde(crate = "rocket::serde", rename_all = "camelCase")]
+pub struct GameCommentUpdate {
+    pub body: String,
+}
+
+custom_errors!(
+    GameCommentsGetError,
+    Unauthorized,
+    Forbidden,
+    InternalServerError
+);
+
+/// Get the discussion about a suggested game.
+#[openapi(tag = "Event Games")]
+#[get(
+    "/events/<event_id>/suggested_games/<game_id>/comments",
+    format = "json"
+)]
+pub async fn get_all(
+    event_id: i32,
+    game_id: i64,
+    pool: &State<PgPool>,
+    user: User,
+) -> Result<Json<GameCommentThread>, GameCommentsGetError> {
+    // Ensure user is invited to the event
+    if let Err(e) = ensure_user_invited(pool, event_id, &user.email).await {
+        return Err(GameCommentsGetError::Forbidden(format!(
+            "You are not invited to this event: {e}"
+        )));
+    }
+
+    match game_comment::get_thread(pool, event_id, game_id, user.email).await {
+        Ok(thread) => Ok(Json(thread)),
+        Err(e) => Err(GameCommentsGetError::InternalServerError(format!(
+            "Error getting comments, due to: {e}"
+        ))),
+    }
+}
+
+custom_errors!(
+    GameCommentPostError,
+    Unauthorized,
+    BadRequest,
+    NotFound,
+    InternalServerError
+);
+
+/// Comment on a suggested game, or reply to a comment.
+#[openapi(tag = "Event Games")]
+#[post(
+    "/events/<event_id>/suggested_games/<game_id>/comments",
+    format = "json",
+    data = "<comment_submit>"
+)]
+pub async fn post(
+    event_id: i32,
+    game_id: i64,
+    comment_submit: Json<GameCommentSubmit>,
+    pool: &State<PgPool>,
+    user: User,
+) -> Result<status::Created<Json<GameComment>>, GameCommentPostError> {
+    match game_comment::create(
+        pool,
+        event_id,
+        game_id,
+        user.email,
+        comment_submit.into_inner(),
+    )
+    .await
+    {
+        Ok(comment) => Ok(status::Created::new(format!(
+            "/events/{event_id}/suggested_games/{game_id}/comments/{}",
+            comment.id
+        ))
+        .body(Json(comment))),
+        Err(Error::NotPermitted(e)) => Err(GameCommentPostError::Unauthorized(e)),
+        Err(Error::BadInput(e)) => Err(GameCommentPostError::BadRequest(e)),
+        Err(Error::NotFound(e)) => Err(GameCommentPostError::NotFound(e)),
+        Err(e) => Err(GameCommentPostError::InternalServerError(format!(
+            "Error creating comment, due to: {e}"
+        ))),
+    }
+}
+
+custom_errors!(
+    GameCommentPutError,
+    Unauthorized,
+    BadRequest,
+    NotFound,
+    InternalServerError
+);
+
+/// Edit one of your comments. Replies aren't included in the response.
+#[openapi(tag = "Event Games")]
+#[put(
+    "/events/<event_id>/suggested_games/<game_id>/comments/<comment_id>",
+    format = "json",
+    data = "<comment_update>"
+)]
+pub async fn put(
+    event_id: i32,
+    game_id: i64,
+    comment_id: i32,
+    comment_update: Json<GameCommentUpdate>,
+    pool: &State<PgPool>,
+    user: User,
+) -> Result<Json<GameComment>, GameCommentPutError> {
+    match game_comment::update(
+        pool,
+        event_id,
+        game_id,
+        comment_id,
+        user.email,
+        comment_update.into_inner().body,
+    )
+    .await
+    {
+        Ok(comment) => Ok(Json(comment)),
+        Err(Error::NotPermitted(e)) => Err(GameCommentPutError::Unauthorized(e)),
+        Err(Error::BadInput(e)) => Err(GameCommentPutError::BadRequest(e)),
+        Err(Error::NotFound(e)) => Err(GameCommentPutError::NotFound(e)),
+        Err(e) => Err(GameCommentPutError::InternalServerError(format!(
+            "Error updating comment, due to: {e}"
+        ))),
+    }
+}
+
+custom_errors!(
+    GameCommentDeleteError,
+    Unauthorized,
+    NotFound,
+    InternalServerError
+);
+
+/// Delete any comment (admin only).
+#[openapi(tag = "Event Games")]
+#[delete("/events/<event_id>/suggested_games/<game_id>/comments/<comment_id>?<_as_admin>")]
+pub async fn delete_admin(
+    event_id: i32,
+    game_id: i64,
+    comment_id: i32,
+    pool: &State<PgPool>,
+    _as_admin: Option<bool>,
+    user: AdminUser,
+) -> Result<status::NoContent, GameCommentDeleteError> {
+    delete_response(
+        game_comment::delete(pool, event_id, game_id, comment_id, user.email, true).await,
+    )
+}
+
+/// Delete one of your comments. Replies to it are kept.
+///
+/// Rank 2: Lower priority than the admin route (rank 1) for the same path.
+#[openapi(tag = "Event Games")]
+#[delete(
+    "/events/<event_id>/suggested_games/<game_id>/comments/<comment_id>",
+    rank = 2
+)]
+pub async fn delete(
+    event_id: i32,
+    game_id: i64,
+    comment_id: i32,
+    pool: &State<PgPool>,
+    user: User,
+) -> Result<status::NoContent, GameCommentDeleteError> {
+    delete_response(
+        game_comment::delete(pool, event_id, game_id, comment_id, user.email, false).await,
+    )
+}
+
+fn delete_response(result: Result<(), Error>) -> Result<status::NoContent, GameCommentDeleteError> {
+    match result {
+        Ok(()) => Ok(status::NoContent),
+        Err(Error::NotPermitted(e)) => Err(GameCommentDeleteError::Unauthorized(e)),
+        Err(Error::NotFound(e)) => Err(GameCommentDeleteError::NotFound(e)),
+        Err(e) => Err(GameCommentDeleteError::InternalServerError(format!(
+            "Error deleting comment, due to: {e}"
+        ))),
+    }
+}
+
+custom_errors!(
+    GameCommentReactionError,
+    Unauthorized,
+    BadRequest,
+    NotFound,
+    InternalServerError
+);
+
+fn reaction_response(
+    result: Result<GameComment, Error>,
+) -> Result<Json<GameComment>, GameCommentReactionError> {
+    match result {
+        Ok(comment) => Ok(Json(comment)),
+        Err(Error::NotPermitted(e)) => Err(GameCommentReactionError::Unauthorized(e)),
+        Err(Error::BadInput(e)) => Err(GameCommentReactionError::BadRequest(e)),
+        Err(Error::NotFound(e)) => Err(GameCommentReactionError::NotFound(e)),
+        Err(e) => Err(GameCommentReactionError::InternalServerError(format!(
+            "Error updating reaction, due to: {e}"
+        ))),
+    }
+}
+
+/// React to a comment with an emoji. Replies aren't included in the response.
+#[openapi(tag = "Event Games")]
+#[put("/events/<event_id>/suggested_games/<game_id>/comments/<comment_id>/reactions/<emoji>")]
+pub async fn put_reaction(
+    event_id: i32,
+    game_id: i64,
+    comment_id: i32,
+    emoji: &str,
+    pool: &State<PgPool>,
+    user: User,
+) -> Result<Json<GameComment>, GameCommentReactionError> {
+    reaction_response(
+        game_comment::react(
+            pool,
+            event_id,
+            game_id,
+            comment_id,
+            user.email,
+            emoji.to_string(),
+            true,
+        )
+        .await,
+    )
+}
+
+/// Take back a reaction to a comment. Replies aren't included in the response.
+#[openapi(tag = "Event Games")]
+#[delete("/events/<event_id>/suggested_games/<game_id>/comments/<comment_id>/reactions/<emoji>")]
+pub async fn delete_reaction(
+    event_id: i32,
+    game_id: i64,
+    comment_id: i32,
+    emoji: &str,
+    pool: &State<PgPool>,
+    user: User,
+) -> Result<Json<GameComment>, GameCommentReactionError> {
+    reaction_response(
+        game_comment::react(
+            pool,
+            event_id,
+            game_id,
+            comment_id,
+            user.email,
+            emoji.to_string(),
+            false,
+        )
+        .await,
+    )
+}
@@ -1,10 +1,10 @@
 use crate::{
     auth::{AdminUser, User},
-    controllers::{game_schedule, Error},
+    controllers::{ensure_user_invited, game_schedule, Error},
 };
-use chrono::{DateTime, Utc};
+use chrono::{DateTime, NaiveTime, Utc};
 use rocket::{
-    delete, get, patch, post,
+    delete, get, patch, post, put,
     serde::{json::Json, Deserialize, Serialize},
     State,
 };
@@ -13,7 +13,21 @@ use rocket_okapi::okapi::schemars::JsonSchema;
 use rocket_okapi::openapi;
 use sqlx::postgres::PgPool;
 
-use super::SchemaExample;
+use super::{games::GameDurationSubmit, SchemaExample};
+
+/// Where a game's scheduled duration came from.
+#[derive(Debug, Clone, Copy, Deserialize, Serialize, JsonSchema, PartialEq, Eq)]
+#[serde(crate = "rocket::serde", rename_all = "camelCase")]
+pub enum GameDurationSource {
+    /// An admin set how long the game takes at this event.
+    Event,
+    /// An admin set how long the game usually takes.
+    Game,
+    /// How long the game was scheduled for at other events.
+    PastSchedules,
+    /// The event's default duration, as nothing else is known.
+    Default,
+}
 
 /// A scheduled game entry in the calendar
 #[derive(Clone, Serialize, JsonSchema)]
@@ -38,6 +52,22 @@ pub struct GameScheduleEntry {
     /// How long the game is scheduled for (in minutes).
     pub duration_minutes: i32,
 
+    /// Minutes left free before the game to set up or download it.
+    pub setup_minutes: i32,
+
+    /// Where the duration of a suggested game came from. Not set for pinned games.
+    pub duration_source: Option<GameDurationSource>,
+
+    /// Which group is playing, counting from 1, when a suggested game is split between
+    /// groups playing at the same time.
+    pub group: Option<i32>,
+
+    /// The track the game is on, counting from 1, when several games are played at once.
+    pub track: i32,
+
+    /// The room the game is in, if the event's tracks are bound to rooms.
+    pub room_id: Option<i32>,
+
     /// Whether this is a pinned (manually scheduled) game.
     pub is_pinned: bool,
 
@@ -45,6 +75,10 @@ pub struct GameScheduleEntry {
     /// Slice 3 will populate this for suggested games.
     pub is_suggested: bool,
 
+    /// Why a suggested game was scheduled when it was, if an explanation was asked for.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub explanation: Option<GameScheduleExplanation>,
+
     /// The date this schedule entry was created.
     pub created_at: DateTime<Utc>,
 
@@ -61,14 +95,132 @@ impl SchemaExample for GameScheduleEntry {
             game_name: "Counter-Strike 2".to_string(),
             start_time: Utc::now(),
             duration_minutes: 120,
+            setup_minutes: 0,
+            duration_source: None,
+            group: None,
+            track: 1,
+            room_id: None,
             is_pinned: true,
             is_suggested: false,
+            explanation: None,
             created_at: Utc::now(),
             last_modified: Utc::now(),
         }
     }
 }
 
+/// Why a suggested game was scheduled when it was
+#[derive(Clone, Serialize, JsonSchema)]
+#[serde(crate = "rocket::serde", rename_all = "camelCase")]
+#[schemars(example = "Self::example")]
+pub struct GameScheduleExplanation {
+    /// Emails of voters for the game who are free when it's scheduled.
+    pub available_voters: Vec<String>,
+
+    /// Emails of voters for the game who aren't at the event then, or are playing something else.
+    pub unavailable_voters: Vec<String>,
+
+    /// The next best times the game could have started, best first.
+    pub runners_up: Vec<ScheduleSlotScore>,
+}
+
+impl SchemaExample for GameScheduleExplanation {
+    fn example() -> Self {
+        Self {
+            available_voters: vec!["gamer@example.com".to_string()],
+            unavailable_voters: vec!["late@example.com".to_string()],
+            runners_up: vec![ScheduleSlotScore::example()],
+        }
+    }
+}
+
+/// A time a game could start at, and how many of its voters could play it then
+#[derive(Clone, Serialize, JsonSchema)]
+#[serde(crate = "rocket::serde", rename_all = "camelCase")]
+#[schemars(example = "Self::example")]
+pub struct ScheduleSlotScore {
+    pub start_time: DateTime<Utc>,
+    pub track: i32,
+    pub room_id: Option<i32>,
+    pub availability_score: i32,
+}
+
+impl SchemaExample for ScheduleSlotScore {
+    fn example() -> Self {
+        Self {
+            start_time: Utc::now(),
+            track: 1,
+            room_id: None,
+            availability_score: 4,
+        }
+    }
+}
+
+/// Why a game couldn't be scheduled.
+#[derive(Debug, Clone, Copy, Deserialize, Serialize, JsonSchema, PartialEq, Eq)]
+#[serde(crate = "rocket::serde", rename_all = "camelCase")]
+pub enum UnscheduledReason {
+    /// The game takes longer than the event runs for.
+    OutsideEvent,
+    /// Not enough of the game's voters are free at any time it fits in the event.
+    NoVoterAvailable,
+    /// Whenever enough voters are free, every track is taken or the time is blocked.
+    NoSlotFits,
+}
+
+/// A suggested game the scheduler couldn't find a time for
+#[derive(Clone, Serialize, JsonSchema)]
+#[serde(crate = "rocket::serde", rename_all = "camelCase")]
+#[schemars(example = "Self::example")]
+pub struct UnscheduledGame {
+    pub game_id: i64,
+    pub game_name: String,
+    pub reason: UnscheduledReason,
+}
+
+impl SchemaExample for UnscheduledGame {
+    fn example() -> Self {
+        Self {
+            game_id: 570,
+            game_name: "Dota 2".to_string(),
+            reason: UnscheduledReason::NoSlotFits,
+        }
+    }
+}
+
+/// The response for the `POST /events/{eventId}/game_schedule/recalculate` endpoint.
+/// Without `explain` this is just the list of suggested games.
+#[derive(Clone, Serialize, JsonSchema)]
+#[serde(crate = "rocket::serde", untagged)]
+pub enum RecalculateResponse {
+    Suggested(Vec<GameScheduleEntry>),
+    Explained(RecalculatedSchedule),
+}
+
+/// A recalculated schedule, explained
+#[derive(Clone, Serialize, JsonSchema)]
+#[serde(crate = "rocket::serde", rename_all = "camelCase")]
+#[schemars(example = "Self::example")]
+pub struct RecalculatedSchedule {
+    /// The suggested games, explained if an explanation was asked for.
+    pub suggested: Vec<GameScheduleEntry>,
+
+    /// Games that couldn't be scheduled, and why.
+    pub unscheduled: Vec<UnscheduledGame>,
+}
+
+impl SchemaExample for RecalculatedSchedule {
+    fn example() -> Self {
+        Self {
+            suggested: vec![GameScheduleEntry {
+                explanation: Some(GameScheduleExplanation::example()),
+                ..GameScheduleEntry::example()
+            }],
+            unscheduled: vec![UnscheduledGame::example()],
+        }
+    }
+}
+
 /// Request body for creating/updating a scheduled game
 #[derive(Deserialize, Serialize, JsonSchema)]
 #[serde(crate = "rocket::serde", rename_all = "camelCase")]
@@ -77,6 +229,12 @@ pub struct GameScheduleRequest {
     pub game_id: i64,
     pub start_time: DateTime<Utc>,
     pub duration_minutes: i32,
+    /// The track to put the game on, the first track if not given.
+    #[serde(default)]
+    pub track: Option<i32>,
+    /// The room the game is in.
+    #[serde(default)]
+    pub room_id: Option<i32>,
 }
 
 impl SchemaExample for GameScheduleRequest {
@@ -85,6 +243,238 @@ impl SchemaExample for GameScheduleRequest {
             game_id: 730,
             start_time: Utc::now(),
             duration_minutes: 120,
+            track: Some(1),
+            room_id: None,
+        }
+    }
+}
+
+/// How long a game suggested for an event will be scheduled for.
+#[derive(Clone, Serialize, JsonSchema)]
+#[serde(crate = "rocket::serde", rename_all = "camelCase")]
+#[schemars(example = "Self::example")]
+pub struct GameDuration {
+    pub game_id: i64,
+    pub game_name: String,
+    pub duration_minutes: i32,
+    pub source: GameDurationSource,
+}
+
+impl SchemaExample for GameDuration {
+    fn example() -> Self {
+        Self {
+            game_id: 730,
+            game_name: "Counter-Strike 2".to_string(),
+            duration_minutes: 90,
+            source: GameDurationSource::PastSchedules,
+        }
+    }
+}
+
+/// The response for the `GET /events/{eventId}/game_schedule/config` endpoint.
+#[derive(Clone, Serialize, JsonSchema)]
+#[serde(crate = "rocket::serde", rename_all = "camelCase")]
+#[schemars(example = "Self::example")]
+pub struct EventScheduleConfig {
+    /// The event ID this configuration belongs to.
+    pub event_id: i32,
+
+    /// How long games are scheduled for when nothing else is known about them.
+    pub default_duration_minutes: i32,
+
+    /// Minutes left free between games to set up or download the next one.
+    pub setup_buffer_minutes: i32,
+
+    /// Whether to estimate how long games take from how long they were scheduled for at other events.
+    pub use_past_schedules: bool,
+
+    /// Whether a game more people want to play than it has room for can be scheduled
+    /// for two groups at the same time.
+    pub allow_parallel_groups: bool,
+
+    /// How many games can be played at the same time, each on its own track.
+    pub track_count: i32,
+
+    /// Whether to have one track per room in the event instead, in the rooms' order.
+    pub tracks_from_rooms: bool,
+
+    /// Whether to search for a schedule that suits more voters, rather than giving each game
+    /// the best slot left in vote order. The same event always gets the same schedule.
+    pub use_optimiser: bool,
+
+    /// When quiet hours start each day (UTC), when no games are played.
+    pub quiet_hours_start: NaiveTime,
+
+    /// When quiet hours end each day (UTC). There are no quiet hours if this is the same as the start.
+    pub quiet_hours_end: NaiveTime,
+
+    /// The date the configuration was created.
+    pub created_at: DateTime<Utc>,
+
+    /// The last time this configuration was modified.
+    pub last_modified: DateTime<Utc>,
+}
+
+impl SchemaExample for EventScheduleConfig {
+    fn example() -> Self {
+        Self {
+            event_id: 1,
+            default_duration_minutes: 120,
+            setup_buffer_minutes: 15,
+            use_past_schedules: true,
+            allow_parallel_groups: false,
+            track_count: 2,
+            tracks_from_rooms: false,
+            use_optimiser: true,
+            quiet_hours_start: NaiveTime::from_hms_opt(1, 0, 0).unwrap_or_default(),
+            quiet_hours_end: NaiveTime::from_hms_opt(10, 0, 0).unwrap_or_default(),
+            created_at: Utc::now(),
+            last_modified: Utc::now(),
+        }
+    }
+}
+
+/// The request body for updating schedule configuration.
+#[derive(Deserialize, Serialize, JsonSchema)]
+#[serde(crate = "rocket::serde", rename_all = "camelCase")]
+#[schemars(example = "Self::example")]
+pub struct EventScheduleConfigSubmit {
+    pub default_duration_minutes: i32,
+    pub setup_buffer_minutes: i32,
+    pub use_past_schedules: bool,
+    #[serde(default)]
+    pub allow_parallel_groups: bool,
+    /// How many games can be played at the same time, one if not given.
+    #[serde(default)]
+    pub track_count: Option<i32>,
+    #[serde(default)]
+    pub tracks_from_rooms: bool,
+    #[serde(default)]
+    pub use_optimiser: bool,
+    /// When quiet hours start each day (UTC), 1am if not given.
+    #[serde(default)]
+    pub quiet_hours_start: Option<NaiveTime>,
+    /// When quiet hours end each day (UTC), 10am if not given.
+    #[serde(default)]
+    pub quiet_hours_end: Option<NaiveTime>,
+}
+
+impl SchemaExample for EventScheduleConfigSubmit {
+    fn example() -> Self {
+        Self {
+            default_duration_minutes: 120,
+            setup_buffer_minutes: 15,
+            use_past_schedules: true,
+            allow_parallel_groups: true,
+            track_count: Some(2),
+            tracks_from_rooms: false,
+            use_optimiser: true,
+            quiet_hours_start: NaiveTime::from_hms_opt(0, 0, 0),
+            quiet_hours_end: NaiveTime::from_hms_opt(9, 0, 0),
+        }
+    }
+}
+
+/// A period no games can be played in, such as dinner or the venue being closed.
+#[derive(Clone, Serialize, JsonSchema)]
+#[serde(crate = "rocket::serde", rename_all = "camelCase")]
+#[schemars(example = "Self::example")]
+pub struct BlockedPeriod {
+    /// The blocked period ID.
+    pub id: i32,
+
+    /// The event ID this period is blocked in.
+    pub event_id: i32,
+
+    /// What's happening instead of games.
+    pub name: String,
+
+    /// When the period starts.
+    pub start_time: DateTime<Utc>,
+
+    /// When the period ends.
+    pub end_time: DateTime<Utc>,
+
+    /// The date the blocked period was created.
+    pub created_at: DateTime<Utc>,
+
+    /// The last time this blocked period was modified.
+    pub last_modified: DateTime<Utc>,
+}
+
+impl SchemaExample for BlockedPeriod {
+    fn example() -> Self {
+        Self {
+            id: 1,
+            event_id: 1,
+            name: "Dinner".to_string(),
+            start_time: Utc::now(),
+            end_time: Utc::now() + chrono::Duration::hours(1),
+            created_at: Utc::now(),
+            last_modified: Utc::now(),
+        }
+    }
+}
+
+/// Request body for creating/updating a blocked period
+#[derive(Deserialize, Serialize, JsonSchema)]
+#[serde(crate = "rocket::serde", rename_all = "camelCase")]
+#[schemars(example = "Self::example")]
+pub struct BlockedPeriodSubmit {
+    pub name: String,
+    pub start_time: DateTime<Utc>,
+    pub end_time: DateTime<Utc>,
+}
+
+impl SchemaExample for BlockedPeriodSubmit {
+    fn example() -> Self {
+        Self {
+            name: "Tournament final".to_string(),
+            start_time: Utc::now(),
+            end_time: Utc::now() + chrono::Duration::hours(2),
+        }
+    }
+}
+
+/// Why no games are scheduled in a block of time.
+#[derive(Debug, Clone, Copy, Deserialize, Serialize, JsonSchema, PartialEq, Eq)]
+#[serde(crate = "rocket::serde", rename_all = "camelCase")]
+pub enum ScheduleBlockKind {
+    /// The event's quiet hours.
+    QuietHours,
+    /// A period an admin blocked out.
+    Blocked,
+}
+
+/// A block of time on the schedule with no games in it
+#[derive(Clone, Serialize, JsonSchema)]
+#[serde(crate = "rocket::serde", rename_all = "camelCase")]
+#[schemars(example = "Self::example")]
+pub struct ScheduleBlock {
+    /// The blocked period's ID. Not set for quiet hours.
+    pub blocked_period_id: Option<i32>,
+
+    /// What's happening instead of games.
+    pub name: String,
+
+    /// When the block starts.
+    pub start_time: DateTime<Utc>,
+
+    /// When the block ends.
+    pub end_time: DateTime<Utc>,
+
+    /// Why there are no games.
+    pub kind: ScheduleBlockKind,
+}
+
+impl SchemaExample for ScheduleBlock {
+    fn example() -> Self {
+        Self {
+            blocked_period_id: None,
+            name: "Quiet hours".to_string(),
+            start_time: Utc::now(),
+            end_time: Utc::now() + chrono::Duration::hours(9),
+            kind: ScheduleBlockKind::QuietHours,
         }
     }
 }
@@ -132,6 +522,7 @@ pub async fn create(
     match game_schedule::create(pool, event_id, request.into_inner(), &admin_user.email).await {
         Ok(entry) => Ok(Json(entry)),
         Err(Error::NotPermitted(e)) => Err(GameScheduleCreateError::Unauthorized(e)),
+        Err(Error::BadInput(e)) => Err(GameScheduleCreateError::BadRequest(e)),
         Err(Error::Controller(e)) if e.contains("overlap") => {
             Err(GameScheduleCreateError::BadRequest(e))
         }
@@ -145,6 +536,7 @@ custom_errors!(
     GameScheduleUpdateError,
     BadRequest,
     Unauthorized,
+    NotFound,
     InternalServerError
 );
 
@@ -165,6 +557,8 @@ pub async fn update(
     match game_schedule::update(pool, schedule_id, request.into_inner(), &admin_user.email).await {
         Ok(entry) => Ok(Json(entry)),
         Err(Error::NotPermitted(e)) => Err(GameScheduleUpdateError::Unauthorized(e)),
+        Err(Error::BadInput(e)) => Err(GameScheduleUpdateError::BadRequest(e)),
+        Err(Error::NotFound(e)) => Err(GameScheduleUpdateError::NotFound(e)),
         Err(Error::Controller(e)) if e.contains("overlap") || e.contains("not found") => {
             Err(GameScheduleUpdateError::BadRequest(e))
         }
@@ -226,6 +620,7 @@ pub async fn pin(
     match game_schedule::pin(pool, event_id, request.into_inner(), &admin_user.email).await {
         Ok(entry) => Ok(Json(entry)),
         Err(Error::NotPermitted(e)) => Err(GameSchedulePinError::Unauthorized(e)),
+        Err(Error::BadInput(e)) => Err(GameSchedulePinError::BadRequest(e)),
         Err(Error::Controller(e)) if e.contains("overlap") => {
             Err(GameSchedulePinError::BadRequest(e))
         }
@@ -241,22 +636,30 @@ custom_errors!(
     InternalServerError
 );
 
-/// Force recalculation of suggested game schedule (admin only)
+/// Force recalculation of suggested game schedule (admin only).
+/// Set `explain` to find out who can play each game when it's scheduled, where else it could
+/// have gone, and why any games couldn't be scheduled. Without it, only the suggested games are given.
 #[openapi(tag = "Game Schedule")]
-#[post("/events/<event_id>/game_schedule/recalculate")]
+#[post("/events/<event_id>/game_schedule/recalculate?<explain>")]
 pub async fn recalculate_suggested_schedule(
     event_id: i32,
+    explain: Option<bool>,
     pool: &State<PgPool>,
     _admin_user: AdminUser,
-) -> Result<Json<Vec<GameScheduleEntry>>, GameScheduleRecalculateError> {
-    match game_schedule::schedule_suggested_games(pool, event_id).await {
-        Ok(suggested) => {
+) -> Result<Json<RecalculateResponse>, GameScheduleRecalculateError> {
+    let explain = explain.unwrap_or(false);
+    match game_schedule::schedule_suggested_games(pool, event_id, explain).await {
+        Ok(recalculated) => {
             log::info!(
                 "Admin forced recalculation of {} suggested games for event {}",
-                suggested.len(),
+                recalculated.suggested.len(),
                 event_id
             );
-            Ok(Json(suggested))
+            if explain {
+                Ok(Json(RecalculateResponse::Explained(recalculated)))
+            } else {
+                Ok(Json(RecalculateResponse::Suggested(recalculated.suggested)))
+            }
         }
         Err(Error::NotPermitted(e)) => Err(GameScheduleRecalculateError::Unauthorized(e)),
         Err(e) => Err(GameScheduleRecalculateError::InternalServerError(format!(
@@ -264,3 +667,250 @@ pub async fn recalculate_suggested_schedule(
         ))),
     }
 }
+
+custom_errors!(
+    EventScheduleConfigGetError,
+    Unauthorized,
+    InternalServerError
+);
+
+/// Get how games are scheduled for an event.
+#[openapi(tag = "Game Schedule")]
+#[get("/events/<event_id>/game_schedule/config", format = "json")]
+pub async fn get_config(
+    event_id: i32,
+    pool: &State<PgPool>,
+    user: User,
+) -> Result<Json<EventScheduleConfig>, EventScheduleConfigGetError> {
+    match ensure_user_invited(pool, event_id, &user.email).await {
+        Ok(()) => (),
+        Err(Error::NotPermitted(e)) => return Err(EventScheduleConfigGetError::Unauthorized(e)),
+        Err(e) => {
+            return Err(EventScheduleConfigGetError::InternalServerError(format!(
+                "Error checking invitation, due to: {e}"
+            )))
+        }
+    }
+
+    match game_schedule::get_config(pool, event_id).await {
+        Ok(config) => Ok(Json(config)),
+        Err(e) => Err(EventScheduleConfigGetError::InternalServerError(format!(
+            "Error getting schedule config, due to: {e}"
+        ))),
+    }
+}
+
+custom_errors!(
+    EventScheduleConfigPutError,
+    Unauthorized,
+    BadRequest,
+    InternalServerError
+);
+
+/// Change how games are scheduled for an event (admin only)
+#[openapi(tag = "Game Schedule")]
+#[put(
+    "/events/<event_id>/game_schedule/config",
+    format = "json",
+    data = "<config_submit>"
+)]
+pub async fn put_config(
+    event_id: i32,
+    config_submit: Json<EventScheduleConfigSubmit>,
+    pool: &State<PgPool>,
+    admin_user: AdminUser,
+) -> Result<Json<EventScheduleConfig>, EventScheduleConfigPutError> {
+    match game_schedule::upsert_config(pool, event_id, config_submit.into_inner(), admin_user.email)
+        .await
+    {
+        Ok(config) => Ok(Json(config)),
+        Err(Error::BadInput(e)) => Err(EventScheduleConfigPutError::BadRequest(e)),
+        Err(e) => Err(EventScheduleConfigPutError::InternalServerError(format!(
+            "Error saving schedule config, due to: {e}"
+        ))),
+    }
+}
+
+custom_errors!(GameDurationsGetError, Unauthorized, InternalServerError);
+
+/// Get how long each game suggested for an event will be scheduled for, and why
+#[openapi(tag = "Game Schedule")]
+#[get("/events/<event_id>/game_schedule/durations", format = "json")]
+pub async fn get_durations(
+    event_id: i32,
+    pool: &State<PgPool>,
+    user: User,
+) -> Result<Json<Vec<GameDuration>>, GameDurationsGetError> {
+    match game_schedule::get_durations(pool, event_id, &user.email).await {
+        Ok(durations) => Ok(Json(durations)),
+        Err(Error::NotPermitted(e)) => Err(GameDurationsGetError::Unauthorized(e)),
+        Err(e) => Err(GameDurationsGetError::InternalServerError(format!(
+            "Error getting game durations, due to: {e}"
+        ))),
+    }
+}
+
+custom_errors!(
+    GameDurationPutError,
+    Unauthorized,
+    BadRequest,
+    NotFound,
+    InternalServerError
+);
+
+/// Set how long a suggested game will be scheduled for at this event (admin only).
+/// Set the duration to null to go back to how long the game usually takes.
+#[openapi(tag = "Game Schedule")]
+#[put(
+    "/events/<event_id>/game_schedule/durations/<game_id>",
+    format = "json",
+    data = "<duration_submit>"
+)]
+pub async fn put_duration(
+    event_id: i32,
+    game_id: i64,
+    duration_submit: Json<GameDurationSubmit>,
+    pool: &State<PgPool>,
+    admin_user: AdminUser,
+) -> Result<Json<GameDuration>, GameDurationPutError> {
+    match game_schedule::set_duration(
+        pool,
+        event_id,
+        game_id,
+        duration_submit.into_inner().duration_minutes,
+        admin_user.email,
+    )
+    .await
+    {
+        Ok(duration) => Ok(Json(duration)),
+        Err(Error::BadInput(e)) => Err(GameDurationPutError::BadRequest(e)),
+        Err(Error::NotFound(e)) => Err(GameDurationPutError::NotFound(e)),
+        Err(e) => Err(GameDurationPutError::InternalServerError(format!(
+            "Error saving game duration, due to: {e}"
+        ))),
+    }
+}
+
+custom_errors!(ScheduleBlocksGetError, Unauthorized, InternalServerError);
+
+/// Get the blocks of time with no games in them for an event: quiet hours and blocked periods
+#[openapi(tag = "Game Schedule")]
+#[get("/events/<event_id>/game_schedule/blocks", format = "json")]
+pub async fn get_blocks(
+    event_id: i32,
+    pool: &State<PgPool>,
+    user: User,
+) -> Result<Json<Vec<ScheduleBlock>>, ScheduleBlocksGetError> {
+    match game_schedule::get_blocks(pool, event_id, &user.email).await {
+        Ok(blocks) => Ok(Json(blocks)),
+        Err(Error::NotPermitted(e)) => Err(ScheduleBlocksGetError::Unauthorized(e)),
+        Err(e) => Err(ScheduleBlocksGetError::InternalServerError(format!(
+            "Error getting schedule blocks, due to: {e}"
+        ))),
+    }
+}
+
+custom_errors!(
+    BlockedPeriodCreateError,
+    Unauthorized,
+    BadRequest,
+    NotFound,
+    InternalServerError
+);
+
+/// Block out a period in which no games can be scheduled (admin only)
+#[openapi(tag = "Game Schedule")]
+#[post(
+    "/events/<event_id>/game_schedule/blocked_periods",
+    format = "json",
+    data = "<blocked_period_submit>"
+)]
+pub async fn create_blocked_period(
+    event_id: i32,
+    blocked_period_submit: Json<BlockedPeriodSubmit>,
+    pool: &State<PgPool>,
+    admin_user: AdminUser,
+) -> Result<Json<BlockedPeriod>, BlockedPeriodCreateError> {
+    match game_schedule::create_blocked_period(
+        pool,
+        event_id,
+        blocked_period_submit.into_inner(),
+        admin_user.email,
+    )
+    .await
+    {
+        Ok(blocked_period) => Ok(Json(blocked_period)),
+        Err(Error::BadInput(e)) => Err(BlockedPeriodCreateError::BadRequest(e)),
+        Err(Error::NotFound(e)) => Err(BlockedPeriodCreateError::NotFound(e)),
+        Err(e) => Err(BlockedPeriodCreateError::InternalServerError(format!(
+            "Error creating blocked period, due to: {e}"
+        ))),
+    }
+}
+
+custom_errors!(
+    BlockedPeriodUpdateError,
+    Unauthorized,
+    BadRequest,
+    NotFound,
+    InternalServerError
+);
+
+/// Change a blocked period (admin only)
+#[openapi(tag = "Game Schedule")]
+#[put(
+    "/events/<event_id>/game_schedule/blocked_periods/<blocked_period_id>",
+    format = "json",
+    data = "<blocked_period_submit>"
+)]
+pub async fn update_blocked_period(
+    event_id: i32,
+    blocked_period_id: i32,
+    blocked_period_submit: Json<BlockedPeriodSubmit>,
+    pool: &State<PgPool>,
+    admin_user: AdminUser,
+) -> Result<Json<BlockedPeriod>, BlockedPeriodUpdateError> {
+    match game_schedule::update_blocked_period(
+        pool,
+        event_id,
+        blocked_period_id,
+        blocked_period_submit.into_inner(),
+        admin_user.email,
+    )
+    .await
+    {
+        Ok(blocked_period) => Ok(Json(blocked_period)),
+        Err(Error::BadInput(e)) => Err(BlockedPeriodUpdateError::BadRequest(e)),
+        Err(Error::NotFound(e)) => Err(BlockedPeriodUpdateError::NotFound(e)),
+        Err(e) => Err(BlockedPeriodUpdateError::InternalServerError(format!(
+            "Error updating blocked period, due to: {e}"
+        ))),
+    }
+}
+
+custom_errors!(
+    BlockedPeriodDeleteError,
+    Unauthorized,
+    NotFound,
+    InternalServerError
+);
+
+/// Remove a blocked period, so games can be scheduled in it again (admin only)
+#[openapi(tag = "Game Schedule")]
+#[delete("/events/<event_id>/game_schedule/blocked_periods/<blocked_period_id>")]
+pub async fn delete_blocked_period(
+    event_id: i32,
+    blocked_period_id: i32,
+    pool: &State<PgPool>,
+    admin_user: AdminUser,
+) -> Result<rocket::response::status::NoContent, BlockedPeriodDeleteError> {
+    match game_schedule::delete_blocked_period(pool, event_id, blocked_period_id, admin_user.email)
+        .await
+    {
+        Ok(()) => Ok(rocket::response::status::NoContent),
+        Err(Error::NotFound(e)) => Err(BlockedPeriodDeleteError::NotFound(e)),
+        Err(e) => Err(BlockedPeriodDeleteError::InternalServerError(format!(
+            "Error deleting blocked period, due to: {e}"
+        ))),
+    }
+}
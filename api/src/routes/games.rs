@@ -1,6 +1,6 @@
 use chrono::{prelude::Utc, DateTime};
 use rocket::{
-    get, post,
+    get, post, put,
     serde::{json::Json, Deserialize, Serialize},
     State,
 };
@@ -14,38 +14,145 @@ use crate::{
     controllers::{game, Error},
 };
 
-custom_errors!(UpdateGameError, Unauthorized, InternalServerError);
+use super::SchemaExample;
+
+#[derive(Clone, Copy, Serialize, JsonSchema)]
+#[serde(crate = "rocket::serde", rename_all = "camelCase")]
+pub enum SteamGameUpdateStatus {
+    Running,
+    Succeeded,
+    Failed,
+}
+
+/// The progress of an update of the list of games from the Steam API.
+#[derive(Clone, Serialize, JsonSchema)]
+#[serde(crate = "rocket::serde", rename_all = "camelCase")]
+#[schemars(example = "Self::example")]
+pub struct SteamGameUpdateResponse {
+    pub id: i32,
+
+    /// When the update started.
+    pub started_at: DateTime<Utc>,
+
+    pub status: SteamGameUpdateStatus,
+
+    /// The admin who started the update, or null if it was started on a schedule.
+    pub triggered_by: Option<String>,
+
+    /// How many pages of the Steam app list have been fetched so far.
+    pub pages_fetched: i32,
+
+    /// How many apps the fetched pages contained.
+    pub apps_fetched: i32,
+
+    /// How many games have been saved.
+    pub rows_upserted: i32,
+
+    /// How many games or pages failed.
+    pub error_count: i32,
+
+    /// The most recent error, if there has been one.
+    pub last_error: Option<String>,
+
+    /// When the update finished, or null while it is still running.
+    pub finished_at: Option<DateTime<Utc>>,
+
+    /// The last time progress was recorded.
+    pub last_modified: DateTime<Utc>,
+
+    /// Only apps changed since this time are fetched, or null for a full update.
+    pub modified_since: Option<DateTime<Utc>>,
+
+    /// The last Steam app ID fetched so far.
+    pub last_appid: i64,
+
+    /// How many games were new, or had previously been removed from Steam.
+    pub apps_added: i32,
+
+    /// How many existing games changed name.
+    pub apps_renamed: i32,
+
+    /// How many games are no longer in the Steam app list. Only full updates can tell this.
+    pub apps_removed: i32,
+}
+
+impl SchemaExample for SteamGameUpdateResponse {
+    fn example() -> Self {
+        Self {
+            id: 1,
+            started_at: Utc::now(),
+            status: SteamGameUpdateStatus::Running,
+            triggered_by: Some("admin@example.com".to_string()),
+            pages_fetched: 2,
+            apps_fetched: 100_000,
+            rows_upserted: 99_998,
+            error_count: 2,
+            last_error: Some("Failed to save game 12345".to_string()),
+            finished_at: None,
+            last_modified: Utc::now(),
+            modified_since: Some(Utc::now()),
+            last_appid: 2_000_000,
+            apps_added: 120,
+            apps_renamed: 3,
+            apps_removed: 0,
+        }
+    }
+}
+
+custom_errors!(UpdateGameError, Unauthorized, Conflict, InternalServerError);
 
 #[openapi(tag = "Games")]
-#[post("/steam-game-update-v2?<_as_admin>")]
-/// Update the list of games from the Steam API v2
+#[post("/steam-game-update-v2?<full>&<_as_admin>")]
+/// Start updating the list of games from the Steam API v2 in the background.
+/// Only apps changed since the last successful update are fetched, unless `full` is set.
+/// Poll `GET /steam-game-update-v2` for progress.
 pub async fn steam_game_update_v2(
+    full: Option<bool>,
     pool: &State<PgPool>,
     steam_api_key: &State<String>,
     _as_admin: Option<bool>,
     user: AdminUser,
-) -> Result<Json<()>, UpdateGameError> {
-    match game::update(pool, steam_api_key.inner()).await {
-        Ok(()) => {
-            // Log audit entry
-            crate::util::log_audit(
-                pool.inner(),
-                Some(user.email),
-                "steam_games.update".to_string(),
-                "steam_games".to_string(),
-                None,
-                None,
-            )
-            .await;
-
-            Ok(Json(()))
-        }
+) -> Result<Json<SteamGameUpdateResponse>, UpdateGameError> {
+    match game::start_update(
+        pool,
+        steam_api_key.inner().clone(),
+        Some(user.email),
+        full.unwrap_or(false),
+    )
+    .await
+    {
+        Ok(update) => Ok(Json(update)),
+        Err(Error::Conflict(e)) => Err(UpdateGameError::Conflict(e)),
         Err(e) => Err(UpdateGameError::InternalServerError(format!(
             "Error updating games, due to: {e}"
         ))),
     }
 }
 
+custom_errors!(
+    UpdateGameStatusError,
+    Unauthorized,
+    NotFound,
+    InternalServerError
+);
+
+#[openapi(tag = "Games")]
+#[get("/steam-game-update-v2?<_as_admin>", format = "json")]
+/// Get the progress of the most recent update of the list of games from the Steam API
+pub async fn steam_game_update_status(
+    pool: &State<PgPool>,
+    _as_admin: Option<bool>,
+    _user: AdminUser,
+) -> Result<Json<SteamGameUpdateResponse>, UpdateGameStatusError> {
+    match game::update_status(pool).await {
+        Ok(update) => Ok(Json(update)),
+        Err(Error::NotFound(e)) => Err(UpdateGameStatusError::NotFound(e)),
+        Err(e) => Err(UpdateGameStatusError::InternalServerError(format!(
+            "Error getting game update status, due to: {e}"
+        ))),
+    }
+}
+
 #[derive(Serialize, Deserialize, JsonSchema, Debug)]
 #[serde(crate = "rocket::serde")]
 pub struct SteamGameResponse {
@@ -53,24 +160,130 @@ pub struct SteamGameResponse {
     pub name: String,
     pub last_modified: DateTime<Utc>,
     pub rank: Option<f32>,
+    /// Details from the Steam store, if they have been fetched.
+    pub details: Option<SteamGameDetails>,
+    pub source: GameSource,
+    /// The Steam store page, or the link given for a custom game.
+    pub url: Option<String>,
+    /// An image for a custom game. Steam games have `details.headerImage` instead.
+    pub image_url: Option<String>,
+}
+
+/// Where a game comes from.
+#[derive(Clone, Copy, Serialize, Deserialize, JsonSchema, Debug, PartialEq, Eq)]
+#[serde(crate = "rocket::serde", rename_all = "camelCase")]
+pub enum GameSource {
+    /// A game from the Steam app list.
+    Steam,
+    /// A game added by an attendee or admin, such as one from another store or a board game.
+    Custom,
+}
+
+#[derive(Clone, Serialize, Deserialize, JsonSchema, Debug)]
+#[serde(crate = "rocket::serde")]
+pub struct SteamGamePlatforms {
+    pub windows: bool,
+    pub mac: bool,
+    pub linux: bool,
+}
+
+/// Details of a game from its Steam store page.
+#[derive(Clone, Serialize, Deserialize, JsonSchema, Debug)]
+#[serde(crate = "rocket::serde", rename_all = "camelCase")]
+#[schemars(example = "Self::example")]
+pub struct SteamGameDetails {
+    /// The banner image shown at the top of the store page.
+    pub header_image: Option<String>,
+
+    pub short_description: Option<String>,
+
+    /// Steam store categories, such as "LAN Co-op", "Online PvP" or "Shared/Split Screen".
+    pub categories: Vec<String>,
+
+    pub genres: Vec<String>,
+
+    /// Whether the game can be played with other people, in any way.
+    pub is_multiplayer: bool,
+
+    /// Whether the game supports LAN play.
+    pub has_lan: bool,
+
+    pub platforms: SteamGamePlatforms,
+
+    pub is_free: bool,
+
+    /// The current price, formatted in the store's currency. Null for free games.
+    pub price: Option<String>,
+
+    /// The release date as shown on the store page, which may be vague such as "Coming soon".
+    pub release_date: Option<String>,
+
+    pub coming_soon: bool,
+
+    /// When these details were fetched from Steam.
+    pub fetched_at: DateTime<Utc>,
+}
+
+impl SchemaExample for SteamGameDetails {
+    fn example() -> Self {
+        Self {
+            header_image: Some(
+                "https://shared.akamai.steamstatic.com/store_item_assets/steam/apps/730/header.jpg"
+                    .to_string(),
+            ),
+            short_description: Some(
+                "For over two decades, Counter-Strike has offered an elite competitive experience."
+                    .to_string(),
+            ),
+            categories: vec![
+                "Multi-player".to_string(),
+                "Online PvP".to_string(),
+                "LAN PvP".to_string(),
+            ],
+            genres: vec!["Action".to_string(), "Free To Play".to_string()],
+            is_multiplayer: true,
+            has_lan: true,
+            platforms: SteamGamePlatforms {
+                windows: true,
+                mac: false,
+                linux: true,
+            },
+            is_free: true,
+            price: None,
+            release_date: Some("21 Aug, 2012".to_string()),
+            coming_soon: false,
+            fetched_at: Utc::now(),
+        }
+    }
 }
 
 custom_errors!(SteamGameError, Unauthorized, BadRequest);
 
+/// Search for games by name. Partial words, numerals and typos are allowed, and DLC, soundtracks
+/// and the like are left out unless `include_extras` is set.
+/// `multiplayer` and `free` only match games whose store details have been fetched.
 #[openapi(tag = "Games")]
-#[get("/steam-game?<query>&<page>")]
+#[get("/steam-game?<query>&<page>&<multiplayer>&<free>&<include_extras>")]
 pub async fn get_steam_game(
     query: String,
     page: Option<i64>,
+    multiplayer: Option<bool>,
+    free: Option<bool>,
+    include_extras: Option<bool>,
     pool: &State<PgPool>,
     _user: User,
 ) -> Result<Json<Vec<SteamGameResponse>>, SteamGameError> {
     const COUNT: i64 = 10;
 
     let page = page.unwrap_or(0);
+    let options = game::SearchOptions {
+        multiplayer: multiplayer.unwrap_or(false),
+        free: free.unwrap_or(false),
+        include_extras: include_extras.unwrap_or(false),
+    };
 
     // Return all games
-    match game::get(pool, query, COUNT, page).await {
+    match game::get(pool, query, options, COUNT, page).await {
         Ok(games) => Ok(Json(games)),
         Err(Error::NotPermitted(e)) => Err(SteamGameError::Unauthorized(e)),
         Err(e) => Err(SteamGameError::BadRequest(format!(
@@ -78,3 +291,149 @@ pub async fn get_steam_game(
         ))),
     }
 }
+
+/// How many people can play a game together, and how.
+#[derive(Clone, Serialize, Deserialize, JsonSchema, Debug)]
+#[serde(crate = "rocket::serde", rename_all = "camelCase")]
+#[schemars(example = "Self::example")]
+pub struct GamePlayers {
+    /// The fewest players the game can be played with, or null if not known.
+    pub min_players: Option<i32>,
+
+    /// The most players that can play together, or null if not known.
+    pub max_players: Option<i32>,
+
+    pub has_lan: bool,
+
+    pub has_split_screen: bool,
+
+    /// True if an admin set these, rather than them being worked out from the Steam store.
+    pub is_manual: bool,
+
+    pub last_modified: DateTime<Utc>,
+}
+
+impl SchemaExample for GamePlayers {
+    fn example() -> Self {
+        Self {
+            min_players: Some(1),
+            max_players: Some(4),
+            has_lan: true,
+            has_split_screen: false,
+            is_manual: true,
+            last_modified: Utc::now(),
+        }
+    }
+}
+
+/// The request body for setting how many people can play a game.
+#[derive(Deserialize, JsonSchema)]
+#[serde(crate = "rocket::serde", rename_all = "camelCase")]
+#[schemars(example = "Self::example")]
+pub struct GamePlayersSubmit {
+    pub min_players: Option<i32>,
+    pub max_players: Option<i32>,
+    pub has_lan: bool,
+    pub has_split_screen: bool,
+}
+
+impl SchemaExample for GamePlayersSubmit {
+    fn example() -> Self {
+        Self {
+            min_players: Some(1),
+            max_players: Some(4),
+            has_lan: true,
+            has_split_screen: false,
+        }
+    }
+}
+
+custom_errors!(
+    GamePlayersPutError,
+    Unauthorized,
+    BadRequest,
+    NotFound,
+    InternalServerError
+);
+
+/// Set how many people can play a game (admin only).
+/// These replace the counts worked out from the Steam store, and aren't changed by it again.
+#[openapi(tag = "Games")]
+#[put(
+    "/steam-game/<appid>/players?<_as_admin>",
+    format = "json",
+    data = "<players_submit>"
+)]
+pub async fn put_players(
+    appid: i64,
+    players_submit: Json<GamePlayersSubmit>,
+    pool: &State<PgPool>,
+    _as_admin: Option<bool>,
+    user: AdminUser,
+) -> Result<Json<GamePlayers>, GamePlayersPutError> {
+    match game::update_players(pool, appid, players_submit.into_inner(), user.email).await {
+        Ok(players) => Ok(Json(players)),
+        Err(Error::BadInput(e)) => Err(GamePlayersPutError::BadRequest(e)),
+        Err(Error::NotFound(e)) => Err(GamePlayersPutError::NotFound(e)),
+        Err(e) => Err(GamePlayersPutError::InternalServerError(format!(
+            "Error saving game players, due to: {e}"
+        ))),
+    }
+}
+
+/// The request body for setting how long a game takes.
+#[derive(Deserialize, Serialize, JsonSchema)]
+#[serde(crate = "rocket::serde", rename_all = "camelCase")]
+#[schemars(example = "Self::example")]
+pub struct GameDurationSubmit {
+    /// How long the game takes in minutes, or null to clear it.
+    pub duration_minutes: Option<i32>,
+}
+
+impl SchemaExample for GameDurationSubmit {
+    fn example() -> Self {
+        Self {
+            duration_minutes: Some(45),
+        }
+    }
+}
+
+custom_errors!(
+    GameDurationPutError,
+    Unauthorized,
+    BadRequest,
+    NotFound,
+    InternalServerError
+);
+
+/// Set how long a game usually takes, so it's given the right amount of time in event schedules (admin only).
+/// Set the duration to null to clear it.
+#[openapi(tag = "Games")]
+#[put(
+    "/steam-game/<appid>/duration?<_as_admin>",
+    format = "json",
+    data = "<duration_submit>"
+)]
+pub async fn put_duration(
+    appid: i64,
+    duration_submit: Json<GameDurationSubmit>,
+    pool: &State<PgPool>,
+    _as_admin: Option<bool>,
+    user: AdminUser,
+) -> Result<Json<GameDurationSubmit>, GameDurationPutError> {
+    match game::update_duration(
+        pool,
+        appid,
+        duration_submit.into_inner().duration_minutes,
+        user.email,
+    )
+    .await
+    {
+        Ok(duration_minutes) => Ok(Json(GameDurationSubmit { duration_minutes })),
+        Err(Error::BadInput(e)) => Err(GameDurationPutError::BadRequest(e)),
+        Err(Error::NotFound(e)) => Err(GameDurationPutError::NotFound(e)),
+        Err(e) => Err(GameDurationPutError::InternalServerError(format!(
+            "Error saving game duration, due to: {e}"
+        ))),
+    }
+}
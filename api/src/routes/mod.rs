@@ -1,17 +1,24 @@
 pub mod activity_ticker;
 pub mod audit_logs;
 pub mod auth;
+pub mod custom_games;
+pub mod event_discord;
 pub mod event_games;
 pub mod event_invitations;
 pub mod event_seating;
+pub mod event_voting;
 pub mod events;
+pub mod game_comments;
 pub mod game_schedule;
 pub mod gamers;
 pub mod games;
+pub mod notification_preferences;
+pub mod now_playing;
 pub mod profiles;
 pub mod rooms;
 pub mod seat_reservations;
 pub mod seats;
+pub mod webhooks;
 
 /// Trait for adds examples to API documentation
 trait SchemaExample {
@@ -0,0 +1,190 @@
+use crate::{auth::User, controllers::notification_preference, util::parse_unsubscribe_token};
+use chrono::{DateTime, Utc};
+use rocket::{
+    get, post, put,
+    response::content::RawHtml,
+    serde::{json::Json, Deserialize, Serialize},
+    State,
+};
+use rocket_dyn_templates::tera::escape_html;
+use rocket_okapi::okapi::schemars;
+use rocket_okapi::okapi::schemars::JsonSchema;
+use rocket_okapi::openapi;
+use rusty_paseto::prelude::*;
+use sqlx::postgres::PgPool;
+
+use super::SchemaExample;
+
+/// The user's email notification preferences.
+#[derive(Serialize, JsonSchema)]
+#[serde(crate = "rocket::serde", rename_all = "camelCase")]
+#[schemars(example = "Self::example")]
+pub struct NotificationPreferences {
+    /// The email address these preferences apply to.
+    pub email: String,
+
+    /// Receive invitations to new events.
+    pub invitations: bool,
+
+    /// Receive reminders about upcoming events.
+    pub reminders: bool,
+
+    /// Receive updates sent by the event organiser.
+    pub event_updates: bool,
+
+    /// Receive notifications when the game schedule for an event changes.
+    pub game_schedule_changes: bool,
+
+    /// Receive periodic digests of activity for upcoming events.
+    pub digests: bool,
+
+    /// The last time these preferences were modified.
+    pub last_modified: DateTime<Utc>,
+}
+
+impl SchemaExample for NotificationPreferences {
+    fn example() -> Self {
+        Self {
+            email: "test@test.invalid".to_string(),
+            invitations: true,
+            reminders: true,
+            event_updates: true,
+            game_schedule_changes: false,
+            digests: true,
+            last_modified: Utc::now(),
+        }
+    }
+}
+
+/// The request body for updating notification preferences.
+#[derive(Deserialize, Serialize, JsonSchema)]
+#[serde(crate = "rocket::serde", rename_all = "camelCase")]
+#[schemars(example = "Self::example")]
+pub struct NotificationPreferencesSubmit {
+    pub invitations: bool,
+    pub reminders: bool,
+    pub event_updates: bool,
+    pub game_schedule_changes: bool,
+    pub digests: bool,
+}
+
+impl SchemaExample for NotificationPreferencesSubmit {
+    fn example() -> Self {
+        Self {
+            invitations: true,
+            reminders: true,
+            event_updates: true,
+            game_schedule_changes: false,
+            digests: true,
+        }
+    }
+}
+
+custom_errors!(NotificationPreferencesGetError, InternalServerError);
+
+/// Return the user's email notification preferences.
+#[openapi(tag = "Profile")]
+#[get("/profile/notifications", format = "json")]
+pub async fn get(
+    pool: &State<PgPool>,
+    user: User,
+) -> Result<Json<NotificationPreferences>, NotificationPreferencesGetError> {
+    match notification_preference::get(pool, &user.email).await {
+        Ok(preferences) => Ok(Json(preferences)),
+        Err(e) => Err(NotificationPreferencesGetError::InternalServerError(
+            format!("Error getting notification preferences, due to: {e}"),
+        )),
+    }
+}
+
+custom_errors!(NotificationPreferencesPutError, InternalServerError);
+
+/// Update the user's email notification preferences.
+#[openapi(tag = "Profile")]
+#[put(
+    "/profile/notifications",
+    format = "json",
+    data = "<preferences_submit>"
+)]
+pub async fn put(
+    pool: &State<PgPool>,
+    user: User,
+    preferences_submit: Json<NotificationPreferencesSubmit>,
+) -> Result<Json<NotificationPreferences>, NotificationPreferencesPutError> {
+    match notification_preference::update(pool, &user.email, preferences_submit.into_inner()).await
+    {
+        Ok(preferences) => Ok(Json(preferences)),
+        Err(e) => Err(NotificationPreferencesPutError::InternalServerError(
+            format!("Error updating notification preferences, due to: {e}"),
+        )),
+    }
+}
+
+custom_errors!(UnsubscribeError, BadRequest, InternalServerError);
+
+fn unsubscribe_page(body: &str) -> RawHtml<String> {
+    RawHtml(format!(
+        r#"<!DOCTYPE html>
+<html>
+  <head>
+    <meta charset="utf-8" />
+    <title>caLANdar - Unsubscribe</title>
+  </head>
+  <body>
+    {body}
+    <p><a href="https://calandar.org">Return to caLANdar</a></p>
+  </body>
+</html>"#
+    ))
+}
+
+/// Show a confirmation page for the unsubscribe link included in emails.
+/// The unsubscribe itself only happens on `POST` so link scanners can't trigger it.
+#[openapi(tag = "Profile")]
+#[get("/unsubscribe?<token>")]
+pub fn get_unsubscribe(
+    token: &str,
+    key: &State<PasetoSymmetricKey<V4, Local>>,
+) -> Result<RawHtml<String>, UnsubscribeError> {
+    let (email, category) = match parse_unsubscribe_token(key, token) {
+        Ok(claims) => claims,
+        Err(e) => return Err(UnsubscribeError::BadRequest(e)),
+    };
+
+    Ok(unsubscribe_page(&format!(
+        r#"<p>Stop sending {} to {}?</p>
+    <form method="post">
+      <input type="hidden" name="List-Unsubscribe" value="One-Click" />
+      <button type="submit">Unsubscribe</button>
+    </form>
+    <p>You can change which emails you receive at any time from your profile.</p>"#,
+        category.label(),
+        escape_html(&email),
+    )))
+}
+
+/// Unsubscribe from a category of emails using the signed token from an email.
+/// Supports one-click unsubscribe (RFC 8058) from the `List-Unsubscribe-Post` header.
+#[openapi(tag = "Profile")]
+#[post("/unsubscribe?<token>")]
+pub async fn post_unsubscribe(
+    token: &str,
+    pool: &State<PgPool>,
+    key: &State<PasetoSymmetricKey<V4, Local>>,
+) -> Result<RawHtml<String>, UnsubscribeError> {
+    let (email, category) = match parse_unsubscribe_token(key, token) {
+        Ok(claims) => claims,
+        Err(e) => return Err(UnsubscribeError::BadRequest(e)),
+    };
+
+    match notification_preference::unsubscribe(pool, &email, category).await {
+        Ok(_) => Ok(unsubscribe_page(&format!(
+            "<p>You will no longer receive {} at {}.</p>",
+            category.label(),
+            escape_html(&email),
+        ))),
+        Err(e) => Err(UnsubscribeError::InternalServerError(format!(
+            "Error unsubscribing, due to: {e}"
+        ))),
+    }
+}
@@ -0,0 +1,99 @@
+use crate::{
+    auth::User,
+    controllers::{ensure_user_invited, presence},
+    routes::event_games::Gamer,
+};
+use chrono::{DateTime, Utc};
+use rocket::{get, serde::json::Json, serde::Serialize, State};
+use rocket_okapi::okapi::schemars;
+use rocket_okapi::okapi::schemars::JsonSchema;
+use rocket_okapi::openapi;
+use sqlx::PgPool;
+
+use super::SchemaExample;
+
+/// Someone playing a game right now
+#[derive(Clone, Serialize, JsonSchema)]
+#[serde(crate = "rocket::serde", rename_all = "camelCase")]
+pub struct NowPlayingGamer {
+    #[serde(flatten)]
+    pub gamer: Gamer,
+    /// When they started playing
+    pub since: Option<DateTime<Utc>>,
+}
+
+/// A game being played by attendees right now, according to Steam
+#[derive(Clone, Serialize, JsonSchema)]
+#[serde(crate = "rocket::serde", rename_all = "camelCase")]
+#[schemars(example = "Self::example")]
+pub struct NowPlayingGame {
+    /// The Steam app ID, missing for games that aren't on Steam
+    pub appid: Option<i64>,
+    pub name: String,
+    pub gamers: Vec<NowPlayingGamer>,
+}
+
+impl SchemaExample for NowPlayingGame {
+    fn example() -> Self {
+        Self {
+            appid: Some(730),
+            name: "Counter-Strike 2".to_string(),
+            gamers: vec![NowPlayingGamer {
+                gamer: Gamer {
+                    avatar_url: Some(
+                        "https://www.gravatar.com/avatar/example?d=robohash".to_string(),
+                    ),
+                    handle: Some("Alice".to_string()),
+                },
+                since: Some(Utc::now()),
+            }],
+        }
+    }
+}
+
+#[derive(Serialize, JsonSchema)]
+#[serde(crate = "rocket::serde", rename_all = "camelCase")]
+#[schemars(example = "Self::example")]
+pub struct NowPlayingResponse {
+    /// Games being played, with the most players first
+    pub games: Vec<NowPlayingGame>,
+}
+
+impl SchemaExample for NowPlayingResponse {
+    fn example() -> Self {
+        Self {
+            games: vec![NowPlayingGame::example()],
+        }
+    }
+}
+
+custom_errors!(
+    NowPlayingGetError,
+    Unauthorized,
+    Forbidden,
+    InternalServerError
+);
+
+/// Get what attendees are playing on Steam right now, grouped by game.
+/// Only updated while the event is happening, and only for attendees with a Steam ID.
+#[openapi(tag = "Events")]
+#[get("/events/<event_id>/now-playing", format = "json")]
+pub async fn get(
+    event_id: i32,
+    pool: &State<PgPool>,
+    user: User,
+) -> Result<Json<NowPlayingResponse>, NowPlayingGetError> {
+    // Ensure user is invited to the event
+    if let Err(e) = ensure_user_invited(pool, event_id, &user.email).await {
+        return Err(NowPlayingGetError::Forbidden(format!(
+            "You are not invited to this event: {e}"
+        )));
+    }
+
+    match presence::get_now_playing(pool, event_id).await {
+        Ok(games) => Ok(Json(NowPlayingResponse { games })),
+        Err(e) => Err(NowPlayingGetError::InternalServerError(format!(
+            "Error retrieving what attendees are playing: {e}"
+        ))),
+    }
+}
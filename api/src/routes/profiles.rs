@@ -53,6 +53,12 @@ pub struct Profile {
 
     /// The number of games that the user owns
     pub game_count: i64,
+
+    /// When the user's games were last refreshed from Steam.
+    pub last_refreshed: Option<DateTime<Utc>>,
+
+    /// Why the last attempt to refresh the user's games failed, such as their Steam profile being private.
+    pub refresh_error: Option<String>,
 }
 
 impl SchemaExample for Profile {
@@ -62,6 +68,8 @@ impl SchemaExample for Profile {
             steam_id: "12345678901234567".to_string(),
             games: vec![UserGame::example()],
             game_count: 123,
+            last_refreshed: Some(Utc::now()),
+            refresh_error: None,
         }
     }
 }
@@ -130,8 +138,16 @@ pub async fn put(
     }
 }
 
-custom_errors!(UpdateUserGameError, Unauthorized, InternalServerError);
+custom_errors!(
+    UpdateUserGameError,
+    Unauthorized,
+    NotFound,
+    BadRequest,
+    TooManyRequests,
+    InternalServerError
+);
 
+/// Refresh the user's games from their Steam library, removing any they no longer own.
 #[openapi(tag = "Profile")]
 #[post("/profile/games/update")]
 pub async fn post_games_update(
@@ -141,6 +157,12 @@ pub async fn post_games_update(
 ) -> Result<Json<Profile>, UpdateUserGameError> {
     match profile::update_user_games(pool, user.email.clone(), steam_api_key.inner()).await {
         Ok(updated_profile) => Ok(Json(updated_profile)),
+        Err(Error::NoData(_)) => Err(UpdateUserGameError::NotFound(format!(
+            "Profile for {}",
+            user.email
+        ))),
+        Err(Error::BadInput(e)) => Err(UpdateUserGameError::BadRequest(e)),
+        Err(Error::RateLimited(e)) => Err(UpdateUserGameError::TooManyRequests(e)),
         Err(e) => Err(UpdateUserGameError::InternalServerError(format!(
             "Error updating games, due to: {e}"
         ))),
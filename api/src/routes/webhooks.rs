@@ -0,0 +1,330 @@
+use crate::{
+    auth::AdminUser,
+    controllers::{webhook, Error},
+};
+use chrono::{DateTime, Utc};
+use rocket::{
+    delete, get, post, put,
+    serde::{json::serde_json::Value as JsonValue, json::Json, Deserialize, Serialize},
+    State,
+};
+use rocket_okapi::okapi::schemars;
+use rocket_okapi::okapi::schemars::JsonSchema;
+use rocket_okapi::openapi;
+use sqlx::PgPool;
+
+use super::SchemaExample;
+
+/// An outbound webhook subscription.
+/// The signing secret is write-only and never returned.
+#[derive(Clone, Serialize, JsonSchema)]
+#[serde(crate = "rocket::serde", rename_all = "camelCase")]
+#[schemars(example = "Self::example")]
+pub struct Webhook {
+    /// The webhook ID.
+    pub id: i32,
+
+    /// The URL events are posted to.
+    pub url: String,
+
+    /// The event types to send, such as `rsvp.update`.
+    /// `game_suggestion.*` matches every game suggestion event and `*` matches everything.
+    pub event_types: Vec<String>,
+
+    /// Whether events are currently being sent.
+    pub is_active: bool,
+
+    /// The date the webhook was created.
+    pub created_at: DateTime<Utc>,
+
+    /// The last time this webhook was modified.
+    pub last_modified: DateTime<Utc>,
+}
+
+impl SchemaExample for Webhook {
+    fn example() -> Self {
+        Self {
+            id: 1,
+            url: "https://example.com/hooks/calandar".to_string(),
+            event_types: vec!["rsvp.update".to_string(), "game_suggestion.*".to_string()],
+            is_active: true,
+            created_at: Utc::now(),
+            last_modified: Utc::now(),
+        }
+    }
+}
+
+/// The request body for creating/updating a webhook.
+#[derive(Deserialize, Serialize, JsonSchema)]
+#[serde(crate = "rocket::serde", rename_all = "camelCase")]
+#[schemars(example = "Self::example")]
+pub struct WebhookSubmit {
+    pub url: String,
+
+    /// The secret used to sign payloads. Required when creating a webhook,
+    /// leave empty when updating to keep the existing secret.
+    pub secret: Option<String>,
+
+    pub event_types: Vec<String>,
+    pub is_active: bool,
+}
+
+impl SchemaExample for WebhookSubmit {
+    fn example() -> Self {
+        Self {
+            url: "https://example.com/hooks/calandar".to_string(),
+            secret: Some("a-long-random-shared-secret".to_string()),
+            event_types: vec!["rsvp.update".to_string(), "game_suggestion.*".to_string()],
+            is_active: true,
+        }
+    }
+}
+
+#[derive(Clone, Copy, Serialize, JsonSchema)]
+#[serde(crate = "rocket::serde", rename_all = "camelCase")]
+pub enum WebhookDeliveryStatus {
+    Pending,
+    Succeeded,
+    Failed,
+}
+
+/// A single event sent, or waiting to be sent, to a webhook.
+#[derive(Clone, Serialize, JsonSchema)]
+#[serde(crate = "rocket::serde", rename_all = "camelCase")]
+#[schemars(example = "Self::example")]
+pub struct WebhookDelivery {
+    /// The delivery ID, also sent in the `X-Calandar-Delivery` header.
+    pub id: i64,
+
+    pub webhook_id: i32,
+
+    /// The event type, also sent in the `X-Calandar-Event` header.
+    pub event_type: String,
+
+    /// The JSON body that is posted.
+    pub payload: JsonValue,
+
+    pub status: WebhookDeliveryStatus,
+
+    /// How many times delivery has been attempted.
+    pub attempts: i32,
+
+    /// When delivery will next be attempted, if it is still pending.
+    pub next_attempt_at: DateTime<Utc>,
+
+    /// The HTTP status returned by the last attempt.
+    pub response_status: Option<i32>,
+
+    /// Why the last attempt failed.
+    pub error: Option<String>,
+
+    pub created_at: DateTime<Utc>,
+    pub last_modified: DateTime<Utc>,
+}
+
+impl SchemaExample for WebhookDelivery {
+    fn example() -> Self {
+        Self {
+            id: 1,
+            webhook_id: 1,
+            event_type: "rsvp.update".to_string(),
+            payload: rocket::serde::json::serde_json::json!({
+                "id": 1,
+                "type": "rsvp.update",
+                "timestamp": "2025-01-01T12:00:00Z",
+                "actor": "test@test.invalid",
+                "entityType": "event",
+                "entityId": "1",
+                "data": {"response": "Yes", "handle": "Gamer"},
+            }),
+            status: WebhookDeliveryStatus::Succeeded,
+            attempts: 1,
+            next_attempt_at: Utc::now(),
+            response_status: Some(200),
+            error: None,
+            created_at: Utc::now(),
+            last_modified: Utc::now(),
+        }
+    }
+}
+
+custom_errors!(WebhookGetAllError, Unauthorized, InternalServerError);
+
+/// Get all webhooks (admin only).
+#[openapi(tag = "Webhooks")]
+#[get("/webhooks?<_as_admin>", format = "json")]
+pub async fn get_all(
+    pool: &State<PgPool>,
+    _as_admin: Option<bool>,
+    _user: AdminUser,
+) -> Result<Json<Vec<Webhook>>, WebhookGetAllError> {
+    match webhook::index(pool).await {
+        Ok(webhooks) => Ok(Json(webhooks)),
+        Err(e) => Err(WebhookGetAllError::InternalServerError(format!(
+            "Error getting webhooks, due to: {e}"
+        ))),
+    }
+}
+
+custom_errors!(WebhookGetError, Unauthorized, NotFound, InternalServerError);
+
+/// Get a specific webhook (admin only).
+#[openapi(tag = "Webhooks")]
+#[get("/webhooks/<webhook_id>?<_as_admin>", format = "json")]
+pub async fn get(
+    webhook_id: i32,
+    pool: &State<PgPool>,
+    _as_admin: Option<bool>,
+    _user: AdminUser,
+) -> Result<Json<Webhook>, WebhookGetError> {
+    match webhook::get(pool, webhook_id).await {
+        Ok(webhook) => Ok(Json(webhook)),
+        Err(Error::NotFound(e)) => Err(WebhookGetError::NotFound(e)),
+        Err(e) => Err(WebhookGetError::InternalServerError(format!(
+            "Error getting webhook, due to: {e}"
+        ))),
+    }
+}
+
+custom_errors!(
+    WebhookPostError,
+    Unauthorized,
+    BadRequest,
+    InternalServerError
+);
+
+/// Create a new webhook (admin only).
+#[openapi(tag = "Webhooks")]
+#[post("/webhooks?<_as_admin>", format = "json", data = "<webhook_submit>")]
+pub async fn post(
+    webhook_submit: Json<WebhookSubmit>,
+    pool: &State<PgPool>,
+    _as_admin: Option<bool>,
+    user: AdminUser,
+) -> Result<Json<Webhook>, WebhookPostError> {
+    match webhook::create(pool, webhook_submit.into_inner(), user.email).await {
+        Ok(webhook) => Ok(Json(webhook)),
+        Err(Error::BadInput(e)) => Err(WebhookPostError::BadRequest(format!(
+            "Invalid request, due to {e}"
+        ))),
+        Err(e) => Err(WebhookPostError::InternalServerError(format!(
+            "Error creating webhook, due to: {e}"
+        ))),
+    }
+}
+
+custom_errors!(
+    WebhookPutError,
+    Unauthorized,
+    BadRequest,
+    NotFound,
+    InternalServerError
+);
+
+/// Update an existing webhook (admin only).
+#[openapi(tag = "Webhooks")]
+#[put(
+    "/webhooks/<webhook_id>?<_as_admin>",
+    format = "json",
+    data = "<webhook_submit>"
+)]
+pub async fn put(
+    webhook_id: i32,
+    webhook_submit: Json<WebhookSubmit>,
+    pool: &State<PgPool>,
+    _as_admin: Option<bool>,
+    user: AdminUser,
+) -> Result<Json<Webhook>, WebhookPutError> {
+    match webhook::update(pool, webhook_id, webhook_submit.into_inner(), user.email).await {
+        Ok(webhook) => Ok(Json(webhook)),
+        Err(Error::BadInput(e)) => Err(WebhookPutError::BadRequest(format!(
+            "Invalid request, due to {e}"
+        ))),
+        Err(Error::NotFound(e)) => Err(WebhookPutError::NotFound(e)),
+        Err(e) => Err(WebhookPutError::InternalServerError(format!(
+            "Error updating webhook, due to: {e}"
+        ))),
+    }
+}
+
+custom_errors!(WebhookDeleteError, Unauthorized, InternalServerError);
+
+/// Delete a webhook and its delivery log (admin only).
+#[openapi(tag = "Webhooks")]
+#[delete("/webhooks/<webhook_id>?<_as_admin>")]
+pub async fn delete(
+    webhook_id: i32,
+    pool: &State<PgPool>,
+    _as_admin: Option<bool>,
+    user: AdminUser,
+) -> Result<rocket::response::status::NoContent, WebhookDeleteError> {
+    match webhook::delete(pool, webhook_id, user.email).await {
+        Ok(()) => Ok(rocket::response::status::NoContent),
+        Err(e) => Err(WebhookDeleteError::InternalServerError(format!(
+            "Error deleting webhook, due to: {e}"
+        ))),
+    }
+}
+
+custom_errors!(
+    WebhookDeliveriesGetError,
+    Unauthorized,
+    NotFound,
+    InternalServerError
+);
+
+/// Get the delivery log for a webhook, newest first (admin only).
+#[openapi(tag = "Webhooks")]
+#[get(
+    "/webhooks/<webhook_id>/deliveries?<limit>&<offset>&<_as_admin>",
+    format = "json"
+)]
+pub async fn get_deliveries(
+    webhook_id: i32,
+    limit: Option<i64>,
+    offset: Option<i64>,
+    pool: &State<PgPool>,
+    _as_admin: Option<bool>,
+    _user: AdminUser,
+) -> Result<Json<Vec<WebhookDelivery>>, WebhookDeliveriesGetError> {
+    match webhook::deliveries(
+        pool,
+        webhook_id,
+        limit.unwrap_or(50).clamp(1, 200),
+        offset.unwrap_or(0).max(0),
+    )
+    .await
+    {
+        Ok(deliveries) => Ok(Json(deliveries)),
+        Err(Error::NotFound(e)) => Err(WebhookDeliveriesGetError::NotFound(e)),
+        Err(e) => Err(WebhookDeliveriesGetError::InternalServerError(format!(
+            "Error getting webhook deliveries, due to: {e}"
+        ))),
+    }
+}
+
+custom_errors!(
+    WebhookTestError,
+    Unauthorized,
+    NotFound,
+    InternalServerError
+);
+
+/// Queue a `webhook.test` event for a webhook, regardless of the event types it is subscribed to (admin only).
+/// The result shows up in the delivery log once it has been sent.
+#[openapi(tag = "Webhooks")]
+#[post("/webhooks/<webhook_id>/test?<_as_admin>")]
+pub async fn test(
+    webhook_id: i32,
+    pool: &State<PgPool>,
+    _as_admin: Option<bool>,
+    user: AdminUser,
+) -> Result<Json<WebhookDelivery>, WebhookTestError> {
+    match webhook::send_test(pool, webhook_id, user.email).await {
+        Ok(delivery) => Ok(Json(delivery)),
+        Err(Error::NotFound(e)) => Err(WebhookTestError::NotFound(e)),
+        Err(e) => Err(WebhookTestError::InternalServerError(format!(
+            "Error sending test event, due to: {e}"
+        ))),
+    }
+}
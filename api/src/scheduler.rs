@@ -1,5 +1,5 @@
-use chrono::{DateTime, Duration, Timelike, Utc};
-use std::collections::HashMap;
+use chrono::{DateTime, Duration, NaiveTime, Utc};
+use std::collections::{HashMap, HashSet};
 
 /// Represents a game that can be scheduled
 #[derive(Debug, Clone, PartialEq, Eq)]
@@ -9,6 +9,12 @@ pub struct Game {
     pub votes: i32,
     /// IDs of voters who voted yes for this game
     pub voter_ids: Vec<String>,
+    /// How long the game is expected to take in minutes
+    pub duration_minutes: i32,
+    /// The fewest players the game can be played with, if known
+    pub min_players: Option<i32>,
+    /// The most players that can play together, if known
+    pub max_players: Option<i32>,
 }
 
 /// Represents a voter and their availability
@@ -25,6 +31,26 @@ pub struct Voter {
 pub struct OccupiedSlot {
     pub start_time: DateTime<Utc>,
     pub duration_minutes: i32,
+    /// The track the game is on, counting from 1
+    pub track: i32,
+    /// IDs of voters playing the game, who can't play anything else at the same time
+    pub voter_ids: Vec<String>,
+}
+
+/// Hours of each day that games can't be played in, such as overnight. Times are UTC,
+/// and the quiet hours run past midnight if they end before they start.
+/// There are none if they start and end at the same time.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct QuietHours {
+    pub start: NaiveTime,
+    pub end: NaiveTime,
+}
+
+/// A period no games can be played in, such as dinner or the venue being closed
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct BlockedPeriod {
+    pub start_time: DateTime<Utc>,
+    pub end_time: DateTime<Utc>,
 }
 
 /// Input to the scheduling algorithm
@@ -38,10 +64,21 @@ pub struct SchedulerInput {
     pub event_start: DateTime<Utc>,
     /// Event end time
     pub event_end: DateTime<Utc>,
-    /// Already scheduled (pinned) games that we cannot overlap with
+    /// Already scheduled (pinned) games that we cannot overlap with on the same track
     pub pinned_slots: Vec<OccupiedSlot>,
-    /// Default duration for each game in minutes
-    pub default_game_duration: i32,
+    /// Hours of each day that no games can be in progress, if any
+    pub quiet_hours: Option<QuietHours>,
+    /// Other periods that no games can be in progress, on any track
+    pub blocked_periods: Vec<BlockedPeriod>,
+    /// How many games can be played at once, each on its own track
+    pub tracks: i32,
+    /// Minutes to leave free between games, to set up or download the next one
+    pub setup_buffer_minutes: i32,
+    /// Whether a game with more available voters than it has room for can be
+    /// played by a second group at the same time
+    pub allow_parallel_groups: bool,
+    /// Whether to explain why each game was scheduled when it was
+    pub explain: bool,
 }
 
 /// A suggested game schedule
@@ -51,168 +88,545 @@ pub struct SuggestedSchedule {
     pub game_name: String,
     pub start_time: DateTime<Utc>,
     pub duration_minutes: i32,
-    /// Number of voters who are available during this time
+    /// Minutes left free before the game to set it up
+    pub setup_minutes: i32,
+    /// Number of voters who are available during this time, up to how many can play
+    pub availability_score: i32,
+    /// Which group this is, counting from 1, when a game is split between groups playing at once.
+    /// Groups of a split game share its track.
+    pub group: Option<i32>,
+    /// The track the game is on, counting from 1
+    pub track: i32,
+    /// Why the game was put here, if the scheduler was asked to explain itself
+    pub explanation: Option<ScheduleExplanation>,
+}
+
+/// Why a game was scheduled when it was
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ScheduleExplanation {
+    /// IDs of voters for the game who are free at the chosen time
+    pub available_voter_ids: Vec<String>,
+    /// IDs of voters for the game who aren't there, or are playing something else, at the chosen time
+    pub unavailable_voter_ids: Vec<String>,
+    /// The next best times the game could have started, best first
+    pub runners_up: Vec<SlotScore>,
+}
+
+/// A time and track a game could start at, and how many could play it
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct SlotScore {
+    pub start_time: DateTime<Utc>,
+    pub track: i32,
     pub availability_score: i32,
 }
 
+/// Why a game wasn't scheduled
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum UnscheduledReason {
+    /// The game is longer than the time the event runs for
+    OutsideEvent,
+    /// Not enough of the game's voters are free at any time the game fits in the event
+    NoVoterAvailable,
+    /// Whenever enough voters are free, every track is taken or the time is blocked
+    NoSlotFits,
+}
+
+/// A game the scheduler couldn't find a time for
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct UnscheduledGame {
+    pub game_id: i64,
+    pub game_name: String,
+    pub reason: UnscheduledReason,
+}
+
 /// Output from the scheduling algorithm
 #[derive(Debug, Clone)]
 pub struct SchedulerOutput {
     pub suggested_schedules: Vec<SuggestedSchedule>,
+    /// Games that couldn't be scheduled, and why, if the scheduler was asked to explain itself
+    pub unscheduled: Vec<UnscheduledGame>,
+}
+
+/// Options for the optimising scheduler
+#[derive(Debug, Clone, Copy)]
+pub struct OptimiserOptions {
+    /// Seed for choosing which changes to try, so the same input and seed give the same schedule
+    pub seed: u64,
+    /// The most changes to try
+    pub max_iterations: u32,
+    /// Stop trying changes once this much time has passed. Schedules can vary between runs
+    /// that hit the time budget before `max_iterations`.
+    pub time_budget: std::time::Duration,
 }
 
 /// Main scheduling function - uses a greedy algorithm to maximize voter availability
 pub fn schedule_games(input: &SchedulerInput) -> SchedulerOutput {
-    let mut suggested_schedules = Vec::new();
-    let mut occupied_slots: HashMap<DateTime<Utc>, i32> = HashMap::new();
-
-    // Add pinned slots to occupied slots
-    for pinned in &input.pinned_slots {
-        occupied_slots.insert(pinned.start_time, pinned.duration_minutes);
-    }
-
     // Build available time slots (30-minute intervals)
+    let blocked_periods = all_blocked_periods(input);
     let available_slots = build_time_slots(
         input.event_start,
         input.event_end,
-        &input.pinned_slots,
         30, // 30-minute slots
     );
 
-    if available_slots.is_empty() {
-        return SchedulerOutput {
-            suggested_schedules,
-        };
+    place_games(
+        input,
+        &games_by_votes(input),
+        &available_slots,
+        &blocked_periods,
+        input.explain,
+    )
+}
+
+/// Search-based scheduling function - uses simulated annealing over the order games are placed in,
+/// maximising the total voter-minutes of the schedule.
+///
+/// The search starts from the greedy order and only keeps a different schedule if it's strictly better,
+/// so it's never worse than `schedule_games`, and matches it when nothing can be gained.
+#[allow(clippy::cast_precision_loss)]
+pub fn optimise_schedule(input: &SchedulerInput, options: &OptimiserOptions) -> SchedulerOutput {
+    let started = std::time::Instant::now();
+    let blocked_periods = all_blocked_periods(input);
+    let available_slots = build_time_slots(input.event_start, input.event_end, 30);
+
+    // Only explain the final schedule, not every one tried along the way
+    let score = |order: &[&Game]| {
+        voter_minutes(
+            &place_games(input, order, &available_slots, &blocked_periods, false)
+                .suggested_schedules,
+        )
+    };
+
+    let mut order = games_by_votes(input);
+    let mut current_score = score(&order);
+    let mut best_order = order.clone();
+    let mut best_score = current_score;
+
+    if order.len() > 1 {
+        let mut rng = SplitMix64(options.seed);
+
+        // Start hot enough to accept losing about one game's worth of voter-minutes, cooling to nothing
+        let start_temperature = (current_score as f64 / order.len() as f64).max(1.0);
+
+        for iteration in 0..options.max_iterations {
+            if started.elapsed() >= options.time_budget {
+                break;
+            }
+
+            // Move one game to somewhere else in the order
+            let from = rng.below(order.len());
+            let to = rng.below(order.len());
+            if from == to {
+                continue;
+            }
+            let mut candidate = order.clone();
+            let game = candidate.remove(from);
+            candidate.insert(to, game);
+
+            let candidate_score = score(&candidate);
+            let delta = candidate_score - current_score;
+            let temperature = start_temperature
+                * (1.0 - f64::from(iteration) / f64::from(options.max_iterations));
+
+            if delta >= 0 || rng.unit() < (delta as f64 / temperature).exp() {
+                order = candidate;
+                current_score = candidate_score;
+
+                if current_score > best_score {
+                    best_order.clone_from(&order);
+                    best_score = current_score;
+                }
+            }
+        }
     }
 
-    // Sort games by votes (descending) to prioritize higher-voted games
-    let mut sorted_games = input.games.clone();
+    place_games(
+        input,
+        &best_order,
+        &available_slots,
+        &blocked_periods,
+        input.explain,
+    )
+}
+
+/// Total voter-minutes of a schedule: how many people can play each game times how long it runs
+pub fn voter_minutes(schedules: &[SuggestedSchedule]) -> i64 {
+    schedules
+        .iter()
+        .map(|schedule| {
+            i64::from(schedule.availability_score) * i64::from(schedule.duration_minutes)
+        })
+        .sum()
+}
+
+/// Sort games by votes (descending) to prioritize higher-voted games
+fn games_by_votes(input: &SchedulerInput) -> Vec<&Game> {
+    let mut sorted_games: Vec<&Game> = input.games.iter().collect();
     sorted_games.sort_by(|a, b| b.votes.cmp(&a.votes));
+    sorted_games
+}
+
+/// Small, fast random number generator (`SplitMix64`). Implemented here so a seed gives
+/// the same schedule whichever version of the rand crate is in use.
+struct SplitMix64(u64);
+
+impl SplitMix64 {
+    const fn next_u64(&mut self) -> u64 {
+        self.0 = self.0.wrapping_add(0x9E37_79B9_7F4A_7C15);
+        let mut z = self.0;
+        z = (z ^ (z >> 30)).wrapping_mul(0xBF58_476D_1CE4_E5B9);
+        z = (z ^ (z >> 27)).wrapping_mul(0x94D0_49BB_1331_11EB);
+        z ^ (z >> 31)
+    }
 
-    // For each game (sorted by votes), find the best time slot
-    for game in &sorted_games {
-        let mut best_slot: Option<(DateTime<Utc>, i32)> = None;
+    /// A random index below `n`, which must not be zero
+    fn below(&mut self, n: usize) -> usize {
+        let n = u64::try_from(n).unwrap_or(u64::MAX);
+        usize::try_from(self.next_u64() % n).unwrap_or(0)
+    }
+
+    /// A random number from 0 up to but not including 1
+    #[allow(clippy::cast_precision_loss)]
+    fn unit(&mut self) -> f64 {
+        (self.next_u64() >> 11) as f64 / (1_u64 << 53) as f64
+    }
+}
+
+/// How many of the next best times to give when explaining a schedule
+const RUNNERS_UP: usize = 3;
+
+/// Where a game is placed, and who plays it there
+struct Placement {
+    start_time: DateTime<Utc>,
+    track: i32,
+    /// Voters for the game who are free then
+    available: Vec<String>,
+    groups: Vec<Vec<String>>,
+}
+
+/// Place games one at a time in the given order, each in the best time slot and track left for it
+#[allow(clippy::too_many_lines)]
+fn place_games(
+    input: &SchedulerInput,
+    games: &[&Game],
+    available_slots: &[DateTime<Utc>],
+    blocked_periods: &[BlockedPeriod],
+    explain: bool,
+) -> SchedulerOutput {
+    let mut suggested_schedules = Vec::new();
+    let mut unscheduled = Vec::new();
+
+    // Pinned slots are occupied from the start
+    let mut occupied_slots: Vec<OccupiedSlot> = input.pinned_slots.clone();
+
+    // For each game (in order), find the best time slot and track
+    for game in games {
+        let mut best_slot: Option<Placement> = None;
         let mut best_score = -1;
+        // Other places the game could go, kept to explain the choice
+        let mut candidates: Vec<SlotScore> = Vec::new();
+        // Remember how far each slot got, to say why a game couldn't be scheduled
+        let mut fits_event = false;
+        let mut voters_free = false;
 
         // Try each slot as a potential start time
-        for slot_start in &available_slots {
-            // Skip if game would start during nighttime (after 1am and before 10am)
-            // Starting at exactly 1am is invalid, but starting at exactly 10am is valid
-            let start_hour = slot_start.hour();
-            if (1..10).contains(&start_hour) {
-                continue;
-            }
-
+        for slot_start in available_slots {
             // Check if game fits within event
-            let candidate_end =
-                *slot_start + Duration::minutes(i64::from(input.default_game_duration));
+            let candidate_end = *slot_start + Duration::minutes(i64::from(game.duration_minutes));
             if candidate_end > input.event_end {
                 continue;
             }
-
-            // Skip if game would end during nighttime (after 1am and before 10am)
-            // Ending at exactly 1am is VALID (we allow it), but ending after 1am (1:01am-9:59am) is invalid
-            // So we check if hour is in range 2..10 (hours 2am through 9am)
-            let end_hour = candidate_end.hour();
-            if (2..10).contains(&end_hour) {
+            fits_event = true;
+
+            // Skip if the game would still be going when quiet hours or a blocked period start.
+            // Ending exactly as one starts is fine. Only skip once voters are checked if explaining,
+            // to tell blocked games apart from ones nobody is free for.
+            let is_blocked = blocked_periods.iter().any(|blocked| {
+                *slot_start < blocked.end_time && candidate_end > blocked.start_time
+            });
+            if is_blocked && !explain {
                 continue;
             }
 
-            // Check if this time range overlaps with already-suggested games
-            if overlaps_with_any(&occupied_slots, *slot_start, input.default_game_duration) {
-                continue;
-            }
-
-            // Calculate availability score for this slot
-            let score = calculate_availability_score(
+            // Find voters available for this slot who aren't already playing something else
+            let busy_voters = busy_voters(&occupied_slots, *slot_start, game.duration_minutes);
+            let available: Vec<String> = available_voters(
                 &input.voters,
                 &game.voter_ids,
                 input.event_start,
                 *slot_start,
-                input.default_game_duration,
+                game.duration_minutes,
+            )
+            .into_iter()
+            .filter(|voter_id| !busy_voters.contains(voter_id.as_str()))
+            .collect();
+
+            // Skip if too few players are available, and only count those who can play
+            let groups = split_into_groups(
+                available.clone(),
+                game.min_players,
+                game.max_players,
+                input.allow_parallel_groups,
             );
+            if groups.is_empty() {
+                continue;
+            }
+            voters_free = true;
+            if is_blocked {
+                continue;
+            }
 
+            // Use the first track where this time range doesn't overlap with other games,
+            // leaving room to set up between them
+            let Some(track) = (1..=input.tracks.max(1)).find(|track| {
+                !overlaps_with_any(
+                    &occupied_slots,
+                    *track,
+                    *slot_start,
+                    game.duration_minutes,
+                    input.setup_buffer_minutes,
+                )
+            }) else {
+                continue;
+            };
+
+            let score =
+                i32::try_from(groups.iter().map(Vec::len).sum::<usize>()).unwrap_or(i32::MAX);
+            if explain {
+                candidates.push(SlotScore {
+                    start_time: *slot_start,
+                    track,
+                    availability_score: score,
+                });
+            }
             if score > best_score {
                 best_score = score;
-                best_slot = Some((*slot_start, input.default_game_duration));
+                best_slot = Some(Placement {
+                    start_time: *slot_start,
+                    track,
+                    available,
+                    groups,
+                });
             }
         }
 
         // If we found a good slot with at least one voter available, add this game
-        if let Some((start_time, duration)) = best_slot {
-            if best_score > 0 {
-                occupied_slots.insert(start_time, duration);
-                suggested_schedules.push(SuggestedSchedule {
-                    game_id: game.id,
-                    game_name: game.name.clone(),
-                    start_time,
-                    duration_minutes: duration,
-                    availability_score: best_score,
-                });
+        let Some(Placement {
+            start_time,
+            track,
+            available,
+            groups,
+        }) = best_slot.filter(|_| best_score > 0)
+        else {
+            if !explain {
+                continue;
             }
+            let reason = if !fits_event {
+                UnscheduledReason::OutsideEvent
+            } else if voters_free {
+                UnscheduledReason::NoSlotFits
+            } else {
+                UnscheduledReason::NoVoterAvailable
+            };
+            unscheduled.push(UnscheduledGame {
+                game_id: game.id,
+                game_name: game.name.clone(),
+                reason,
+            });
+            continue;
+        };
+
+        occupied_slots.push(OccupiedSlot {
+            start_time,
+            duration_minutes: game.duration_minutes,
+            track,
+            voter_ids: groups.concat(),
+        });
+
+        let explanation = explain.then(|| {
+            // Best first, earliest first when they're as good as each other
+            candidates.retain(|slot| slot.start_time != start_time || slot.track != track);
+            candidates.sort_by_key(|slot| std::cmp::Reverse(slot.availability_score));
+            candidates.truncate(RUNNERS_UP);
+
+            ScheduleExplanation {
+                unavailable_voter_ids: game
+                    .voter_ids
+                    .iter()
+                    .filter(|voter_id| !available.contains(voter_id))
+                    .cloned()
+                    .collect(),
+                available_voter_ids: available,
+                runners_up: candidates,
+            }
+        });
+
+        let is_split = groups.len() > 1;
+        for (group, players) in (1..).zip(groups) {
+            suggested_schedules.push(SuggestedSchedule {
+                game_id: game.id,
+                game_name: game.name.clone(),
+                start_time,
+                duration_minutes: game.duration_minutes,
+                setup_minutes: input.setup_buffer_minutes,
+                availability_score: i32::try_from(players.len()).unwrap_or(i32::MAX),
+                group: is_split.then_some(group),
+                track,
+                explanation: explanation.clone(),
+            });
         }
     }
 
     SchedulerOutput {
         suggested_schedules,
+        unscheduled,
+    }
+}
+
+/// Split the voters available for a game into the groups that would play it, largest first.
+/// A second group is only formed if parallel groups are allowed and there are enough players left over.
+/// Returns no groups if too few players are available.
+fn split_into_groups(
+    mut available: Vec<String>,
+    min_players: Option<i32>,
+    max_players: Option<i32>,
+    allow_parallel_groups: bool,
+) -> Vec<Vec<String>> {
+    let min_players = usize::try_from(min_players.unwrap_or(1))
+        .unwrap_or(0)
+        .max(1);
+    if available.len() < min_players {
+        return Vec::new();
+    }
+
+    let Some(max_players) = max_players.and_then(|max| usize::try_from(max).ok()) else {
+        return vec![available];
+    };
+
+    let mut left_over = available.split_off(max_players.min(available.len()));
+    if allow_parallel_groups && left_over.len() >= min_players {
+        left_over.truncate(max_players);
+        vec![available, left_over]
+    } else {
+        vec![available]
+    }
+}
+
+/// The periods quiet hours cover from the start to the end of an event, cut to fit the event
+pub fn quiet_periods(
+    quiet_hours: QuietHours,
+    event_start: DateTime<Utc>,
+    event_end: DateTime<Utc>,
+) -> Vec<BlockedPeriod> {
+    if quiet_hours.start == quiet_hours.end {
+        return Vec::new();
+    }
+
+    let mut periods = Vec::new();
+    // Start the day before, in case the event starts during quiet hours that began before midnight
+    let mut date = event_start.date_naive() - Duration::days(1);
+
+    while date <= event_end.date_naive() {
+        let start_time = date.and_time(quiet_hours.start).and_utc();
+        let mut end_time = date.and_time(quiet_hours.end).and_utc();
+        if quiet_hours.end < quiet_hours.start {
+            end_time += Duration::days(1);
+        }
+
+        if start_time < event_end && end_time > event_start {
+            periods.push(BlockedPeriod {
+                start_time: start_time.max(event_start),
+                end_time: end_time.min(event_end),
+            });
+        }
+        date += Duration::days(1);
     }
+
+    periods
 }
 
-/// Build a list of potential start times (30-minute intervals)
+/// Quiet hours and blocked periods together
+fn all_blocked_periods(input: &SchedulerInput) -> Vec<BlockedPeriod> {
+    let mut periods = input.blocked_periods.clone();
+    if let Some(quiet_hours) = input.quiet_hours {
+        periods.extend(quiet_periods(
+            quiet_hours,
+            input.event_start,
+            input.event_end,
+        ));
+    }
+    periods
+}
+
+/// Build a list of potential start times (30-minute intervals).
+/// Blocked periods, pinned and suggested games are checked track by track when placing each game.
 fn build_time_slots(
     event_start: DateTime<Utc>,
     event_end: DateTime<Utc>,
-    pinned_slots: &[OccupiedSlot],
     slot_duration_minutes: i64,
 ) -> Vec<DateTime<Utc>> {
     let mut slots = Vec::new();
     let mut current = event_start;
 
     while current < event_end {
-        let next = current + Duration::minutes(slot_duration_minutes);
-        let slot_end = next.min(event_end);
-
-        // Check if this slot overlaps with any pinned game
-        let overlaps = pinned_slots.iter().any(|pinned| {
-            let pinned_end =
-                pinned.start_time + Duration::minutes(i64::from(pinned.duration_minutes));
-            // Overlap if slot starts before pinned ends AND slot ends after pinned starts
-            current < pinned_end && slot_end > pinned.start_time
-        });
-
-        if !overlaps {
-            slots.push(current);
-        }
-
-        current = next;
+        slots.push(current);
+        current += Duration::minutes(slot_duration_minutes);
     }
 
     slots
 }
 
-/// Check if a time range overlaps with any already-occupied slot
-fn overlaps_with_any(
-    occupied: &HashMap<DateTime<Utc>, i32>,
+/// Whether two time ranges overlap, once the first is padded by `buffer_minutes` on both sides
+fn ranges_overlap(
     start_time: DateTime<Utc>,
     duration_minutes: i32,
+    buffer_minutes: i32,
+    occupied: &OccupiedSlot,
 ) -> bool {
+    let buffer = Duration::minutes(i64::from(buffer_minutes));
     let end_time = start_time + Duration::minutes(i64::from(duration_minutes));
+    let occupied_end =
+        occupied.start_time + Duration::minutes(i64::from(occupied.duration_minutes));
 
-    occupied.iter().any(|(occupied_start, occupied_duration)| {
-        let occupied_end = *occupied_start + Duration::minutes(i64::from(*occupied_duration));
-        // Overlap check
-        start_time < occupied_end && end_time > *occupied_start
+    start_time < occupied_end + buffer && end_time + buffer > occupied.start_time
+}
+
+/// Check if a time range overlaps with any already-occupied slot on a track,
+/// or comes within `buffer_minutes` of one
+fn overlaps_with_any(
+    occupied: &[OccupiedSlot],
+    track: i32,
+    start_time: DateTime<Utc>,
+    duration_minutes: i32,
+    buffer_minutes: i32,
+) -> bool {
+    occupied.iter().any(|occupied| {
+        occupied.track == track
+            && ranges_overlap(start_time, duration_minutes, buffer_minutes, occupied)
     })
 }
 
-/// Calculate how many voters are available during a time slot
-fn calculate_availability_score(
+/// Voters already playing a game on any track during a time range
+fn busy_voters(
+    occupied: &[OccupiedSlot],
+    start_time: DateTime<Utc>,
+    duration_minutes: i32,
+) -> HashSet<&str> {
+    occupied
+        .iter()
+        .filter(|occupied| ranges_overlap(start_time, duration_minutes, 0, occupied))
+        .flat_map(|occupied| occupied.voter_ids.iter().map(String::as_str))
+        .collect()
+}
+
+/// Find which voters are available for the whole of a time slot
+fn available_voters(
     voters: &HashMap<String, Voter>,
     voter_ids: &[String],
     event_start: DateTime<Utc>,
     slot_start: DateTime<Utc>,
     slot_duration_minutes: i32,
-) -> i32 {
+) -> Vec<String> {
     // Calculate which bucket indices this slot covers
     // Attendance buckets are 6-hour blocks starting at 6am on event start date
     const BUCKET_DURATION_MINUTES: i64 = 360; // 6 hours
@@ -254,8 +668,8 @@ fn calculate_availability_score(
         0
     };
 
-    // Count how many voters are available for the entire slot
-    let mut available_count = 0;
+    // Find the voters available for the entire slot
+    let mut available = Vec::new();
 
     for voter_id in voter_ids {
         if let Some(voter) = voters.get(voter_id) {
@@ -276,21 +690,28 @@ fn calculate_availability_score(
             }
 
             if available_for_slot {
-                available_count += 1;
+                available.push(voter_id.clone());
             }
         }
     }
 
-    available_count
+    available
 }
 
 #[cfg(test)]
 mod tests {
     use super::*;
-    use chrono::{Datelike, TimeZone};
+    use chrono::{Datelike, TimeZone, Timelike};
 
-    #[test]
-    fn test_two_gamers_different_availability() {
+    /// The quiet hours events have by default, 1am to 10am
+    fn overnight_quiet_hours() -> QuietHours {
+        QuietHours {
+            start: NaiveTime::from_hms_opt(1, 0, 0).expect("Invalid time"),
+            end: NaiveTime::from_hms_opt(10, 0, 0).expect("Invalid time"),
+        }
+    }
+
+    fn two_gamers_fixture() -> SchedulerInput {
         // Test case: 2 gamers, one available for 2 days, other only available on second day
         // Both want Game A, only Gamer 1 wants Game B
         // Expected: Game A scheduled on Day 2 (when both available)
@@ -304,6 +725,9 @@ mod tests {
             id: 1,
             name: "Game A".to_string(),
             votes: 2,
+            duration_minutes: 120,
+            min_players: None,
+            max_players: None,
             voter_ids: vec!["gamer1".to_string(), "gamer2".to_string()],
         };
 
@@ -312,6 +736,9 @@ mod tests {
             id: 2,
             name: "Game B".to_string(),
             votes: 1,
+            duration_minutes: 120,
+            min_players: None,
+            max_players: None,
             voter_ids: vec!["gamer1".to_string()],
         };
 
@@ -340,14 +767,24 @@ mod tests {
         voters.insert("gamer1".to_string(), gamer1);
         voters.insert("gamer2".to_string(), gamer2);
 
-        let input = SchedulerInput {
+        SchedulerInput {
             games: vec![game_a, game_b], // Sorted by votes descending
             voters,
             event_start,
             event_end,
             pinned_slots: vec![],
-            default_game_duration: 120, // 2 hours
-        };
+            quiet_hours: Some(overnight_quiet_hours()),
+            blocked_periods: vec![],
+            setup_buffer_minutes: 0,
+            allow_parallel_groups: false,
+            tracks: 1,
+            explain: false,
+        }
+    }
+
+    #[test]
+    fn test_two_gamers_different_availability() {
+        let input = two_gamers_fixture();
 
         let output = schedule_games(&input);
 
@@ -400,8 +837,7 @@ mod tests {
         assert!(no_overlap, "Games should not overlap");
     }
 
-    #[test]
-    fn test_no_scheduling_during_nighttime() {
+    fn nighttime_fixture() -> SchedulerInput {
         // Test case: Ensure games are not in progress between 1am and 10am
         // Event runs from 10pm to midday next day (spanning overnight)
         // Gamer is available the entire time, but games should not be in progress during 1am-10am
@@ -416,6 +852,9 @@ mod tests {
             id: 1,
             name: "Game A".to_string(),
             votes: 3,
+            duration_minutes: 120,
+            min_players: None,
+            max_players: None,
             voter_ids: vec!["gamer1".to_string()],
         };
 
@@ -423,6 +862,9 @@ mod tests {
             id: 2,
             name: "Game B".to_string(),
             votes: 2,
+            duration_minutes: 120,
+            min_players: None,
+            max_players: None,
             voter_ids: vec!["gamer1".to_string()],
         };
 
@@ -430,6 +872,9 @@ mod tests {
             id: 3,
             name: "Game C".to_string(),
             votes: 1,
+            duration_minutes: 120,
+            min_players: None,
+            max_players: None,
             voter_ids: vec!["gamer1".to_string()],
         };
 
@@ -447,14 +892,24 @@ mod tests {
         let mut voters = HashMap::new();
         voters.insert("gamer1".to_string(), gamer1);
 
-        let input = SchedulerInput {
+        SchedulerInput {
             games: vec![game_a, game_b, game_c], // Three games to schedule
             voters,
             event_start,
             event_end,
             pinned_slots: vec![],
-            default_game_duration: 120, // 2 hours
-        };
+            quiet_hours: Some(overnight_quiet_hours()),
+            blocked_periods: vec![],
+            setup_buffer_minutes: 0,
+            allow_parallel_groups: false,
+            tracks: 1,
+            explain: false,
+        }
+    }
+
+    #[test]
+    fn test_no_scheduling_during_nighttime() {
+        let input = nighttime_fixture();
 
         let output = schedule_games(&input);
 
@@ -511,8 +966,7 @@ mod tests {
         }
     }
 
-    #[test]
-    fn test_game_prioritization_by_votes() {
+    fn prioritization_fixture() -> SchedulerInput {
         // Test case: Limited time means not all games can be scheduled
         // Games should be prioritized by vote count
         // Event is 4 hours long, each game is 2 hours, so only 2 games can fit
@@ -528,6 +982,9 @@ mod tests {
             id: 1,
             name: "Game Low Priority".to_string(),
             votes: 1,
+            duration_minutes: 120,
+            min_players: None,
+            max_players: None,
             voter_ids: vec!["gamer1".to_string()],
         };
 
@@ -536,6 +993,9 @@ mod tests {
             id: 2,
             name: "Game High Priority".to_string(),
             votes: 3,
+            duration_minutes: 120,
+            min_players: None,
+            max_players: None,
             voter_ids: vec![
                 "gamer1".to_string(),
                 "gamer2".to_string(),
@@ -548,6 +1008,9 @@ mod tests {
             id: 3,
             name: "Game Medium Priority".to_string(),
             votes: 2,
+            duration_minutes: 120,
+            min_players: None,
+            max_players: None,
             voter_ids: vec!["gamer1".to_string(), "gamer2".to_string()],
         };
 
@@ -572,14 +1035,24 @@ mod tests {
         voters.insert("gamer2".to_string(), gamer2);
         voters.insert("gamer3".to_string(), gamer3);
 
-        let input = SchedulerInput {
+        SchedulerInput {
             games: vec![game_low_priority, game_high_priority, game_medium_priority],
             voters,
             event_start,
             event_end,
             pinned_slots: vec![],
-            default_game_duration: 120, // 2 hours per game
-        };
+            quiet_hours: Some(overnight_quiet_hours()),
+            blocked_periods: vec![],
+            setup_buffer_minutes: 0,
+            allow_parallel_groups: false,
+            tracks: 1,
+            explain: false,
+        }
+    }
+
+    #[test]
+    fn test_game_prioritization_by_votes() {
+        let input = prioritization_fixture();
 
         let output = schedule_games(&input);
 
@@ -627,8 +1100,7 @@ mod tests {
         }
     }
 
-    #[test]
-    fn test_pinned_slots_block_scheduling() {
+    fn pinned_slots_fixture() -> SchedulerInput {
         // Test case: Pinned slots should block time and prevent scheduling in those times
         // This matches how the controller uses the scheduler:
         // - Pinned games are filtered OUT of the games list before calling the scheduler
@@ -647,6 +1119,9 @@ mod tests {
             id: 2,
             name: "Game To Schedule".to_string(),
             votes: 2,
+            duration_minutes: 120,
+            min_players: None,
+            max_players: None,
             voter_ids: vec!["gamer1".to_string(), "gamer2".to_string()],
         };
 
@@ -669,16 +1144,28 @@ mod tests {
         let pinned_slot = OccupiedSlot {
             start_time: Utc.with_ymd_and_hms(2024, 11, 24, 10, 0, 0).unwrap(),
             duration_minutes: 60, // 1 hour
+            track: 1,
+            voter_ids: vec![],
         };
 
-        let input = SchedulerInput {
+        SchedulerInput {
             games: vec![game_to_schedule], // Only unpinned games
             voters,
             event_start,
             event_end,
             pinned_slots: vec![pinned_slot], // Pinned slot blocks 10am-11am
-            default_game_duration: 120,      // 2 hours per game
-        };
+            quiet_hours: Some(overnight_quiet_hours()),
+            blocked_periods: vec![],
+            setup_buffer_minutes: 0,
+            allow_parallel_groups: false,
+            tracks: 1,
+            explain: false,
+        }
+    }
+
+    #[test]
+    fn test_pinned_slots_block_scheduling() {
+        let input = pinned_slots_fixture();
 
         let output = schedule_games(&input);
 
@@ -708,14 +1195,13 @@ mod tests {
         let game_end =
             game_schedule.start_time + Duration::minutes(i64::from(game_schedule.duration_minutes));
         assert!(
-            game_end <= event_end,
+            game_end <= input.event_end,
             "Scheduled game should fit within event boundaries"
         );
     }
 
-    #[test]
-    #[allow(clippy::too_many_lines, clippy::similar_names)]
-    fn test_overnight_event_with_pinned_slot() {
+    #[allow(clippy::similar_names)]
+    fn overnight_pinned_fixture() -> SchedulerInput {
         // Test case: Reproduce specific scenario with overnight event
         // Event: 9pm (21:00) to 1pm (13:00) next day - 16 hours total
         // 1 gamer available throughout
@@ -737,6 +1223,9 @@ mod tests {
             id: 1,
             name: "Game 1".to_string(),
             votes: 1,
+            duration_minutes: 120,
+            min_players: None,
+            max_players: None,
             voter_ids: vec!["gamer1".to_string()],
         };
 
@@ -744,6 +1233,9 @@ mod tests {
             id: 3,
             name: "Game 3".to_string(),
             votes: 1,
+            duration_minutes: 120,
+            min_players: None,
+            max_players: None,
             voter_ids: vec!["gamer1".to_string()],
         };
 
@@ -764,16 +1256,29 @@ mod tests {
         let pinned_slot = OccupiedSlot {
             start_time: Utc.with_ymd_and_hms(2024, 11, 25, 1, 0, 0).unwrap(), // 1am
             duration_minutes: 180,                                            // 3 hours
+            track: 1,
+            voter_ids: vec![],
         };
 
-        let input = SchedulerInput {
+        SchedulerInput {
             games: vec![game_1, game_3], // Only games with votes
             voters,
             event_start,
             event_end,
             pinned_slots: vec![pinned_slot], // Pinned slot blocks 1am-4am
-            default_game_duration: 120,      // 2 hours per game
-        };
+            quiet_hours: Some(overnight_quiet_hours()),
+            blocked_periods: vec![],
+            setup_buffer_minutes: 0,
+            allow_parallel_groups: false,
+            tracks: 1,
+            explain: false,
+        }
+    }
+
+    #[test]
+    #[allow(clippy::too_many_lines)]
+    fn test_overnight_event_with_pinned_slot() {
+        let input = overnight_pinned_fixture();
 
         let output = schedule_games(&input);
 
@@ -888,4 +1393,653 @@ mod tests {
             game_3_schedule.start_time, game_3_end
         );
     }
+
+    #[test]
+    fn test_games_use_their_own_durations() {
+        // A 3 hour game and a 1 hour game in a 4 hour event should fill it exactly
+        let event_start = Utc.with_ymd_and_hms(2024, 11, 24, 10, 0, 0).unwrap(); // 10am
+        let event_end = Utc.with_ymd_and_hms(2024, 11, 24, 14, 0, 0).unwrap(); // 2pm
+
+        let campaign = Game {
+            id: 1,
+            name: "Campaign".to_string(),
+            votes: 2,
+            duration_minutes: 180,
+            min_players: None,
+            max_players: None,
+            voter_ids: vec!["gamer1".to_string()],
+        };
+
+        let party_game = Game {
+            id: 2,
+            name: "Party Game".to_string(),
+            votes: 1,
+            duration_minutes: 60,
+            min_players: None,
+            max_players: None,
+            voter_ids: vec!["gamer1".to_string()],
+        };
+
+        let mut voters = HashMap::new();
+        voters.insert(
+            "gamer1".to_string(),
+            Voter {
+                id: "gamer1".to_string(),
+                attendance: vec![1, 1],
+            },
+        );
+
+        let input = SchedulerInput {
+            games: vec![campaign, party_game],
+            voters,
+            event_start,
+            event_end,
+            pinned_slots: vec![],
+            quiet_hours: Some(overnight_quiet_hours()),
+            blocked_periods: vec![],
+            setup_buffer_minutes: 0,
+            allow_parallel_groups: false,
+            tracks: 1,
+            explain: false,
+        };
+
+        let output = schedule_games(&input);
+        assert_eq!(output.suggested_schedules.len(), 2);
+
+        let campaign_schedule = &output.suggested_schedules[0];
+        assert_eq!(campaign_schedule.game_id, 1);
+        assert_eq!(campaign_schedule.duration_minutes, 180);
+        assert_eq!(campaign_schedule.start_time.hour(), 10);
+
+        let party_schedule = &output.suggested_schedules[1];
+        assert_eq!(party_schedule.game_id, 2);
+        assert_eq!(party_schedule.duration_minutes, 60);
+        assert_eq!(
+            party_schedule.start_time.hour(),
+            13,
+            "The party game should fill the hour after the campaign"
+        );
+    }
+
+    #[test]
+    fn test_setup_buffer_between_games() {
+        // Two 90 minute games with an hour to set up between them
+        let event_start = Utc.with_ymd_and_hms(2024, 11, 24, 10, 0, 0).unwrap(); // 10am
+        let event_end = Utc.with_ymd_and_hms(2024, 11, 24, 14, 0, 0).unwrap(); // 2pm
+
+        let game_a = Game {
+            id: 1,
+            name: "Game A".to_string(),
+            votes: 2,
+            duration_minutes: 90,
+            min_players: None,
+            max_players: None,
+            voter_ids: vec!["gamer1".to_string()],
+        };
+
+        let game_b = Game {
+            id: 2,
+            name: "Game B".to_string(),
+            votes: 1,
+            duration_minutes: 90,
+            min_players: None,
+            max_players: None,
+            voter_ids: vec!["gamer1".to_string()],
+        };
+
+        let mut voters = HashMap::new();
+        voters.insert(
+            "gamer1".to_string(),
+            Voter {
+                id: "gamer1".to_string(),
+                attendance: vec![1, 1],
+            },
+        );
+
+        let input = SchedulerInput {
+            games: vec![game_a, game_b],
+            voters,
+            event_start,
+            event_end,
+            pinned_slots: vec![],
+            quiet_hours: Some(overnight_quiet_hours()),
+            blocked_periods: vec![],
+            setup_buffer_minutes: 60,
+            allow_parallel_groups: false,
+            tracks: 1,
+            explain: false,
+        };
+
+        let output = schedule_games(&input);
+        assert_eq!(output.suggested_schedules.len(), 2);
+
+        // Game A runs 10am-11:30am, so Game B can't start until 12:30pm
+        let schedule_a = &output.suggested_schedules[0];
+        let schedule_b = &output.suggested_schedules[1];
+        assert_eq!(
+            schedule_a.start_time,
+            Utc.with_ymd_and_hms(2024, 11, 24, 10, 0, 0).unwrap()
+        );
+        assert_eq!(
+            schedule_b.start_time,
+            Utc.with_ymd_and_hms(2024, 11, 24, 12, 30, 0).unwrap()
+        );
+        assert_eq!(schedule_b.setup_minutes, 60);
+
+        // Pinned games need the same setup time after them
+        let pinned_input = SchedulerInput {
+            games: vec![input.games[1].clone()],
+            pinned_slots: vec![OccupiedSlot {
+                start_time: Utc.with_ymd_and_hms(2024, 11, 24, 10, 0, 0).unwrap(),
+                duration_minutes: 90,
+                track: 1,
+                voter_ids: vec![],
+            }],
+            ..input
+        };
+
+        let output = schedule_games(&pinned_input);
+        assert_eq!(output.suggested_schedules.len(), 1);
+        assert_eq!(
+            output.suggested_schedules[0].start_time,
+            Utc.with_ymd_and_hms(2024, 11, 24, 12, 30, 0).unwrap(),
+            "Game B needs an hour after the pinned game finishes at 11:30am"
+        );
+    }
+
+    /// Voters available for the whole of a day-long event starting at 10am
+    fn all_day_voters(count: usize) -> HashMap<String, Voter> {
+        (1..=count)
+            .map(|i| {
+                let id = format!("gamer{i}");
+                let voter = Voter {
+                    id: id.clone(),
+                    attendance: vec![1, 1, 1],
+                };
+                (id, voter)
+            })
+            .collect()
+    }
+
+    fn player_count_input(
+        voter_count: usize,
+        min_players: Option<i32>,
+        max_players: Option<i32>,
+        allow_parallel_groups: bool,
+    ) -> SchedulerInput {
+        let voters = all_day_voters(voter_count);
+        let game = Game {
+            id: 1,
+            name: "Game".to_string(),
+            votes: 10,
+            duration_minutes: 120,
+            min_players,
+            max_players,
+            voter_ids: voters.keys().cloned().collect(),
+        };
+
+        SchedulerInput {
+            games: vec![game],
+            voters,
+            event_start: Utc.with_ymd_and_hms(2024, 11, 24, 10, 0, 0).unwrap(),
+            event_end: Utc.with_ymd_and_hms(2024, 11, 24, 22, 0, 0).unwrap(),
+            pinned_slots: vec![],
+            quiet_hours: Some(overnight_quiet_hours()),
+            blocked_periods: vec![],
+            setup_buffer_minutes: 0,
+            allow_parallel_groups,
+            tracks: 1,
+            explain: false,
+        }
+    }
+
+    #[test]
+    fn test_split_into_groups() {
+        let group_sizes = |available: usize, min_players, max_players, allow_parallel_groups| {
+            let available = (1..=available).map(|i| format!("gamer{i}")).collect();
+            split_into_groups(available, min_players, max_players, allow_parallel_groups)
+                .iter()
+                .map(Vec::len)
+                .collect::<Vec<_>>()
+        };
+
+        assert_eq!(group_sizes(10, None, None, true), vec![10]);
+        assert_eq!(group_sizes(10, Some(2), Some(4), false), vec![4]);
+        assert_eq!(group_sizes(10, Some(2), Some(4), true), vec![4, 4]);
+        assert_eq!(group_sizes(5, Some(2), Some(4), true), vec![4]);
+        assert!(group_sizes(1, Some(2), Some(4), true).is_empty());
+        assert!(group_sizes(0, None, None, false).is_empty());
+    }
+
+    #[test]
+    fn test_score_capped_at_max_players() {
+        let output = schedule_games(&player_count_input(10, Some(1), Some(4), false));
+
+        assert_eq!(output.suggested_schedules.len(), 1);
+        assert_eq!(
+            output.suggested_schedules[0].availability_score, 4,
+            "Only 4 of the 10 available voters can play"
+        );
+        assert_eq!(output.suggested_schedules[0].group, None);
+    }
+
+    #[test]
+    fn test_not_scheduled_below_min_players() {
+        let output = schedule_games(&player_count_input(2, Some(4), Some(8), false));
+
+        assert!(
+            output.suggested_schedules.is_empty(),
+            "A 4 player game shouldn't be scheduled for 2 voters"
+        );
+    }
+
+    #[test]
+    fn test_parallel_groups_when_demand_exceeds_max_players() {
+        let output = schedule_games(&player_count_input(10, Some(2), Some(4), true));
+
+        assert_eq!(output.suggested_schedules.len(), 2);
+        let first = &output.suggested_schedules[0];
+        let second = &output.suggested_schedules[1];
+        assert_eq!(first.game_id, second.game_id);
+        assert_eq!(first.start_time, second.start_time);
+        assert_eq!(first.group, Some(1));
+        assert_eq!(second.group, Some(2));
+        assert_eq!(first.availability_score, 4);
+        assert_eq!(second.availability_score, 4);
+    }
+
+    fn track_test_game(id: i64, votes: i32, voter_ids: &[&str]) -> Game {
+        Game {
+            id,
+            name: format!("Game {id}"),
+            votes,
+            duration_minutes: 120,
+            min_players: None,
+            max_players: None,
+            voter_ids: voter_ids.iter().map(ToString::to_string).collect(),
+        }
+    }
+
+    fn track_test_input(games: Vec<Game>, tracks: i32, event_hours: i64) -> SchedulerInput {
+        let event_start = Utc.with_ymd_and_hms(2024, 11, 24, 10, 0, 0).unwrap();
+
+        SchedulerInput {
+            games,
+            voters: all_day_voters(4),
+            event_start,
+            event_end: event_start + Duration::hours(event_hours),
+            pinned_slots: vec![],
+            quiet_hours: Some(overnight_quiet_hours()),
+            blocked_periods: vec![],
+            setup_buffer_minutes: 0,
+            allow_parallel_groups: false,
+            tracks,
+            explain: false,
+        }
+    }
+
+    #[test]
+    fn test_parallel_tracks() {
+        // Two groups who want different games, with time for only one game each
+        let games = vec![
+            track_test_game(1, 2, &["gamer1", "gamer2"]),
+            track_test_game(2, 2, &["gamer3", "gamer4"]),
+        ];
+
+        let output = schedule_games(&track_test_input(games.clone(), 1, 2));
+        assert_eq!(
+            output.suggested_schedules.len(),
+            1,
+            "Only one game fits on a single track"
+        );
+
+        let output = schedule_games(&track_test_input(games, 2, 2));
+        assert_eq!(output.suggested_schedules.len(), 2);
+        let first = &output.suggested_schedules[0];
+        let second = &output.suggested_schedules[1];
+        assert_eq!(first.start_time, second.start_time);
+        assert_eq!(first.track, 1);
+        assert_eq!(second.track, 2);
+    }
+
+    #[test]
+    fn test_voters_play_one_game_at_a_time() {
+        // gamer1 wants both games, so can't be counted for both at 10am
+        let games = vec![
+            track_test_game(1, 2, &["gamer1", "gamer2"]),
+            track_test_game(2, 1, &["gamer1"]),
+        ];
+
+        let output = schedule_games(&track_test_input(games, 2, 4));
+        assert_eq!(output.suggested_schedules.len(), 2);
+        let second = &output.suggested_schedules[1];
+        assert_eq!(second.game_id, 2);
+        assert_eq!(
+            second.start_time,
+            Utc.with_ymd_and_hms(2024, 11, 24, 12, 0, 0).unwrap(),
+            "Game 2 should wait until gamer1 has finished Game 1"
+        );
+        assert_eq!(second.track, 1, "The first track is free again by then");
+    }
+
+    #[test]
+    fn test_pinned_game_only_blocks_its_track() {
+        let mut input = track_test_input(vec![track_test_game(1, 2, &["gamer1", "gamer2"])], 2, 2);
+        input.pinned_slots = vec![OccupiedSlot {
+            start_time: input.event_start,
+            duration_minutes: 120,
+            track: 1,
+            voter_ids: vec!["gamer3".to_string(), "gamer4".to_string()],
+        }];
+
+        let output = schedule_games(&input);
+        assert_eq!(output.suggested_schedules.len(), 1);
+        assert_eq!(output.suggested_schedules[0].start_time, input.event_start);
+        assert_eq!(output.suggested_schedules[0].track, 2);
+    }
+
+    /// A higher-voted game that fits anywhere, and a lower-voted one whose only voter leaves at noon
+    fn stranded_game_fixture() -> SchedulerInput {
+        let mut voters = all_day_voters(2);
+        voters.insert(
+            "gamer3".to_string(),
+            Voter {
+                id: "gamer3".to_string(),
+                attendance: vec![1, 0],
+            },
+        );
+
+        let mut input = track_test_input(
+            vec![
+                track_test_game(1, 3, &["gamer1", "gamer2"]),
+                track_test_game(2, 2, &["gamer3"]),
+            ],
+            1,
+            4,
+        );
+        input.voters = voters;
+        input
+    }
+
+    fn optimiser_options(seed: u64) -> OptimiserOptions {
+        OptimiserOptions {
+            seed,
+            max_iterations: 500,
+            time_budget: std::time::Duration::from_secs(30),
+        }
+    }
+
+    #[test]
+    fn test_optimiser_schedules_stranded_game() {
+        let input = stranded_game_fixture();
+
+        let greedy = schedule_games(&input);
+        assert_eq!(
+            greedy.suggested_schedules.len(),
+            1,
+            "Greedy takes the morning for Game 1, leaving no time for Game 2"
+        );
+
+        let optimised = optimise_schedule(&input, &optimiser_options(1));
+        assert_eq!(optimised.suggested_schedules.len(), 2);
+        assert_eq!(voter_minutes(&greedy.suggested_schedules), 240);
+        assert_eq!(voter_minutes(&optimised.suggested_schedules), 360);
+
+        let stranded = optimised
+            .suggested_schedules
+            .iter()
+            .find(|s| s.game_id == 2)
+            .expect("Game 2 should be scheduled");
+        assert_eq!(stranded.start_time, input.event_start);
+    }
+
+    #[test]
+    fn test_optimiser_is_deterministic() {
+        let input = stranded_game_fixture();
+
+        let first = optimise_schedule(&input, &optimiser_options(42));
+        let second = optimise_schedule(&input, &optimiser_options(42));
+        assert_eq!(first.suggested_schedules, second.suggested_schedules);
+    }
+
+    #[test]
+    fn test_optimiser_against_greedy() {
+        let fixtures = [
+            ("two gamers", two_gamers_fixture()),
+            ("nighttime", nighttime_fixture()),
+            ("prioritization", prioritization_fixture()),
+            ("pinned slots", pinned_slots_fixture()),
+            ("overnight pinned", overnight_pinned_fixture()),
+            (
+                "parallel groups",
+                player_count_input(10, Some(2), Some(4), true),
+            ),
+            ("stranded game", stranded_game_fixture()),
+        ];
+
+        for (name, input) in fixtures {
+            let greedy = voter_minutes(&schedule_games(&input).suggested_schedules);
+            let optimised = voter_minutes(
+                &optimise_schedule(&input, &optimiser_options(7)).suggested_schedules,
+            );
+
+            assert!(
+                optimised >= greedy,
+                "The optimiser should never do worse than greedy on the {name} fixture, \
+                 got {optimised} voter-minutes against {greedy}"
+            );
+            if name == "stranded game" {
+                assert!(
+                    optimised > greedy,
+                    "The optimiser should fit in the game greedy strands"
+                );
+            }
+        }
+    }
+
+    #[test]
+    fn test_optimiser_stops_at_time_budget() {
+        let options = OptimiserOptions {
+            time_budget: std::time::Duration::ZERO,
+            ..optimiser_options(1)
+        };
+
+        let output = optimise_schedule(&stranded_game_fixture(), &options);
+        assert_eq!(
+            output.suggested_schedules,
+            schedule_games(&stranded_game_fixture()).suggested_schedules,
+            "With no time to search, the greedy schedule is used"
+        );
+    }
+
+    #[test]
+    fn test_blocked_period() {
+        // Dinner from 11am to 1pm in an event from 10am to 4pm
+        let mut input = track_test_input(vec![track_test_game(1, 2, &["gamer1"])], 1, 6);
+        input.blocked_periods = vec![BlockedPeriod {
+            start_time: Utc.with_ymd_and_hms(2024, 11, 24, 11, 0, 0).unwrap(),
+            end_time: Utc.with_ymd_and_hms(2024, 11, 24, 13, 0, 0).unwrap(),
+        }];
+
+        let output = schedule_games(&input);
+        assert_eq!(output.suggested_schedules.len(), 1);
+        assert_eq!(
+            output.suggested_schedules[0].start_time,
+            Utc.with_ymd_and_hms(2024, 11, 24, 13, 0, 0).unwrap(),
+            "A 2 hour game doesn't fit before dinner, so starts when it finishes"
+        );
+    }
+
+    #[test]
+    fn test_quiet_hours_past_midnight() {
+        // Quiet from 11pm to 7am in an event from 8pm to 10am the next day
+        let games = (1..=3)
+            .map(|id| track_test_game(id, 1, &["gamer1"]))
+            .collect();
+        let mut input = track_test_input(games, 1, 14);
+        input.event_start = Utc.with_ymd_and_hms(2024, 11, 24, 20, 0, 0).unwrap();
+        input.event_end = Utc.with_ymd_and_hms(2024, 11, 25, 10, 0, 0).unwrap();
+        input.voters = HashMap::from([(
+            "gamer1".to_string(),
+            Voter {
+                id: "gamer1".to_string(),
+                attendance: vec![1; 8],
+            },
+        )]);
+        input.quiet_hours = Some(QuietHours {
+            start: NaiveTime::from_hms_opt(23, 0, 0).expect("Invalid time"),
+            end: NaiveTime::from_hms_opt(7, 0, 0).expect("Invalid time"),
+        });
+
+        let start_times: Vec<_> = schedule_games(&input)
+            .suggested_schedules
+            .iter()
+            .map(|schedule| schedule.start_time)
+            .collect();
+        assert_eq!(
+            start_times,
+            vec![
+                Utc.with_ymd_and_hms(2024, 11, 24, 20, 0, 0).unwrap(),
+                Utc.with_ymd_and_hms(2024, 11, 25, 7, 0, 0).unwrap(),
+            ],
+            "Only one game fits before 11pm, and one between 7am and the end of the event"
+        );
+    }
+
+    #[test]
+    fn test_quiet_periods() {
+        let event_start = Utc.with_ymd_and_hms(2024, 11, 24, 3, 0, 0).unwrap();
+        let event_end = Utc.with_ymd_and_hms(2024, 11, 25, 12, 0, 0).unwrap();
+
+        assert_eq!(
+            quiet_periods(overnight_quiet_hours(), event_start, event_end),
+            vec![
+                BlockedPeriod {
+                    start_time: event_start,
+                    end_time: Utc.with_ymd_and_hms(2024, 11, 24, 10, 0, 0).unwrap(),
+                },
+                BlockedPeriod {
+                    start_time: Utc.with_ymd_and_hms(2024, 11, 25, 1, 0, 0).unwrap(),
+                    end_time: Utc.with_ymd_and_hms(2024, 11, 25, 10, 0, 0).unwrap(),
+                },
+            ],
+            "Quiet hours are cut to fit the event"
+        );
+
+        let midnight = NaiveTime::from_hms_opt(0, 0, 0).expect("Invalid time");
+        let no_quiet_hours = QuietHours {
+            start: midnight,
+            end: midnight,
+        };
+        assert!(quiet_periods(no_quiet_hours, event_start, event_end).is_empty());
+    }
+
+    #[test]
+    fn test_explanation() {
+        let game = track_test_game(1, 2, &["gamer1", "gamer2", "gamer9"]);
+        let mut input = track_test_input(vec![game], 1, 4);
+
+        let output = schedule_games(&input);
+        assert_eq!(output.suggested_schedules[0].explanation, None);
+
+        input.explain = true;
+        let output = schedule_games(&input);
+        let explanation = output.suggested_schedules[0]
+            .explanation
+            .as_ref()
+            .expect("Schedule should be explained");
+        assert_eq!(explanation.available_voter_ids, vec!["gamer1", "gamer2"]);
+        assert_eq!(
+            explanation.unavailable_voter_ids,
+            vec!["gamer9"],
+            "gamer9 isn't at the event"
+        );
+
+        // The 2 hour game could start any half hour until midday
+        let runner_up_times: Vec<_> = explanation
+            .runners_up
+            .iter()
+            .map(|slot| slot.start_time)
+            .collect();
+        assert_eq!(
+            runner_up_times,
+            vec![
+                Utc.with_ymd_and_hms(2024, 11, 24, 10, 30, 0).unwrap(),
+                Utc.with_ymd_and_hms(2024, 11, 24, 11, 0, 0).unwrap(),
+                Utc.with_ymd_and_hms(2024, 11, 24, 11, 30, 0).unwrap(),
+            ]
+        );
+        assert!(explanation
+            .runners_up
+            .iter()
+            .all(|slot| slot.availability_score == 2));
+    }
+
+    #[test]
+    fn test_unscheduled_reasons() {
+        let mut too_long = track_test_game(1, 4, &["gamer1"]);
+        too_long.duration_minutes = 5 * 60;
+        let games = vec![
+            too_long,
+            track_test_game(2, 3, &["gamer1", "gamer2"]),
+            track_test_game(3, 2, &["gamer9"]),
+            track_test_game(4, 1, &["gamer3", "gamer4"]),
+        ];
+
+        let mut input = track_test_input(games, 1, 2);
+        assert!(
+            schedule_games(&input).unscheduled.is_empty(),
+            "Unscheduled games are only given when explaining"
+        );
+
+        input.explain = true;
+        let output = schedule_games(&input);
+        assert_eq!(output.suggested_schedules.len(), 1);
+        assert_eq!(output.suggested_schedules[0].game_id, 2);
+
+        let reasons: Vec<_> = output
+            .unscheduled
+            .iter()
+            .map(|game| (game.game_id, game.reason))
+            .collect();
+        assert_eq!(
+            reasons,
+            vec![
+                (1, UnscheduledReason::OutsideEvent),
+                (3, UnscheduledReason::NoVoterAvailable),
+                (4, UnscheduledReason::NoSlotFits),
+            ]
+        );
+    }
+
+    #[test]
+    fn test_unscheduled_when_blocked() {
+        // Blocked from 11am until the event ends at 2pm, and gamer1 only arrives at midday
+        let game = track_test_game(1, 2, &["gamer1"]);
+        let mut input = track_test_input(vec![game], 1, 4);
+        input.voters.insert(
+            "gamer1".to_string(),
+            Voter {
+                id: "gamer1".to_string(),
+                attendance: vec![0, 1],
+            },
+        );
+        input.blocked_periods = vec![BlockedPeriod {
+            start_time: Utc.with_ymd_and_hms(2024, 11, 24, 11, 0, 0).unwrap(),
+            end_time: Utc.with_ymd_and_hms(2024, 11, 24, 14, 0, 0).unwrap(),
+        }];
+        input.explain = true;
+
+        let output = schedule_games(&input);
+        assert!(output.suggested_schedules.is_empty());
+        assert_eq!(
+            output
+                .unscheduled
+                .iter()
+                .map(|game| (game.game_id, game.reason))
+                .collect::<Vec<_>>(),
+            vec![(1, UnscheduledReason::NoSlotFits)],
+            "gamer1 is free, but only while the time is blocked"
+        );
+    }
 }
@@ -0,0 +1,359 @@
+use std::collections::HashSet;
+
+/// Roman numerals in game titles, so "Civilization 6" finds "Civilization VI"
+const ROMAN_NUMERALS: [(&str, &str); 20] = [
+    ("i", "1"),
+    ("ii", "2"),
+    ("iii", "3"),
+    ("iv", "4"),
+    ("v", "5"),
+    ("vi", "6"),
+    ("vii", "7"),
+    ("viii", "8"),
+    ("ix", "9"),
+    ("x", "10"),
+    ("xi", "11"),
+    ("xii", "12"),
+    ("xiii", "13"),
+    ("xiv", "14"),
+    ("xv", "15"),
+    ("xvi", "16"),
+    ("xvii", "17"),
+    ("xviii", "18"),
+    ("xix", "19"),
+    ("xx", "20"),
+];
+
+/// Words that mark an app as something other than a game, such as DLC or a soundtrack
+const EXTRA_WORDS: [&str; 11] = [
+    "soundtrack",
+    "ost",
+    "dlc",
+    "artbook",
+    "demo",
+    "playtest",
+    "sdk",
+    "trailer",
+    "wallpaper",
+    "wallpapers",
+    "benchmark",
+];
+
+/// Phrases that mark an app as something other than a game
+const EXTRA_PHRASES: [&str; 5] = [
+    "season pass",
+    "dedicated server",
+    "art book",
+    "bonus content",
+    "soundtrack edition",
+];
+
+/// Words that mark an app as another edition of a game, such as a remaster or a bundle
+const EDITION_WORDS: [&str; 19] = [
+    "edition",
+    "definitive",
+    "remastered",
+    "remaster",
+    "goty",
+    "deluxe",
+    "ultimate",
+    "complete",
+    "anniversary",
+    "enhanced",
+    "hd",
+    "gold",
+    "premium",
+    "collectors",
+    "special",
+    "directors",
+    "cut",
+    "bundle",
+    "pack",
+];
+
+/// Phrases that mark an app as another edition of a game
+const EDITION_PHRASES: [&str; 2] = ["game of the year", "collector s"];
+
+/// How similar a name needs to be to a misspelt query to count as a match
+const MIN_SIMILARITY: f32 = 0.3;
+
+/// Split text into lowercase words, ignoring punctuation
+fn words(text: &str) -> Vec<String> {
+    text.split(|c: char| !c.is_alphanumeric())
+        .filter(|word| !word.is_empty())
+        .map(str::to_lowercase)
+        .collect()
+}
+
+/// Split text into lowercase words, with roman numerals as numbers
+pub fn normalise(text: &str) -> Vec<String> {
+    words(text)
+        .into_iter()
+        .map(|word| {
+            ROMAN_NUMERALS
+                .iter()
+                .find(|(roman, _)| *roman == word)
+                .map_or(word, |(_, number)| (*number).to_string())
+        })
+        .collect()
+}
+
+/// Build a full text search query matching names with words starting with each word
+/// of the query, such as `counter:* & str:*` for "counter str".
+/// Returns an empty string if the query has no words.
+pub fn prefix_tsquery(query: &str) -> String {
+    words(query)
+        .iter()
+        .map(|word| format!("{word}:*"))
+        .collect::<Vec<_>>()
+        .join(" & ")
+}
+
+/// Build a `LIKE` pattern matching names starting with the query, ignoring case
+pub fn like_prefix(query: &str) -> String {
+    let mut pattern = String::with_capacity(query.len() + 1);
+    for c in query.trim().to_lowercase().chars() {
+        if matches!(c, '%' | '_' | '\\') {
+            pattern.push('\\');
+        }
+        pattern.push(c);
+    }
+    pattern.push('%');
+    pattern
+}
+
+/// Whether an app looks like DLC, a soundtrack or similar rather than a game, going by its name
+pub fn looks_like_extra(name: &str) -> bool {
+    let words = words(name);
+    let joined = words.join(" ");
+
+    words
+        .iter()
+        .any(|word| EXTRA_WORDS.contains(&word.as_str()))
+        || EXTRA_PHRASES.iter().any(|phrase| joined.contains(phrase))
+}
+
+/// The words of a game's name without any edition, DLC or soundtrack markers,
+/// so "Skyrim Special Edition" and "Skyrim Soundtrack" both become "skyrim"
+fn base_name(name: &str) -> Vec<String> {
+    let mut text = format!(" {} ", normalise(name).join(" "));
+    for phrase in EXTRA_PHRASES.iter().chain(EDITION_PHRASES.iter()) {
+        text = text.replace(&format!(" {phrase} "), " ");
+    }
+
+    text.split_whitespace()
+        .filter(|word| !EXTRA_WORDS.contains(word) && !EDITION_WORDS.contains(word))
+        .map(str::to_string)
+        .collect()
+}
+
+/// Whether two apps look like versions of the same game going by their names,
+/// such as a game and its soundtrack, demo or definitive edition
+pub fn is_variant(name: &str, other_name: &str) -> bool {
+    let base = base_name(name);
+    !base.is_empty() && base == base_name(other_name)
+}
+
+/// The trigrams of some words, padded the same way as `pg_trgm`
+fn trigrams(words: &[String]) -> HashSet<[char; 3]> {
+    let mut trigrams = HashSet::new();
+    for word in words {
+        let padded: Vec<char> = format!("  {word} ").chars().collect();
+        for window in padded.windows(3) {
+            trigrams.insert([window[0], window[1], window[2]]);
+        }
+    }
+    trigrams
+}
+
+#[allow(clippy::cast_precision_loss)]
+fn similarity(a: &HashSet<[char; 3]>, b: &HashSet<[char; 3]>) -> f32 {
+    let union = a.union(b).count();
+    if union == 0 {
+        return 0.0;
+    }
+    a.intersection(b).count() as f32 / union as f32
+}
+
+/// How closely a run of words in the name matches the query, from 0 to 1, allowing for typos
+fn word_similarity(query: &[String], name: &[String]) -> f32 {
+    let query_trigrams = trigrams(query);
+    if name.len() <= query.len() {
+        return similarity(&query_trigrams, &trigrams(name));
+    }
+
+    name.windows(query.len())
+        .map(|window| similarity(&query_trigrams, &trigrams(window)))
+        .fold(0.0, f32::max)
+}
+
+/// How relevant a game name is to a search query, from 0 to 1, or None if it doesn't match.
+///
+/// Exact matches come first, then names starting with the query, then names with words
+/// starting with every word of the query, then names that are similar allowing for typos.
+#[allow(clippy::cast_precision_loss)]
+pub fn relevance(query: &str, name: &str) -> Option<f32> {
+    let query = normalise(query);
+    let name = normalise(name);
+    if query.is_empty() || name.is_empty() {
+        return None;
+    }
+
+    let query_text = query.join(" ");
+    let name_text = name.join(" ");
+
+    if query_text == name_text {
+        return Some(1.0);
+    }
+
+    // Favour names that are mostly made up of the query
+    let coverage = query_text.len() as f32 / name_text.len().max(query_text.len()) as f32;
+
+    if name_text.starts_with(&query_text) {
+        return Some(0.1f32.mul_add(coverage, 0.85));
+    }
+
+    if query.iter().all(|query_word| {
+        name.iter()
+            .any(|word| word.starts_with(query_word.as_str()))
+    }) {
+        return Some(0.1f32.mul_add(coverage, 0.7));
+    }
+
+    let similarity = word_similarity(&query, &name);
+    (similarity >= MIN_SIMILARITY).then_some(0.6 * similarity)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// A small catalogue with the kinds of names the Steam app list has
+    const CATALOGUE: [&str; 20] = [
+        "Counter-Strike 2",
+        "Counter-Strike: Global Offensive",
+        "Counter-Strike: Source",
+        "Counter-Strike 2 Soundtrack",
+        "Sid Meier's Civilization VI",
+        "Sid Meier's Civilization V",
+        "Sid Meier's Civilization VI - Rise and Fall",
+        "Age of Empires II: Definitive Edition",
+        "Age of Empires IV",
+        "Rocket League",
+        "Rocket League - Season Pass",
+        "Left 4 Dead 2",
+        "Left 4 Dead 2 Dedicated Server",
+        "Team Fortress 2",
+        "Portal 2",
+        "Portal",
+        "Stardew Valley",
+        "Deep Rock Galactic",
+        "Garry's Mod",
+        "Pokémon Mystery Dungeon",
+    ];
+
+    /// Search the catalogue the same way the API does, leaving out extras
+    fn search(query: &str) -> Vec<&'static str> {
+        let mut results: Vec<(&str, f32)> = CATALOGUE
+            .iter()
+            .filter(|name| !looks_like_extra(name))
+            .filter_map(|name| relevance(query, name).map(|relevance| (*name, relevance)))
+            .collect();
+        results.sort_by(|a, b| b.1.total_cmp(&a.1).then_with(|| a.0.len().cmp(&b.0.len())));
+        results.into_iter().map(|(name, _)| name).collect()
+    }
+
+    #[test]
+    fn test_partial_words_match() {
+        let results = search("counter str");
+        assert_eq!(results[0], "Counter-Strike 2");
+        assert!(results.contains(&"Counter-Strike: Global Offensive"));
+        assert!(results.contains(&"Counter-Strike: Source"));
+    }
+
+    #[test]
+    fn test_words_in_any_order_match() {
+        assert_eq!(search("galactic rock")[0], "Deep Rock Galactic");
+    }
+
+    #[test]
+    fn test_exact_matches_come_first() {
+        assert_eq!(search("portal")[0], "Portal");
+        assert_eq!(search("Portal 2")[0], "Portal 2");
+    }
+
+    #[test]
+    fn test_numerals_match_roman_numerals() {
+        assert_eq!(search("civilization 6")[0], "Sid Meier's Civilization VI");
+        assert_eq!(
+            search("age of empires 2")[0],
+            "Age of Empires II: Definitive Edition"
+        );
+        assert_eq!(search("left 4 dead")[0], "Left 4 Dead 2");
+    }
+
+    #[test]
+    fn test_typos_match() {
+        assert_eq!(search("stardew valey")[0], "Stardew Valley");
+        assert_eq!(search("rocket leage")[0], "Rocket League");
+        assert_eq!(search("teem fortress")[0], "Team Fortress 2");
+    }
+
+    #[test]
+    fn test_accents_and_punctuation_match() {
+        assert_eq!(search("pokémon")[0], "Pokémon Mystery Dungeon");
+        assert_eq!(search("garrys mod")[0], "Garry's Mod");
+    }
+
+    #[test]
+    fn test_unrelated_names_dont_match() {
+        assert!(!search("stardew").contains(&"Portal"));
+        assert!(search("zzzz").is_empty());
+    }
+
+    #[test]
+    fn test_extras_are_recognised() {
+        assert!(looks_like_extra("Counter-Strike 2 Soundtrack"));
+        assert!(looks_like_extra("Rocket League - Season Pass"));
+        assert!(looks_like_extra("Left 4 Dead 2 Dedicated Server"));
+        assert!(looks_like_extra("Deep Rock Galactic - OST"));
+        assert!(!looks_like_extra("Demolition Derby"));
+        assert!(!looks_like_extra("Counter-Strike 2"));
+    }
+
+    #[test]
+    fn test_variants_of_a_game_are_recognised() {
+        assert!(is_variant(
+            "Counter-Strike 2 Soundtrack",
+            "Counter-Strike 2"
+        ));
+        assert!(is_variant("Rocket League", "Rocket League - Season Pass"));
+        assert!(is_variant(
+            "Age of Empires II: Definitive Edition",
+            "Age of Empires 2 HD"
+        ));
+        assert!(is_variant(
+            "The Elder Scrolls V: Skyrim Special Edition",
+            "The Elder Scrolls V: Skyrim"
+        ));
+        assert!(is_variant(
+            "Left 4 Dead 2 Dedicated Server",
+            "Left 4 Dead 2"
+        ));
+    }
+
+    #[test]
+    fn test_sequels_and_spin_offs_are_not_variants() {
+        assert!(!is_variant("Portal 2", "Portal"));
+        assert!(!is_variant("Counter-Strike: Source", "Counter-Strike 2"));
+        assert!(!is_variant("Age of Empires IV", "Age of Empires II"));
+        assert!(!is_variant("Soundtrack", "OST"));
+    }
+
+    #[test]
+    fn test_search_queries_are_escaped() {
+        assert_eq!(prefix_tsquery("Counter-Str"), "counter:* & str:*");
+        assert_eq!(prefix_tsquery("!!"), "");
+        assert_eq!(like_prefix("100%_Orange\\"), "100\\%\\_orange\\\\%");
+    }
+}
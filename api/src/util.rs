@@ -1,29 +1,174 @@
 use chrono::{prelude::Utc, Datelike, Duration};
-use resend_rs::{types::CreateEmailBaseOptions, Resend};
+use hmac::{Hmac, Mac};
+use resend_rs::{
+    types::{Attachment, CreateEmailBaseOptions},
+    Resend,
+};
 use rocket_dyn_templates::tera::{Context, Tera};
 use rusty_paseto::prelude::*;
+use sha2::Sha256;
 use sqlx::PgPool;
 
 use crate::{
     controllers::event,
+    repositories::{self, notification_preference::NotificationCategory},
     routes::{
         event_invitations::{InvitationResponse, InvitationsResponse},
         events::Event,
     },
 };
 
+const EMAIL_FROM: &str = "CaLANdar <lewis+calandar@updates.oaten.name>";
+const EMAIL_REPLY_TO: &str = "lewis+calandar@oaten.name";
+
+/// A rendered email, with the HTML body and its plain-text alternative
+pub struct EmailBody {
+    pub html: String,
+    pub text: String,
+}
+
+/// A file attached to an outgoing email
+pub struct EmailAttachment {
+    pub filename: String,
+    pub content_type: String,
+    pub content: Vec<u8>,
+}
+
+/// Add the plain-text part and any attachments to an email
+fn with_body_and_attachments(
+    mut email: CreateEmailBaseOptions,
+    body: &EmailBody,
+    attachments: Vec<EmailAttachment>,
+) -> CreateEmailBaseOptions {
+    email = email.with_html(&body.html).with_text(&body.text);
+
+    for attachment in attachments {
+        email = email.with_attachment(
+            Attachment::from_content(attachment.content)
+                .with_filename(&attachment.filename)
+                .with_content_type(&attachment.content_type),
+        );
+    }
+
+    email
+}
+
+/// How long unsubscribe links keep working, long enough for old emails to still be useful
+const UNSUBSCRIBE_LINK_LIFETIME_DAYS: i64 = 365;
+
+/// Create a signed link that unsubscribes an email address from a category of email
+pub fn unsubscribe_url(
+    key: &PasetoSymmetricKey<V4, Local>,
+    email: &str,
+    category: NotificationCategory,
+) -> Result<String, String> {
+    let token = unsubscribe_token(
+        key,
+        email,
+        category,
+        Utc::now() + Duration::days(UNSUBSCRIBE_LINK_LIFETIME_DAYS),
+    )?;
+
+    Ok(format!(
+        "https://calandar.org/api/unsubscribe?token={token}"
+    ))
+}
+
+fn unsubscribe_token(
+    key: &PasetoSymmetricKey<V4, Local>,
+    email: &str,
+    category: NotificationCategory,
+    expires_at: chrono::DateTime<Utc>,
+) -> Result<String, String> {
+    let Ok(expiration_claim) = ExpirationClaim::try_from(expires_at.to_rfc3339()) else {
+        return Err("Can't create time for expiration claim".to_string());
+    };
+
+    let Ok(category_claim) =
+        CustomClaim::try_from((String::from("c"), category.as_str().to_string()))
+    else {
+        return Err("Can't create notification category claim".to_string());
+    };
+
+    let Ok(token) = PasetoBuilder::<V4, Local>::default()
+        .set_claim(IssuerClaim::from("calandar.org"))
+        .set_claim(TokenIdentifierClaim::from("unsub"))
+        .set_claim(SubjectClaim::from(email))
+        .set_claim(category_claim)
+        .set_claim(expiration_claim)
+        .build(key)
+    else {
+        return Err("Error building unsubscribe token".to_string());
+    };
+
+    Ok(token)
+}
+
+/// Validate an unsubscribe token, returning the email address and category it unsubscribes
+pub fn parse_unsubscribe_token(
+    key: &PasetoSymmetricKey<V4, Local>,
+    token: &str,
+) -> Result<(String, NotificationCategory), String> {
+    let Ok(generic_token) = PasetoParser::<V4, Local>::default()
+        .check_claim(IssuerClaim::from("calandar.org"))
+        .check_claim(TokenIdentifierClaim::from("unsub"))
+        .parse(token, key)
+    else {
+        return Err(
+            "Invalid or expired unsubscribe link, change which emails you receive from your profile"
+                .to_string(),
+        );
+    };
+
+    let Some(email) = generic_token["sub"].as_str() else {
+        return Err("Unsubscribe link has no email address".to_string());
+    };
+
+    let Some(category) = generic_token["c"]
+        .as_str()
+        .and_then(NotificationCategory::parse)
+    else {
+        return Err("Unsubscribe link has an unknown category".to_string());
+    };
+
+    Ok((email.to_string(), category))
+}
+
+/// Add an unsubscribe link for the recipient to an email template context,
+/// returning the link so it can also be sent in the `List-Unsubscribe` header
+pub fn add_unsubscribe_link(
+    context: &mut Context,
+    key: &PasetoSymmetricKey<V4, Local>,
+    email: &str,
+    category: NotificationCategory,
+) -> Result<String, String> {
+    let url = unsubscribe_url(key, email, category)?;
+
+    context.insert("unsubscribe_url", &url);
+    context.insert("unsubscribe_label", category.label());
+
+    Ok(url)
+}
+
 pub async fn send_email(
     sender: &Resend,
     tos: Vec<&str>,
     subject: &str,
-    body: &str,
+    body: &EmailBody,
+    attachments: Vec<EmailAttachment>,
+    unsubscribe_url: Option<&str>,
 ) -> Result<(), String> {
-    let from = "CaLANdar <lewis+calandar@updates.oaten.name>";
-    let reply_to = "lewis+calandar@oaten.name";
-
-    let email = CreateEmailBaseOptions::new(from, tos.clone(), subject)
-        .with_reply(reply_to)
-        .with_html(body);
+    let mut email = with_body_and_attachments(
+        CreateEmailBaseOptions::new(EMAIL_FROM, tos, subject).with_reply(EMAIL_REPLY_TO),
+        body,
+        attachments,
+    );
+
+    if let Some(unsubscribe_url) = unsubscribe_url {
+        email = email
+            .with_header("List-Unsubscribe", &format!("<{unsubscribe_url}>"))
+            .with_header("List-Unsubscribe-Post", "List-Unsubscribe=One-Click");
+    }
 
     match sender.emails.send(email).await {
         Ok(_response) => Ok(()),
@@ -35,33 +180,210 @@ pub async fn send_email(
     }
 }
 
-pub async fn send_email_bcc(
-    sender: &Resend,
-    bccs: Vec<&str>,
-    subject: &str,
-    body: &str,
-) -> Result<(), String> {
-    let from = "CaLANdar <lewis+calandar@updates.oaten.name>";
-    let reply_to = "lewis+calandar@oaten.name";
-
-    // Send to the from address so there's a valid "to" recipient
-    // All actual recipients are in BCC to hide email addresses from each other
-    let mut email = CreateEmailBaseOptions::new(from, vec![from], subject)
-        .with_reply(reply_to)
-        .with_html(body);
-
-    // Add each BCC recipient individually
-    for bcc in bccs {
-        email = email.with_bcc(bcc);
+/// Render an HTML email template along with its plain-text alternative.
+/// The text part comes from the sibling `.txt.tera` template if one is registered,
+/// otherwise it is generated from the rendered HTML.
+pub fn render_email(tera: &Tera, template: &str, context: &Context) -> Result<EmailBody, String> {
+    let html = match tera.render(template, context) {
+        Ok(html) => html,
+        Err(e) => return Err(format!("Error rendering email with: {e}")),
+    };
+
+    let text_template = template.replace(".html.tera", ".txt.tera");
+    let has_text_template =
+        text_template != template && tera.get_template_names().any(|name| name == text_template);
+
+    let text = if has_text_template {
+        match tera.render(text_template.as_str(), context) {
+            Ok(text) => text.trim().to_string(),
+            Err(e) => return Err(format!("Error rendering plain-text email with: {e}")),
+        }
+    } else {
+        html_to_text(&html)
+    };
+
+    Ok(EmailBody { html, text })
+}
+
+/// Convert a rendered HTML email into a readable plain-text version.
+/// Only handles the small subset of HTML used by our email templates.
+pub fn html_to_text(html: &str) -> String {
+    let mut text = String::new();
+    let mut chars = html.chars();
+    let mut link_href: Option<String> = None;
+    let mut link_text_start = 0;
+    let mut skip_content = false;
+
+    while let Some(c) = chars.next() {
+        if c == '<' {
+            let mut tag = String::new();
+            for t in chars.by_ref() {
+                if t == '>' {
+                    break;
+                }
+                tag.push(t);
+            }
+
+            let tag = tag.trim();
+            let is_closing = tag.starts_with('/');
+            let name = tag
+                .trim_start_matches('/')
+                .split(|t: char| t.is_whitespace() || t == '/')
+                .next()
+                .unwrap_or_default()
+                .to_lowercase();
+
+            match (name.as_str(), is_closing) {
+                ("head" | "style" | "script", false) => skip_content = true,
+                ("head" | "style" | "script", true) => skip_content = false,
+                ("br", _) => text.push('\n'),
+                ("p" | "div" | "ul" | "ol" | "h1" | "h2" | "h3" | "code", _) => {
+                    text.push_str("\n\n");
+                }
+                ("li", false) => text.push_str("\n- "),
+                ("a", false) => {
+                    link_href = extract_attribute(tag, "href");
+                    link_text_start = text.len();
+                }
+                ("a", true) => {
+                    if let Some(href) = link_href.take() {
+                        let link_text = text[link_text_start..].trim();
+                        if link_text != href {
+                            text.push_str(" (");
+                            text.push_str(&href);
+                            text.push(')');
+                        }
+                    }
+                }
+                _ => {}
+            }
+            continue;
+        }
+
+        if skip_content {
+            continue;
+        }
+
+        if c.is_whitespace() {
+            // Collapse runs of whitespace the same way a browser would
+            if !text.ends_with([' ', '\n']) && !text.is_empty() {
+                text.push(' ');
+            }
+        } else if c == '&' {
+            let mut entity = String::new();
+            for e in chars.by_ref() {
+                if e == ';' || entity.len() > 8 {
+                    break;
+                }
+                entity.push(e);
+            }
+            text.push_str(match entity.as_str() {
+                "amp" => "&",
+                "lt" => "<",
+                "gt" => ">",
+                "quot" => "\"",
+                "#39" | "apos" => "'",
+                "nbsp" => " ",
+                _ => "",
+            });
+        } else {
+            text.push(c);
+        }
     }
 
-    match sender.emails.send(email).await {
-        Ok(_response) => Ok(()),
-        Err(e) => {
-            // Try to log more details if possible
-            eprintln!("Resend error: {e:?}");
-            Err(format!("Resend error: {e}"))
+    // Tidy up the line structure: trim each line and allow at most one blank line in a row
+    let mut result = String::new();
+    let mut blank_lines = 0;
+    for line in text.lines().map(str::trim) {
+        if line.is_empty() {
+            blank_lines += 1;
+            continue;
+        }
+        if !result.is_empty() {
+            result.push_str(if blank_lines > 0 { "\n\n" } else { "\n" });
+        }
+        result.push_str(line);
+        blank_lines = 0;
+    }
+
+    result
+}
+
+fn extract_attribute(tag: &str, attribute: &str) -> Option<String> {
+    let start = tag.find(&format!("{attribute}=\""))? + attribute.len() + 2;
+    let end = tag[start..].find('"')? + start;
+    Some(tag[start..end].replace("&amp;", "&"))
+}
+
+/// Escape text for use in an iCalendar property value (RFC 5545 section 3.3.11)
+fn escape_ics_text(text: &str) -> String {
+    text.replace('\\', "\\\\")
+        .replace(';', "\\;")
+        .replace(',', "\\,")
+        .replace("\r\n", "\\n")
+        .replace('\n', "\\n")
+}
+
+/// Fold an iCalendar content line so no line is longer than 75 octets (RFC 5545 section 3.1)
+fn fold_ics_line(line: &str) -> String {
+    let mut folded = String::new();
+    let mut line_length = 0;
+
+    for c in line.chars() {
+        if line_length + c.len_utf8() > 75 {
+            folded.push_str("\r\n ");
+            line_length = 1;
         }
+        folded.push(c);
+        line_length += c.len_utf8();
+    }
+
+    folded.push_str("\r\n");
+    folded
+}
+
+/// Build an iCalendar file describing an event, so recipients can add it to their calendar.
+/// The UID is stable per event so that updated copies replace the original in calendar clients.
+pub fn event_ics(event: &repositories::event::Event) -> String {
+    let ics_time_format = "%Y%m%dT%H%M%SZ";
+    let sequence = (event.last_modified - event.created_at)
+        .num_seconds()
+        .max(0);
+
+    [
+        "BEGIN:VCALENDAR".to_string(),
+        "VERSION:2.0".to_string(),
+        "PRODID:-//caLANdar//caLANdar//EN".to_string(),
+        "CALSCALE:GREGORIAN".to_string(),
+        "METHOD:PUBLISH".to_string(),
+        "BEGIN:VEVENT".to_string(),
+        format!("UID:event-{}@calandar.org", event.id),
+        format!("SEQUENCE:{sequence}"),
+        format!("DTSTAMP:{}", event.last_modified.format(ics_time_format)),
+        format!(
+            "LAST-MODIFIED:{}",
+            event.last_modified.format(ics_time_format)
+        ),
+        format!("DTSTART:{}", event.time_begin.format(ics_time_format)),
+        format!("DTEND:{}", event.time_end.format(ics_time_format)),
+        format!("SUMMARY:{}", escape_ics_text(&event.title)),
+        format!("DESCRIPTION:{}", escape_ics_text(&event.description)),
+        format!("URL:https://calandar.org/events/{}", event.id),
+        "STATUS:CONFIRMED".to_string(),
+        "END:VEVENT".to_string(),
+        "END:VCALENDAR".to_string(),
+    ]
+    .iter()
+    .map(|line| fold_ics_line(line))
+    .collect()
+}
+
+/// Calendar invite attachment for an event
+pub fn event_ics_attachment(event: &repositories::event::Event) -> EmailAttachment {
+    EmailAttachment {
+        filename: "invite.ics".to_string(),
+        content_type: "text/calendar; charset=utf-8; method=PUBLISH".to_string(),
+        content: event_ics(event).into_bytes(),
     }
 }
 
@@ -69,6 +391,9 @@ pub struct PreauthEmailDetails {
     pub address: String,
     pub subject: String,
     pub template: String,
+    pub attachments: Vec<EmailAttachment>,
+    /// The category of notification, if the recipient is able to unsubscribe from this email
+    pub category: Option<NotificationCategory>,
 }
 
 pub async fn send_preauth_email(
@@ -104,18 +429,22 @@ pub async fn send_preauth_email(
 
     template_context.insert("token", &token);
 
-    let body = match tera.render(email.template.as_str(), template_context) {
-        Ok(body) => body,
-        Err(e) => {
-            return Err(format!("Error rendering email with: {e}"));
-        }
-    };
+    let unsubscribe_url = email
+        .category
+        .map(|category| {
+            add_unsubscribe_link(template_context, key, email.address.as_str(), category)
+        })
+        .transpose()?;
+
+    let body = render_email(tera, email.template.as_str(), template_context)?;
 
     match send_email(
         sender,
         vec![email.address.as_str()],
         email.subject.as_str(),
-        body.as_str(),
+        &body,
+        email.attachments,
+        unsubscribe_url.as_deref(),
     )
     .await
     {
@@ -256,6 +585,15 @@ pub fn format_attendance_description(
     format!("{}, and {}", parts.join(", "), last)
 }
 
+/// Sign a webhook payload with the webhook's secret.
+/// The timestamp is included so receivers can reject replayed requests.
+pub fn webhook_signature(secret: &str, timestamp: i64, body: &str) -> String {
+    let mut mac =
+        Hmac::<Sha256>::new_from_slice(secret.as_bytes()).expect("HMAC accepts keys of any length");
+    mac.update(format!("{timestamp}.{body}").as_bytes());
+    hex::encode(mac.finalize().into_bytes())
+}
+
 /// Helper function to log audit entries
 /// Fails softly - errors are logged but don't block the operation
 pub async fn log_audit(
@@ -277,9 +615,147 @@ pub async fn log_audit(
     };
 
     match audit_log::insert(pool, audit_entry).await {
-        Ok(_) => {}
+        Ok(audit_log) => {
+            crate::controllers::webhook::enqueue(pool, &audit_log).await;
+
+            // Posting to Discord calls out to an external API, so don't hold up the request
+            let pool = pool.clone();
+            tokio::spawn(async move {
+                crate::controllers::discord::on_audit(&pool, &audit_log).await;
+            });
+        }
         Err(e) => {
             log::error!("Failed to write audit log: {e}");
         }
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use chrono::TimeZone;
+
+    fn test_event() -> repositories::event::Event {
+        repositories::event::Event {
+            id: 42,
+            created_at: Utc.with_ymd_and_hms(2025, 1, 1, 12, 0, 0).unwrap(),
+            last_modified: Utc.with_ymd_and_hms(2025, 1, 1, 12, 5, 0).unwrap(),
+            title: "Winter LAN, 2025; the sequel".to_string(),
+            description: "Bring a cable.\nAnd snacks!".to_string(),
+            image: None,
+            time_begin: Utc.with_ymd_and_hms(2025, 2, 7, 18, 0, 0).unwrap(),
+            time_end: Utc.with_ymd_and_hms(2025, 2, 9, 16, 30, 0).unwrap(),
+        }
+    }
+
+    #[test]
+    fn test_html_to_text_paragraphs_and_line_breaks() {
+        let html = "<html><head><meta charset=\"utf-8\" /></head><body>\n    <p>Dear Lewis,</p>\n\n    <p>First line<br>second   line</p></body></html>";
+        assert_eq!(html_to_text(html), "Dear Lewis,\n\nFirst line\nsecond line");
+    }
+
+    #[test]
+    fn test_html_to_text_links_show_their_target() {
+        let html = r#"<p>Please <a href="https://calandar.org/verify_email?token=abc">click here</a> to RSVP.</p>"#;
+        assert_eq!(
+            html_to_text(html),
+            "Please click here (https://calandar.org/verify_email?token=abc) to RSVP."
+        );
+    }
+
+    #[test]
+    fn test_html_to_text_link_text_matching_target_is_not_repeated() {
+        let html = r#"<p><a href="https://calandar.org">https://calandar.org</a></p>"#;
+        assert_eq!(html_to_text(html), "https://calandar.org");
+    }
+
+    #[test]
+    fn test_html_to_text_lists_and_entities() {
+        let html = "<ul><li><strong>Starts:</strong> Fri</li><li>Tom &amp; Jerry&#39;s &lt;LAN&gt;</li></ul>";
+        assert_eq!(html_to_text(html), "- Starts: Fri\n- Tom & Jerry's <LAN>");
+    }
+
+    #[test]
+    fn test_event_ics_contains_event_details() {
+        let ics = event_ics(&test_event());
+
+        assert!(ics.starts_with("BEGIN:VCALENDAR\r\n"));
+        assert!(ics.ends_with("END:VCALENDAR\r\n"));
+        assert!(ics.contains("UID:event-42@calandar.org\r\n"));
+        assert!(ics.contains("SEQUENCE:300\r\n"));
+        assert!(ics.contains("DTSTART:20250207T180000Z\r\n"));
+        assert!(ics.contains("DTEND:20250209T163000Z\r\n"));
+        assert!(ics.contains("URL:https://calandar.org/events/42\r\n"));
+    }
+
+    #[test]
+    fn test_event_ics_escapes_text() {
+        let ics = event_ics(&test_event());
+
+        assert!(ics.contains("SUMMARY:Winter LAN\\, 2025\\; the sequel\r\n"));
+        assert!(ics.contains("DESCRIPTION:Bring a cable.\\nAnd snacks!\r\n"));
+    }
+
+    #[test]
+    fn test_unsubscribe_token_round_trip() {
+        let key = PasetoSymmetricKey::<V4, Local>::from(Key::from([0; 32]));
+
+        let url = unsubscribe_url(
+            &key,
+            "test@test.invalid",
+            NotificationCategory::EventUpdates,
+        )
+        .expect("Unsubscribe URL built.");
+        let token = url
+            .strip_prefix("https://calandar.org/api/unsubscribe?token=")
+            .expect("Unsubscribe URL points at the API.");
+
+        let (email, category) =
+            parse_unsubscribe_token(&key, token).expect("Unsubscribe token parsed.");
+        assert_eq!(email, "test@test.invalid");
+        assert_eq!(category, NotificationCategory::EventUpdates);
+    }
+
+    #[test]
+    fn test_unsubscribe_token_expires() {
+        let key = PasetoSymmetricKey::<V4, Local>::from(Key::from([0; 32]));
+
+        let token = unsubscribe_token(
+            &key,
+            "test@test.invalid",
+            NotificationCategory::EventUpdates,
+            Utc::now() - Duration::days(1),
+        )
+        .expect("Unsubscribe token built.");
+
+        assert!(parse_unsubscribe_token(&key, &token).is_err());
+    }
+
+    #[test]
+    fn test_unsubscribe_token_rejects_other_tokens() {
+        let key = PasetoSymmetricKey::<V4, Local>::from(Key::from([0; 32]));
+
+        // An API token must not be usable to unsubscribe someone
+        let token = PasetoBuilder::<V4, Local>::default()
+            .set_claim(IssuerClaim::from("calandar.org"))
+            .set_claim(TokenIdentifierClaim::from("api"))
+            .set_claim(SubjectClaim::from("test@test.invalid"))
+            .build(&key)
+            .expect("Valid token built.");
+
+        assert!(parse_unsubscribe_token(&key, &token).is_err());
+    }
+
+    #[test]
+    fn test_fold_ics_line_limits_line_length() {
+        let line = format!("DESCRIPTION:{}", "é".repeat(100));
+        let folded = fold_ics_line(&line);
+
+        for physical_line in folded.split("\r\n") {
+            assert!(physical_line.len() <= 75);
+        }
+
+        // Unfolding gives back the original line
+        assert_eq!(folded.replace("\r\n ", "").trim_end(), line);
+    }
+}
@@ -0,0 +1,368 @@
+use std::collections::{HashMap, HashSet};
+
+/// How votes for an event's suggested games are counted
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Method {
+    /// Vote yes for any number of games, and the most yes votes wins
+    Approval,
+    /// Give each game 0 to 5 stars, and the most stars in total wins
+    Score,
+    /// Rank games in order of preference. The game with the fewest first preferences is
+    /// knocked out and its votes go to the next preference, until one game is left.
+    InstantRunoff,
+    /// Rank games in order of preference. Each ranking gives a game a point for every
+    /// suggested game ranked below it, and the most points wins.
+    Borda,
+}
+
+/// Someone's vote for a game
+#[derive(Debug, Clone)]
+pub struct Vote {
+    pub voter: String,
+    pub game_id: i64,
+    /// Whether they voted yes, for approval voting
+    pub approved: bool,
+    /// Stars out of 5, for score voting
+    pub score: Option<i16>,
+    /// Where they ranked the game, 1 being their favourite, for ranked choice voting
+    pub rank: Option<i16>,
+}
+
+/// How a game did in the vote
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Standing {
+    pub game_id: i64,
+    /// Yes votes, total stars, Borda points, or for instant-runoff,
+    /// first preferences in the last round the game was in
+    pub points: i64,
+    /// How many people voted for the game at all
+    pub voter_count: i64,
+    /// For instant-runoff, the round the game was knocked out in, starting at 1.
+    /// Not set for the winner or other methods.
+    pub eliminated_in_round: Option<usize>,
+}
+
+/// Count the votes for some games, best first. Votes for games that aren't candidates are ignored.
+pub fn tally(method: Method, candidates: &[i64], votes: &[Vote]) -> Vec<Standing> {
+    let candidates: Vec<i64> = {
+        let mut seen = HashSet::new();
+        candidates
+            .iter()
+            .copied()
+            .filter(|game_id| seen.insert(*game_id))
+            .collect()
+    };
+    let votes: Vec<&Vote> = votes
+        .iter()
+        .filter(|vote| candidates.contains(&vote.game_id))
+        .collect();
+
+    let mut standings = match method {
+        Method::Approval => count_points(&candidates, &votes, |vote| vote.approved.then_some(1)),
+        Method::Score => count_points(&candidates, &votes, |vote| {
+            vote.score.filter(|score| *score > 0).map(i64::from)
+        }),
+        Method::Borda => borda(&candidates, &votes),
+        Method::InstantRunoff => return instant_runoff(&candidates, &votes),
+    };
+
+    standings.sort_by(|a, b| {
+        b.points
+            .cmp(&a.points)
+            .then_with(|| b.voter_count.cmp(&a.voter_count))
+            .then_with(|| a.game_id.cmp(&b.game_id))
+    });
+    standings
+}
+
+/// How strongly to prioritise each game when scheduling, higher being better.
+/// Instant-runoff points can't be compared between rounds, so games are prioritised by where they finished.
+pub fn priorities(method: Method, standings: &[Standing]) -> HashMap<i64, i32> {
+    let count = standings.len();
+    standings
+        .iter()
+        .enumerate()
+        .map(|(index, standing)| {
+            let priority = if method == Method::InstantRunoff {
+                i32::try_from(count - index).unwrap_or(i32::MAX)
+            } else {
+                i32::try_from(standing.points).unwrap_or(i32::MAX)
+            };
+            (standing.game_id, priority)
+        })
+        .collect()
+}
+
+/// Add up the points each vote gives a game, counting anyone who gave it points as a voter
+fn count_points(
+    candidates: &[i64],
+    votes: &[&Vote],
+    points: impl Fn(&Vote) -> Option<i64>,
+) -> Vec<Standing> {
+    candidates
+        .iter()
+        .map(|game_id| {
+            let game_points: Vec<i64> = votes
+                .iter()
+                .filter(|vote| vote.game_id == *game_id)
+                .filter_map(|vote| points(vote))
+                .collect();
+            Standing {
+                game_id: *game_id,
+                points: game_points.iter().sum(),
+                voter_count: i64::try_from(game_points.len()).unwrap_or(i64::MAX),
+                eliminated_in_round: None,
+            }
+        })
+        .collect()
+}
+
+/// Each voter's ranking of the candidates, favourite first. Duplicate ranks are ordered by game ID.
+fn ballots(votes: &[&Vote]) -> Vec<Vec<i64>> {
+    let mut by_voter: HashMap<String, Vec<(i16, i64)>> = HashMap::new();
+    for vote in votes {
+        if let Some(rank) = vote.rank {
+            by_voter
+                .entry(vote.voter.to_lowercase())
+                .or_default()
+                .push((rank, vote.game_id));
+        }
+    }
+
+    let mut emails: Vec<String> = by_voter.keys().cloned().collect();
+    emails.sort();
+    emails
+        .into_iter()
+        .filter_map(|voter| by_voter.remove(&voter))
+        .map(|mut ranking| {
+            ranking.sort_unstable();
+            ranking.into_iter().map(|(_, game_id)| game_id).collect()
+        })
+        .collect()
+}
+
+fn borda(candidates: &[i64], votes: &[&Vote]) -> Vec<Standing> {
+    let most_points = i64::try_from(candidates.len()).unwrap_or(i64::MAX) - 1;
+    let mut points: HashMap<i64, (i64, i64)> = HashMap::new();
+
+    for ballot in ballots(votes) {
+        for (position, game_id) in (0_i64..).zip(ballot) {
+            let entry = points.entry(game_id).or_default();
+            entry.0 += most_points - position;
+            entry.1 += 1;
+        }
+    }
+
+    candidates
+        .iter()
+        .map(|game_id| {
+            let (points, voter_count) = points.get(game_id).copied().unwrap_or_default();
+            Standing {
+                game_id: *game_id,
+                points,
+                voter_count,
+                eliminated_in_round: None,
+            }
+        })
+        .collect()
+}
+
+fn instant_runoff(candidates: &[i64], votes: &[&Vote]) -> Vec<Standing> {
+    let ballots = ballots(votes);
+
+    // Ties for last place are broken by Borda points, so the game with broader support stays in
+    let tie_breaks: HashMap<i64, i64> = borda(candidates, votes)
+        .into_iter()
+        .map(|standing| (standing.game_id, standing.points))
+        .collect();
+    let voter_counts: HashMap<i64, i64> = candidates
+        .iter()
+        .map(|game_id| {
+            let count = ballots
+                .iter()
+                .filter(|ballot| ballot.contains(game_id))
+                .count();
+            (*game_id, i64::try_from(count).unwrap_or(i64::MAX))
+        })
+        .collect();
+
+    let mut remaining: Vec<i64> = candidates.to_vec();
+    let mut eliminated: Vec<Standing> = Vec::new();
+    let mut round = 0;
+
+    loop {
+        round += 1;
+
+        // Each ballot counts for its highest ranked game still in the running
+        let mut first_preferences: HashMap<i64, i64> =
+            remaining.iter().map(|game_id| (*game_id, 0)).collect();
+        for ballot in &ballots {
+            if let Some(game_id) = ballot.iter().find(|game_id| remaining.contains(game_id)) {
+                *first_preferences.entry(*game_id).or_default() += 1;
+            }
+        }
+
+        let standing = |game_id: i64, eliminated_in_round: Option<usize>| Standing {
+            game_id,
+            points: first_preferences.get(&game_id).copied().unwrap_or_default(),
+            voter_count: voter_counts.get(&game_id).copied().unwrap_or_default(),
+            eliminated_in_round,
+        };
+
+        if remaining.len() > 1 {
+            let loser = remaining
+                .iter()
+                .copied()
+                .min_by(|a, b| {
+                    first_preferences[a]
+                        .cmp(&first_preferences[b])
+                        .then_with(|| tie_breaks[a].cmp(&tie_breaks[b]))
+                        .then_with(|| b.cmp(a))
+                })
+                .unwrap_or_default();
+            eliminated.push(standing(loser, Some(round)));
+            remaining.retain(|game_id| *game_id != loser);
+        }
+
+        if remaining.len() <= 1 {
+            let mut standings: Vec<Standing> = remaining
+                .iter()
+                .map(|game_id| standing(*game_id, None))
+                .collect();
+            standings.extend(eliminated.into_iter().rev());
+            return standings;
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn approve(voter: &str, game_id: i64) -> Vote {
+        Vote {
+            voter: voter.to_string(),
+            game_id,
+            approved: true,
+            score: None,
+            rank: None,
+        }
+    }
+
+    fn score(voter: &str, game_id: i64, stars: i16) -> Vote {
+        Vote {
+            score: Some(stars),
+            approved: stars > 0,
+            ..approve(voter, game_id)
+        }
+    }
+
+    /// Votes ranking games in order, favourite first
+    fn rank(voter: &str, game_ids: &[i64]) -> Vec<Vote> {
+        (1..)
+            .zip(game_ids)
+            .map(|(rank, game_id)| Vote {
+                rank: Some(rank),
+                ..approve(voter, *game_id)
+            })
+            .collect()
+    }
+
+    fn order(standings: &[Standing]) -> Vec<i64> {
+        standings.iter().map(|standing| standing.game_id).collect()
+    }
+
+    #[test]
+    fn test_approval_counts_yes_votes() {
+        let votes = vec![
+            approve("a", 1),
+            approve("b", 1),
+            approve("a", 2),
+            Vote {
+                approved: false,
+                ..approve("c", 2)
+            },
+        ];
+        let standings = tally(Method::Approval, &[1, 2, 3], &votes);
+        assert_eq!(order(&standings), vec![1, 2, 3]);
+        assert_eq!(standings[0].points, 2);
+        assert_eq!(standings[1].voter_count, 1);
+        assert_eq!(standings[2].points, 0);
+    }
+
+    #[test]
+    fn test_score_adds_up_stars() {
+        let votes = vec![
+            score("a", 1, 5),
+            score("b", 1, 0),
+            score("a", 2, 3),
+            score("b", 2, 3),
+        ];
+        let standings = tally(Method::Score, &[1, 2], &votes);
+        assert_eq!(order(&standings), vec![2, 1]);
+        assert_eq!(standings[0].points, 6);
+        assert_eq!(standings[1].points, 5);
+        assert_eq!(standings[1].voter_count, 1);
+    }
+
+    #[test]
+    fn test_borda_rewards_broad_support() {
+        // Game 1 is the favourite of two people but the last choice of three,
+        // while everyone likes game 2
+        let mut votes = Vec::new();
+        votes.extend(rank("a", &[1, 2, 3]));
+        votes.extend(rank("b", &[1, 2, 3]));
+        votes.extend(rank("c", &[2, 3, 1]));
+        votes.extend(rank("d", &[3, 2, 1]));
+        votes.extend(rank("e", &[3, 2, 1]));
+
+        let standings = tally(Method::Borda, &[1, 2, 3], &votes);
+        assert_eq!(order(&standings), vec![2, 3, 1]);
+        assert_eq!(standings[0].points, 6);
+    }
+
+    #[test]
+    fn test_instant_runoff_transfers_knocked_out_votes() {
+        // Game 1 has the most first preferences, but game 3's voters prefer game 2
+        let mut votes = Vec::new();
+        votes.extend(rank("a", &[1, 2]));
+        votes.extend(rank("b", &[1, 2]));
+        votes.extend(rank("c", &[1]));
+        votes.extend(rank("d", &[2, 1]));
+        votes.extend(rank("e", &[2, 3]));
+        votes.extend(rank("f", &[3, 2]));
+        votes.extend(rank("g", &[3, 2]));
+
+        let standings = tally(Method::InstantRunoff, &[1, 2, 3], &votes);
+        assert_eq!(order(&standings), vec![2, 1, 3]);
+        assert_eq!(standings[0].points, 4);
+        assert_eq!(standings[0].eliminated_in_round, None);
+        assert_eq!(standings[1].eliminated_in_round, Some(2));
+        assert_eq!(standings[2].eliminated_in_round, Some(1));
+    }
+
+    #[test]
+    fn test_instant_runoff_includes_games_nobody_ranked() {
+        let standings = tally(Method::InstantRunoff, &[1, 2], &rank("a", &[2]));
+        assert_eq!(order(&standings), vec![2, 1]);
+        assert_eq!(standings[1].voter_count, 0);
+    }
+
+    #[test]
+    fn test_votes_for_other_games_are_ignored() {
+        let standings = tally(Method::Approval, &[1], &[approve("a", 1), approve("a", 9)]);
+        assert_eq!(order(&standings), vec![1]);
+    }
+
+    #[test]
+    fn test_instant_runoff_priorities_follow_the_finishing_order() {
+        let standings = tally(Method::InstantRunoff, &[1, 2, 3], &rank("a", &[3, 1, 2]));
+        let finishing = priorities(Method::InstantRunoff, &standings);
+        assert_eq!(finishing[&3], 3);
+        assert_eq!(finishing[&1], 2);
+        assert_eq!(finishing[&2], 1);
+
+        let standings = tally(Method::Score, &[1], &[score("a", 1, 4)]);
+        assert_eq!(priorities(Method::Score, &standings)[&1], 4);
+    }
+}
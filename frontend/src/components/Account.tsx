@@ -53,6 +53,10 @@ const Account = () => {
           });
         } else if (response.status === 401) {
           signOut();
+        } else if (response.status === 429) {
+          enqueueSnackbar("Steam is busy, try refreshing your games later", {
+            variant: "warning",
+          });
         } else {
           response
             .text()
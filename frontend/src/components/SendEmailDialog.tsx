@@ -76,8 +76,8 @@ export default function SendEmailDialog(props: SendEmailDialogProps) {
       }),
     })
       .then((response) => {
-        if (response.status === 204) {
-          enqueueSnackbar("Email sent successfully!", { variant: "success" });
+        if (response.status === 202) {
+          enqueueSnackbar("Email is being sent!", { variant: "success" });
           // Reset form
           setSubject("");
           setMessage("");